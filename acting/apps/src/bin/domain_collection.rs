@@ -2,14 +2,21 @@ use ompas_core::model::sym_domain::cst;
 use ompas_core::model::sym_domain::domain_test::DomainTest;
 use ompas_core::model::sym_domain::domain_test::DomainTest::*;
 use ompas_core::model::sym_domain::type_lattice::TypeLattice;
+use ompas_core::ompas::scheme::monitor::ModMonitor;
+use ompas_language::monitor::debug_conversion::EXPORT_PDDL;
 use ompas_language::sym_table::TYPE_OBJECT;
 use ompas_middleware::Master;
+use sompas_core::{eval, eval_init, get_root_env, parse};
+use sompas_modules::ModExtendedStd;
+use sompas_structs::lenv::ImportType::WithoutPrefix;
+use sompas_structs::lenv::LEnv;
 use std::env::set_current_dir;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::Command;
+use structopt::StructOpt;
 
 fn meet(dc: &TypeLattice, ta: impl Into<DomainTest>, tb: impl Into<DomainTest>) -> DomainTest {
     let ta = ta.into();
@@ -37,7 +44,57 @@ fn sub(dc: &TypeLattice, ta: impl Into<DomainTest>, tb: impl Into<DomainTest>) -
     r
 }
 
-fn main() {
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "domain_collection",
+    about = "Exploration of the type lattice, and export of OMPAS domains in PDDL/HDDL."
+)]
+struct Opt {
+    /// SOMPAS domain to export in PDDL, or in HDDL if it defines tasks.
+    #[structopt(short = "d", long = "domain")]
+    domain: Option<PathBuf>,
+
+    /// Output directory of the exported domain and problem.
+    #[structopt(short = "o", long = "output", default_value = "/tmp/pddl")]
+    output: PathBuf,
+
+    /// Initial tasks of the HDDL problem, as SOMPAS expressions.
+    #[structopt(short = "t", long = "task")]
+    tasks: Vec<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let opt: Opt = Opt::from_args();
+    match &opt.domain {
+        Some(domain) => export_pddl(domain.clone(), &opt).await,
+        None => type_lattice(),
+    }
+}
+
+/// Loads a SOMPAS domain and exports it with the primitive export-pddl.
+async fn export_pddl(domain: PathBuf, opt: &Opt) {
+    let monitor = ModMonitor::new(domain, None).await;
+    let mut env: LEnv = get_root_env().await;
+    env.import_module(ModExtendedStd::default(), WithoutPrefix);
+    env.import_module(monitor, WithoutPrefix);
+    eval_init(&mut env).await;
+
+    let mut expr = format!("({} {:?}", EXPORT_PDDL, opt.output);
+    for task in &opt.tasks {
+        expr.push_str(format!(" '{}", task).as_str());
+    }
+    expr.push(')');
+    let lv = parse(&expr, &mut env)
+        .await
+        .unwrap_or_else(|e| panic!("{}", e));
+    match eval(&lv, &mut env, None).await {
+        Ok(report) => println!("{}", report),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+fn type_lattice() {
     println!("Hello, world!");
     //let tn = &TypeNetwork::default();
     let mut dc = TypeLattice::new();
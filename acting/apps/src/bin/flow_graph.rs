@@ -82,6 +82,7 @@ Graph flow converter for SOMPAS code!\n
                     PLEnv {
                         env,
                         unpure_bindings: Default::default(),
+                        unrolling: Default::default(),
                         pc: Default::default(),
                     },
                 )
@@ -0,0 +1,88 @@
+use ompas_core::ompas::manager::platform::mock::{MockFault, MockPlatform};
+use ompas_language::interface::LOG_TOPIC_PLATFORM;
+use ompas_middleware::Master;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "OMPAS mock platform",
+    about = "A grpc execution platform simulating the commands with their sim-model."
+)]
+struct Opt {
+    #[structopt(short = "d", long = "domain")]
+    domain: PathBuf,
+
+    #[structopt(short = "s", long = "socket")]
+    socket: Option<SocketAddr>,
+
+    /// Commands rejected by the platform, as <command>[:<occurrence>]
+    #[structopt(long = "reject")]
+    reject: Vec<String>,
+
+    /// Commands whose execution fails, as <command>[:<occurrence>]
+    #[structopt(long = "fail")]
+    fail: Vec<String>,
+
+    /// Commands whose execution is delayed, as <command>[:<occurrence>]:<seconds>
+    #[structopt(long = "delay")]
+    delay: Vec<String>,
+
+    #[structopt(short = "l", long = "log")]
+    log: bool,
+}
+
+fn parse_target(s: &str) -> (String, Option<usize>) {
+    match s.split_once(':') {
+        None => (s.to_string(), None),
+        Some((command, occurrence)) => (
+            command.to_string(),
+            Some(
+                occurrence
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{} is not a valid occurrence.", occurrence)),
+            ),
+        ),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let opt: Opt = Opt::from_args();
+    println!("{:?}", opt);
+
+    let mut platform = MockPlatform::new(opt.domain.clone());
+    if let Some(socket) = opt.socket {
+        platform.set_server_info(socket);
+    }
+    for r in &opt.reject {
+        let (command, occurrence) = parse_target(r);
+        platform.add_fault(command, occurrence, MockFault::Reject);
+    }
+    for f in &opt.fail {
+        let (command, occurrence) = parse_target(f);
+        platform.add_fault(command, occurrence, MockFault::Fail);
+    }
+    for d in &opt.delay {
+        let (target, delay) = d.rsplit_once(':').unwrap_or_else(|| {
+            panic!(
+                "{} is not of the form <command>[:<occurrence>]:<seconds>",
+                d
+            )
+        });
+        let (command, occurrence) = parse_target(target);
+        let delay: f64 = delay
+            .parse()
+            .unwrap_or_else(|_| panic!("{} is not a valid delay.", delay));
+        platform.add_fault(command, occurrence, MockFault::Delay(delay));
+    }
+
+    if opt.log {
+        Master::start_display_log_topic(LOG_TOPIC_PLATFORM).await;
+    }
+
+    platform.serve().await;
+    println!("Mock platform serving on {}", platform.service_info);
+    Master::wait_end().await;
+}
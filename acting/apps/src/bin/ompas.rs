@@ -1,10 +1,15 @@
+use ompas_core::ompas::manager::platform::platform_declaration::PlatformDeclaration;
+use ompas_core::ompas::manager::platform::replay::ReplayPlatform;
+use ompas_core::ompas::manager::trace::Trace;
 use ompas_core::ompas::scheme::monitor::ModMonitor;
 use ompas_core::OMPAS_LOG;
+use ompas_language::monitor::model::IMPORT_PDDL;
 use ompas_language::process::LOG_TOPIC_OMPAS;
 use ompas_middleware::logger::FileDescriptor;
 use ompas_middleware::Master;
 use sompas_modules::ModExtendedStd;
 use sompas_repl::lisp_interpreter::{LispInterpreter, LispInterpreterConfig};
+use sompas_repl::server::{ReplServerAddress, ReplServerConfig, REPL_TOKEN_ENV_VAR};
 use std::fs;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -21,10 +26,34 @@ struct Opt {
     problem: Option<PathBuf>,
 
     #[structopt(short = "d", long = "domain")]
-    domain: PathBuf,
+    domain: Option<PathBuf>,
+
+    /// PDDL or HDDL domain imported before the SOMPAS domain.
+    #[structopt(long = "pddl-domain")]
+    pddl_domain: Option<PathBuf>,
+
+    /// PDDL or HDDL problem of the imported PDDL domain.
+    #[structopt(long = "pddl-problem", requires = "pddl-domain")]
+    pddl_problem: Option<PathBuf>,
+
+    /// Trace replayed as execution platform, the decisions of the engine being checked against it.
+    #[structopt(long = "replay")]
+    replay: Option<PathBuf>,
 
     #[structopt(short = "v", long = "view")]
     _view: bool,
+
+    /// Serves remote sessions on a tcp address (127.0.0.1:8257) or a unix socket (unix:/tmp/ompas.sock).
+    #[structopt(long = "listen")]
+    listen: Option<ReplServerAddress>,
+
+    /// Token of the remote sessions, read from SOMPAS_REPL_TOKEN if not given.
+    #[structopt(long = "token", requires = "listen")]
+    token: Option<String>,
+
+    /// Does not read expressions on stdin, the interpreter being only driven by remote sessions.
+    #[structopt(long = "headless", requires = "listen")]
+    headless: bool,
 }
 
 #[tokio::main]
@@ -61,9 +90,19 @@ async fn lisp_interpreter(opt: &Opt) {
     li.import_namespace(mod_extended_std);
 
     let mut com = li.subscribe();
-    let str = fs::read_to_string(&opt.domain).expect("Something went wrong reading the file");
-    //println!("string in file: {}", str);
-    com.send(str).await.expect("could not send to LI");
+    if let Some(d) = &opt.pddl_domain {
+        let mut str = format!("({} {:?}", IMPORT_PDDL, d);
+        if let Some(p) = &opt.pddl_problem {
+            str.push_str(format!(" {:?}", p).as_str());
+        }
+        str.push(')');
+        com.send(str).await.expect("could not send to LI");
+    }
+    if let Some(d) = &opt.domain {
+        let str = fs::read_to_string(d).expect("Something went wrong reading the file");
+        //println!("string in file: {}", str);
+        com.send(str).await.expect("could not send to LI");
+    }
     if let Some(p) = &opt.problem {
         let str = fs::read_to_string(p).unwrap_or_else(|_| {
             panic!("Something went wrong reading the file {:?}", p.as_os_str())
@@ -83,7 +122,15 @@ async fn lisp_interpreter(opt: &Opt) {
         }
     });
 
-    let ctx_rae = ModMonitor::new("nil", opt.log.clone()).await;
+    let platform: PlatformDeclaration = match &opt.replay {
+        Some(path) => {
+            let trace = Trace::load(path).unwrap_or_else(|e| panic!("{}", e));
+            ReplayPlatform::new("nil", trace).into()
+        }
+        None => "nil".into(),
+    };
+
+    let ctx_rae = ModMonitor::new(platform, opt.log.clone()).await;
 
     if OMPAS_LOG.get() {
         Master::start_display_log_topic(LOG_TOPIC_OMPAS).await;
@@ -91,7 +138,22 @@ async fn lisp_interpreter(opt: &Opt) {
 
     li.import_namespace(ctx_rae);
 
-    li.set_config(LispInterpreterConfig::new(true));
+    let mut config = LispInterpreterConfig::new(!opt.headless);
+    if let Some(address) = &opt.listen {
+        let token = opt
+            .token
+            .clone()
+            .or_else(|| std::env::var(REPL_TOKEN_ENV_VAR).ok())
+            .filter(|token| !token.is_empty())
+            .unwrap_or_else(|| {
+                panic!(
+                    "--listen requires a non-empty --token or {}",
+                    REPL_TOKEN_ENV_VAR
+                )
+            });
+        config = config.with_server(ReplServerConfig::new(address.clone(), token));
+    }
+    li.set_config(config);
 
     li.run(
         opt.log
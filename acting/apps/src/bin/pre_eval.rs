@@ -19,6 +19,7 @@ async fn main() {
     let mut p_env = PLEnv {
         env,
         unpure_bindings: Default::default(),
+        unrolling: Default::default(),
         pc: Default::default(),
     };
 
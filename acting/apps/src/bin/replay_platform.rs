@@ -0,0 +1,48 @@
+use ompas_core::ompas::manager::platform::replay::ReplayPlatform;
+use ompas_core::ompas::manager::trace::Trace;
+use ompas_language::interface::LOG_TOPIC_PLATFORM;
+use ompas_middleware::Master;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "OMPAS replay platform",
+    about = "A grpc execution platform replaying a trace recorded by OMPAS."
+)]
+struct Opt {
+    #[structopt(short = "t", long = "trace")]
+    trace: PathBuf,
+
+    #[structopt(short = "s", long = "socket")]
+    socket: Option<SocketAddr>,
+
+    #[structopt(short = "l", long = "log")]
+    log: bool,
+}
+
+#[tokio::main]
+async fn main() {
+    let opt: Opt = Opt::from_args();
+    println!("{:?}", opt);
+
+    let trace = Trace::load(&opt.trace).unwrap_or_else(|e| panic!("{}", e));
+    let mut platform = ReplayPlatform::new("nil", trace);
+    if let Some(socket) = opt.socket {
+        platform.set_server_info(socket);
+    }
+
+    if opt.log {
+        Master::start_display_log_topic(LOG_TOPIC_PLATFORM).await;
+    }
+
+    platform.serve().await;
+    println!("Replay platform serving on {}", platform.service_info);
+    println!(
+        "Use ({} {:?}) in OMPAS to check its decisions against the trace.",
+        ompas_language::monitor::control::CHECK_TRACE,
+        opt.trace
+    );
+    Master::wait_end().await;
+}
@@ -16,9 +16,22 @@ pub static OMPAS_PLAN_ENCODING_OPTIMIZATION: EnvParam<bool> =
     EnvParam::new("OMPAS_PLAN_ENCODING_OPTIMIZATION", "true");
 pub static OMPAS_DELIBERATION_FREQUENCY: EnvParam<u64> =
     EnvParam::new("OMPAS_DELIBERATION_FREQUENCY", "1");
+pub static OMPAS_CLOCK_MODE: EnvParam<ClockMode> = EnvParam::new("OMPAS_CLOCK_MODE", "real-time");
+pub static OMPAS_VIRTUAL_CLOCK_QUIESCENCE: EnvParam<u64> =
+    EnvParam::new("OMPAS_VIRTUAL_CLOCK_QUIESCENCE", "500");
+pub static OMPAS_TRACE: EnvParam<bool> = EnvParam::new("OMPAS_TRACE", "false");
+pub static OMPAS_PREEMPTION: EnvParam<bool> = EnvParam::new("OMPAS_PREEMPTION", "false");
+pub static OMPAS_DEADLOCK_POLICY: EnvParam<DeadlockPolicy> =
+    EnvParam::new("OMPAS_DEADLOCK_POLICY", "none");
+pub static OMPAS_DIVERGENCE_POLICY: EnvParam<DivergencePolicy> =
+    EnvParam::new("OMPAS_DIVERGENCE_POLICY", "none");
+pub static OMPAS_LEARNING_FILE: EnvParam<String> =
+    EnvParam::new("OMPAS_LEARNING_FILE", "/tmp/ompas_learning.json");
 pub static OMPAS_DEBUG_CONTINUOUS_PLANNING: EnvParam<bool> =
     EnvParam::new("OMPAS_DEBUG_CONTINUOUS_PLANNING", "false");
 
+pub static OMPAS_PLAN_REPAIR: EnvParam<bool> = EnvParam::new("OMPAS_PLAN_REPAIR", "false");
+
 pub static OMPAS_PLANNER_OUTPUT: EnvParam<bool> = EnvParam::new("OMPAS_PLANNER_OUTPUT", "false");
 
 pub static OMPAS_PATH: EnvParam<String> = EnvParam::new("OMPAS_PATH", "~/ompas");
@@ -27,6 +40,7 @@ pub static OMPAS_RESOURCE_ENCODING: EnvParam<ResourceEncoding> =
 
 pub static OMPAS_PRE_COMPUTE_MODELS: EnvParam<bool> =
     EnvParam::new("OMPAS_PRE_COMPUTE_MODELS", "true");
+pub static OMPAS_UNROLL_BOUND: EnvParam<usize> = EnvParam::new("OMPAS_UNROLL_BOUND", "3");
 
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
 pub enum ResourceEncoding {
@@ -46,6 +60,73 @@ impl FromStr for ResourceEncoding {
     }
 }
 
+/// Resolution of the deadlocks detected between the acquisitions of resources.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeadlockPolicy {
+    /// The deadlock is only logged, the acquisitions of the cycle keep waiting.
+    /// This is the default policy.
+    None,
+    /// The youngest waiting acquisition of the cycle fails with err::deadlock.
+    Fail,
+    /// The task of the youngest waiting acquisition is preempted and retried.
+    Preempt,
+}
+
+impl FromStr for DeadlockPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "fail" => Ok(Self::Fail),
+            "preempt" => Ok(Self::Preempt),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Resolution of the divergences between the planned chronicles and the observed state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DivergencePolicy {
+    /// The divergence is only recorded.
+    None,
+    /// The acting tree is replanned.
+    Replan,
+    /// The top-level task of the divergent process is preempted and retried.
+    Retry,
+}
+
+impl FromStr for DivergencePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "replan" => Ok(Self::Replan),
+            "retry" => Ok(Self::Retry),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClockMode {
+    RealTime,
+    Virtual,
+}
+
+impl FromStr for ClockMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "real-time" => Ok(Self::RealTime),
+            "virtual" => Ok(Self::Virtual),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ChronicleDebug {
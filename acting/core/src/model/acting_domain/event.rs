@@ -1,6 +1,8 @@
 use crate::model::acting_domain::parameters::Parameters;
 use function_name::named;
-use ompas_language::monitor::model::{ONCE, WHENEVER};
+use ompas_language::monitor::model::{
+    AFTER, EVERY, FALLING, FOR_DURATION, ONCE, RISING, WHENEVER, WITHIN,
+};
 use sompas_structs::kindlvalue::KindLValue;
 use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::LValue;
@@ -31,19 +33,28 @@ impl Display for Event {
 #[derive(Clone, Debug)]
 pub struct Trigger {
     pub(crate) trigger_activation: TriggerActivation,
+    pub(crate) kind: TriggerKind,
     pub(crate) pre_conditions: LValue,
+    /// Conditions starting the timeout of a trigger of kind *Within*.
+    pub(crate) arming: Option<LValue>,
 }
 impl Display for Trigger {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}: {}",
+            "{}",
             match self.trigger_activation {
                 TriggerActivation::Once => ONCE,
                 TriggerActivation::Whenever => WHENEVER,
-            },
-            self.pre_conditions
-        )
+            }
+        )?;
+        if self.kind != TriggerKind::Level {
+            write!(f, " {}", self.kind)?;
+        }
+        if let Some(arming) = &self.arming {
+            write!(f, " {} {}", AFTER, arming)?;
+        }
+        write!(f, ": {}", self.pre_conditions)
     }
 }
 
@@ -51,7 +62,48 @@ impl Trigger {
     pub fn new(trigger_activation: TriggerActivation, pre_conditions: LValue) -> Self {
         Self {
             trigger_activation,
+            kind: Default::default(),
             pre_conditions,
+            arming: None,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: TriggerKind, arming: Option<LValue>) -> Self {
+        self.kind = kind;
+        self.arming = arming;
+        self
+    }
+}
+
+/// Temporal semantics of the pre-conditions of a trigger.
+/// Durations are in seconds.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum TriggerKind {
+    /// Fires when the pre-conditions are true.
+    #[default]
+    Level,
+    /// Fires when the pre-conditions become true.
+    Rising,
+    /// Fires when the pre-conditions become false.
+    Falling,
+    /// Fires once the pre-conditions have held continuously for the duration.
+    For(f64),
+    /// Fires periodically while the pre-conditions hold.
+    Every(f64),
+    /// Fires if the pre-conditions have not become true within the duration, counted from the
+    /// moment the arming conditions became true.
+    Within(f64),
+}
+
+impl Display for TriggerKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TriggerKind::Level => Ok(()),
+            TriggerKind::Rising => write!(f, "{RISING}"),
+            TriggerKind::Falling => write!(f, "{FALLING}"),
+            TriggerKind::For(d) => write!(f, "{FOR_DURATION} {d}"),
+            TriggerKind::Every(d) => write!(f, "{EVERY} {d}"),
+            TriggerKind::Within(d) => write!(f, "{WITHIN} {d}"),
         }
     }
 }
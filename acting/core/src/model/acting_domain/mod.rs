@@ -29,6 +29,7 @@ pub mod event;
 pub mod method;
 pub mod model;
 pub mod parameters;
+pub mod pddl;
 pub mod state_function;
 pub mod task;
 
@@ -0,0 +1,902 @@
+use crate::model::acting_domain::model::ModelKind;
+use crate::model::acting_domain::parameters::Parameters;
+use crate::model::acting_domain::pddl::PDDL_OBJECT;
+use crate::model::acting_domain::OMPASDomain;
+use crate::ompas::manager::state::world_state_snapshot::WorldStateSnapshot;
+use crate::ompas::manager::state::StateType;
+use ompas_language::exec::state::{DURATIVE_EFFECT, EFFECT, INSTANCE, INSTANCES};
+use ompas_language::sym_table::TYPE_OBJECT;
+use sompas_language::basic_math::{EQ, GEQ, GT, LEQ, LT, NEQ, NOT, NOT_SHORT};
+use sompas_language::error::FN_ERR;
+use sompas_language::first_order_logic::{EXISTS, FORALL};
+use sompas_language::kind::{BOOL, FLOAT, INT, NUMBER};
+use sompas_structs::list;
+use sompas_structs::llambda::{LLambda, LambdaArgs};
+use sompas_structs::lprimitive::LPrimitive;
+use sompas_structs::lvalue::LValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+pub const PDDL_DOMAIN_NAME: &str = "ompas";
+pub const PDDL_PROBLEM_NAME: &str = "ompas-problem";
+
+/// Domain and problem exported from OMPAS, in PDDL 2.1, or in HDDL if the domain defines tasks.
+pub struct PddlExport {
+    pub hierarchical: bool,
+    pub domain: String,
+    pub problem: String,
+    /// Constructs of the OMPAS domain that could not be expressed, and were left out of the export.
+    pub unsupported: Vec<String>,
+}
+
+impl PddlExport {
+    pub fn extension(&self) -> &'static str {
+        if self.hierarchical {
+            "hddl"
+        } else {
+            "pddl"
+        }
+    }
+}
+
+#[derive(Clone)]
+enum SfKind {
+    Predicate,
+    Numeric,
+    /// State function whose value is an object of the given type.
+    /// It is exported as a predicate with an additional parameter for the value.
+    Object(String),
+}
+
+#[derive(Default)]
+struct ModelBody {
+    conditions: Vec<LValue>,
+    effects: Vec<LValue>,
+    end_effects: Vec<LValue>,
+    duration: Option<LValue>,
+    subtasks: Vec<LValue>,
+}
+
+struct PddlExporter<'a> {
+    domain: &'a OMPASDomain,
+    types: BTreeMap<String, String>,
+    state_functions: HashMap<String, (Vec<(String, String)>, SfKind)>,
+    requirements: BTreeSet<&'static str>,
+    unsupported: Vec<String>,
+}
+
+fn pddl_list(head: &str, args: Vec<LValue>) -> LValue {
+    let mut list = vec![head.into()];
+    list.extend(args);
+    list.into()
+}
+
+fn conjunction(mut conjuncts: Vec<LValue>) -> LValue {
+    if conjuncts.len() == 1 {
+        conjuncts.pop().unwrap()
+    } else {
+        pddl_list("and", conjuncts)
+    }
+}
+
+/// Returns the conjuncts of a PDDL formula.
+fn conjuncts(lv: LValue) -> Vec<LValue> {
+    match &lv {
+        LValue::List(list) if list[0].to_string() == "and" => list[1..].to_vec(),
+        _ => vec![lv],
+    }
+}
+
+/// Returns the PDDL variable of a parameter, prefixed by `?`.
+fn variable(p: &str) -> String {
+    if p.starts_with('?') {
+        p.to_string()
+    } else {
+        format!("?{}", p)
+    }
+}
+
+/// Replaces the symbols of parameters by their PDDL variables, quoted expressions being kept.
+fn rename_variables(lv: &LValue, variables: &HashMap<String, String>) -> LValue {
+    match lv {
+        LValue::Symbol(s) => match variables.get(s.as_str()) {
+            Some(v) => v.as_str().into(),
+            None => lv.clone(),
+        },
+        LValue::List(list) if list.first() != Some(&LValue::Primitive(LPrimitive::Quote)) => list
+            .iter()
+            .map(|e| rename_variables(e, variables))
+            .collect::<Vec<LValue>>()
+            .into(),
+        _ => lv.clone(),
+    }
+}
+
+impl<'a> PddlExporter<'a> {
+    fn new(domain: &'a OMPASDomain, state: &WorldStateSnapshot) -> Self {
+        let mut exporter = Self {
+            domain,
+            types: Default::default(),
+            state_functions: Default::default(),
+            requirements: Default::default(),
+            unsupported: vec![],
+        };
+
+        let lattice = state.instance.st.get_lattice();
+        for t in state.instance.inner.keys() {
+            if t.eq_ignore_ascii_case(TYPE_OBJECT) {
+                continue;
+            }
+            let parent = lattice
+                .get_type_id(t)
+                .and_then(|id| lattice.get_parent(id).first().copied())
+                .map(|id| lattice.format_type(&id))
+                .filter(|p| state.instance.inner.contains_key(p))
+                .map(|p| exporter.pddl_type(&p))
+                .unwrap_or_else(|| PDDL_OBJECT.to_string());
+            exporter.types.insert(t.to_string(), parent);
+        }
+
+        for (label, sf) in &domain.state_functions {
+            let context = format!("state function {}", label);
+            let params = exporter.params(&sf.parameters, &context);
+            let result = sf.result_debug.to_ascii_lowercase();
+            let kind = match result.as_str() {
+                BOOL | "boolean" => SfKind::Predicate,
+                INT | FLOAT | NUMBER => {
+                    exporter.requirements.insert(":numeric-fluents");
+                    SfKind::Numeric
+                }
+                t if t == PDDL_OBJECT || exporter.is_object_type(t) => {
+                    SfKind::Object(exporter.pddl_type(t))
+                }
+                _ => {
+                    exporter.unsupported(&context, &sf.result_debug);
+                    continue;
+                }
+            };
+            exporter
+                .state_functions
+                .insert(label.to_string(), (params, kind));
+        }
+        exporter
+    }
+
+    fn unsupported(&mut self, context: &str, construct: impl ToString) {
+        let message = format!("{}: {} is not expressible", context, construct.to_string());
+        if !self.unsupported.contains(&message) {
+            self.unsupported.push(message)
+        }
+    }
+
+    fn is_object_type(&self, t: &str) -> bool {
+        t.eq_ignore_ascii_case(TYPE_OBJECT) || self.types.keys().any(|k| k.eq_ignore_ascii_case(t))
+    }
+
+    fn pddl_type(&self, t: &str) -> String {
+        if t.eq_ignore_ascii_case(TYPE_OBJECT) {
+            PDDL_OBJECT.to_string()
+        } else {
+            t.to_string()
+        }
+    }
+
+    fn params(&mut self, params: &Parameters, context: &str) -> Vec<(String, String)> {
+        let mut typed = vec![];
+        for (p, t) in params.inner() {
+            let t = t.get_debug().to_string();
+            let t = if self.is_object_type(&t) || t == PDDL_OBJECT {
+                self.pddl_type(&t)
+            } else {
+                self.unsupported(context, format!("parameter {} of type {}", p, t));
+                PDDL_OBJECT.to_string()
+            };
+            typed.push((variable(p), t));
+        }
+        typed
+    }
+
+    fn format_params(params: &[(String, String)]) -> String {
+        let mut str = String::new();
+        for (i, (p, t)) in params.iter().enumerate() {
+            if i > 0 {
+                str.push(' ');
+            }
+            write!(str, "{} - {}", p, t).unwrap();
+        }
+        str
+    }
+
+    fn sf_call<'b>(&self, lv: &'b LValue) -> Option<(&'b [LValue], &SfKind)> {
+        let (name, args) = match lv {
+            LValue::List(list) => (list[0].to_string(), &list[1..]),
+            LValue::Symbol(s) => (s.to_string(), &[] as &[LValue]),
+            _ => return None,
+        };
+        match self.state_functions.get(&name) {
+            Some((params, kind)) if params.len() == args.len() => Some((args, kind)),
+            _ => None,
+        }
+    }
+
+    fn term(lv: &LValue) -> Result<LValue, LValue> {
+        match lv {
+            LValue::Symbol(_) | LValue::Number(_) => Ok(lv.clone()),
+            _ => Err(lv.clone()),
+        }
+    }
+
+    fn atom(name: &LValue, args: &[LValue]) -> Result<LValue, LValue> {
+        let mut atom = vec![name.clone()];
+        for arg in args {
+            atom.push(Self::term(arg)?);
+        }
+        Ok(atom.into())
+    }
+
+    fn numeric(&self, lv: &LValue) -> Result<LValue, LValue> {
+        match lv {
+            LValue::Symbol(_) | LValue::Number(_) => match self.sf_call(lv) {
+                Some((_, SfKind::Numeric)) => Ok(list![lv.clone()]),
+                Some(_) => Err(lv.clone()),
+                None => Ok(lv.clone()),
+            },
+            LValue::List(list) => match list[0].to_string().as_str() {
+                "+" | "-" | "*" | "/" if list.len() == 3 => Ok(list![
+                    list[0].clone(),
+                    self.numeric(&list[1])?,
+                    self.numeric(&list[2])?
+                ]),
+                _ => match self.sf_call(lv) {
+                    Some((args, SfKind::Numeric)) => Self::atom(&list[0], args),
+                    _ => Err(lv.clone()),
+                },
+            },
+            _ => Err(lv.clone()),
+        }
+    }
+
+    /// Translates an equality, equalities on state functions whose value is an object becoming atoms.
+    fn equality(&mut self, a: &LValue, b: &LValue) -> Result<LValue, LValue> {
+        for (sf, value) in [(a, b), (b, a)] {
+            match self.sf_call(sf) {
+                Some((args, SfKind::Object(_))) => {
+                    let mut atom = vec![];
+                    if let LValue::List(list) = sf {
+                        atom.push(list[0].clone())
+                    } else {
+                        atom.push(sf.clone())
+                    }
+                    for arg in args.iter().chain([value]) {
+                        atom.push(Self::term(arg)?);
+                    }
+                    return Ok(atom.into());
+                }
+                Some((_, SfKind::Numeric)) => {
+                    return Ok(list![EQ.into(), self.numeric(a)?, self.numeric(b)?]);
+                }
+                _ => {}
+            }
+        }
+        Ok(list![EQ.into(), Self::term(a)?, Self::term(b)?])
+    }
+
+    /// Translates a SOMPAS condition, macros as `and` and `or` being already expanded.
+    fn condition(&mut self, lv: &LValue) -> Result<LValue, LValue> {
+        let list = match lv {
+            LValue::True => return Ok(pddl_list("and", vec![])),
+            LValue::List(list) => list,
+            LValue::Symbol(_) => {
+                return match self.sf_call(lv) {
+                    Some((_, SfKind::Predicate)) => Ok(list![lv.clone()]),
+                    _ => Err(lv.clone()),
+                }
+            }
+            _ => return Err(lv.clone()),
+        };
+        let args = &list[1..];
+        match list[0].to_string().as_str() {
+            "and" => {
+                let mut and = vec![];
+                for c in args {
+                    and.append(&mut conjuncts(self.condition(c)?));
+                }
+                Ok(conjunction(and))
+            }
+            "or" => {
+                let mut or = vec![];
+                for c in args {
+                    or.push(self.condition(c)?);
+                }
+                Ok(pddl_list("or", or))
+            }
+            "if" if args.len() == 3 && args[2] == LValue::Nil => {
+                let mut and = conjuncts(self.condition(&args[0])?);
+                and.append(&mut conjuncts(self.condition(&args[1])?));
+                Ok(conjunction(and))
+            }
+            "if" if args.len() == 3 && args[1] == LValue::True => Ok(list![
+                "or".into(),
+                self.condition(&args[0])?,
+                self.condition(&args[2])?
+            ]),
+            NOT | NOT_SHORT if args.len() == 1 => {
+                self.requirements.insert(":negative-preconditions");
+                Ok(list!["not".into(), self.condition(&args[0])?])
+            }
+            NEQ if args.len() == 2 => {
+                self.requirements.insert(":negative-preconditions");
+                Ok(list!["not".into(), self.equality(&args[0], &args[1])?])
+            }
+            EQ if args.len() == 2 => self.equality(&args[0], &args[1]),
+            LT | LEQ | GT | GEQ if args.len() == 2 => Ok(list![
+                list[0].clone(),
+                self.numeric(&args[0])?,
+                self.numeric(&args[1])?
+            ]),
+            q @ (EXISTS | FORALL) if args.len() == 2 => {
+                let tpe = match &args[0] {
+                    LValue::List(i) if i.len() == 2 && i[0].to_string() == INSTANCES => {
+                        i[1].to_string()
+                    }
+                    _ => return Err(lv.clone()),
+                };
+                let (var, body) = match &args[1] {
+                    LValue::List(l) if l.len() == 3 => match &l[1] {
+                        LValue::List(vars) if vars.len() == 1 => {
+                            let var = vars[0].to_string();
+                            let variables = HashMap::from([(var.clone(), variable(&var))]);
+                            (variable(&var), rename_variables(&l[2], &variables))
+                        }
+                        _ => return Err(lv.clone()),
+                    },
+                    _ => return Err(lv.clone()),
+                };
+                if !self.is_object_type(&tpe) {
+                    return Err(lv.clone());
+                }
+                self.requirements.insert(if q == EXISTS {
+                    ":existential-preconditions"
+                } else {
+                    ":universal-preconditions"
+                });
+                Ok(list![
+                    q.into(),
+                    list![
+                        var.as_str().into(),
+                        "-".into(),
+                        self.pddl_type(&tpe).as_str().into()
+                    ],
+                    self.condition(&body)?
+                ])
+            }
+            _ => match self.sf_call(lv) {
+                Some((args, SfKind::Predicate)) => Self::atom(&list[0], args),
+                _ => Err(lv.clone()),
+            },
+        }
+    }
+
+    /// Translates an update of a fact into PDDL effects.
+    fn effect(&mut self, args: &[LValue]) -> Result<Vec<LValue>, LValue> {
+        let construct = || -> LValue { pddl_list(EFFECT, args.to_vec()) };
+        if args.len() < 2 {
+            return Err(construct());
+        }
+        let name = match &args[0] {
+            LValue::List(quote) if quote.len() == 2 => quote[1].clone(),
+            name => name.clone(),
+        };
+        let (sf_args, value) = (&args[1..args.len() - 1], &args[args.len() - 1]);
+        let kind = match self.state_functions.get(&name.to_string()) {
+            Some((params, kind)) if params.len() == sf_args.len() => kind.clone(),
+            _ => return Err(construct()),
+        };
+        let atom = Self::atom(&name, sf_args)?;
+        match kind {
+            SfKind::Predicate => match value {
+                LValue::True => Ok(vec![atom]),
+                LValue::Nil => Ok(vec![list!["not".into(), atom]]),
+                _ => Err(construct()),
+            },
+            SfKind::Numeric => {
+                if let LValue::List(op) = value {
+                    if op.len() == 3 && self.numeric(&op[1]) == Ok(atom.clone()) {
+                        match op[0].to_string().as_str() {
+                            "+" => {
+                                return Ok(vec![list![
+                                    "increase".into(),
+                                    atom,
+                                    self.numeric(&op[2])?
+                                ]])
+                            }
+                            "-" => {
+                                return Ok(vec![list![
+                                    "decrease".into(),
+                                    atom,
+                                    self.numeric(&op[2])?
+                                ]])
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(vec![list!["assign".into(), atom, self.numeric(value)?]])
+            }
+            SfKind::Object(tpe) => {
+                self.requirements.insert(":conditional-effects");
+                let mut delete = match &atom {
+                    LValue::List(atom) => atom.to_vec(),
+                    _ => unreachable!(),
+                };
+                let old = "?_old";
+                delete.push(old.into());
+                let mut add = delete.clone();
+                add.pop();
+                add.push(Self::term(value)?);
+                //Deletes are applied before adds in PDDL, so the new value is kept.
+                Ok(vec![
+                    list![
+                        "forall".into(),
+                        list![old.into(), "-".into(), tpe.as_str().into()],
+                        list!["not".into(), delete.into()]
+                    ],
+                    add.into(),
+                ])
+            }
+        }
+    }
+
+    /// Collects the conditions, effects and subtasks of the body of a model.
+    fn walk(&mut self, lv: &LValue, context: &str, body: &mut ModelBody) {
+        let list = match lv {
+            LValue::Nil | LValue::True => return,
+            LValue::List(list) => list,
+            lv => return self.unsupported(context, lv),
+        };
+        let head = list[0].to_string();
+        let r = match head.as_str() {
+            "do" | "begin" => {
+                for e in &list[1..] {
+                    self.walk(e, context, body)
+                }
+                Ok(())
+            }
+            //Expansion of (check c)
+            "if" if list.len() == 4
+                && list[2] == LValue::Nil
+                && matches!(&list[3], LValue::List(err) if err[0].to_string() == FN_ERR) =>
+            {
+                let is_type_test = match &list[1] {
+                    LValue::List(test) => {
+                        let t = test[0].to_string();
+                        t == INSTANCE || t.ends_with('?')
+                    }
+                    _ => false,
+                };
+                if is_type_test {
+                    Ok(())
+                } else if !body.subtasks.is_empty() {
+                    Err(list[1].clone())
+                } else {
+                    self.condition(&list[1])
+                        .map(|c| body.conditions.append(&mut conjuncts(c)))
+                }
+            }
+            EFFECT => self
+                .effect(&list[1..])
+                .map(|mut e| body.effects.append(&mut e)),
+            DURATIVE_EFFECT if list.len() > 2 => match self.numeric(&list[1]) {
+                Ok(d) if body.duration.is_none() || body.duration == Some(d.clone()) => {
+                    body.duration = Some(d);
+                    self.effect(&list[2..])
+                        .map(|mut e| body.end_effects.append(&mut e))
+                }
+                _ => Err(lv.clone()),
+            },
+            label
+                if self.domain.tasks.contains_key(label)
+                    || self.domain.commands.contains_key(label) =>
+            {
+                Self::atom(&list[0], &list[1..]).map(|t| body.subtasks.push(t))
+            }
+            _ => Err(lv.clone()),
+        };
+        if let Err(construct) = r {
+            self.unsupported(context, construct)
+        }
+    }
+
+    /// Collects the body of a model, its parameters being renamed into PDDL variables.
+    fn lambda_body(&mut self, lv: &LValue, context: &str) -> ModelBody {
+        let mut body = ModelBody::default();
+        match LLambda::try_from(lv) {
+            Ok(l) => {
+                let variables: HashMap<String, String> = match l.get_params() {
+                    LambdaArgs::List(params) => params
+                        .iter()
+                        .map(|p| (p.to_string(), variable(p)))
+                        .collect(),
+                    _ => Default::default(),
+                };
+                self.walk(
+                    &rename_variables(l.get_body(), &variables),
+                    context,
+                    &mut body,
+                )
+            }
+            Err(_) => self.unsupported(context, lv),
+        }
+        body
+    }
+
+    fn action(&mut self, label: &str, out: &mut String) {
+        let domain = self.domain;
+        let command = &domain.commands[label];
+        let context = format!("command {}", label);
+        let params = self.params(command.get_parameters(), &context);
+        let model = match command.get_model(&ModelKind::PlanModel) {
+            Some(model) => model,
+            None => return self.unsupported(&context, "a command without model"),
+        };
+        let body = self.lambda_body(&model, &context);
+
+        match &body.duration {
+            None => {
+                writeln!(out, "  (:action {}", label).unwrap();
+                writeln!(out, "    :parameters ({})", Self::format_params(&params)).unwrap();
+                writeln!(out, "    :precondition {}", conjunction(body.conditions)).unwrap();
+                writeln!(out, "    :effect {})", conjunction(body.effects)).unwrap();
+            }
+            Some(duration) => {
+                self.requirements.insert(":durative-actions");
+                //Conditions are checked at the start of the action.
+                let at = |when: &str, lv: LValue| -> LValue { list!["at".into(), when.into(), lv] };
+                let conditions = body
+                    .conditions
+                    .into_iter()
+                    .map(|c| at("start", c))
+                    .collect();
+                let effects = body
+                    .effects
+                    .into_iter()
+                    .map(|e| at("start", e))
+                    .chain(body.end_effects.into_iter().map(|e| at("end", e)))
+                    .collect();
+                writeln!(out, "  (:durative-action {}", label).unwrap();
+                writeln!(out, "    :parameters ({})", Self::format_params(&params)).unwrap();
+                writeln!(out, "    :duration (= ?duration {})", duration).unwrap();
+                writeln!(out, "    :condition {}", conjunction(conditions)).unwrap();
+                writeln!(out, "    :effect {})", conjunction(effects)).unwrap();
+            }
+        }
+    }
+
+    fn method(&mut self, label: &str, out: &mut String) {
+        let domain = self.domain;
+        let method = &domain.methods[label];
+        let context = format!("method {}", label);
+        let params = self.params(&method.parameters, &context);
+        let task = match domain.tasks.get(&method.task_label) {
+            Some(task) => task,
+            None => return self.unsupported(&context, format!("task {}", method.task_label)),
+        };
+        let mut task_expr = vec![method.task_label.as_str().into()];
+        task_expr.extend(
+            params[..task.get_parameters().get_number().min(params.len())]
+                .iter()
+                .map(|(p, _)| LValue::from(p.as_str())),
+        );
+        let body = self.lambda_body(&method.lambda_body, &context);
+        if !body.effects.is_empty() || !body.end_effects.is_empty() {
+            self.unsupported(&context, "an effect in a method")
+        }
+
+        writeln!(out, "  (:method {}", label).unwrap();
+        writeln!(out, "    :parameters ({})", Self::format_params(&params)).unwrap();
+        writeln!(out, "    :task {}", LValue::from(task_expr)).unwrap();
+        writeln!(out, "    :precondition {}", conjunction(body.conditions)).unwrap();
+        let subtasks: Vec<LValue> = body
+            .subtasks
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| list![format!("task{}", i).into(), t])
+            .collect();
+        writeln!(out, "    :ordered-subtasks {})", conjunction(subtasks)).unwrap();
+    }
+
+    fn export_domain(&mut self) -> String {
+        let domain = self.domain;
+        let mut body = String::new();
+        let hierarchical = !domain.tasks.is_empty();
+
+        let mut by_parent: BTreeMap<&String, Vec<&String>> = BTreeMap::new();
+        for (t, parent) in &self.types {
+            by_parent.entry(parent).or_default().push(t);
+        }
+        body.push_str("  (:types");
+        for (parent, types) in by_parent {
+            for t in types {
+                write!(body, " {}", t).unwrap();
+            }
+            write!(body, " - {}", parent).unwrap();
+        }
+        body.push_str(")\n");
+
+        let mut predicates = String::new();
+        let mut functions = String::new();
+        let mut labels: Vec<&String> = self.state_functions.keys().collect();
+        labels.sort();
+        for label in labels {
+            let (params, kind) = &self.state_functions[label];
+            match kind {
+                SfKind::Predicate => {
+                    writeln!(
+                        predicates,
+                        "    ({} {})",
+                        label,
+                        Self::format_params(params)
+                    )
+                }
+                SfKind::Numeric => {
+                    writeln!(functions, "    ({} {})", label, Self::format_params(params))
+                }
+                SfKind::Object(t) => {
+                    let mut params = params.clone();
+                    params.push(("?value".to_string(), t.to_string()));
+                    writeln!(
+                        predicates,
+                        "    ({} {})",
+                        label,
+                        Self::format_params(&params)
+                    )
+                }
+            }
+            .unwrap();
+        }
+        write!(body, "  (:predicates\n{}  )\n", predicates).unwrap();
+        if !functions.is_empty() {
+            write!(body, "  (:functions\n{}  )\n", functions).unwrap();
+        }
+
+        let mut labels: Vec<&String> = domain.tasks.keys().collect();
+        labels.sort();
+        for label in labels {
+            let task = &domain.tasks[label];
+            let context = format!("task {}", label);
+            let params = self.params(task.get_parameters(), &context);
+            writeln!(
+                body,
+                "  (:task {} :parameters ({}))",
+                label,
+                Self::format_params(&params)
+            )
+            .unwrap();
+            if task.get_model(&ModelKind::PlanModel).is_some() {
+                self.unsupported(&context, "the model of a task")
+            }
+        }
+
+        let mut labels: Vec<&String> = domain.methods.keys().collect();
+        labels.sort();
+        for label in labels {
+            self.method(label, &mut body);
+        }
+        let mut labels: Vec<&String> = domain.commands.keys().collect();
+        labels.sort();
+        for label in labels {
+            self.action(label, &mut body);
+        }
+
+        let mut domain = format!("(define (domain {})\n", PDDL_DOMAIN_NAME);
+        let mut requirements: Vec<&str> = vec![":typing", ":equality"];
+        if hierarchical {
+            requirements.push(":hierarchy")
+        }
+        requirements.extend(self.requirements.iter());
+        writeln!(domain, "  (:requirements {})", requirements.join(" ")).unwrap();
+        domain.push_str(&body);
+        domain.push_str(")\n");
+        domain
+    }
+
+    fn export_problem(&mut self, state: &WorldStateSnapshot, tasks: &[LValue]) -> String {
+        let hierarchical = !self.domain.tasks.is_empty();
+        let mut problem = format!(
+            "(define (problem {})\n  (:domain {})\n  (:objects",
+            PDDL_PROBLEM_NAME, PDDL_DOMAIN_NAME
+        );
+        let mut types: Vec<&String> = state.instance.inner.keys().collect();
+        types.sort();
+        for t in types {
+            let mut objects: Vec<&String> = state.instance.inner[t].elements.iter().collect();
+            if objects.is_empty() {
+                continue;
+            }
+            objects.sort();
+            for o in objects {
+                write!(problem, " {}", o).unwrap();
+            }
+            write!(problem, " - {}", self.pddl_type(t)).unwrap();
+        }
+        problem.push_str(")\n");
+
+        if hierarchical && !tasks.is_empty() {
+            let subtasks: Vec<LValue> = tasks
+                .iter()
+                .enumerate()
+                .map(|(i, t)| list![format!("task{}", i).into(), t.clone()])
+                .collect();
+            writeln!(
+                problem,
+                "  (:htn :ordered-subtasks {})",
+                conjunction(subtasks)
+            )
+            .unwrap();
+        }
+
+        let mut init: BTreeSet<String> = BTreeSet::new();
+        let facts = state
+            .get_state(Some(StateType::Static))
+            .union(&state.get_state(Some(StateType::Dynamic)));
+        for (key, fact) in facts.inner {
+            let key: LValue = key.into();
+            let value: LValue = fact.value.into();
+            let name = match &key {
+                LValue::List(list) => list[0].clone(),
+                key => key.clone(),
+            };
+            let context = format!("fact {}", key);
+            let fact = match self.sf_call(&key) {
+                Some((args, SfKind::Predicate)) => match value {
+                    LValue::True => Self::atom(&name, args),
+                    LValue::Nil => continue,
+                    value => Err(value),
+                },
+                Some((args, SfKind::Numeric)) => Self::atom(&name, args)
+                    .and_then(|atom| Ok(list![EQ.into(), atom, self.numeric(&value)?])),
+                Some((args, SfKind::Object(_))) => {
+                    let mut args = args.to_vec();
+                    args.push(value);
+                    Self::atom(&name, &args)
+                }
+                None => Err(name.clone()),
+            };
+            match fact {
+                Ok(fact) => {
+                    init.insert(fact.to_string());
+                }
+                Err(construct) => self.unsupported(&context, construct),
+            }
+        }
+        problem.push_str("  (:init\n");
+        for fact in init {
+            writeln!(problem, "    {}", fact).unwrap();
+        }
+        problem.push_str("  )\n");
+        if !hierarchical {
+            problem.push_str("  (:goal (and))\n");
+        }
+        problem.push_str(")\n");
+        problem
+    }
+}
+
+/// Exports the domain of OMPAS and the current state in PDDL 2.1, or in HDDL if the domain defines tasks.
+/// The HDDL problem decomposes the given tasks.
+/// State functions whose values are objects are exported as predicates with an additional parameter for the value.
+pub fn export_pddl(
+    domain: &OMPASDomain,
+    state: &WorldStateSnapshot,
+    tasks: &[LValue],
+) -> PddlExport {
+    let mut exporter = PddlExporter::new(domain, state);
+    let domain_str = exporter.export_domain();
+    let problem = exporter.export_problem(state, tasks);
+    PddlExport {
+        hierarchical: !domain.tasks.is_empty(),
+        domain: domain_str,
+        problem,
+        unsupported: exporter.unsupported,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::acting_domain::pddl::import::{PddlDomain, PddlProblem};
+    use crate::model::acting_domain::pddl::parse_pddl;
+    use crate::ompas::scheme::monitor::model::ModModel;
+    use crate::ompas::scheme::monitor::ModMonitor;
+    use sompas_core::{eval, eval_init, get_root_env, parse};
+    use sompas_modules::ModExtendedStd;
+    use sompas_structs::lenv::ImportType::WithoutPrefix;
+    use sompas_structs::lruntimeerror::LRuntimeError;
+    use std::fs;
+
+    const GRIPPER: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../domains/gripper/");
+
+    /// Command whose parameters are not prefixed by `?`.
+    const STAY: &str = "(begin
+        (def-command stay (:params (r room)))
+        (def-command-pddl-model stay
+            (:params (r room))
+            (:pre-conditions (= (at-robby) r))
+            (:effects ('at-robby r))))";
+
+    /// Returns the symbols of an expression.
+    fn symbols(lv: &LValue, symbols: &mut Vec<String>) {
+        match lv {
+            LValue::Symbol(s) => symbols.push(s.to_string()),
+            LValue::List(list) => list.iter().for_each(|e| self::symbols(e, symbols)),
+            _ => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_gripper() -> Result<(), LRuntimeError> {
+        let monitor = ModMonitor::default();
+        let mod_model = ModModel::new(&monitor);
+        let mut env = get_root_env().await;
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        env.import_module(ModModel::new(&monitor), WithoutPrefix);
+        eval_init(&mut env).await;
+        let mut definitions = vec![];
+        for file in ["base.scm", "om.scm"] {
+            definitions.push(fs::read_to_string(format!("{}{}", GRIPPER, file)).unwrap());
+        }
+        definitions.push(STAY.to_string());
+        for definition in &definitions {
+            let lv = parse(definition, &mut env).await?;
+            eval(&lv, &mut env, None).await?;
+        }
+
+        let domain = mod_model.domain_manager.get_inner().await;
+        let state = mod_model.get_plan_state().await;
+        let export = export_pddl(&domain, &state, &[]);
+        assert!(export.hierarchical);
+
+        //The export is parsed back.
+        let pddl_domain: PddlDomain = (&parse_pddl(&export.domain)?).try_into()?;
+        let pddl_problem: PddlProblem = (&parse_pddl(&export.problem)?).try_into()?;
+        assert_eq!(pddl_problem.name, PDDL_PROBLEM_NAME);
+
+        let mut labels: Vec<&str> = pddl_domain
+            .actions
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect();
+        labels.sort();
+        assert_eq!(labels, vec!["drop", "move", "pick", "stay"]);
+        assert_eq!(pddl_domain.methods.len(), 5);
+        for action in &pddl_domain.actions {
+            let mut used = vec![];
+            for lv in action
+                .conditions
+                .iter()
+                .chain(&action.effects)
+                .chain(&action.end_effects)
+            {
+                symbols(lv, &mut used);
+            }
+            for p in &action.params {
+                assert!(p.symbol.starts_with('?'), "{} of {}", p.symbol, action.name);
+                assert!(
+                    !used.contains(&p.symbol.trim_start_matches('?').to_string()),
+                    "{} is not a variable in {}",
+                    p.symbol,
+                    action.name
+                );
+            }
+        }
+        let stay = pddl_domain
+            .actions
+            .iter()
+            .find(|a| a.name == "stay")
+            .unwrap();
+        assert_eq!(stay.params[0].symbol, "?r");
+        assert_eq!(stay.conditions[0].to_string(), "(at-robby ?r)");
+        assert_eq!(stay.effects[1].to_string(), "(at-robby ?r)");
+        Ok(())
+    }
+}
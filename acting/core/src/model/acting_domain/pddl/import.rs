@@ -0,0 +1,1036 @@
+use crate::model::acting_domain::pddl::{
+    parse_keywords, parse_typed_list, parse_typed_symbols, TypedSymbol, PDDL_NUMBER, PDDL_OBJECT,
+};
+use ompas_language::exec::state::{DURATIVE, INSTANCES};
+use ompas_language::monitor::model::{
+    BODY, COST, DEF_COMMAND, DEF_COMMAND_PDDL_MODEL, DEF_FACTS, DEF_FUNCTION, DEF_METHOD,
+    DEF_OBJECTS, DEF_STATE_FUNCTION, DEF_STATIC_FACTS, DEF_TASK, DEF_TYPES, EFFECTS, PARAMETERS,
+    PRE_CONDITIONS, RESULT, TASK,
+};
+use sompas_language::basic_math::{NEQ, NOT_SHORT};
+use sompas_language::first_order_logic::{EXISTS, FORALL};
+use sompas_language::utils::{AND, OR};
+use sompas_structs::list;
+use sompas_structs::lprimitive::LPrimitive;
+use sompas_structs::lruntimeerror;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalue::LValue;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+
+const BOOLEAN: &str = "boolean";
+
+/// Signature of a predicate, a function or a task.
+#[derive(Clone, Debug)]
+pub struct Signature {
+    pub name: String,
+    pub params: Vec<TypedSymbol>,
+}
+
+impl TryFrom<&LValue> for Signature {
+    type Error = LRuntimeError;
+
+    fn try_from(lv: &LValue) -> Result<Self, Self::Error> {
+        match lv {
+            LValue::List(list) => Ok(Self {
+                name: arg(list, 0)?.to_string(),
+                params: parse_typed_symbols(&list[1..].to_vec().into())?,
+            }),
+            LValue::Symbol(s) => Ok(Self {
+                name: s.to_string(),
+                params: vec![],
+            }),
+            lv => Err(lruntimeerror!(
+                "Signature::try_from",
+                format!("expected a signature, got {}", lv)
+            )),
+        }
+    }
+}
+
+/// Action of a PDDL domain. The effects of durative actions occurring at their end are durative effects.
+#[derive(Clone, Debug)]
+pub struct PddlAction {
+    pub name: String,
+    pub params: Vec<TypedSymbol>,
+    pub duration: Option<LValue>,
+    pub conditions: Vec<LValue>,
+    pub effects: Vec<LValue>,
+    pub end_effects: Vec<LValue>,
+}
+
+/// Method of an HDDL domain, its subtasks being ordered following the orderings of the method.
+#[derive(Clone, Debug)]
+pub struct PddlMethod {
+    pub name: String,
+    pub params: Vec<TypedSymbol>,
+    pub task: Vec<LValue>,
+    pub conditions: Vec<LValue>,
+    pub subtasks: Vec<LValue>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PddlDomain {
+    pub name: String,
+    pub types: Vec<TypedSymbol>,
+    pub constants: Vec<TypedSymbol>,
+    pub predicates: Vec<Signature>,
+    pub functions: Vec<(Signature, String)>,
+    pub tasks: Vec<Signature>,
+    pub actions: Vec<PddlAction>,
+    pub methods: Vec<PddlMethod>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PddlProblem {
+    pub name: String,
+    pub objects: Vec<TypedSymbol>,
+    pub init: Vec<LValue>,
+    pub goal: Option<LValue>,
+    pub tasks: Vec<LValue>,
+}
+
+/// Returns the sections of a description of the form `(define (<kind> <name>) sections...)`.
+fn parse_define<'a>(lv: &'a LValue, kind: &str) -> Result<(String, &'a [LValue]), LRuntimeError> {
+    if let LValue::List(list) = lv {
+        if list.len() >= 2 && list[0].to_string() == "define" {
+            if let LValue::List(header) = &list[1] {
+                if header.len() == 2 && header[0].to_string() == kind {
+                    return Ok((header[1].to_string(), &list[2..]));
+                }
+            }
+        }
+    }
+    Err(lruntimeerror!(
+        "parse_define",
+        format!("expected (define ({} <name>) ...)", kind)
+    ))
+}
+
+/// Returns the i-th element of an expression, or an error if the expression is too short.
+fn arg(list: &[LValue], i: usize) -> Result<&LValue, LRuntimeError> {
+    list.get(i).ok_or_else(|| {
+        lruntimeerror!(
+            "pddl::import",
+            format!("missing argument {} in {}", i, LValue::from(list.to_vec()))
+        )
+    })
+}
+
+/// Returns the conjuncts of a formula.
+fn conjuncts(lv: &LValue) -> Result<Vec<LValue>, LRuntimeError> {
+    match lv {
+        LValue::Nil => Ok(vec![]),
+        LValue::List(list) if arg(list, 0)?.to_string() == AND => {
+            let mut conjuncts_list = vec![];
+            for c in &list[1..] {
+                conjuncts_list.append(&mut conjuncts(c)?);
+            }
+            Ok(conjuncts_list)
+        }
+        lv => Ok(vec![lv.clone()]),
+    }
+}
+
+/// Returns the subtasks of a task network, ordered following its ordering constraints.
+fn parse_task_network(map: &HashMap<String, LValue>) -> Result<Vec<LValue>, LRuntimeError> {
+    let (subtasks, ordered) = if let Some(subtasks) = map
+        .get(":ordered-subtasks")
+        .or_else(|| map.get(":ordered-tasks"))
+    {
+        (subtasks, true)
+    } else if let Some(subtasks) = map.get(":subtasks").or_else(|| map.get(":tasks")) {
+        (subtasks, false)
+    } else {
+        return Ok(vec![]);
+    };
+
+    let mut ids: Vec<Option<String>> = vec![];
+    let mut tasks: Vec<LValue> = vec![];
+    for subtask in conjuncts(subtasks)? {
+        match &subtask {
+            LValue::List(list) if list.len() == 2 && matches!(list[1], LValue::List(_)) => {
+                ids.push(Some(list[0].to_string()));
+                tasks.push(list[1].clone());
+            }
+            _ => {
+                ids.push(None);
+                tasks.push(subtask);
+            }
+        }
+    }
+
+    if ordered {
+        return Ok(tasks);
+    }
+
+    let mut predecessors: Vec<HashSet<usize>> = vec![Default::default(); tasks.len()];
+    let index = |id: &LValue| -> Result<usize, LRuntimeError> {
+        ids.iter()
+            .position(|i| i.as_deref() == Some(id.to_string().as_str()))
+            .ok_or_else(|| {
+                lruntimeerror!(
+                    "parse_task_network",
+                    format!("unknown subtask id {} in ordering", id)
+                )
+            })
+    };
+    for ordering in conjuncts(map.get(":ordering").unwrap_or(&LValue::Nil))? {
+        match &ordering {
+            LValue::List(list) if list.len() == 3 && list[0].to_string() == "<" => {
+                predecessors[index(&list[2])?].insert(index(&list[1])?);
+            }
+            lv => {
+                return Err(lruntimeerror!(
+                    "parse_task_network",
+                    format!("ordering {} is not supported", lv)
+                ))
+            }
+        }
+    }
+
+    //Subtasks are sequenced following a topological order keeping the order of declaration.
+    let mut sequenced: Vec<usize> = vec![];
+    while sequenced.len() < tasks.len() {
+        let next = (0..tasks.len())
+            .find(|i| {
+                !sequenced.contains(i) && predecessors[*i].iter().all(|p| sequenced.contains(p))
+            })
+            .ok_or_else(|| {
+                lruntimeerror!("parse_task_network", "cycle in the ordering of subtasks")
+            })?;
+        sequenced.push(next);
+    }
+    Ok(sequenced.drain(..).map(|i| tasks[i].clone()).collect())
+}
+
+fn parse_action(section: &[LValue], durative: bool) -> Result<PddlAction, LRuntimeError> {
+    let name = section
+        .get(1)
+        .ok_or_else(|| lruntimeerror!("parse_action", "missing name of action"))?
+        .to_string();
+    let map = parse_keywords(section, 2)?;
+    let params = parse_typed_symbols(map.get(":parameters").unwrap_or(&LValue::Nil))?;
+    if !durative {
+        return Ok(PddlAction {
+            name,
+            params,
+            duration: None,
+            conditions: conjuncts(map.get(":precondition").unwrap_or(&LValue::Nil))?,
+            effects: conjuncts(map.get(":effect").unwrap_or(&LValue::Nil))?,
+            end_effects: vec![],
+        });
+    }
+
+    let duration = match map.get(":duration") {
+        Some(LValue::List(d)) if d.len() == 3 && d[0].to_string() == "=" => d[2].clone(),
+        d => {
+            return Err(lruntimeerror!(
+                "parse_action",
+                format!(
+                    "duration {} of {} is not supported",
+                    d.cloned().unwrap_or_default(),
+                    name
+                )
+            ))
+        }
+    };
+
+    //Conditions are all checked at the start of the action.
+    let mut conditions = vec![];
+    for c in conjuncts(map.get(":condition").unwrap_or(&LValue::Nil))? {
+        match &c {
+            LValue::List(list)
+                if list.len() == 3
+                    && matches!(
+                        (list[0].to_string().as_str(), list[1].to_string().as_str()),
+                        ("at", "start") | ("at", "end") | ("over", "all")
+                    ) =>
+            {
+                conditions.append(&mut conjuncts(&list[2])?)
+            }
+            lv => {
+                return Err(lruntimeerror!(
+                    "parse_action",
+                    format!("condition {} of {} is not supported", lv, name)
+                ))
+            }
+        }
+    }
+
+    let mut effects = vec![];
+    let mut end_effects = vec![];
+    for e in conjuncts(map.get(":effect").unwrap_or(&LValue::Nil))? {
+        match &e {
+            LValue::List(list) if list.len() == 3 && list[0].to_string() == "at" => {
+                match list[1].to_string().as_str() {
+                    "start" => effects.append(&mut conjuncts(&list[2])?),
+                    "end" => end_effects.append(&mut conjuncts(&list[2])?),
+                    _ => {
+                        return Err(lruntimeerror!(
+                            "parse_action",
+                            format!("effect {} of {} is not supported", e, name)
+                        ))
+                    }
+                }
+            }
+            lv => {
+                return Err(lruntimeerror!(
+                    "parse_action",
+                    format!("effect {} of {} is not supported", lv, name)
+                ))
+            }
+        }
+    }
+
+    Ok(PddlAction {
+        name,
+        params,
+        duration: Some(duration),
+        conditions,
+        effects,
+        end_effects,
+    })
+}
+
+fn parse_method(section: &[LValue]) -> Result<PddlMethod, LRuntimeError> {
+    let name = section
+        .get(1)
+        .ok_or_else(|| lruntimeerror!("parse_method", "missing name of method"))?
+        .to_string();
+    let map = parse_keywords(section, 2)?;
+    let task = match map.get(":task") {
+        Some(LValue::List(task)) => task.to_vec(),
+        Some(LValue::Symbol(task)) => vec![task.as_str().into()],
+        _ => {
+            return Err(lruntimeerror!(
+                "parse_method",
+                format!("missing :task in method {}", name)
+            ))
+        }
+    };
+    let mut conditions = conjuncts(map.get(":precondition").unwrap_or(&LValue::Nil))?;
+    conditions.append(&mut conjuncts(
+        map.get(":constraints").unwrap_or(&LValue::Nil),
+    )?);
+    Ok(PddlMethod {
+        name,
+        params: parse_typed_symbols(map.get(":parameters").unwrap_or(&LValue::Nil))?,
+        task,
+        conditions,
+        subtasks: parse_task_network(&map)?,
+    })
+}
+
+impl TryFrom<&LValue> for PddlDomain {
+    type Error = LRuntimeError;
+
+    fn try_from(lv: &LValue) -> Result<Self, Self::Error> {
+        let (name, sections) = parse_define(lv, "domain")?;
+        let mut domain = PddlDomain {
+            name,
+            ..Default::default()
+        };
+
+        for section in sections {
+            let section = match section {
+                LValue::List(list) => list.as_slice(),
+                lv => {
+                    return Err(lruntimeerror!(
+                        "PddlDomain::try_from",
+                        format!("expected a section, got {}", lv)
+                    ))
+                }
+            };
+            let head = arg(section, 0)?.to_string();
+            let args: LValue = section[1..].to_vec().into();
+            match head.as_str() {
+                ":requirements" => {}
+                ":types" => domain.types = parse_typed_symbols(&args)?,
+                ":constants" => domain.constants = parse_typed_symbols(&args)?,
+                ":predicates" => {
+                    for p in &section[1..] {
+                        domain.predicates.push(p.try_into()?)
+                    }
+                }
+                ":functions" => {
+                    for (f, t) in parse_typed_list(&args, PDDL_NUMBER)? {
+                        domain.functions.push(((&f).try_into()?, t))
+                    }
+                }
+                ":tasks" => {
+                    for t in &section[1..] {
+                        domain.tasks.push(t.try_into()?)
+                    }
+                }
+                ":task" => {
+                    let map = parse_keywords(section, 2)?;
+                    domain.tasks.push(Signature {
+                        name: arg(section, 1)?.to_string(),
+                        params: parse_typed_symbols(
+                            map.get(":parameters").unwrap_or(&LValue::Nil),
+                        )?,
+                    })
+                }
+                ":action" => domain.actions.push(parse_action(section, false)?),
+                ":durative-action" => domain.actions.push(parse_action(section, true)?),
+                ":method" => domain.methods.push(parse_method(section)?),
+                s => {
+                    return Err(lruntimeerror!(
+                        "PddlDomain::try_from",
+                        format!("section {} is not supported", s)
+                    ))
+                }
+            }
+        }
+        Ok(domain)
+    }
+}
+
+impl TryFrom<&LValue> for PddlProblem {
+    type Error = LRuntimeError;
+
+    fn try_from(lv: &LValue) -> Result<Self, Self::Error> {
+        let (name, sections) = parse_define(lv, "problem")?;
+        let mut problem = PddlProblem {
+            name,
+            ..Default::default()
+        };
+
+        for section in sections {
+            let section = match section {
+                LValue::List(list) => list.as_slice(),
+                lv => {
+                    return Err(lruntimeerror!(
+                        "PddlProblem::try_from",
+                        format!("expected a section, got {}", lv)
+                    ))
+                }
+            };
+            match arg(section, 0)?.to_string().as_str() {
+                ":domain" | ":requirements" => {}
+                ":objects" => problem.objects = parse_typed_symbols(&section[1..].to_vec().into())?,
+                ":init" => problem.init = section[1..].to_vec(),
+                ":goal" => problem.goal = section.get(1).cloned(),
+                ":htn" => problem.tasks = parse_task_network(&parse_keywords(section, 1)?)?,
+                s => {
+                    return Err(lruntimeerror!(
+                        "PddlProblem::try_from",
+                        format!("section {} is not supported", s)
+                    ))
+                }
+            }
+        }
+        Ok(problem)
+    }
+}
+
+/// Translation of a PDDL or HDDL domain and problem into the definitions of an OMPAS domain.
+struct PddlImport<'a> {
+    domain: &'a PddlDomain,
+    /// Predicates and functions modified by no action.
+    statics: HashSet<String>,
+    /// Predicates appearing negatively in conditions.
+    negated: HashSet<String>,
+}
+
+impl<'a> PddlImport<'a> {
+    fn new(domain: &'a PddlDomain) -> Result<Self, LRuntimeError> {
+        let mut dynamics = HashSet::new();
+        let mut negated = HashSet::new();
+        for action in &domain.actions {
+            for e in action.effects.iter().chain(action.end_effects.iter()) {
+                if let Some(name) = Self::effect_target(e)? {
+                    dynamics.insert(name);
+                }
+            }
+        }
+        for c in domain
+            .actions
+            .iter()
+            .flat_map(|a| a.conditions.iter())
+            .chain(domain.methods.iter().flat_map(|m| m.conditions.iter()))
+        {
+            Self::collect_negated(c, false, &mut negated)?;
+        }
+        let statics = domain
+            .predicates
+            .iter()
+            .map(|p| &p.name)
+            .chain(domain.functions.iter().map(|(f, _)| &f.name))
+            .filter(|n| !dynamics.contains(*n))
+            .cloned()
+            .collect();
+        Ok(Self {
+            domain,
+            statics,
+            negated,
+        })
+    }
+
+    /// Returns the name of the predicate or function modified by an effect.
+    fn effect_target(e: &LValue) -> Result<Option<String>, LRuntimeError> {
+        let mut e = e;
+        loop {
+            match e {
+                LValue::List(list) => match arg(list, 0)?.to_string().as_str() {
+                    "not" | "assign" | "increase" | "decrease" | "scale-up" | "scale-down" => {
+                        e = arg(list, 1)?
+                    }
+                    name => return Ok(Some(name.to_string())),
+                },
+                LValue::Symbol(s) => return Ok(Some(s.to_string())),
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    fn collect_negated(
+        c: &LValue,
+        negative: bool,
+        negated: &mut HashSet<String>,
+    ) -> Result<(), LRuntimeError> {
+        if let LValue::List(list) = c {
+            match arg(list, 0)?.to_string().as_str() {
+                "not" => Self::collect_negated(arg(list, 1)?, !negative, negated)?,
+                "imply" => {
+                    Self::collect_negated(arg(list, 1)?, !negative, negated)?;
+                    Self::collect_negated(arg(list, 2)?, negative, negated)?;
+                }
+                "and" | "or" => {
+                    for c in &list[1..] {
+                        Self::collect_negated(c, negative, negated)?;
+                    }
+                }
+                "exists" | "forall" => Self::collect_negated(arg(list, 2)?, negative, negated)?,
+                name => {
+                    if negative {
+                        negated.insert(name.to_string());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn params(params: &[TypedSymbol]) -> LValue {
+        let mut list: Vec<LValue> = vec![PARAMETERS.into()];
+        for p in params {
+            list.push(list![p.symbol.as_str().into(), p.tpe.as_str().into()]);
+        }
+        list.into()
+    }
+
+    fn condition(c: &LValue) -> Result<LValue, LRuntimeError> {
+        let list = match c {
+            LValue::List(list) => list,
+            LValue::Nil => return Ok(LValue::True),
+            lv => return Ok(lv.clone()),
+        };
+        let head = arg(list, 0)?.to_string();
+        let args = &list[1..];
+        let translate = |head: &str, args: &[LValue]| -> Result<LValue, LRuntimeError> {
+            let mut expr: Vec<LValue> = vec![head.into()];
+            for c in args {
+                expr.push(Self::condition(c)?)
+            }
+            Ok(expr.into())
+        };
+        Ok(match head.as_str() {
+            "and" => translate(AND, args)?,
+            "or" => translate(OR, args)?,
+            "not" => match arg(args, 0)? {
+                LValue::List(eq) if arg(eq, 0)?.to_string() == "=" => {
+                    let mut expr = eq.to_vec();
+                    expr[0] = NEQ.into();
+                    expr.into()
+                }
+                c => list![NOT_SHORT.into(), Self::condition(c)?],
+            },
+            "imply" => list![
+                OR.into(),
+                list![NOT_SHORT.into(), Self::condition(arg(args, 0)?)?],
+                Self::condition(arg(args, 1)?)?
+            ],
+            q @ ("exists" | "forall") => {
+                let q = if q == "exists" { EXISTS } else { FORALL };
+                let mut expr = Self::condition(arg(args, 1)?)?;
+                for var in parse_typed_symbols(arg(args, 0)?)?.iter().rev() {
+                    expr = list![
+                        q.into(),
+                        list![INSTANCES.into(), var.tpe.as_str().into()],
+                        list![
+                            LPrimitive::DefLambda.into(),
+                            list![var.symbol.as_str().into()],
+                            expr
+                        ]
+                    ];
+                }
+                expr
+            }
+            _ => c.clone(),
+        })
+    }
+
+    fn conditions(conditions: &[LValue]) -> Result<LValue, LRuntimeError> {
+        let mut list: Vec<LValue> = vec![PRE_CONDITIONS.into()];
+        for c in conditions {
+            list.push(Self::condition(c)?);
+        }
+        if list.len() == 1 {
+            list.push(LValue::True);
+        }
+        Ok(list.into())
+    }
+
+    /// Translates an effect into the update of a fact, durative if a duration is given.
+    fn effect(
+        sf: &LValue,
+        value: LValue,
+        duration: Option<&LValue>,
+    ) -> Result<LValue, LRuntimeError> {
+        let (name, args) = match sf {
+            LValue::List(list) => (arg(list, 0)?.clone(), list[1..].to_vec()),
+            LValue::Symbol(_) => (sf.clone(), vec![]),
+            lv => {
+                return Err(lruntimeerror!(
+                    "PddlImport::effect",
+                    format!("{} can not be updated", lv)
+                ))
+            }
+        };
+        let mut expr: Vec<LValue> = vec![];
+        if let Some(d) = duration {
+            expr.push(DURATIVE.into());
+            expr.push(d.clone());
+        }
+        expr.push(list![LPrimitive::Quote.into(), name]);
+        expr.extend(args);
+        expr.push(value);
+        Ok(expr.into())
+    }
+
+    /// Translates effects, negative effects being applied first as in PDDL.
+    fn effects(
+        effects: &[LValue],
+        duration: Option<&LValue>,
+    ) -> Result<Vec<LValue>, LRuntimeError> {
+        let mut deletes = vec![];
+        let mut others = vec![];
+        for e in effects {
+            let list = match e {
+                LValue::List(list) => list,
+                e => {
+                    others.push(Self::effect(e, LValue::True, duration)?);
+                    continue;
+                }
+            };
+            let op = |operator: &str| -> Result<LValue, LRuntimeError> {
+                Ok(list![
+                    operator.into(),
+                    arg(list, 1)?.clone(),
+                    arg(list, 2)?.clone()
+                ])
+            };
+            match arg(list, 0)?.to_string().as_str() {
+                "not" => deletes.push(Self::effect(arg(list, 1)?, LValue::Nil, duration)?),
+                "assign" => others.push(Self::effect(
+                    arg(list, 1)?,
+                    arg(list, 2)?.clone(),
+                    duration,
+                )?),
+                "increase" => others.push(Self::effect(arg(list, 1)?, op("+")?, duration)?),
+                "decrease" => others.push(Self::effect(arg(list, 1)?, op("-")?, duration)?),
+                "scale-up" => others.push(Self::effect(arg(list, 1)?, op("*")?, duration)?),
+                "scale-down" => others.push(Self::effect(arg(list, 1)?, op("/")?, duration)?),
+                "forall" | "when" => {
+                    return Err(lruntimeerror!(
+                        "PddlImport::effects",
+                        format!("effect {} is not supported", e)
+                    ))
+                }
+                _ => others.push(Self::effect(e, LValue::True, duration)?),
+            }
+        }
+        deletes.append(&mut others);
+        Ok(deletes)
+    }
+
+    fn types(&self) -> LValue {
+        let parents: HashMap<&str, &str> = self
+            .domain
+            .types
+            .iter()
+            .map(|t| (t.symbol.as_str(), t.tpe.as_str()))
+            .collect();
+        let mut declared: Vec<&str> = vec![PDDL_OBJECT];
+        let mut expr: Vec<LValue> = vec![DEF_TYPES.into(), PDDL_OBJECT.into()];
+        //Parents are declared before their subtypes.
+        for t in &self.domain.types {
+            let mut branch = vec![];
+            let mut t = t.symbol.as_str();
+            while !declared.contains(&t) && !branch.contains(&t) {
+                branch.push(t);
+                t = parents.get(t).copied().unwrap_or(PDDL_OBJECT);
+            }
+            for t in branch.drain(..).rev() {
+                let parent = parents.get(t).copied().unwrap_or(PDDL_OBJECT);
+                expr.push(list![t.into(), parent.into()]);
+                declared.push(t);
+            }
+        }
+        expr.into()
+    }
+
+    fn objects(objects: &[TypedSymbol]) -> Option<LValue> {
+        if objects.is_empty() {
+            return None;
+        }
+        let mut by_type: Vec<(&str, Vec<LValue>)> = vec![];
+        for o in objects {
+            match by_type.iter_mut().find(|(t, _)| *t == o.tpe.as_str()) {
+                Some((_, list)) => list.push(o.symbol.as_str().into()),
+                None => by_type.push((o.tpe.as_str(), vec![o.symbol.as_str().into()])),
+            }
+        }
+        let mut expr: Vec<LValue> = vec![DEF_OBJECTS.into()];
+        for (t, mut list) in by_type {
+            list.push(t.into());
+            expr.push(list.into());
+        }
+        Some(expr.into())
+    }
+
+    fn state_function(&self, sf: &Signature, result: &str) -> LValue {
+        let def = if self.statics.contains(&sf.name) {
+            DEF_FUNCTION
+        } else {
+            DEF_STATE_FUNCTION
+        };
+        let mut expr: Vec<LValue> = vec![def.into(), sf.name.as_str().into()];
+        if !sf.params.is_empty() {
+            expr.push(Self::params(&sf.params));
+        }
+        expr.push(list![RESULT.into(), result.into()]);
+        expr.into()
+    }
+
+    fn command(action: &PddlAction) -> Result<Vec<LValue>, LRuntimeError> {
+        let label: LValue = action.name.as_str().into();
+        let params = Self::params(&action.params);
+        let mut effects: Vec<LValue> = vec![EFFECTS.into()];
+        effects.append(&mut Self::effects(&action.effects, None)?);
+        effects.append(&mut Self::effects(
+            &action.end_effects,
+            action.duration.as_ref(),
+        )?);
+        Ok(vec![
+            list![DEF_COMMAND.into(), label.clone(), params.clone()],
+            list![
+                DEF_COMMAND_PDDL_MODEL.into(),
+                label,
+                params,
+                Self::conditions(&action.conditions)?,
+                effects.into()
+            ],
+        ])
+    }
+
+    fn task(task: &Signature) -> LValue {
+        let mut expr: Vec<LValue> = vec![DEF_TASK.into(), task.name.as_str().into()];
+        if !task.params.is_empty() {
+            expr.push(Self::params(&task.params));
+        }
+        expr.into()
+    }
+
+    /// Translates an HDDL method. The parameters of an OMPAS method start with the parameters of its task,
+    /// arguments of the task that are constants or repeated variables being bound by new parameters.
+    fn method(&self, method: &PddlMethod) -> Result<LValue, LRuntimeError> {
+        let task_label = arg(&method.task, 0)?.to_string();
+        let task = self
+            .domain
+            .tasks
+            .iter()
+            .find(|t| t.name == task_label)
+            .ok_or_else(|| {
+                lruntimeerror!(
+                    "PddlImport::method",
+                    format!("task {} of {} is not declared", task_label, method.name)
+                )
+            })?;
+        if task.params.len() != method.task.len() - 1 {
+            return Err(lruntimeerror!(
+                "PddlImport::method",
+                format!(
+                    "wrong number of arguments for task {} in {}",
+                    task_label, method.name
+                )
+            ));
+        }
+
+        let mut params: Vec<TypedSymbol> = vec![];
+        let mut conditions = vec![];
+        for (i, (arg, task_param)) in method.task[1..].iter().zip(&task.params).enumerate() {
+            let arg = arg.to_string();
+            match method.params.iter().find(|p| p.symbol == arg) {
+                Some(p) if !params.contains(p) => params.push(p.clone()),
+                _ => {
+                    let p = format!("?_{}{}", task_param.symbol.trim_start_matches('?'), i);
+                    conditions.push(list!["=".into(), p.as_str().into(), arg.as_str().into()]);
+                    params.push(TypedSymbol {
+                        symbol: p,
+                        tpe: task_param.tpe.clone(),
+                    })
+                }
+            }
+        }
+        for p in &method.params {
+            if !params.contains(p) {
+                params.push(p.clone())
+            }
+        }
+        conditions.extend(method.conditions.iter().cloned());
+
+        let body = match method.subtasks.len() {
+            0 => LValue::Nil,
+            1 => method.subtasks[0].clone(),
+            _ => {
+                let mut body = vec![LPrimitive::Do.into()];
+                body.extend(method.subtasks.iter().cloned());
+                body.into()
+            }
+        };
+
+        Ok(list![
+            DEF_METHOD.into(),
+            method.name.as_str().into(),
+            list![TASK.into(), task_label.as_str().into()],
+            Self::params(&params),
+            Self::conditions(&conditions)?,
+            list![COST.into(), 0i64.into()],
+            list![BODY.into(), body]
+        ])
+    }
+
+    /// Returns the initial facts, static facts first.
+    /// Missing facts of predicates appearing negatively in conditions are false, following the closed world assumption.
+    fn facts(&self, problem: &PddlProblem) -> Result<(Vec<LValue>, Vec<LValue>), LRuntimeError> {
+        let mut statics = vec![];
+        let mut dynamics = vec![];
+        let mut known: HashSet<String> = HashSet::new();
+        let mut push = |key: LValue, value: LValue| -> Result<(), LRuntimeError> {
+            let name = match &key {
+                LValue::List(list) => arg(list, 0)?.to_string(),
+                key => key.to_string(),
+            };
+            let fact = list![key, value];
+            if self.statics.contains(&name) {
+                statics.push(fact)
+            } else {
+                dynamics.push(fact)
+            }
+            Ok(())
+        };
+
+        for fact in &problem.init {
+            let (key, value) = match fact {
+                LValue::List(list) if list.len() == 3 && list[0].to_string() == "=" => {
+                    (list[1].clone(), list[2].clone())
+                }
+                LValue::List(list)
+                    if list.len() == 3
+                        && list[0].to_string() == "at"
+                        && matches!(list[1], LValue::Number(_)) =>
+                {
+                    return Err(lruntimeerror!(
+                        "PddlImport::facts",
+                        format!("timed initial literal {} is not supported", fact)
+                    ))
+                }
+                LValue::List(_) | LValue::Symbol(_) => (fact.clone(), LValue::True),
+                lv => {
+                    return Err(lruntimeerror!(
+                        "PddlImport::facts",
+                        format!("expected a fact, got {}", lv)
+                    ))
+                }
+            };
+            let key = Self::fact_key(&key);
+            known.insert(key.to_string());
+            push(key, value)?;
+        }
+
+        let mut objects: Vec<&TypedSymbol> = self.domain.constants.iter().collect();
+        objects.extend(problem.objects.iter());
+        for p in self
+            .domain
+            .predicates
+            .iter()
+            .filter(|p| self.negated.contains(&p.name))
+        {
+            let mut keys: Vec<Vec<LValue>> = vec![vec![p.name.as_str().into()]];
+            for param in &p.params {
+                let instances: Vec<LValue> = objects
+                    .iter()
+                    .filter(|o| self.is_subtype(&o.tpe, &param.tpe))
+                    .map(|o| o.symbol.as_str().into())
+                    .collect();
+                keys = keys
+                    .drain(..)
+                    .flat_map(|key| {
+                        instances.iter().map(move |i| {
+                            let mut key = key.clone();
+                            key.push(i.clone());
+                            key
+                        })
+                    })
+                    .collect();
+            }
+            for key in keys {
+                let key = Self::fact_key(&key.into());
+                if !known.contains(&key.to_string()) {
+                    push(key, LValue::Nil)?;
+                }
+            }
+        }
+
+        Ok((statics, dynamics))
+    }
+
+    /// Facts of predicates and functions without parameters are keyed by their symbol.
+    fn fact_key(key: &LValue) -> LValue {
+        match key {
+            LValue::List(list) if list.len() == 1 => list[0].clone(),
+            key => key.clone(),
+        }
+    }
+
+    fn is_subtype(&self, t: &str, parent: &str) -> bool {
+        let mut t = t;
+        let mut depth = 0;
+        loop {
+            if t == parent || parent == PDDL_OBJECT {
+                return true;
+            }
+            match self.domain.types.iter().find(|tpe| tpe.symbol == t) {
+                Some(tpe) if depth < self.domain.types.len() => {
+                    t = tpe.tpe.as_str();
+                    depth += 1;
+                }
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// Translates a PDDL or HDDL domain and an optional problem into the SOMPAS expressions defining them in OMPAS.
+/// Predicates and functions modified by no action are declared as static functions.
+pub fn pddl_to_sompas(
+    domain: &PddlDomain,
+    problem: Option<&PddlProblem>,
+) -> Result<Vec<LValue>, LRuntimeError> {
+    let import = PddlImport::new(domain)?;
+    let mut definitions = vec![import.types()];
+    definitions.extend(PddlImport::objects(&domain.constants));
+    if let Some(problem) = problem {
+        definitions.extend(PddlImport::objects(&problem.objects));
+    }
+    for p in &domain.predicates {
+        definitions.push(import.state_function(p, BOOLEAN));
+    }
+    for (f, result) in &domain.functions {
+        definitions.push(import.state_function(f, result));
+    }
+    for action in &domain.actions {
+        definitions.append(&mut PddlImport::command(action)?);
+    }
+    for task in &domain.tasks {
+        definitions.push(PddlImport::task(task));
+    }
+    for method in &domain.methods {
+        definitions.push(import.method(method)?);
+    }
+    if let Some(problem) = problem {
+        let (mut statics, mut dynamics) = import.facts(problem)?;
+        if !statics.is_empty() {
+            statics.insert(0, DEF_STATIC_FACTS.into());
+            definitions.push(statics.into());
+        }
+        if !dynamics.is_empty() {
+            dynamics.insert(0, DEF_FACTS.into());
+            definitions.push(dynamics.into());
+        }
+    }
+    Ok(definitions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::acting_domain::pddl::{parse_pddl, read_pddl_file};
+
+    const GRIPPER: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../domains/gripper/gripper.pddl"
+    );
+
+    const GRIPPER_PROBLEM: &str = "(define (problem gripper-1) (:domain gripper)
+        (:objects rooma roomb ball1 left)
+        (:init (room rooma) (room roomb) (ball ball1) (gripper left)
+               (at-robby rooma) (at ball1 rooma) (free left))
+        (:goal (at ball1 roomb)))";
+
+    fn import(domain: &str) -> Result<Vec<LValue>, LRuntimeError> {
+        let domain: PddlDomain = (&parse_pddl(domain)?).try_into()?;
+        pddl_to_sompas(&domain, None)
+    }
+
+    #[test]
+    fn test_import_gripper() -> Result<(), LRuntimeError> {
+        let domain: PddlDomain = (&read_pddl_file(GRIPPER)?).try_into()?;
+        assert_eq!(domain.name, "gripper");
+        assert_eq!(domain.predicates.len(), 7);
+        assert_eq!(
+            domain
+                .actions
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["move", "pick", "drop"]
+        );
+        let problem: PddlProblem = (&parse_pddl(GRIPPER_PROBLEM)?).try_into()?;
+
+        let definitions: Vec<String> = pddl_to_sompas(&domain, Some(&problem))?
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        for expected in [
+            "(def-function room (:params (?r object)) (:result boolean))",
+            "(def-state-function at-robby (:params (?r object)) (:result boolean))",
+            "(def-command-pddl-model move (:params (?from object) (?to object)) \
+            (:pre-conditions (room ?from) (room ?to) (at-robby ?from)) \
+            (:effects ((quote at-robby) ?from nil) ((quote at-robby) ?to true)))",
+            "(def-static-facts ((room rooma) true) ((room roomb) true) ((ball ball1) true) ((gripper left) true))",
+            "(def-facts ((at-robby rooma) true) ((at ball1 rooma) true) ((free left) true))",
+        ] {
+            assert!(definitions.contains(&expected.to_string()), "missing {}", expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_malformed() {
+        for malformed in [
+            "(:action a :precondition (not))",
+            "(:action a :precondition (imply (p)))",
+            "(:action a :precondition (exists (?x)))",
+            "(:action a :effect (not))",
+            "(:action a :effect (increase (f)))",
+            "(:action a :effect (assign (f)))",
+        ] {
+            let domain = format!("(define (domain d) (:predicates (p) (f)) {})", malformed);
+            assert!(import(&domain).is_err(), "{} was imported", malformed);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Conversion of PDDL and HDDL descriptions into OMPAS domains.
+use sompas_core::parse_into_lvalue;
+use sompas_structs::lruntimeerror;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalue::LValue;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+pub mod export;
+pub mod import;
+
+pub const PDDL_OBJECT: &str = "object";
+pub const PDDL_NUMBER: &str = "number";
+pub const PDDL_TYPE_SEPARATOR: &str = "-";
+
+/// Reads and parses a PDDL or HDDL file.
+pub fn read_pddl_file(path: impl AsRef<Path>) -> Result<LValue, LRuntimeError> {
+    let path = path.as_ref();
+    let str = fs::read_to_string(path).map_err(|e| {
+        lruntimeerror!(
+            "read_pddl_file",
+            format!("could not read {}: {}", path.display(), e)
+        )
+    })?;
+    parse_pddl(&str)
+}
+
+/// Parses a PDDL or HDDL description.
+pub fn parse_pddl(str: &str) -> Result<LValue, LRuntimeError> {
+    match aries_planning::parsing::sexpr::parse(str) {
+        Ok(se) => Ok(parse_into_lvalue(&se)),
+        Err(e) => Err(lruntimeerror!("parse_pddl", e.to_string())),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypedSymbol {
+    pub symbol: String,
+    pub tpe: String,
+}
+
+impl Display for TypedSymbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {})", self.symbol, self.tpe)
+    }
+}
+
+/// Parses a typed list of the form `a b - t c`, untyped elements being of type default.
+pub fn parse_typed_list(
+    lv: &LValue,
+    default: &str,
+) -> Result<Vec<(LValue, String)>, LRuntimeError> {
+    let list = match lv {
+        LValue::List(list) => list.as_slice(),
+        LValue::Nil => return Ok(vec![]),
+        lv => {
+            return Err(lruntimeerror!(
+                "parse_typed_list",
+                format!("expected a typed list, got {}", lv)
+            ))
+        }
+    };
+    let mut typed = vec![];
+    let mut pending = vec![];
+    let mut iter = list.iter();
+    while let Some(e) = iter.next() {
+        if e.to_string() == PDDL_TYPE_SEPARATOR {
+            let tpe = match iter.next() {
+                Some(LValue::Symbol(t)) => t.to_string(),
+                Some(t) => {
+                    return Err(lruntimeerror!(
+                        "parse_typed_list",
+                        format!("type {} is not supported", t)
+                    ))
+                }
+                None => {
+                    return Err(lruntimeerror!(
+                        "parse_typed_list",
+                        format!("missing type at the end of {}", lv)
+                    ))
+                }
+            };
+            typed.extend(pending.drain(..).map(|e| (e, tpe.clone())));
+        } else {
+            pending.push(e.clone());
+        }
+    }
+    typed.extend(pending.drain(..).map(|e| (e, default.to_string())));
+    Ok(typed)
+}
+
+/// Parses a typed list of symbols, as parameters, objects or types.
+pub fn parse_typed_symbols(lv: &LValue) -> Result<Vec<TypedSymbol>, LRuntimeError> {
+    parse_typed_list(lv, PDDL_OBJECT)?
+        .drain(..)
+        .map(|(s, tpe)| match s {
+            LValue::Symbol(s) => Ok(TypedSymbol {
+                symbol: s.to_string(),
+                tpe,
+            }),
+            lv => Err(lruntimeerror!(
+                "parse_typed_symbols",
+                format!("expected a symbol, got {}", lv)
+            )),
+        })
+        .collect()
+}
+
+/// Parses the keyword arguments of a section, as in `(:action name :parameters (...) :effect (...))`.
+/// The first n elements are not keyword arguments and are skipped.
+pub fn parse_keywords(
+    section: &[LValue],
+    n: usize,
+) -> Result<HashMap<String, LValue>, LRuntimeError> {
+    let mut map = HashMap::new();
+    let mut iter = section.iter().skip(n);
+    while let Some(k) = iter.next() {
+        let key = k.to_string();
+        if !key.starts_with(':') {
+            return Err(lruntimeerror!(
+                "parse_keywords",
+                format!("expected a keyword, got {}", key)
+            ));
+        }
+        let value = iter.next().cloned().ok_or_else(|| {
+            lruntimeerror!("parse_keywords", format!("missing value for {}", key))
+        })?;
+        map.insert(key, value);
+    }
+    Ok(map)
+}
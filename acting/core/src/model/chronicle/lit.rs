@@ -21,6 +21,7 @@ pub enum Lit {
     Await(VarId),
     Acquire(AcquireLit),
     Release(VarId),
+    Stock(StockLit),
     Read(Vec<VarId>),
     Write(Vec<VarId>),
     Exec(Vec<VarId>),
@@ -36,6 +37,20 @@ pub struct AcquireLit {
     pub release_time: VarId,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StockOperation {
+    Consume,
+    Produce,
+}
+
+/// Consumption or production of a quantity of a consumable resource.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StockLit {
+    pub resource: VarId,
+    pub quantity: VarId,
+    pub operation: StockOperation,
+}
+
 impl Lit {
     pub fn apply(vec: Vec<VarId>) -> Self {
         Self::Apply(vec)
@@ -114,7 +129,7 @@ impl TryFrom<&Lit> for Vec<VarId> {
                 LitSet::Finite(set) => Ok(set.clone()),
                 LitSet::Domain(d) => Ok(vec![*d]),
             },
-            Lit::Acquire(_) => Err(Default::default()),
+            Lit::Acquire(_) | Lit::Stock(_) => Err(Default::default()),
         }
     }
 }
@@ -320,6 +335,17 @@ impl FormatWithSymTable for Lit {
                     acq.release_time.format(st, sym_version)
                 )
             }
+            Lit::Stock(stock) => {
+                format!(
+                    "{}({},{})",
+                    match stock.operation {
+                        StockOperation::Consume => "consume",
+                        StockOperation::Produce => "produce",
+                    },
+                    stock.resource.format(st, sym_version),
+                    stock.quantity.format(st, sym_version)
+                )
+            }
         }
     }
 }
@@ -342,6 +368,10 @@ impl FlatBindings for Lit {
                 }
                 acq.release_time.flat_bindings(st);
             }
+            Lit::Stock(stock) => {
+                stock.resource.flat_bindings(st);
+                stock.quantity.flat_bindings(st);
+            }
         }
     }
 }
@@ -370,6 +400,7 @@ impl GetVariables for Lit {
                 }
                 vec.to_set()
             }
+            Lit::Stock(stock) => hash_set!(stock.resource, stock.quantity),
         }
     }
 }
@@ -384,6 +415,10 @@ impl Replace for Lit {
                 vec.replace(old, new)
             }
             Lit::Computation(c) => c.replace(old, new),
+            Lit::Stock(stock) => {
+                stock.resource.replace(old, new);
+                stock.quantity.replace(old, new);
+            }
             Lit::Set(_) => {}
             _ => {}
         }
@@ -6,12 +6,16 @@ use std::sync::Arc;
 pub const VALUE_NO_APPLICABLE_METHOD: usize = 0;
 pub const VALUE_ACTION_FAILURE: usize = 1;
 pub const VALUE_EVALUATION_ERROR: usize = 2;
+pub const VALUE_DEADLOCK: usize = 3;
+pub const VALUE_TIMEOUT: usize = 4;
 
 #[derive(Debug, Copy, Clone)]
 pub enum RaeExecError {
     NoApplicableMethod,
     ActionFailure,
     EvaluationError,
+    Deadlock,
+    Timeout,
     Unknown,
 }
 
@@ -35,6 +39,8 @@ impl RaeExecError {
             0 => Self::NoApplicableMethod,
             1 => Self::ActionFailure,
             2 => Self::EvaluationError,
+            3 => Self::Deadlock,
+            4 => Self::Timeout,
             _ => Self::Unknown,
         }
     }
@@ -48,6 +54,8 @@ impl From<RaeExecError> for LValue {
             }
             RaeExecError::ActionFailure => LValue::Err(Arc::new(VALUE_ACTION_FAILURE.into())),
             RaeExecError::EvaluationError => LValue::Err(Arc::new(VALUE_EVALUATION_ERROR.into())),
+            RaeExecError::Deadlock => LValue::Err(Arc::new(VALUE_DEADLOCK.into())),
+            RaeExecError::Timeout => LValue::Err(Arc::new(VALUE_TIMEOUT.into())),
             RaeExecError::Unknown => LValue::Err(Arc::new(LValue::from(-1))),
         }
     }
@@ -1,6 +1,10 @@
 use crate::ompas::interface::trigger_collection::Response;
+use ompas_language::exec::resource::PRIORITY;
+use ompas_language::monitor::control::*;
+use sompas_structs::kindlvalue::KindLValue;
 use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::LValue;
+use sompas_structs::wrong_type;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -86,3 +90,133 @@ impl Job {
 }
 
 pub type JobId = usize;
+
+/// Splits a task from its priority, given as last argument with `(:priority n)`.
+/// The priority of a task without this argument is 0.
+pub fn split_task_priority(task: &[LValue]) -> Result<(&[LValue], usize), LRuntimeError> {
+    if let Some((LValue::List(last), args)) = task.split_last() {
+        if last.len() == 2 && last[0].to_string() == PRIORITY {
+            return Ok((args, (&last[1]).try_into()?));
+        }
+    }
+    Ok((task, 0))
+}
+
+/// Time window of a task released by the agenda, the times being in seconds since the start of OMPAS.
+/// A recurring task is released every *every* seconds from *start* until *until*,
+/// the deadline of each occurrence being shifted by the same amount.
+/// A hard deadline stops the task, a soft deadline only reports its lateness.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct JobTiming {
+    pub start: Option<f64>,
+    pub deadline: Option<f64>,
+    pub every: Option<f64>,
+    pub until: Option<f64>,
+    pub soft: bool,
+}
+
+impl JobTiming {
+    /// Returns the release time and the deadline of the k-th occurrence,
+    /// or None if the occurrence is never released.
+    pub fn occurrence(&self, k: u32) -> Option<(f64, Option<f64>)> {
+        let shift = match self.every {
+            Some(every) => every * k as f64,
+            None if k == 0 => 0.0,
+            None => return None,
+        };
+        let release = self.start.unwrap_or(0.0) + shift;
+        match self.until {
+            Some(until) if release > until => None,
+            _ => Some((release, self.deadline.map(|d| d + shift))),
+        }
+    }
+}
+
+impl TryFrom<&LValue> for JobTiming {
+    type Error = LRuntimeError;
+
+    fn try_from(lv: &LValue) -> Result<Self, Self::Error> {
+        let list = match lv {
+            LValue::List(list) => list.as_slice(),
+            LValue::Nil => &[],
+            lv => return Err(wrong_type!(EXEC_TIMED_TASK, lv, KindLValue::List)),
+        };
+        let mut timing = JobTiming::default();
+        for option in list.chunks(2) {
+            let [key, value] = option else {
+                return Err(LRuntimeError::new(
+                    EXEC_TIMED_TASK,
+                    format!("{} has no value", option[0]),
+                ));
+            };
+            match key.to_string().as_str() {
+                TIMING_START => timing.start = Some(value.try_into()?),
+                TIMING_DEADLINE => timing.deadline = Some(value.try_into()?),
+                TIMING_EVERY => timing.every = Some(value.try_into()?),
+                TIMING_UNTIL => timing.until = Some(value.try_into()?),
+                TIMING_SOFT => timing.soft = value.try_into()?,
+                key => {
+                    return Err(LRuntimeError::new(
+                        EXEC_TIMED_TASK,
+                        format!(
+                            "{} is not a valid option, expecting {}, {}, {}, {} or {}",
+                            key,
+                            TIMING_START,
+                            TIMING_DEADLINE,
+                            TIMING_EVERY,
+                            TIMING_UNTIL,
+                            TIMING_SOFT
+                        ),
+                    ))
+                }
+            }
+        }
+        if matches!(timing.every, Some(every) if every <= 0.0) {
+            return Err(LRuntimeError::new(
+                EXEC_TIMED_TASK,
+                "the period of a recurring task should be positive",
+            ));
+        }
+        if let (Some(start), Some(deadline)) = (timing.start, timing.deadline) {
+            if deadline < start {
+                return Err(LRuntimeError::new(
+                    EXEC_TIMED_TASK,
+                    format!("the deadline {deadline} is before the start {start}"),
+                ));
+            }
+        }
+        Ok(timing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_job_timing() {
+        let options: LValue = vec![
+            LValue::from(TIMING_START),
+            10.into(),
+            LValue::from(TIMING_DEADLINE),
+            40.into(),
+            LValue::from(TIMING_EVERY),
+            60.into(),
+            LValue::from(TIMING_UNTIL),
+            130.into(),
+        ]
+        .into();
+        let timing = JobTiming::try_from(&options).unwrap();
+        assert_eq!(timing.occurrence(0), Some((10.0, Some(40.0))));
+        assert_eq!(timing.occurrence(2), Some((130.0, Some(160.0))));
+        assert_eq!(timing.occurrence(3), None);
+
+        let options: LValue = vec![LValue::from(TIMING_DEADLINE), 5.into()].into();
+        let timing = JobTiming::try_from(&options).unwrap();
+        assert_eq!(timing.occurrence(0), Some((0.0, Some(5.0))));
+        assert_eq!(timing.occurrence(1), None);
+
+        let options: LValue = vec![LValue::from(TIMING_EVERY), 0.into()].into();
+        assert!(JobTiming::try_from(&options).is_err());
+    }
+}
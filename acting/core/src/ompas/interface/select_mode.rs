@@ -7,6 +7,7 @@ pub const UPOM_D_MAX_DEFAULT: u64 = 10;
 pub const UPOM_N_RO_DEFAULT: u64 = 10;
 pub const UPOM_TIMEOUT_DEFAULT: f64 = 1.0;
 pub const UPOM_C_DEFAULT: f64 = 2.0;
+pub const LEARNING_C_DEFAULT: f64 = 0.5;
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum SelectMode {
@@ -14,6 +15,7 @@ pub enum SelectMode {
     Random,
     Cost,
     Planning(Planner),
+    Learning(LearningConfig),
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
@@ -21,6 +23,7 @@ pub enum SelectModeSerde {
     Greedy,
     Random,
     Score,
+    Learning,
     Aries,
     AriesOpt,
     UPOM,
@@ -34,6 +37,7 @@ impl From<SelectMode> for SelectModeSerde {
             SelectMode::Greedy => Self::Greedy,
             SelectMode::Random => Self::Random,
             SelectMode::Cost => Self::Score,
+            SelectMode::Learning(_) => Self::Learning,
             SelectMode::Planning(p) => match p {
                 Planner::Aries(aries) => match aries {
                     AriesConfig::Satisfactory => Self::Aries,
@@ -119,6 +123,49 @@ impl UPOMConfig {
     }
 }
 
+/// Configuration of the selection based on the statistics learned on the executions of the methods.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct LearningConfig {
+    ///Value estimate used to rank the methods: efficiency or success rate.
+    mode: UPOMMode,
+    ///Weight of the exploration of the methods that have been rarely executed.
+    c: f64,
+    ///If set, the methods are selected by UPOM, the learned values being its priors.
+    upom: Option<UPOMConfig>,
+}
+
+impl Default for LearningConfig {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            c: LEARNING_C_DEFAULT,
+            upom: None,
+        }
+    }
+}
+
+impl LearningConfig {
+    pub fn new_with_upom(upom: UPOMConfig) -> Self {
+        Self {
+            mode: upom.get_mode(),
+            upom: Some(upom),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_mode(&self) -> UPOMMode {
+        self.mode
+    }
+
+    pub fn get_c(&self) -> f64 {
+        self.c
+    }
+
+    pub fn get_upom(&self) -> Option<UPOMConfig> {
+        self.upom
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug, Serialize, Deserialize)]
 pub struct CChoiceConfig {
     ///Number of methods to compare.
@@ -191,6 +238,10 @@ impl Display for SelectMode {
                 SelectMode::Planning(p) => format!("{}", p),
                 SelectMode::Random => RANDOM.to_string(),
                 SelectMode::Cost => COST.to_string(),
+                SelectMode::Learning(config) => match config.upom {
+                    Some(_) => format!("{}; config = {:?}", LEARNING_UPOM, config),
+                    None => format!("{}; config = {:?}", LEARNING, config),
+                },
             }
         )
     }
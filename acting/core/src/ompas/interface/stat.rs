@@ -277,6 +277,30 @@ impl OMPASRunData {
         }
     }
 
+    /// Returns the average ratio of the previous plan kept by the instances that repaired it.
+    pub fn get_plan_repair_kept_ratio(&self) -> f64 {
+        match self.inner.iter().find_map(|stat| {
+            if let OMPASStat::Planner(p) = stat {
+                Some(p)
+            } else {
+                None
+            }
+        }) {
+            None => 0.0,
+            Some(p) => {
+                let ratios: Vec<f64> = p
+                    .inner
+                    .iter()
+                    .filter_map(|p_stat| p_stat.repair.map(|r| r.kept_ratio()))
+                    .collect();
+                match ratios.len() {
+                    0 => 0.0,
+                    n => ratios.iter().sum::<f64>() / n as f64,
+                }
+            }
+        }
+    }
+
     pub fn get_coverage(&self) -> f64 {
         let mut success = 0;
         let mut i = 0;
@@ -1,5 +1,5 @@
 use crate::model::process_ref::ProcessRef;
-use crate::ompas::interface::job::JobType;
+use crate::ompas::interface::job::{JobTiming, JobType};
 use ompas_utils::other::get_and_update_id_counter;
 use sompas_structs::lasynchandler::LAsyncHandle;
 use sompas_structs::lvalue::LValue;
@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use std::mem;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
 pub type JobId = usize;
 
@@ -15,6 +15,8 @@ pub struct PendingJob {
     pub id: JobId,
     pub r#type: JobType,
     pub lvalue: LValue,
+    /// Set for the tasks released by the agenda.
+    pub timing: Option<JobTiming>,
 }
 
 #[derive(Default)]
@@ -26,7 +28,11 @@ pub struct JobCollection {
 
 impl JobCollection {
     pub async fn clear(&self) {
-        self.inner.write().await.clear();
+        for (_, job) in self.inner.write().await.drain() {
+            if let JobHandle::Scheduled(scheduled) = job {
+                scheduled.cancel()
+            }
+        }
         let n = self.next_id.load(Ordering::Acquire);
         let _ = self
             .next_id
@@ -34,11 +40,29 @@ impl JobCollection {
     }
 
     pub async fn add_pending_job(&self, r#type: JobType, lvalue: LValue) -> usize {
+        self.add_pending(r#type, lvalue, None).await
+    }
+
+    pub async fn add_pending_timed_job(&self, timing: JobTiming, lvalue: LValue) -> usize {
+        self.add_pending(JobType::Task, lvalue, Some(timing)).await
+    }
+
+    async fn add_pending(
+        &self,
+        r#type: JobType,
+        lvalue: LValue,
+        timing: Option<JobTiming>,
+    ) -> usize {
         let id = self.get_next_id();
 
         let mut pendings = self.pendings.write().await;
         let rank = pendings.len();
-        pendings.push(PendingJob { id, r#type, lvalue });
+        pendings.push(PendingJob {
+            id,
+            r#type,
+            lvalue,
+            timing,
+        });
         self.inner
             .write()
             .await
@@ -51,6 +75,20 @@ impl JobCollection {
         *id
     }
 
+    pub async fn set_scheduled(&self, id: &JobId, scheduled: ScheduledJob) -> JobId {
+        *self.inner.write().await.get_mut(id).unwrap() = JobHandle::Scheduled(scheduled);
+        *id
+    }
+
+    pub async fn add_scheduled(&self, scheduled: ScheduledJob) -> usize {
+        let id = self.get_next_id();
+        self.inner
+            .write()
+            .await
+            .insert(id, JobHandle::Scheduled(scheduled));
+        id
+    }
+
     pub async fn add_process(&self, task_process: TaskProcess) -> usize {
         let id = self.get_next_id();
         self.inner
@@ -83,6 +121,52 @@ pub enum Response {
 pub enum JobHandle {
     Pending(usize),
     Process(TaskProcess),
+    Scheduled(ScheduledJob),
+}
+
+/// Task released by the agenda according to its timing.
+/// The occurrences are added as they are released.
+#[derive(Clone)]
+pub struct ScheduledJob {
+    timing: JobTiming,
+    occurrences: Arc<RwLock<Vec<TaskProcess>>>,
+    cancel: Arc<watch::Sender<bool>>,
+}
+
+impl ScheduledJob {
+    /// Returns the job and the receiver notified when the job is cancelled.
+    pub fn new(timing: JobTiming) -> (Self, watch::Receiver<bool>) {
+        let (tx, rx) = watch::channel(false);
+        (
+            Self {
+                timing,
+                occurrences: Default::default(),
+                cancel: Arc::new(tx),
+            },
+            rx,
+        )
+    }
+
+    pub fn get_timing(&self) -> JobTiming {
+        self.timing
+    }
+
+    pub async fn add_occurrence(&self, task_process: TaskProcess) {
+        self.occurrences.write().await.push(task_process)
+    }
+
+    pub async fn get_occurrences(&self) -> Vec<TaskProcess> {
+        self.occurrences.read().await.clone()
+    }
+
+    pub async fn get_last_occurrence(&self) -> Option<TaskProcess> {
+        self.occurrences.read().await.last().cloned()
+    }
+
+    /// Stops the release of the next occurrences.
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(true);
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -12,4 +12,15 @@ pub struct ActingStat {
     pub continuous_planning_mode: ContinuousPlanningModeSerde,
     pub deliberation_reactivity: f64,
     pub planner_reactivity: f64,
+    /// Number of tasks released with a deadline.
+    #[serde(default)]
+    pub n_deadline: u32,
+    #[serde(default)]
+    pub n_deadline_missed: u32,
+    /// Sum of the lateness of the tasks that ended after their deadline, in seconds.
+    /// The tasks still running are late from their deadline to the export of the stats.
+    #[serde(default)]
+    pub total_lateness: f64,
+    #[serde(default)]
+    pub max_lateness: f64,
 }
@@ -31,9 +31,13 @@ use crate::ompas::manager::planning::plan_update::{ActingTreeUpdate, Choice, Cho
 use crate::ompas::manager::planning::planner_manager_interface::{
     FilterWatchedProcesses, PlannerManagerInterface,
 };
+use crate::ompas::manager::planning::planner_stat::RepairStat;
 use crate::ompas::manager::planning::problem_update::{PlannerUpdate, VarUpdate};
-use crate::ompas::manager::resource::{Quantity, ResourceManager, WaitAcquire, WaiterPriority};
+use crate::ompas::manager::resource::{
+    ClientId, ClientOwner, Quantity, ResourceId, ResourceManager, WaitAcquire, WaiterPriority,
+};
 use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::trace::{TraceEvent, TraceManager};
 use crate::planning::conversion::_convert;
 use crate::planning::conversion::flow_graph::algo::annotate::annotate;
 use crate::planning::conversion::flow_graph::algo::p_eval::p_eval;
@@ -50,6 +54,7 @@ use serde::{Deserialize, Serialize};
 use sompas_structs::lenv::LEnv;
 use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter, Write};
@@ -110,7 +115,12 @@ pub struct InnerActingManager {
     domain_manager: DomainManager,
     pub env: Option<LEnv>,
     deliberation_manager: DeliberationManager,
+    trace_manager: TraceManager,
     planner_manager_interface: Option<PlannerManagerInterface>,
+    /// Deadlines of the tasks released by the agenda.
+    deadlines: HashMap<ActingProcessId, Timepoint>,
+    /// Priorities given to the top-level tasks, inherited by their resource acquisitions.
+    priorities: HashMap<ActingProcessId, usize>,
 }
 
 impl InnerActingManager {
@@ -119,6 +129,7 @@ impl InnerActingManager {
         clock_manager: ClockManager,
         domain_manager: DomainManager,
         deliberation_manager: DeliberationManager,
+        trace_manager: TraceManager,
         st: RefSymTable,
     ) -> Self {
         let mut new = Self {
@@ -132,6 +143,9 @@ impl InnerActingManager {
             planner_manager_interface: None,
             env: None,
             deliberation_manager,
+            trace_manager,
+            deadlines: Default::default(),
+            priorities: Default::default(),
         };
         new.init();
         new
@@ -171,6 +185,8 @@ impl InnerActingManager {
         self.models.clear();
         self.acting_vars.clear().await;
         self.st.clear();
+        self.deadlines.clear();
+        self.priorities.clear();
         self.init();
     }
 }
@@ -266,6 +282,10 @@ impl InnerActingManager {
         }
     }
 
+    pub fn contains(&self, id: &ActingProcessId) -> bool {
+        *id < self.processes.len()
+    }
+
     pub fn get_kind(&self, id: &ActingProcessId) -> ActingProcessKind {
         self.processes[*id].inner.kind()
     }
@@ -286,6 +306,38 @@ impl InnerActingManager {
         self.processes[*id].parent()
     }
 
+    /// Returns the top-level task from which the process has been refined.
+    pub fn get_top_level_task(&self, id: &ActingProcessId) -> ActingProcessId {
+        let mut id = *id;
+        loop {
+            match self.get_parent(&id) {
+                0 => return id,
+                parent => id = parent,
+            }
+        }
+    }
+
+    /// Returns the priority of the top-level task from which the process has been refined.
+    pub fn get_task_priority(&self, id: &ActingProcessId) -> usize {
+        self.priorities
+            .get(&self.get_top_level_task(id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the acquisitions holding the resource with the given clients.
+    pub fn get_holders(&self, resource: &ResourceId, clients: &[ClientId]) -> Vec<ActingProcessId> {
+        self.processes
+            .iter()
+            .filter_map(|p| {
+                let acquire = p.inner.as_acquire()?;
+                (acquire.get_resource_id() == Some(*resource)
+                    && clients.contains(&acquire.get_client_id()?))
+                .then_some(p.id())
+            })
+            .collect()
+    }
+
     fn get_am_id(&self, id: &ActingProcessId) -> AMId {
         self.processes[*id].am_id()
     }
@@ -462,6 +514,14 @@ impl InnerActingManager {
         self.set_status(id, status)
     }
 
+    pub fn set_task_priority(&mut self, id: &ActingProcessId, priority: usize) {
+        self.priorities.insert(*id, priority);
+    }
+
+    pub fn set_deadline(&mut self, id: &ActingProcessId, deadline: Timepoint) {
+        self.deadlines.insert(*id, deadline);
+    }
+
     pub fn set_process_args(&mut self, id: &ActingProcessId, args: Vec<Cst>) {
         let mut debug = "(".to_string();
         let vec_args = match &self.processes[*id].inner {
@@ -524,6 +584,10 @@ impl InnerActingManager {
             .as_mut_task()
             .unwrap()
             .set_executed(refinement_label.refinement_id, method, refinement_trace);
+        self.trace_manager.record(TraceEvent::Refinement {
+            task: *action,
+            method: self.get_debug(method).clone().unwrap_or_default(),
+        });
         let instant = self.clock_manager.now();
         if self.get_status(action) == ProcessStatus::Pending {
             self.set_status(action, ProcessStatus::Running(None));
@@ -582,6 +646,10 @@ impl InnerActingManager {
         self.set_start(id, Some(now));
         self.set_execution_val(&var_ref, value.clone());
         self.set_end(id, Some(now), ProcessStatus::Success);
+        self.trace_manager.record(TraceEvent::Arbitrary {
+            id: *id,
+            value: value.to_string(),
+        });
 
         value
     }
@@ -1239,10 +1307,13 @@ impl InnerActingManager {
         }
 
         let handle = Handle::current();
+        //The conversion runs in blocking threads, not seen by the virtual clock.
+        let _busy = self.clock_manager.busy();
 
         let mut p_env = PLEnv {
             env: self.env.clone().unwrap(),
             unpure_bindings: Default::default(),
+            unrolling: Default::default(),
             pc: Default::default(),
         };
 
@@ -1326,10 +1397,12 @@ impl InnerActingManager {
         let mut p_env = PLEnv {
             env: self.env.clone().unwrap(),
             unpure_bindings: Default::default(),
+            unrolling: Default::default(),
             pc: Default::default(),
         };
 
         let handle = Handle::current();
+        let _busy = self.clock_manager.busy();
         let debug = lv.to_string();
         let p_eval_lv = p_eval(&lv, &mut p_env).await?;
         let lv_om = annotate(p_eval_lv);
@@ -1508,10 +1581,12 @@ impl InnerActingManager {
         quantity: Quantity,
         priority: WaiterPriority,
     ) -> Result<WaitAcquire, LRuntimeError> {
-        let acquire = self.processes[*id].inner.as_acquire().unwrap();
-        let ref_r = acquire.resource.clone();
-        let ref_q = acquire.quantity.clone();
-
+        let priority = match priority {
+            WaiterPriority::Execution(p) => {
+                WaiterPriority::Execution(p.max(self.get_task_priority(id)))
+            }
+            p => p,
+        };
         let reservation = self.processes[*id]
             .inner
             .as_mut_acquire()
@@ -1557,6 +1632,70 @@ impl InnerActingManager {
             }
         };
 
+        self.start_acquire(id, &resource, &waiter).await;
+        Ok(waiter)
+    }
+
+    /// Acquires atomically the resources, each acquisition process acquiring the resource at the
+    /// same index. The reservations of the processes are dropped.
+    pub async fn acquire_all(
+        &mut self,
+        ids: &[ActingProcessId],
+        resources: Vec<(String, Quantity)>,
+        priority: WaiterPriority,
+    ) -> Result<Vec<WaitAcquire>, LRuntimeError> {
+        let priority = match priority {
+            WaiterPriority::Execution(p) => WaiterPriority::Execution(
+                ids.iter()
+                    .map(|id| self.get_task_priority(id))
+                    .fold(p, usize::max),
+            ),
+            p => p,
+        };
+        for id in ids {
+            let reservation = self.processes[*id]
+                .inner
+                .as_mut_acquire()
+                .unwrap()
+                .move_reservation();
+            if let Some(reservation) = reservation {
+                self.resource_manager.remove_waiter(reservation).await;
+            }
+        }
+
+        let waiters = self
+            .resource_manager
+            .acquire_all(&resources, priority)
+            .await?;
+
+        for ((id, (resource, _)), waiter) in ids.iter().zip(&resources).zip(&waiters) {
+            self.processes[*id]
+                .inner
+                .as_mut_acquire()
+                .unwrap()
+                .set_acquire_id(waiter);
+            self.start_acquire(id, resource, waiter).await;
+        }
+        Ok(waiters)
+    }
+
+    /// Binds the acquisition process to the client waiting for the resource.
+    async fn start_acquire(&mut self, id: &ActingProcessId, resource: &str, waiter: &WaitAcquire) {
+        let acquire = self.processes[*id].inner.as_acquire().unwrap();
+        let ref_r = acquire.resource.clone();
+        let ref_q = acquire.quantity.clone();
+
+        self.resource_manager
+            .set_owner(
+                &waiter.get_resource_id(),
+                &waiter.get_client_id(),
+                ClientOwner {
+                    task: self.get_top_level_task(id),
+                    acquire: *id,
+                },
+            )
+            .await;
+
         let capacity = self
             .resource_manager
             .get_client_quantity(&waiter.get_resource_id(), &waiter.get_client_id())
@@ -1565,8 +1704,6 @@ impl InnerActingManager {
         self.set_execution_val(&ref_r, resource.to_string());
         self.set_execution_val(&ref_q, capacity);
         self.set_status(id, ProcessStatus::Running(None));
-
-        Ok(waiter)
     }
 
     async fn reserve(
@@ -1682,6 +1819,75 @@ impl InnerActingManager {
     }
 
     pub fn get_current_chronicles(&self) -> Vec<ChronicleInstance> {
+        self.collect_chronicles(None, &mut Default::default())
+    }
+
+    /// Returns the chronicles of the acting tree in which the refinements suggested by the
+    /// previous plan are kept, except for the reopened top-level tasks.
+    pub fn get_repaired_chronicles(
+        &self,
+        reopened: &HashSet<ActingProcessId>,
+    ) -> (Vec<ChronicleInstance>, RepairStat) {
+        let mut stat = RepairStat::default();
+        let chronicles = self.collect_chronicles(Some(reopened), &mut stat);
+        (chronicles, stat)
+    }
+
+    /// Returns the top-level tasks whose plan is affected by the updates, or None if the whole
+    /// acting tree has to be replanned.
+    /// A state update affects the tasks whose planned chronicles have a condition on an updated
+    /// state function.
+    pub fn get_reopened_tasks(
+        &self,
+        updates: &[PlannerUpdate],
+    ) -> Option<HashSet<ActingProcessId>> {
+        let mut reopened = HashSet::new();
+        let mut updated_sf: HashSet<String> = HashSet::new();
+        for update in updates {
+            match update {
+                PlannerUpdate::Plan => return None,
+                PlannerUpdate::ProblemUpdate(id) => {
+                    reopened.insert(self.get_top_level_task(id));
+                }
+                PlannerUpdate::VarUpdate(v) => {
+                    if let Some(id) = self
+                        .processes
+                        .iter()
+                        .position(|p| p.am_id() == v.var_ref.am_id)
+                    {
+                        reopened.insert(self.get_top_level_task(&id));
+                    }
+                }
+                PlannerUpdate::StateUpdate(svs) => {
+                    updated_sf.extend(svs.iter().map(|sv| match sv {
+                        LValueS::List(sv) => sv[0].to_string(),
+                        sv => sv.to_string(),
+                    }))
+                }
+            }
+        }
+        if !updated_sf.is_empty() {
+            let (chronicles, _) = self.get_repaired_chronicles(&Default::default());
+            for c in chronicles {
+                let ProcessRef::Id(id) = c.pr else { continue };
+                let chronicle = &c.instantiated_chronicle;
+                if chronicle.get_conditions().iter().any(|cond| {
+                    self.st
+                        .var_as_cst(cond.sv[0])
+                        .is_some_and(|sf| updated_sf.contains(&sf.to_string()))
+                }) {
+                    reopened.insert(self.get_top_level_task(&id));
+                }
+            }
+        }
+        Some(reopened)
+    }
+
+    fn collect_chronicles(
+        &self,
+        reopened: Option<&HashSet<ActingProcessId>>,
+        stat: &mut RepairStat,
+    ) -> Vec<ChronicleInstance> {
         struct ExecChronicle {
             id: ActingProcessId,
             origin: ChronicleOrigin,
@@ -1782,7 +1988,19 @@ impl InnerActingManager {
                         let methods = if let Some(chosen) = refinement.get_executed() {
                             vec![chosen]
                         } else {
-                            refinement.get_possibilities()
+                            match (reopened, refinement.get_suggested()) {
+                                (Some(reopened), Some(suggested))
+                                    if !reopened.contains(&self.get_top_level_task(&id)) =>
+                                {
+                                    stat.kept += 1;
+                                    vec![suggested]
+                                }
+                                (Some(_), Some(_)) => {
+                                    stat.reopened += 1;
+                                    refinement.get_possibilities()
+                                }
+                                _ => refinement.get_possibilities(),
+                            }
                         };
                         for refinement_id in methods {
                             let method_process = &self.processes[*refinement_id];
@@ -1838,6 +2056,10 @@ impl InnerActingManager {
             acting_models,
             choices,
         } = update;
+        self.trace_manager.record(TraceEvent::PlanUpdate {
+            models: acting_models.len(),
+            choices: choices.len(),
+        });
         self.add_processes_from_chronicles(acting_models);
         let updated = self.absorb_choices(choices).await;
         self.notify_plan_update(FilterWatchedProcesses::Some(updated));
@@ -2099,6 +2321,20 @@ impl InnerActingManager {
 ///Export functions
 impl InnerActingManager {
     pub async fn get_acting_stat(&self) -> ActingStat {
+        let now = self.clock_manager.now();
+        let mut n_deadline_missed = 0;
+        let mut lateness: Vec<f64> = vec![];
+        for (id, deadline) in &self.deadlines {
+            let end = self
+                .get_acting_var_val(&self.processes[*id].end)
+                .unwrap_or(now);
+            if end > *deadline {
+                lateness.push((end - *deadline).as_secs());
+            }
+            if end > *deadline || self.processes[*id].status == ProcessStatus::DeadlineMissed {
+                n_deadline_missed += 1;
+            }
+        }
         ActingStat {
             n_root_task: self.processes[0].inner.as_root().unwrap().tasks.len() as u32,
             n_command_task: self.processes[0].inner.as_root().unwrap().commands.len() as u32,
@@ -2112,6 +2348,10 @@ impl InnerActingManager {
                 .into(),
             deliberation_reactivity: self.deliberation_manager.get_deliberation_reactivity(),
             planner_reactivity: self.deliberation_manager.get_planner_reactivity(),
+            n_deadline: self.deadlines.len() as u32,
+            n_deadline_missed,
+            total_lateness: lateness.iter().sum(),
+            max_lateness: lateness.iter().copied().fold(0.0, f64::max),
         }
     }
 
@@ -2394,7 +2634,10 @@ impl InnerActingManager {
             ProcessStatus::Success => {
                 write!(str, "fillcolor=\"#ceffce\",")
             }
-            ProcessStatus::Failure | ProcessStatus::Cancelled(_) | ProcessStatus::Rejected => {
+            ProcessStatus::Failure
+            | ProcessStatus::Cancelled(_)
+            | ProcessStatus::Rejected
+            | ProcessStatus::DeadlineMissed => {
                 write!(str, "fillcolor = \"#ffcece\",")
             }
         }
@@ -2607,3 +2850,85 @@ fn format_acting_process(
     }
     f
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ompas::manager::acting::ActingManager;
+
+    /// Adds a top-level task that can be refined by two methods, the first one being suggested
+    /// by the previous plan. Returns the id of the task and of its methods.
+    fn new_planned_task(
+        inner: &mut InnerActingManager,
+        label: &str,
+    ) -> (ActingProcessId, Vec<ActingProcessId>) {
+        let pr = inner.new_high_level_task(label.to_string(), vec![Cst::Symbol(label.to_string())]);
+        let task = inner.get_id(pr).unwrap();
+        let refinement_id = inner.new_refinement(&task) - 1;
+        let mut methods = vec![];
+        for i in 0..2 {
+            let debug = format!("m_{label}_{i}");
+            let model = ActingModel {
+                lv: LValue::Nil,
+                lv_om: LValue::Nil,
+                lv_expanded: Some(LValue::Nil),
+                runtime_info: Default::default(),
+                chronicle: Some(Chronicle::new(&debug, ChronicleKind::Method, inner.st())),
+            };
+            methods.push(inner.new_method(
+                RefinementLabel {
+                    refinement_id,
+                    method_label: MethodLabel::Possibility(i),
+                },
+                &task,
+                debug,
+                vec![],
+                model,
+                ProcessOrigin::Planner,
+            ));
+        }
+        inner.processes[task]
+            .inner
+            .as_mut_task()
+            .unwrap()
+            .set_suggested(refinement_id, &methods[0]);
+        (task, methods)
+    }
+
+    #[tokio::test]
+    async fn test_plan_repair() {
+        let acting_manager = ActingManager::default();
+        let mut inner = acting_manager.inner.write().await;
+        let (t1, m1) = new_planned_task(&mut inner, "t1");
+        let (t2, m2) = new_planned_task(&mut inner, "t2");
+
+        //Only the task concerned by the update is reopened.
+        let reopened = inner
+            .get_reopened_tasks(&[PlannerUpdate::ProblemUpdate(m2[1])])
+            .unwrap();
+        assert!(reopened.contains(&t2) && !reopened.contains(&t1));
+        assert!(inner.get_reopened_tasks(&[PlannerUpdate::Plan]).is_none());
+
+        //The suggested refinement of the unaffected task is kept, the affected one is replanned.
+        let (chronicles, stat) = inner.get_repaired_chronicles(&reopened);
+        assert_eq!(
+            stat,
+            RepairStat {
+                kept: 1,
+                reopened: 1
+            }
+        );
+        let planned: Vec<ActingProcessId> = chronicles
+            .iter()
+            .filter_map(|c| match c.pr {
+                ProcessRef::Id(id) if id != 0 => Some(id),
+                _ => None,
+            })
+            .collect();
+        assert!(planned.contains(&m1[0]) && !planned.contains(&m1[1]));
+        assert!(planned.contains(&m2[0]) && planned.contains(&m2[1]));
+
+        //Without repair, all the possible refinements are planned.
+        assert_eq!(inner.get_current_chronicles().len(), chronicles.len() + 1);
+    }
+}
@@ -6,23 +6,30 @@ use crate::model::sym_table::r#ref::RefSymTable;
 use crate::ompas::interface::stat::OMPASRunData;
 use crate::ompas::manager::acting::filter::ProcessFilter;
 use crate::ompas::manager::acting::inner::ActingProcessKind;
-use crate::ompas::manager::acting::interval::Timepoint;
+use crate::ompas::manager::acting::interval::{Duration, Timepoint};
 use crate::ompas::manager::acting::process::task::RefinementTrace;
 use crate::ompas::manager::acting::process::ProcessOrigin;
 use crate::ompas::manager::clock::ClockManager;
 use crate::ompas::manager::deliberation::DeliberationManager;
 use crate::ompas::manager::domain::DomainManager;
 use crate::ompas::manager::event::EventManager;
+use crate::ompas::manager::learning::LearningManager;
+use crate::ompas::manager::monitoring::{get_expectations, Divergence, ExecutionMonitor};
 use crate::ompas::manager::planning::plan_update::ActingTreeUpdate;
 use crate::ompas::manager::planning::planner_manager_interface::FilterWatchedProcesses;
 use crate::ompas::manager::planning::problem_update::ExecutionProblem;
 use crate::ompas::manager::planning::PlannerManager;
-use crate::ompas::manager::resource::{Quantity, ResourceManager, WaitAcquire, WaiterPriority};
+use crate::ompas::manager::preemption::PreemptionManager;
+use crate::ompas::manager::resource::{
+    Deadlock, Quantity, ResourceManager, WaitAcquire, WaiterPriority,
+};
 use crate::ompas::manager::state::action_status::ProcessStatus;
 use crate::ompas::manager::state::StateManager;
+use crate::ompas::manager::trace::{TraceEvent, TraceManager};
 use crate::planning::conversion::flow_graph::algo::pre_processing::expand_lambda;
 use crate::planning::conversion::flow_graph::graph::Dot;
 use crate::planning::planner::solver::PMetric;
+use crate::{DeadlockPolicy, DivergencePolicy, OMPAS_DEADLOCK_POLICY, OMPAS_DIVERGENCE_POLICY};
 use inner::InnerActingManager;
 use ompas_language::exec::acting_context::DEF_PROCESS_ID;
 use ompas_language::process::{LOG_TOPIC_OMPAS, PROCESS_TOPIC_OMPAS};
@@ -34,6 +41,8 @@ use sompas_structs::llambda::LLambda;
 use sompas_structs::lprimitive::LPrimitive;
 use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
+use std::collections::HashSet;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::PathBuf;
@@ -73,6 +82,10 @@ pub struct ActingManager {
     pub inner: RefInnerActingManager,
     pub clock_manager: ClockManager,
     pub deliberation_manager: DeliberationManager,
+    pub trace_manager: TraceManager,
+    pub learning_manager: LearningManager,
+    pub preemption_manager: PreemptionManager,
+    pub execution_monitor: ExecutionMonitor,
     acting_tree_displayer: Arc<RwLock<Option<ActingTreeDisplayer>>>,
 }
 
@@ -89,7 +102,9 @@ impl ActingManager {
         let mut ompas_domain = OMPASDomain::default();
         ompas_domain.init(&st);
         let domain_manager: DomainManager = ompas_domain.into();
-        let state_manager = StateManager::new(clock_manager.clone(), st.clone());
+        let trace_manager = TraceManager::new(clock_manager.clone());
+        let state_manager =
+            StateManager::new(clock_manager.clone(), trace_manager.clone(), st.clone());
         let event_manager = EventManager::new(state_manager.clone(), clock_manager.clone());
         let deliberation_manager = DeliberationManager::default();
         Self {
@@ -103,10 +118,15 @@ impl ActingManager {
                 clock_manager.clone(),
                 domain_manager,
                 deliberation_manager.clone(),
+                trace_manager.clone(),
                 st,
             ))),
             clock_manager,
             deliberation_manager,
+            trace_manager,
+            learning_manager: Default::default(),
+            preemption_manager: Default::default(),
+            execution_monitor: Default::default(),
             acting_tree_displayer: Arc::new(Default::default()),
         }
     }
@@ -129,6 +149,8 @@ impl ActingManager {
     pub async fn clear(&self) {
         self.event_manager.clear().await;
         self.resource_manager.clear().await;
+        self.preemption_manager.clear();
+        self.execution_monitor.clear();
         self.inner.write().await.clear().await;
     }
 
@@ -180,6 +202,10 @@ impl ActingManager {
         self.inner.read().await.get_origin(id)
     }
 
+    pub async fn contains(&self, id: &ActingProcessId) -> bool {
+        self.inner.read().await.contains(id)
+    }
+
     pub async fn get_kind(&self, id: &ActingProcessId) -> ActingProcessKind {
         self.inner.read().await.get_kind(id)
     }
@@ -188,6 +214,10 @@ impl ActingManager {
         self.inner.read().await.get_tried(id)
     }
 
+    pub async fn get_execution_time(&self, id: &ActingProcessId) -> Duration {
+        self.inner.read().await.get_execution_time(id)
+    }
+
     //ActingProcess declaration
 
     pub async fn new_high_level_task(&self, debug: String, args: Vec<Cst>) -> ProcessRef {
@@ -293,6 +323,10 @@ impl ActingManager {
         self.inner.write().await.set_end(id, instant, status)
     }
 
+    pub async fn set_deadline(&self, id: &ActingProcessId, deadline: Timepoint) {
+        self.inner.write().await.set_deadline(id, deadline)
+    }
+
     pub async fn set_status(&self, id: &ActingProcessId, status: ProcessStatus) {
         self.inner.write().await.set_status(id, status)
     }
@@ -401,6 +435,115 @@ impl ActingManager {
             .await
     }
 
+    pub async fn acquire_all(
+        &self,
+        ids: &[ActingProcessId],
+        resources: Vec<(String, Quantity)>,
+        priority: WaiterPriority,
+    ) -> Result<Vec<WaitAcquire>, LRuntimeError> {
+        self.inner
+            .write()
+            .await
+            .acquire_all(ids, resources, priority)
+            .await
+    }
+
+    /// Preempts the top-level tasks holding the resource awaited by the acquisition with a lower
+    /// priority, so that the acquisition can be served. Returns the preempted tasks.
+    pub async fn preempt(
+        &self,
+        acquire_id: &ActingProcessId,
+        wait: &WaitAcquire,
+    ) -> Vec<ActingProcessId> {
+        let clients = self
+            .resource_manager
+            .get_preemptible(&wait.get_resource_id(), &wait.get_client_id())
+            .await;
+        if clients.is_empty() {
+            return vec![];
+        }
+        let inner = self.inner.read().await;
+        let task = inner.get_top_level_task(acquire_id);
+        inner
+            .get_holders(&wait.get_resource_id(), &clients)
+            .iter()
+            .map(|acquire| inner.get_top_level_task(acquire))
+            .filter(|holder| *holder != task && self.preemption_manager.preempt(holder))
+            .collect()
+    }
+
+    /// Detects the deadlock formed by the waiting acquisition, and resolves it with the policy
+    /// OMPAS_DEADLOCK_POLICY: the youngest waiting acquisition of the cycle fails, or its task is
+    /// preempted. The preemption falls back on the failure if the task is not preemptible.
+    pub async fn resolve_deadlock(&self, wait: &WaitAcquire) -> Option<Deadlock> {
+        let deadlock = self
+            .resource_manager
+            .detect_deadlock(&wait.get_resource_id(), &wait.get_client_id())
+            .await?;
+        let youngest = deadlock.youngest().clone();
+        match OMPAS_DEADLOCK_POLICY.get() {
+            DeadlockPolicy::None => {}
+            DeadlockPolicy::Preempt if self.preemption_manager.preempt(&youngest.waiter.task) => {}
+            DeadlockPolicy::Preempt | DeadlockPolicy::Fail => {
+                self.resource_manager
+                    .fail_waiter(&youngest.resource_id, &youngest.client_id, deadlock.clone())
+                    .await
+            }
+        }
+        Some(deadlock)
+    }
+
+    /// Compares the chronicles of the acting tree with the state, the updated state variables being
+    /// checked, and resolves the divergences with the policy OMPAS_DIVERGENCE_POLICY: the acting
+    /// tree is replanned, or the top-level tasks of the divergent processes are preempted and
+    /// retried. The preemption falls back on the replanning if the task is not preemptible.
+    /// The chronicles are only available when the continuous planning is activated.
+    pub async fn monitor_execution(&self, updated: &HashSet<LValueS>) -> Vec<Divergence> {
+        let expectations = {
+            let inner = self.inner.read().await;
+            if !inner.is_planner_activated() {
+                return vec![];
+            }
+            let chronicles = inner.get_current_chronicles();
+            get_expectations(&chronicles, |id| inner.get_status(id), &self.st)
+        };
+        let state = self.state_manager.get_state(None).await;
+        let divergences = self.execution_monitor.check(expectations, &state, updated);
+        if divergences.is_empty() {
+            return divergences;
+        }
+        for divergence in &divergences {
+            self.trace_manager
+                .record(TraceEvent::Divergence(divergence.clone()));
+        }
+        let replan = match OMPAS_DIVERGENCE_POLICY.get() {
+            DivergencePolicy::None => false,
+            DivergencePolicy::Replan => true,
+            DivergencePolicy::Retry => {
+                let tasks: HashSet<ActingProcessId> = {
+                    let inner = self.inner.read().await;
+                    divergences
+                        .iter()
+                        .map(|d| inner.get_top_level_task(&d.process))
+                        .collect()
+                };
+                tasks
+                    .iter()
+                    .filter(|task| !self.preemption_manager.preempt(task))
+                    .count()
+                    > 0
+            }
+        };
+        if replan {
+            self.inner.write().await.plan();
+        }
+        divergences
+    }
+
+    pub async fn set_task_priority(&self, id: &ActingProcessId, priority: usize) {
+        self.inner.write().await.set_task_priority(id, priority)
+    }
+
     pub async fn set_s_acq(&self, acquire_id: &ActingProcessId, instant: Option<Timepoint>) {
         self.inner.write().await.set_s_acq(acquire_id, instant)
     }
@@ -1,30 +1,144 @@
 use crate::ompas::manager::acting::interval::Timepoint;
-use crate::OMPAS_DELIBERATION_FREQUENCY;
+use crate::{
+    ClockMode, OMPAS_CLOCK_MODE, OMPAS_DELIBERATION_FREQUENCY, OMPAS_VIRTUAL_CLOCK_QUIESCENCE,
+};
 use core::time::Duration;
 use ompas_language::process::{LOG_TOPIC_OMPAS, PROCESS_TOPIC_OMPAS};
 use ompas_middleware::ProcessInterface;
-use std::sync::Arc;
-use tokio::sync::{watch, RwLock};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::runtime::{Handle, RuntimeMetrics};
+use tokio::sync::{oneshot, watch, RwLock};
 use tokio::time::Instant;
 
+pub const PROCESS_VIRTUAL_CLOCK: &str = "VIRTUAL_CLOCK";
+
+/// Source of time of the ClockManager.
+enum ClockSource {
+    /// Time elapsed since the creation of the manager, as measured by tokio.
+    RealTime(Instant),
+    /// Discrete-event time, advanced to the next pending timer when the system is idle.
+    Virtual(VirtualClock),
+}
+
 #[derive(Clone)]
 pub struct ClockManager {
-    instant: Arc<Instant>,
+    source: Arc<ClockSource>,
     clock_subscriber: Arc<RwLock<Option<watch::Receiver<u64>>>>,
 }
 
 impl Default for ClockManager {
     fn default() -> Self {
+        Self::new(OMPAS_CLOCK_MODE.get())
+    }
+}
+
+impl ClockManager {
+    pub fn new(mode: ClockMode) -> Self {
+        let source = match mode {
+            ClockMode::RealTime => ClockSource::RealTime(Instant::now()),
+            ClockMode::Virtual => ClockSource::Virtual(VirtualClock::default()),
+        };
         Self {
-            instant: Arc::new(Instant::now()),
+            source: Arc::new(source),
             clock_subscriber: Arc::new(RwLock::new(None)),
         }
     }
-}
 
-impl ClockManager {
+    pub fn mode(&self) -> ClockMode {
+        match self.source.as_ref() {
+            ClockSource::RealTime(_) => ClockMode::RealTime,
+            ClockSource::Virtual(_) => ClockMode::Virtual,
+        }
+    }
+
     pub fn now(&self) -> Timepoint {
-        Timepoint::new_micros(self.instant.elapsed().as_micros())
+        match self.source.as_ref() {
+            ClockSource::RealTime(instant) => Timepoint::new_micros(instant.elapsed().as_micros()),
+            ClockSource::Virtual(clock) => Timepoint::new_micros(clock.now() as u128),
+        }
+    }
+
+    /// Waits for *duration* according to the clock source.
+    /// In virtual mode, the future completes as soon as the virtual time reaches the deadline,
+    /// which happens as soon as all other tasks are blocked.
+    pub async fn sleep(&self, duration: Duration) {
+        match self.source.as_ref() {
+            ClockSource::RealTime(_) => tokio::time::sleep(duration).await,
+            ClockSource::Virtual(clock) => {
+                if duration.is_zero() {
+                    tokio::task::yield_now().await;
+                    return;
+                }
+                let rx = clock.register(duration);
+                self.start_virtual_clock_driver().await;
+                let _ = rx.await;
+            }
+        }
+    }
+
+    /// Keeps the virtual time from advancing as long as the returned guard is alive.
+    /// Used around CPU-bound work running outside of the runtime workers, like a planner solving
+    /// in a blocking thread, that the clock cannot see. Has no effect on a real-time clock.
+    pub fn busy(&self) -> BusyGuard {
+        if let ClockSource::Virtual(clock) = self.source.as_ref() {
+            clock.busy.fetch_add(1, AtomicOrdering::AcqRel);
+        }
+        BusyGuard {
+            source: self.source.clone(),
+        }
+    }
+
+    /// Starts the thread advancing the virtual time.
+    /// It runs outside of the runtime so that its own activity does not keep the runtime busy.
+    async fn start_virtual_clock_driver(&self) {
+        let clock = match self.source.as_ref() {
+            ClockSource::Virtual(clock) => clock,
+            ClockSource::RealTime(_) => return,
+        };
+        if clock
+            .driver_started
+            .compare_exchange(false, true, AtomicOrdering::AcqRel, AtomicOrdering::Acquire)
+            .is_err()
+        {
+            return;
+        }
+
+        let source = Arc::downgrade(&self.source);
+        let period = Duration::from_micros(OMPAS_VIRTUAL_CLOCK_QUIESCENCE.get());
+        let metrics = Handle::current().metrics();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopped_2 = stopped.clone();
+        let mut process =
+            ProcessInterface::new(PROCESS_VIRTUAL_CLOCK, PROCESS_TOPIC_OMPAS, LOG_TOPIC_OMPAS)
+                .await;
+        tokio::spawn(async move {
+            process.recv().await;
+            stopped_2.store(true, AtomicOrdering::Release);
+        });
+        thread::spawn(move || {
+            while !stopped.load(AtomicOrdering::Acquire) {
+                thread::sleep(period);
+                //The clock is not used anymore.
+                let Some(source) = source.upgrade() else {
+                    break;
+                };
+                let ClockSource::Virtual(clock) = source.as_ref() else {
+                    unreachable!()
+                };
+                //The runtime has to stay idle over a whole period, to not miss a task woken up in between.
+                let Some(before) = clock.idle_snapshot(&metrics) else {
+                    continue;
+                };
+                thread::sleep(period);
+                if clock.idle_snapshot(&metrics).as_ref() == Some(&before) {
+                    clock.advance();
+                }
+            }
+        });
     }
 
     /*pub async fn reset(&self) {
@@ -36,6 +150,7 @@ impl ClockManager {
         let (tx, rx) = watch::channel(0);
         let mut process =
             ProcessInterface::new("GLOBAL_CLOCK", PROCESS_TOPIC_OMPAS, LOG_TOPIC_OMPAS).await;
+        let clock = self.clone();
         tokio::spawn(
             async move {
                 let mut tick = 0;
@@ -44,7 +159,7 @@ impl ClockManager {
                         _ = process.recv() => {
                             break 'main;
                         }
-                        _ = tokio::time::sleep(Duration::from_micros(period)) => {
+                        _ = clock.sleep(Duration::from_micros(period)) => {
                             //println!("{}", tick);
                             if tx.send(tick).is_err() {
                                 //
@@ -69,3 +184,168 @@ impl ClockManager {
         }
     }
 }
+
+struct VirtualTimer {
+    deadline: u64,
+    id: u64,
+    waker: oneshot::Sender<()>,
+}
+
+impl PartialEq for VirtualTimer {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline && self.id == other.id
+    }
+}
+
+impl Eq for VirtualTimer {}
+
+impl PartialOrd for VirtualTimer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VirtualTimer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.deadline, self.id).cmp(&(other.deadline, other.id))
+    }
+}
+
+/// Prevents the virtual time from advancing until dropped.
+pub struct BusyGuard {
+    source: Arc<ClockSource>,
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        if let ClockSource::Virtual(clock) = self.source.as_ref() {
+            clock.busy.fetch_sub(1, AtomicOrdering::AcqRel);
+        }
+    }
+}
+
+/// Discrete-event clock. The time (in microseconds) only moves forward when every task is
+/// blocked, waiting on the clock: all the workers of the runtime are parked and no busy guard
+/// is alive. It then jumps to the earliest pending timer and wakes up all timers sharing this deadline.
+#[derive(Default)]
+struct VirtualClock {
+    now: AtomicU64,
+    next_timer_id: AtomicU64,
+    busy: AtomicUsize,
+    driver_started: AtomicBool,
+    timers: Mutex<BinaryHeap<Reverse<VirtualTimer>>>,
+}
+
+impl VirtualClock {
+    fn now(&self) -> u64 {
+        self.now.load(AtomicOrdering::Acquire)
+    }
+
+    /// Returns the park counters of the workers if no task can make progress, None otherwise.
+    /// A worker is parked when its counter is odd.
+    fn idle_snapshot(&self, metrics: &RuntimeMetrics) -> Option<Vec<u64>> {
+        if self.busy.load(AtomicOrdering::Acquire) > 0 || metrics.global_queue_depth() > 0 {
+            return None;
+        }
+        let counters: Vec<u64> = (0..metrics.num_workers())
+            .map(|worker| metrics.worker_park_unpark_count(worker))
+            .collect();
+        counters.iter().all(|c| c % 2 == 1).then_some(counters)
+    }
+
+    fn register(&self, duration: Duration) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        let timer = VirtualTimer {
+            deadline: self.now() + duration.as_micros() as u64,
+            id: self.next_timer_id.fetch_add(1, AtomicOrdering::AcqRel),
+            waker: tx,
+        };
+        self.timers.lock().unwrap().push(Reverse(timer));
+        rx
+    }
+
+    /// Jumps to the next deadline and wakes up the corresponding timers.
+    /// Timers whose receiver has been dropped (e.g. an interrupted sleep) are discarded
+    /// without moving the time.
+    fn advance(&self) {
+        let mut timers = self.timers.lock().unwrap();
+        while let Some(Reverse(timer)) = timers.peek() {
+            if timer.waker.is_closed() {
+                timers.pop();
+            } else {
+                break;
+            }
+        }
+        let deadline = match timers.peek() {
+            Some(Reverse(timer)) => timer.deadline,
+            None => return,
+        };
+        if deadline > self.now() {
+            self.now.store(deadline, AtomicOrdering::Release);
+        }
+        while let Some(Reverse(timer)) = timers.peek() {
+            if timer.deadline > deadline {
+                break;
+            }
+            let Reverse(timer) = timers.pop().unwrap();
+            let _ = timer.waker.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_virtual_clock() {
+        let clock = ClockManager::new(ClockMode::Virtual);
+        assert_eq!(clock.now(), Timepoint::new_micros(0));
+
+        let c1 = clock.clone();
+        let long = tokio::spawn(async move {
+            c1.sleep(Duration::from_secs(3600)).await;
+            c1.now()
+        });
+        let c2 = clock.clone();
+        let short = tokio::spawn(async move {
+            c2.sleep(Duration::from_secs(60)).await;
+            c2.now()
+        });
+
+        let real = std::time::Instant::now();
+        assert_eq!(short.await.unwrap(), Timepoint::new_micros(60_000_000));
+        assert_eq!(long.await.unwrap(), Timepoint::new_micros(3_600_000_000));
+        assert!(real.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_virtual_clock_busy() {
+        let clock = ClockManager::new(ClockMode::Virtual);
+        let c1 = clock.clone();
+        let sleeper = tokio::spawn(async move {
+            c1.sleep(Duration::from_secs(60)).await;
+            c1.now()
+        });
+
+        //Work done outside of the runtime keeps the time from advancing.
+        let busy = clock.busy();
+        tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        assert_eq!(clock.now(), Timepoint::new_micros(0));
+        assert!(!sleeper.is_finished());
+
+        //So does a task running on the runtime.
+        drop(busy);
+        let c2 = clock.clone();
+        let now = tokio::spawn(async move {
+            std::thread::sleep(Duration::from_millis(50));
+            c2.now()
+        })
+        .await
+        .unwrap();
+        assert_eq!(now, Timepoint::new_micros(0));
+        assert_eq!(sleeper.await.unwrap(), Timepoint::new_micros(60_000_000));
+    }
+}
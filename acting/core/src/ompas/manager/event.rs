@@ -1,4 +1,4 @@
-use crate::model::acting_domain::event::{Event, TriggerActivation};
+use crate::model::acting_domain::event::{Event, TriggerActivation, TriggerKind};
 use crate::ompas::interface::job::Job;
 use crate::ompas::interface::rae_command::OMPASJob;
 use crate::ompas::interface::trigger_collection::Response;
@@ -15,10 +15,12 @@ use ompas_middleware::logger::LogClient;
 use ompas_middleware::ProcessInterface;
 use sompas_core::eval;
 use sompas_structs::lenv::LEnv;
+use sompas_structs::lruntimeerror::LResult;
 use sompas_structs::lvalue::LValue;
 use sompas_structs::lvalues::LValueS;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::{oneshot, Mutex, RwLock};
 use tokio::task::JoinHandle;
@@ -59,12 +61,120 @@ impl FluentCollection {
     }
 }
 
-#[derive(Clone, Hash, PartialEq, Eq)]
+/// Margin, in seconds, on the comparison of dates, so that a timer waking up at its deadline fires.
+const TIMER_EPSILON: f64 = 1e-6;
+
+#[derive(Clone)]
 pub struct WatchedEvent {
     label: String,
     pre_condition: LValue,
     body: LValue,
     trigger_activation: TriggerActivation,
+    kind: TriggerKind,
+    arming: Option<LValue>,
+    status: TriggerStatus,
+}
+
+/// Temporal status of a watched event, updated at each check of its conditions.
+#[derive(Clone, Default)]
+struct TriggerStatus {
+    /// Value of the pre-conditions at the last check.
+    holds: bool,
+    /// Value of the arming conditions at the last check.
+    armed: bool,
+    /// Date, in seconds, since which the pre-conditions hold, or since which the timeout runs.
+    since: Option<f64>,
+    last_fire: Option<f64>,
+}
+
+impl WatchedEvent {
+    fn new(event: &Event, parameters: Vec<LValue>, now: f64) -> Self {
+        let apply = |lambda: &LValue| {
+            let mut expr = vec![lambda.clone()];
+            expr.append(&mut parameters.clone());
+            LValue::from(expr)
+        };
+        let arming = event.trigger.arming.as_ref().map(apply);
+        let mut status = TriggerStatus::default();
+        //Without arming conditions, the timeout starts when the event is watched.
+        if matches!(event.trigger.kind, TriggerKind::Within(_)) && arming.is_none() {
+            status.armed = true;
+            status.since = Some(now);
+        }
+        Self {
+            label: event.label.to_string(),
+            pre_condition: apply(&event.trigger.pre_conditions),
+            body: apply(&event.lambda_body),
+            trigger_activation: event.trigger.trigger_activation,
+            kind: event.trigger.kind,
+            arming,
+            status,
+        }
+    }
+
+    /// Returns true if the trigger depends on the time, and not only on the updates of the state.
+    fn is_timed(&self) -> bool {
+        matches!(
+            self.kind,
+            TriggerKind::For(_) | TriggerKind::Every(_) | TriggerKind::Within(_)
+        )
+    }
+
+    /// Updates the status with the values of the conditions at *now*,
+    /// and returns true if the event fires.
+    fn update(&mut self, holds: bool, armed: bool, now: f64) -> bool {
+        let status = &mut self.status;
+        let held = std::mem::replace(&mut status.holds, holds);
+        let fire = match self.kind {
+            TriggerKind::Level => holds,
+            TriggerKind::Rising => holds && !held,
+            TriggerKind::Falling => !holds && held,
+            TriggerKind::For(d) => match holds {
+                true => now - *status.since.get_or_insert(now) + TIMER_EPSILON >= d,
+                false => {
+                    status.since = None;
+                    false
+                }
+            },
+            TriggerKind::Every(d) => {
+                holds
+                    && status
+                        .last_fire
+                        .is_none_or(|t| now - t + TIMER_EPSILON >= d)
+            }
+            TriggerKind::Within(d) => {
+                let was_armed = std::mem::replace(&mut status.armed, armed);
+                if armed && !was_armed {
+                    status.since = Some(now);
+                }
+                match status.since {
+                    //the conditions became true in time
+                    Some(_) if holds => {
+                        status.since = None;
+                        false
+                    }
+                    Some(since) => now - since + TIMER_EPSILON >= d,
+                    None => false,
+                }
+            }
+        };
+        if fire {
+            status.last_fire = Some(now);
+            if matches!(self.kind, TriggerKind::For(_) | TriggerKind::Within(_)) {
+                status.since = None;
+            }
+        }
+        fire
+    }
+
+    /// Date at which the trigger may fire without any update of the state.
+    fn deadline(&self) -> Option<f64> {
+        match self.kind {
+            TriggerKind::For(d) | TriggerKind::Within(d) => self.status.since.map(|t| t + d),
+            TriggerKind::Every(d) if self.status.holds => self.status.last_fire.map(|t| t + d),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -83,7 +193,12 @@ pub struct EventCollection {
 }
 
 impl EventCollection {
-    pub fn init_events(&mut self, events: HashMap<String, Event>, instances: InstanceCollection) {
+    pub fn init_events(
+        &mut self,
+        events: HashMap<String, Event>,
+        instances: InstanceCollection,
+        now: f64,
+    ) {
         for t in instances.inner.keys() {
             self.managed_events
                 .insert(t.to_string(), ManagedEvent::default());
@@ -101,11 +216,11 @@ impl EventCollection {
             }
         }
         self.events = events;
-        self.init_watched_events(instances)
+        self.init_watched_events(instances, now)
     }
-    fn init_watched_events(&mut self, mut instances: InstanceCollection) {
+    fn init_watched_events(&mut self, mut instances: InstanceCollection, now: f64) {
         //Create
-        for (label, event) in &self.events {
+        for event in self.events.values() {
             let mut params_enum: Vec<_> = vec![];
             for (_, param) in event.parameters.inner() {
                 let t_label = param.get_debug().to_string();
@@ -119,19 +234,7 @@ impl EventCollection {
                 for param in e {
                     parameters.push((*param).into())
                 }
-                let mut pre_condition = vec![event.trigger.pre_conditions.clone()];
-                pre_condition.append(&mut parameters.clone());
-                let pre_condition = pre_condition.into();
-                let mut body = vec![event.lambda_body.clone()];
-                body.append(&mut parameters);
-                let body = body.into();
-                let event = WatchedEvent {
-                    label: label.to_string(),
-                    pre_condition,
-                    trigger_activation: event.trigger.trigger_activation,
-                    body,
-                };
-                self.watched.push(event)
+                self.watched.push(WatchedEvent::new(event, parameters, now))
             }
         }
 
@@ -146,6 +249,7 @@ impl EventCollection {
         r#type: String,
         instance: String,
         instances: &mut InstanceCollection,
+        now: f64,
     ) {
         let managed_event = self.managed_events.get(&r#type).unwrap();
 
@@ -169,19 +273,7 @@ impl EventCollection {
                 for param in e {
                     parameters.push((*param).into())
                 }
-                let mut pre_condition = vec![event.trigger.pre_conditions.clone()];
-                pre_condition.append(&mut parameters.clone());
-                let pre_condition = pre_condition.into();
-                let mut body = vec![event.lambda_body.clone()];
-                body.append(&mut parameters);
-                let body = body.into();
-                let event = WatchedEvent {
-                    label: event.label.to_string(),
-                    pre_condition,
-                    trigger_activation: event.trigger.trigger_activation,
-                    body,
-                };
-                self.watched.push(event)
+                self.watched.push(WatchedEvent::new(event, parameters, now))
             }
         }
     }
@@ -219,7 +311,8 @@ impl EventManager {
                 Ok(lv) => {
                     if let LValue::True = lv {
                         let waiter = waiters.map.remove(id).unwrap();
-                        waiter.channel.send(true).expect("");
+                        //the waiter may have been cancelled in the meantime
+                        let _ = waiter.channel.send(true);
                         log.debug(format!("{} became true", waiter.lambda));
                     }
                 }
@@ -239,13 +332,11 @@ impl EventManager {
         let mut event_collection = self.event_collection.lock().await;
         event_collection.tx_ompas = Some(tx_ompas);
         let instances = self.state_manager.get_instance_collection().await;
-        event_collection.init_events(events, instances);
+        event_collection.init_events(events, instances, self.clock_manager.now().as_secs());
     }
 
     pub async fn check_events(&self, updated: StateUpdate) {
-        let mut env = self.env.read().await.clone();
         let mut instances = self.state_manager.get_instance_collection().await;
-        let log = self.log.read().await.clone();
         let mut event_collection = self.event_collection.lock().await;
         let new_instances: Vec<_> = updated
             .iter()
@@ -259,17 +350,52 @@ impl EventManager {
             })
             .collect();
 
+        let now = self.clock_manager.now().as_secs();
         for (t, instance) in new_instances {
-            event_collection.populate_events_for_new_instance(t, instance, &mut instances);
+            event_collection.populate_events_for_new_instance(t, instance, &mut instances, now);
         }
 
+        self.check_watched(&mut event_collection, false).await
+    }
+
+    /// Checks the events whose triggers depend on the time.
+    pub async fn check_timers(&self) {
+        let mut event_collection = self.event_collection.lock().await;
+        self.check_watched(&mut event_collection, true).await
+    }
+
+    /// Returns the delay after which a timed trigger may fire, if any.
+    pub async fn next_timeout(&self) -> Option<Duration> {
+        let now = self.clock_manager.now().as_secs();
+        self.event_collection
+            .lock()
+            .await
+            .watched
+            .iter()
+            .filter_map(|e| e.deadline())
+            .min_by(f64::total_cmp)
+            .map(|t| Duration::from_micros(((t - now).max(0.0) * 1e6).ceil() as u64))
+    }
+
+    async fn check_watched(&self, event_collection: &mut EventCollection, timers_only: bool) {
+        let mut env = self.env.read().await.clone();
+        let log = self.log.read().await.clone();
+        let now = self.clock_manager.now().as_secs();
+
+        let holds = |r: LResult| matches!(r, Ok(lv) if !matches!(lv, LValue::Err(_)));
         let mut to_run: Vec<usize> = vec![];
-        for (i, event) in event_collection.watched.iter().enumerate() {
-            if let Ok(r) = eval(&event.pre_condition, &mut env, None).await {
-                if !matches!(r, LValue::Err(_)) {
-                    log.info(format!("event triggered: {} ", event.body));
-                    to_run.push(i)
-                }
+        for (i, event) in event_collection.watched.iter_mut().enumerate() {
+            if timers_only && !event.is_timed() {
+                continue;
+            }
+            let pre_conditions = holds(eval(&event.pre_condition, &mut env, None).await);
+            let armed = match &event.arming {
+                Some(arming) => holds(eval(arming, &mut env, None).await),
+                None => true,
+            };
+            if event.update(pre_conditions, armed, now) {
+                log.info(format!("event triggered: {} ", event.body));
+                to_run.push(i)
             }
         }
 
@@ -357,10 +483,21 @@ pub async fn run_event_checker(
     *event_manager.env.write().await = env;
     event_manager.init_events(events, tx_ompas).await;
     loop {
+        let timeout = event_manager.next_timeout().await;
+        let clock_manager = event_manager.clock_manager.clone();
+        let timer = async move {
+            match timeout {
+                Some(timeout) => clock_manager.sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
         tokio::select! {
             Some(updated) = update.channel.recv() => {
                 event_manager.check_events(updated).await
             }
+            _ = timer => {
+                event_manager.check_timers().await
+            }
             _ = process.recv() => {
                 break;
             }
@@ -386,3 +523,60 @@ pub async fn run_fluent_checker(mut update: StateUpdateSubscriber, event_manager
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn watched(kind: TriggerKind) -> WatchedEvent {
+        WatchedEvent {
+            label: "e".to_string(),
+            pre_condition: LValue::Nil,
+            body: LValue::Nil,
+            trigger_activation: TriggerActivation::Whenever,
+            kind,
+            arming: None,
+            status: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_trigger_kind() {
+        let mut rising = watched(TriggerKind::Rising);
+        assert!(rising.update(true, true, 0.0));
+        assert!(!rising.update(true, true, 1.0));
+        assert!(!rising.update(false, true, 2.0));
+        assert!(rising.update(true, true, 3.0));
+
+        let mut falling = watched(TriggerKind::Falling);
+        assert!(!falling.update(true, true, 0.0));
+        assert!(falling.update(false, true, 1.0));
+
+        let mut held = watched(TriggerKind::For(5.0));
+        assert!(!held.update(true, true, 0.0));
+        assert_eq!(held.deadline(), Some(5.0));
+        assert!(!held.update(false, true, 3.0));
+        assert!(!held.update(true, true, 4.0));
+        assert!(held.update(true, true, 9.0));
+
+        let mut every = watched(TriggerKind::Every(2.0));
+        assert!(every.update(true, true, 0.0));
+        assert!(!every.update(true, true, 1.0));
+        assert_eq!(every.deadline(), Some(2.0));
+        assert!(every.update(true, true, 2.0));
+
+        //the watchdog is armed at 1, and the conditions are false 60s later
+        let mut watchdog = watched(TriggerKind::Within(60.0));
+        assert!(!watchdog.update(false, false, 0.0));
+        assert!(!watchdog.update(false, true, 1.0));
+        assert_eq!(watchdog.deadline(), Some(61.0));
+        assert!(watchdog.update(false, true, 61.0));
+        assert_eq!(watchdog.deadline(), None);
+        //rearmed, and satisfied in time
+        assert!(!watchdog.update(false, false, 70.0));
+        assert!(!watchdog.update(false, true, 71.0));
+        assert_eq!(watchdog.deadline(), Some(131.0));
+        assert!(!watchdog.update(true, true, 80.0));
+        assert!(!watchdog.update(false, true, 200.0));
+    }
+}
@@ -0,0 +1,302 @@
+//! Statistics on the outcomes of the executed methods, learned across the runs of the engine.
+//! Each execution of a method updates the value estimate of the triple (task, method, abstracted
+//! state), the abstracted state being the dynamic facts about the arguments of the method, lifted
+//! by replacing the arguments by their position.
+//! The statistics are saved in a JSON file after each outcome, and loaded back at the next run.
+use crate::ompas::manager::acting::ActingProcessId;
+use crate::ompas::manager::state::world_state_snapshot::WorldStateSnapshot;
+use crate::ompas::manager::state::StateType;
+use serde::{Deserialize, Serialize};
+use sompas_structs::lruntimeerror;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Efficiency of a method executed instantaneously.
+const MAX_EFFICIENCY: f64 = 10e12;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LearningKey {
+    pub task: String,
+    pub method: String,
+    pub state: String,
+}
+
+impl LearningKey {
+    /// Builds the key of the method instance, refining the task, in the given state.
+    pub fn new(task: &[LValue], method: &[LValue], state: &WorldStateSnapshot) -> Self {
+        Self {
+            task: task[0].to_string(),
+            method: method[0].to_string(),
+            state: abstract_state(&method[1..], state),
+        }
+    }
+}
+
+impl Display for LearningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {} in {{{}}}", self.task, self.method, self.state)
+    }
+}
+
+/// Returns the dynamic facts referring to one of the parameters, the parameters being
+/// replaced by ?0, ?1, ... Floats are rounded to one decimal so that close states are merged.
+pub fn abstract_state(params: &[LValue], state: &WorldStateSnapshot) -> String {
+    let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+
+    fn lift(lv: &LValueS, params: &[String]) -> String {
+        match lv {
+            LValueS::Symbol(s) => match params.iter().position(|p| p == s) {
+                Some(i) => format!("?{i}"),
+                None => s.clone(),
+            },
+            LValueS::Float(f) => format!("{:.1}", f),
+            LValueS::List(list) => format!(
+                "({})",
+                list.iter()
+                    .map(|e| lift(e, params))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            lv => lv.to_string(),
+        }
+    }
+
+    fn refers_to(lv: &LValueS, params: &[String]) -> bool {
+        match lv {
+            LValueS::Symbol(s) => params.contains(s),
+            LValueS::List(list) => list.iter().any(|e| refers_to(e, params)),
+            _ => false,
+        }
+    }
+
+    let facts: BTreeSet<String> = state
+        .get_state(Some(StateType::Dynamic))
+        .inner
+        .iter()
+        .filter(|(k, v)| refers_to(k, &params) || refers_to(&v.value, &params))
+        .map(|(k, v)| format!("{} = {}", lift(k, &params), lift(&v.value, &params)))
+        .collect();
+    facts.into_iter().collect::<Vec<_>>().join("; ")
+}
+
+/// Value estimates of a method, computed on its executions.
+/// *efficiency* is the mean of the inverse of the execution time of the successful executions,
+/// a failure counting for 0, as the efficiency used by UPOM.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MethodStats {
+    pub n: u64,
+    pub successes: u64,
+    pub efficiency: f64,
+}
+
+impl MethodStats {
+    pub fn update(&mut self, success: bool, duration: f64) {
+        let reward = match success {
+            true if duration > 1.0 / MAX_EFFICIENCY => 1.0 / duration,
+            true => MAX_EFFICIENCY,
+            false => 0.0,
+        };
+        self.efficiency = (self.n as f64 * self.efficiency + reward) / (self.n + 1) as f64;
+        self.n += 1;
+        if success {
+            self.successes += 1;
+        }
+    }
+
+    pub fn success_rate(&self) -> f64 {
+        match self.n {
+            0 => 0.0,
+            n => self.successes as f64 / n as f64,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LearningEntry {
+    #[serde(flatten)]
+    key: LearningKey,
+    #[serde(flatten)]
+    stats: MethodStats,
+}
+
+#[derive(Default)]
+struct InnerLearningManager {
+    stats: HashMap<LearningKey, MethodStats>,
+    /// Executed methods whose outcome is not known yet.
+    pending: HashMap<ActingProcessId, LearningKey>,
+    /// File in which the statistics are saved, set once the learning is enabled.
+    path: Option<PathBuf>,
+}
+
+impl InnerLearningManager {
+    fn save(&self) -> Result<(), LRuntimeError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut entries: Vec<LearningEntry> = self
+            .stats
+            .iter()
+            .map(|(key, stats)| LearningEntry {
+                key: key.clone(),
+                stats: *stats,
+            })
+            .collect();
+        entries.sort_by(|e1, e2| e1.key.cmp(&e2.key));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| lruntimeerror!("LearningManager::save", e.to_string()))?;
+        }
+        let content = serde_json::to_string_pretty(&entries)
+            .map_err(|e| lruntimeerror!("LearningManager::save", e.to_string()))?;
+        std::fs::write(path, content).map_err(|e| {
+            lruntimeerror!(
+                "LearningManager::save",
+                format!("could not write {}: {}", path.display(), e)
+            )
+        })
+    }
+}
+
+/// Learns the value of the methods from their executions in the acting tree.
+#[derive(Clone, Default)]
+pub struct LearningManager {
+    inner: Arc<Mutex<InnerLearningManager>>,
+}
+
+impl LearningManager {
+    /// Enables the learning, loading the statistics saved in the file if it exists.
+    /// The statistics are then saved in the file after each outcome.
+    pub fn enable(&self, path: PathBuf) -> Result<(), LRuntimeError> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.path.as_ref() == Some(&path) {
+            return Ok(());
+        }
+        if path.exists() {
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                lruntimeerror!(
+                    "LearningManager::enable",
+                    format!("could not read {}: {}", path.display(), e)
+                )
+            })?;
+            let entries: Vec<LearningEntry> = serde_json::from_str(&content).map_err(|e| {
+                lruntimeerror!(
+                    "LearningManager::enable",
+                    format!("{}: invalid statistics: {}", path.display(), e)
+                )
+            })?;
+            inner.stats = entries.into_iter().map(|e| (e.key, e.stats)).collect();
+        }
+        inner.path = Some(path);
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().path.is_some()
+    }
+
+    pub fn get_stats(&self, key: &LearningKey) -> Option<MethodStats> {
+        self.inner.lock().unwrap().stats.get(key).copied()
+    }
+
+    /// Registers the key of a method that starts its execution.
+    pub fn start(&self, method: ActingProcessId, key: LearningKey) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.path.is_some() {
+            inner.pending.insert(method, key);
+        }
+    }
+
+    /// Updates the statistics with the outcome of an executed method, and saves them.
+    pub fn end(
+        &self,
+        method: &ActingProcessId,
+        success: bool,
+        duration: f64,
+    ) -> Result<(), LRuntimeError> {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(key) = inner.pending.remove(method) else {
+            return Ok(());
+        };
+        inner
+            .stats
+            .entry(key)
+            .or_default()
+            .update(success, duration);
+        inner.save()
+    }
+}
+
+impl Display for LearningManager {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        match &inner.path {
+            Some(path) => writeln!(f, "statistics saved in {}", path.display())?,
+            None => writeln!(f, "learning disabled")?,
+        }
+        let mut stats: Vec<_> = inner.stats.iter().collect();
+        stats.sort_by_key(|(k, _)| *k);
+        for (key, s) in stats {
+            writeln!(
+                f,
+                "{key}: n = {}, success rate = {:.2}, efficiency = {:.3}",
+                s.n,
+                s.success_rate(),
+                s.efficiency
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ompas::manager::state::partial_state::PartialState;
+
+    #[test]
+    fn test_learning() {
+        let mut dynamic: im::HashMap<LValueS, LValueS> = Default::default();
+        let at = |r: &str| LValueS::List(vec!["robot.at".into(), r.into()]);
+        dynamic.insert(at("r1"), "l1".into());
+        dynamic.insert(at("r2"), "l2".into());
+        dynamic.insert(
+            LValueS::List(vec!["robot.battery".into(), "r1".into()]),
+            LValueS::Float(0.84),
+        );
+        let state = WorldStateSnapshot {
+            dynamic: PartialState::from(dynamic),
+            ..Default::default()
+        };
+
+        let task: Vec<LValue> = vec!["t_move".into(), "r1".into(), "l2".into()];
+        let method: Vec<LValue> = vec!["m_move".into(), "r1".into(), "l2".into()];
+        let key = LearningKey::new(&task, &method, &state);
+        assert_eq!(
+            key.state,
+            "(robot.at ?0) = l1; (robot.at r2) = ?1; (robot.battery ?0) = 0.8"
+        );
+
+        let path = std::env::temp_dir().join(format!("ompas_learning_{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let learning = LearningManager::default();
+        learning.enable(path.clone()).unwrap();
+        learning.start(3, key.clone());
+        learning.end(&3, true, 2.0).unwrap();
+        learning.start(5, key.clone());
+        learning.end(&5, false, 1.0).unwrap();
+        let stats = learning.get_stats(&key).unwrap();
+        assert_eq!(stats.n, 2);
+        assert_eq!(stats.success_rate(), 0.5);
+        assert_eq!(stats.efficiency, 0.25);
+
+        let learning = LearningManager::default();
+        learning.enable(path.clone()).unwrap();
+        assert_eq!(learning.get_stats(&key), Some(stats));
+        let _ = std::fs::remove_file(&path);
+    }
+}
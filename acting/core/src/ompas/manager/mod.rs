@@ -3,7 +3,11 @@ pub mod clock;
 pub mod deliberation;
 pub mod domain;
 pub mod event;
+pub mod learning;
+pub mod monitoring;
 pub mod planning;
 pub mod platform;
+pub mod preemption;
 pub mod resource;
 pub mod state;
+pub mod trace;
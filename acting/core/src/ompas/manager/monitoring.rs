@@ -0,0 +1,357 @@
+//! Monitoring of the execution against the planned models.
+//! The chronicles of the processes of the acting tree give the expected values of the state
+//! variables: the conditions of the processes that are not terminated, and the effects of the
+//! processes that ended with success. The expectations are compared with the observed state, and
+//! a mismatch is recorded as a *divergence*, before it makes a method fail.
+//! The divergences are resolved with the policy OMPAS_DIVERGENCE_POLICY.
+use crate::model::chronicle::effect::EffectOperationInner;
+use crate::model::process_ref::ProcessRef;
+use crate::model::sym_table::r#ref::RefSymTable;
+use crate::model::sym_table::VarId;
+use crate::ompas::manager::acting::{ActingManager, ActingProcessId};
+use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::state::partial_state::PartialState;
+use crate::ompas::manager::state::state_update_manager::StateUpdateSubscriber;
+use crate::planning::planner::problem::ChronicleInstance;
+use ompas_language::process::{LOG_TOPIC_OMPAS, PROCESS_MONITOR_EXECUTION, PROCESS_TOPIC_OMPAS};
+use ompas_middleware::{LogLevel, ProcessInterface};
+use serde::{Deserialize, Serialize};
+use sompas_structs::lvalues::LValueS;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// Tolerance of the comparison of numerical values.
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectationKind {
+    /// Condition of a process that is not terminated, on a state variable that no planned effect
+    /// changes.
+    Condition,
+    /// Effect of a process that ended with success, on a state variable that no planned effect
+    /// changes afterwards.
+    Effect,
+}
+
+impl Display for ExpectationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpectationKind::Condition => write!(f, "condition"),
+            ExpectationKind::Effect => write!(f, "effect"),
+        }
+    }
+}
+
+/// Expected value of a state variable, given by the chronicle of a process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expectation {
+    pub process: ActingProcessId,
+    pub kind: ExpectationKind,
+    pub sv: LValueS,
+    pub value: LValueS,
+}
+
+impl Expectation {
+    /// Returns true if the observed value is the expected one.
+    pub fn is_met(&self, observed: Option<&LValueS>) -> bool {
+        match (&self.value, observed) {
+            (_, None) => false,
+            (LValueS::Int(_) | LValueS::Float(_), Some(LValueS::Int(_) | LValueS::Float(_))) => {
+                let expected: f64 = (&self.value).try_into().unwrap();
+                let observed: f64 = observed.unwrap().try_into().unwrap();
+                (expected - observed).abs() <= EPSILON
+            }
+            (expected, Some(observed)) => expected == observed,
+        }
+    }
+}
+
+/// Mismatch between the expected value of a state variable and its observed value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub process: ActingProcessId,
+    pub kind: ExpectationKind,
+    pub sv: LValueS,
+    pub expected: LValueS,
+    pub observed: Option<LValueS>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of process({}): {} expected to be {}, observed ",
+            self.kind, self.process, self.sv, self.expected
+        )?;
+        match &self.observed {
+            Some(observed) => write!(f, "{observed}"),
+            None => write!(f, "undefined"),
+        }
+    }
+}
+
+/// Returns the expectations given by the chronicles of the acting tree.
+/// Only the conditions and assignments whose state variable and value are instantiated are kept.
+pub fn get_expectations(
+    chronicles: &[ChronicleInstance],
+    status: impl Fn(&ActingProcessId) -> ProcessStatus,
+    st: &RefSymTable,
+) -> Vec<Expectation> {
+    let ground = |var: &VarId| st.var_as_cst(*var).map(LValueS::from);
+    let ground_sv = |sv: &[VarId]| -> Option<LValueS> {
+        let mut sv: Vec<LValueS> = sv.iter().map(ground).collect::<Option<_>>()?;
+        Some(match sv.len() {
+            1 => sv.remove(0),
+            _ => LValueS::List(sv),
+        })
+    };
+
+    let chronicles: Vec<(ActingProcessId, ProcessStatus, &ChronicleInstance)> = chronicles
+        .iter()
+        .filter_map(|c| match c.pr {
+            ProcessRef::Id(id) => Some((id, status(&id), c)),
+            ProcessRef::Relative(..) => None,
+        })
+        .collect();
+
+    //State variables that the processes not terminated are planned to change.
+    let planned: HashSet<LValueS> = chronicles
+        .iter()
+        .filter(|(_, status, _)| !status.is_terminated())
+        .flat_map(|(_, _, c)| c.instantiated_chronicle.get_effects())
+        .filter_map(|e| ground_sv(&e.sv))
+        .collect();
+
+    let mut expectations = vec![];
+    for (id, status, c) in chronicles {
+        let chronicle = &c.instantiated_chronicle;
+        if status.is_success() {
+            for e in chronicle.get_effects() {
+                if !matches!(e.operation.inner, EffectOperationInner::Assign) {
+                    continue;
+                }
+                if let (Some(sv), Some(value)) = (ground_sv(&e.sv), ground(&e.operation.var_id)) {
+                    if !planned.contains(&sv) {
+                        expectations.push(Expectation {
+                            process: id,
+                            kind: ExpectationKind::Effect,
+                            sv,
+                            value,
+                        })
+                    }
+                }
+            }
+        } else if !status.is_terminated() {
+            for condition in chronicle.get_conditions() {
+                if let (Some(sv), Some(value)) =
+                    (ground_sv(&condition.sv), ground(&condition.value))
+                {
+                    if !planned.contains(&sv) {
+                        expectations.push(Expectation {
+                            process: id,
+                            kind: ExpectationKind::Condition,
+                            sv,
+                            value,
+                        })
+                    }
+                }
+            }
+        }
+    }
+    expectations
+}
+
+#[derive(Default)]
+struct InnerExecutionMonitor {
+    /// Processes whose effects have been seen once.
+    seen: HashSet<ActingProcessId>,
+    /// Processes whose effects have been compared with the state.
+    checked: HashSet<ActingProcessId>,
+    /// Divergent expectations, reported only once.
+    reported: HashSet<(ActingProcessId, LValueS)>,
+    divergences: Vec<Divergence>,
+}
+
+/// Compares the expectations of the acting tree with the observed state.
+#[derive(Clone, Default)]
+pub struct ExecutionMonitor {
+    inner: Arc<Mutex<InnerExecutionMonitor>>,
+}
+
+impl ExecutionMonitor {
+    /// Returns the new divergences between the expectations and the state.
+    /// The expectations are checked when their state variable is updated. The effects of a
+    /// terminated process are also checked at the first check after the one in which they appear,
+    /// so that the platform has the time to send the updates of the state.
+    pub fn check(
+        &self,
+        expectations: Vec<Expectation>,
+        state: &PartialState,
+        updated: &HashSet<LValueS>,
+    ) -> Vec<Divergence> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut seen = vec![];
+        let mut ended = vec![];
+        let mut to_check = vec![];
+        for e in expectations {
+            let check = if e.kind == ExpectationKind::Effect && !inner.checked.contains(&e.process)
+            {
+                if inner.seen.contains(&e.process) {
+                    ended.push(e.process);
+                    true
+                } else {
+                    seen.push(e.process);
+                    updated.contains(&e.sv)
+                }
+            } else {
+                updated.contains(&e.sv)
+            };
+            if check {
+                to_check.push(e)
+            }
+        }
+        inner.seen.extend(seen);
+        inner.checked.extend(ended);
+
+        let mut divergences = vec![];
+        for e in to_check {
+            let observed = state.get(&e.sv).map(|f| f.value.clone());
+            if e.is_met(observed.as_ref()) || !inner.reported.insert((e.process, e.sv.clone())) {
+                continue;
+            }
+            divergences.push(Divergence {
+                process: e.process,
+                kind: e.kind,
+                sv: e.sv,
+                expected: e.value,
+                observed,
+            })
+        }
+        inner.divergences.extend(divergences.iter().cloned());
+        divergences
+    }
+
+    pub fn get_divergences(&self) -> Vec<Divergence> {
+        self.inner.lock().unwrap().divergences.clone()
+    }
+
+    pub fn clear(&self) {
+        *self.inner.lock().unwrap() = Default::default();
+    }
+}
+
+impl Display for ExecutionMonitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock().unwrap();
+        if inner.divergences.is_empty() {
+            return writeln!(f, "no divergence");
+        }
+        for d in &inner.divergences {
+            writeln!(f, "{d}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Monitors the execution on each update of the state.
+pub async fn run_execution_monitor(
+    mut update: StateUpdateSubscriber,
+    acting_manager: ActingManager,
+) {
+    let mut process: ProcessInterface = ProcessInterface::new(
+        PROCESS_MONITOR_EXECUTION,
+        PROCESS_TOPIC_OMPAS,
+        LOG_TOPIC_OMPAS,
+    )
+    .await;
+    loop {
+        tokio::select! {
+            Some(updated) = update.channel.recv() => {
+                let mut updated: HashSet<LValueS> = updated.into_iter().collect();
+                while let Ok(next) = update.channel.try_recv() {
+                    updated.extend(next)
+                }
+                for divergence in acting_manager.monitor_execution(&updated).await {
+                    process.log(format!("divergence: {divergence}"), LogLevel::Warn);
+                }
+            }
+            _ = process.recv() => {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_execution_monitor() {
+        let at = |r: &str| LValueS::List(vec!["robot.at".into(), r.into()]);
+        let battery = LValueS::List(vec!["robot.battery".into(), "r1".into()]);
+        let door: LValueS = "door.open".into();
+        let mut facts: im::HashMap<LValueS, LValueS> = Default::default();
+        facts.insert(at("r1"), "l1".into());
+        facts.insert(battery.clone(), LValueS::Float(0.5));
+        facts.insert(door.clone(), true.into());
+
+        let expectations = vec![
+            Expectation {
+                process: 3,
+                kind: ExpectationKind::Condition,
+                sv: door.clone(),
+                value: true.into(),
+            },
+            Expectation {
+                process: 4,
+                kind: ExpectationKind::Effect,
+                sv: at("r1"),
+                value: "l2".into(),
+            },
+            Expectation {
+                process: 4,
+                kind: ExpectationKind::Effect,
+                sv: battery,
+                value: LValueS::Float(0.5000001),
+            },
+        ];
+
+        let monitor = ExecutionMonitor::default();
+        let state = PartialState::from(facts.clone());
+        //The effects are checked at the check following their appearance.
+        assert!(monitor
+            .check(expectations.clone(), &state, &Default::default())
+            .is_empty());
+        let divergences = monitor.check(expectations.clone(), &state, &Default::default());
+        assert_eq!(
+            divergences
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>(),
+            vec![format!(
+                "effect of process(4): {} expected to be l2, observed l1",
+                at("r1")
+            )]
+        );
+        //A divergence is reported only once.
+        let updated = HashSet::from([at("r1")]);
+        assert!(monitor
+            .check(expectations.clone(), &state, &updated)
+            .is_empty());
+
+        //The conditions are checked when their state variable is updated.
+        facts.insert(door.clone(), false.into());
+        let state = PartialState::from(facts);
+        assert!(monitor
+            .check(expectations.clone(), &state, &Default::default())
+            .is_empty());
+        let divergences = monitor.check(expectations, &state, &HashSet::from([door]));
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].kind, ExpectationKind::Condition);
+        assert_eq!(divergences[0].observed, Some(false.into()));
+        assert_eq!(monitor.get_divergences().len(), 2);
+    }
+}
@@ -19,7 +19,7 @@ use crate::ompas::manager::planning::acting_var_ref_table::ActingVarRefTable;
 use crate::ompas::manager::planning::plan_update::*;
 use crate::ompas::manager::planning::planner_manager_interface::FilterWatchedProcesses;
 use crate::ompas::manager::planning::planner_stat::{
-    PlannerMode, PlannerStat, PlanningInstanceStat, PlanningStatus,
+    PlannerMode, PlannerStat, PlanningInstanceStat, PlanningStatus, RepairStat,
 };
 use crate::ompas::manager::planning::problem_update::{ExecutionProblem, PlannerUpdate, VarUpdate};
 use crate::ompas::manager::resource::{ResourceManager, WaiterPriority};
@@ -39,6 +39,7 @@ use crate::planning::planner::result::PlanResult;
 use crate::planning::planner::solver::{PMetric, PlannerInterruptSender};
 use crate::{
     ChronicleDebug, OMPAS_CHRONICLE_DEBUG, OMPAS_DEBUG_CONTINUOUS_PLANNING, OMPAS_PLAN_OUTPUT,
+    OMPAS_PLAN_REPAIR,
 };
 use aries::collections::seq::Seq;
 use aries::model::extensions::{AssignmentExt, SavedAssignment, Shaped};
@@ -116,6 +117,7 @@ pub struct UpdateConfig {
     state_update_subscriber: StateUpdateSubscriber,
     acting_tree_update_subscriber: UnboundedReceiver<PlannerUpdate>,
     planner_reactivity: Reactivity<Planner>,
+    clock_manager: ClockManager,
 }
 
 impl UpdateConfig {
@@ -190,7 +192,10 @@ impl PlannerManager {
     }
 
     async fn wait_next_cycle(update_config: &mut UpdateConfig) -> Vec<PlannerUpdate> {
-        tokio::time::sleep(update_config.planner_reactivity.get_duration()).await;
+        update_config
+            .clock_manager
+            .sleep(update_config.planner_reactivity.get_duration())
+            .await;
         update_config.wait_on_update().await
     }
 
@@ -224,6 +229,7 @@ impl PlannerManager {
                 .await,
             acting_tree_update_subscriber: rx_update,
             planner_reactivity: planner_reactivity.clone(),
+            clock_manager: clock_manager.clone(),
         };
 
         let mut last_updates: Option<Vec<PlannerUpdate>> = None;
@@ -243,7 +249,12 @@ impl PlannerManager {
             let debug_date = DebugDate::new(instance);
             debug_date.print_msg("New planning instance");
 
-            let explanation = {
+            let reopened = match OMPAS_PLAN_REPAIR.get() {
+                true => acting_manager.read().await.get_reopened_tasks(&updates),
+                false => None,
+            };
+
+            let mut explanation = {
                 let mut explanation = format!("Planning instance n°{}\n", debug_date.instance);
                 for update in updates {
                     let update: PlannerUpdate = update;
@@ -279,7 +290,22 @@ impl PlannerManager {
             env.update_context(ModState::new_from_snapshot(new_state.clone()));
             debug_date.print_msg("Getting new state");
 
-            let chronicles = acting_manager.read().await.get_current_chronicles();
+            let (chronicles, repair) = match &reopened {
+                Some(reopened) => {
+                    let (chronicles, repair) = acting_manager
+                        .read()
+                        .await
+                        .get_repaired_chronicles(reopened);
+                    writeln!(
+                        explanation,
+                        "- Repair of the previous plan: {} refinements kept, {} reopened.",
+                        repair.kept, repair.reopened
+                    )
+                    .unwrap();
+                    (chronicles, Some(repair))
+                }
+                None => (acting_manager.read().await.get_current_chronicles(), None),
+            };
             debug_date.print_msg("Getting current chronicles");
 
             let ep = ExecutionProblem {
@@ -302,6 +328,7 @@ impl PlannerManager {
                 },
                 clock_manager: clock_manager.clone(),
                 explanation,
+                repair,
             };
 
             debug_date.print_msg("Creating new planning instance");
@@ -332,6 +359,12 @@ impl PlannerManager {
                                 }
                                 PlannerResult::Stat(stat) => {
                                         debug_date.print_msg("Planning instance terminated");
+                                        //A repair without solution falls back on the replanning of the whole tree.
+                                        if stat.status == PlanningStatus::Unsat
+                                            && stat.repair.is_some_and(|r| r.kept > 0)
+                                        {
+                                            last_updates = Some(vec![PlannerUpdate::Plan]);
+                                        }
                                         stats.write().await.add_stat(stat);
                                         break 'instance
                                 }
@@ -409,6 +442,8 @@ pub struct PlannerInstanceConfig {
     pub config: OMPASLCPConfig,
     pub clock_manager: ClockManager,
     pub explanation: String,
+    /// Part of the previous plan kept in the problem, if the previous plan is repaired.
+    pub repair: Option<RepairStat>,
 }
 
 pub struct PlannerInstance {
@@ -443,6 +478,7 @@ impl PlannerInstance {
             } else {
                 PlannerMode::Satisfactory
             },
+            repair: config.repair,
         };
 
         let PlannerInstanceConfig {
@@ -450,6 +486,7 @@ impl PlannerInstance {
             config,
             clock_manager,
             explanation,
+            ..
         } = config;
 
         let debug_date = config.debug_date;
@@ -468,7 +505,9 @@ impl PlannerInstance {
             let (tx, mut rx) = mpsc::unbounded_channel();
             let ep = Arc::new(execution_problem);
             let ep2 = ep.clone();
+            let busy = clock_manager.busy();
             let _f = tokio::spawn(async move {
+                let _busy = busy;
                 let r = ompas_lcp::run_planner(&ep2, &config, Some(interrupted), Some(tx.clone()))
                     .await;
                 let _ = tx.send(r);
@@ -19,6 +19,26 @@ pub struct PlanningInstanceStat {
     pub status: PlanningStatus,
     pub n_solution: usize,
     pub planner_mode: PlannerMode,
+    /// Part of the previous plan kept, if the instance repaired the previous plan.
+    #[serde(default)]
+    pub repair: Option<RepairStat>,
+}
+
+/// Refinements of the previous plan that have been kept or reopened by a plan repair.
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RepairStat {
+    pub kept: usize,
+    pub reopened: usize,
+}
+
+impl RepairStat {
+    /// Returns the ratio of the refinements of the previous plan that have been kept.
+    pub fn kept_ratio(&self) -> f64 {
+        match self.kept + self.reopened {
+            0 => 1.0,
+            n => self.kept as f64 / n as f64,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
@@ -11,6 +11,7 @@ use crate::ompas::manager::state::action_status::ProcessStatus;
 use crate::ompas::manager::state::partial_state::Fact;
 use crate::ompas::manager::state::partial_state::PartialState;
 use crate::ompas::manager::state::StateType;
+use crate::ompas::manager::trace::{Trace, TraceEvent};
 use async_trait::async_trait;
 use ompas_interface::platform_interface::command_request::Request;
 use ompas_interface::platform_interface::command_response::Response;
@@ -68,6 +69,12 @@ impl ExecPlatform {
 
         self.log
             .debug(format!("New command request: {}", LValue::from(command)));
+        self.acting_manager
+            .trace_manager
+            .record(TraceEvent::CommandRequest {
+                id: command_id,
+                command: LValue::from(command).to_string(),
+            });
 
         let request = CommandRequest {
             request: Some(Request::Execution(CommandExecutionRequest {
@@ -85,6 +92,9 @@ impl ExecPlatform {
     }
 
     pub async fn cancel_command(&self, command_id: usize) {
+        self.acting_manager
+            .trace_manager
+            .record(TraceEvent::CommandCancel { id: command_id });
         let request = CommandRequest {
             request: Some(Request::Cancel(CommandCancelRequest {
                 command_id: command_id as u64,
@@ -191,9 +201,17 @@ impl ExecPlatform {
                                                         panic!()
                                                     }
                                                 };
+                                                acting_manager.trace_manager.record(TraceEvent::Resource {
+                                                    label: label.clone(),
+                                                    capacity,
+                                                });
                                                 acting_manager.resource_manager.new_resource(label , Some(capacity)).await
                                             }
                                             Some(event::Event::Instance(instance)) => {
+                                                acting_manager.trace_manager.record(TraceEvent::Instance {
+                                                    object: instance.object.clone(),
+                                                    r#type: instance.r#type.clone(),
+                                                });
                                                 acting_manager.state_manager.add_instance(&instance.object, &instance.r#type).await;
                                             }
                                             Some(event::Event::Task(_)) => {
@@ -285,6 +303,7 @@ impl ExecPlatform {
 
                                 }
                                 };
+                                acting_manager.trace_manager.record(TraceEvent::CommandResponse { id, status });
                                 acting_manager.set_status(&id, status).await;
                             }
                         }
@@ -306,11 +325,16 @@ impl PlatformDescriptor for ExecPlatform {
         )
         .await;
 
-        self.inner
-            .write()
-            .await
-            .start(self.config.read().await.clone())
-            .await;
+        {
+            let mut inner = self.inner.write().await;
+            inner.set_clock_manager(self.acting_manager.clock_manager.clone());
+            inner.start(self.config.read().await.clone()).await;
+        }
+
+        if let Some(trace) = self.trace().await {
+            process.log_info("Checking the decisions against the replayed trace");
+            self.acting_manager.trace_manager.set_reference(&trace);
+        }
 
         let server: SocketAddr = self.socket().await;
         let socket = format!("https://{}", server);
@@ -359,4 +383,9 @@ impl PlatformDescriptor for ExecPlatform {
     async fn socket(&self) -> SocketAddr {
         self.inner.read().await.socket().await
     }
+
+    ///Returns the recorded trace replayed by the platform, against which the decisions are checked
+    async fn trace(&self) -> Option<Trace> {
+        self.inner.read().await.trace().await
+    }
 }
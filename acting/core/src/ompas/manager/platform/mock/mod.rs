@@ -0,0 +1,368 @@
+//! Pure-Rust execution platform simulating the commands of a SOMPAS domain.
+//! The commands received through the grpc platform interface are executed by evaluating their
+//! *:sim-model*, and the resulting state updates are streamed back to OMPAS.
+use crate::ompas::manager::platform::mock::service::MockPlatformService;
+use crate::ompas::manager::platform::platform_config::PlatformConfig;
+use crate::ompas::manager::platform::scheme_domain::SchemeDomain;
+use crate::ompas::manager::platform::PlatformDescriptor;
+use async_trait::async_trait;
+use ompas_interface::platform_interface::platform_interface_server::PlatformInterfaceServer;
+use ompas_language::interface::{
+    DEFAULT_PLATFORM_SERVICE_IP, DEFAULT_PLATFROM_SERVICE_PORT, LOG_TOPIC_PLATFORM,
+};
+use ompas_language::process::PROCESS_TOPIC_OMPAS;
+use ompas_middleware::ProcessInterface;
+use sompas_structs::lmodule::LModule;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tonic::transport::Server;
+
+pub mod service;
+
+pub const PROCESS_MOCK_PLATFORM_SERVER: &str = "__PROCESS_MOCK_PLATFORM_SERVER__";
+const PROCESS_MOCK_PLATFORM_SERVICE_GET_UPDATES: &str =
+    "__PROCESS_MOCK_PLATFORM_SERVICE_GET_UPDATES__";
+const PROCESS_MOCK_PLATFORM_SERVICE_SEND_COMMANDS: &str =
+    "__PROCESS_MOCK_PLATFORM_SERVICE_SEND_COMMANDS__";
+
+/// Faults that can be injected in the execution of a command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MockFault {
+    /// The command is rejected by the platform.
+    Reject,
+    /// The command is accepted, but its execution fails without evaluating its sim-model.
+    Fail,
+    /// The execution of the sim-model is delayed by a duration in seconds.
+    Delay(f64),
+}
+
+impl Display for MockFault {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockFault::Reject => write!(f, "reject"),
+            MockFault::Fail => write!(f, "fail"),
+            MockFault::Delay(d) => write!(f, "delay {d}"),
+        }
+    }
+}
+
+/// Script of faults to inject, indexed by the label of the command.
+/// A fault can target a specific occurrence of the command (starting at 0), or all of them.
+#[derive(Clone, Default, Debug)]
+pub struct MockScript {
+    faults: HashMap<String, Vec<(Option<usize>, MockFault)>>,
+}
+
+impl MockScript {
+    pub fn add_fault(
+        &mut self,
+        command: impl ToString,
+        occurrence: Option<usize>,
+        fault: MockFault,
+    ) {
+        self.faults
+            .entry(command.to_string())
+            .or_default()
+            .push((occurrence, fault))
+    }
+
+    pub fn get_faults(&self, command: &str, occurrence: usize) -> Vec<MockFault> {
+        match self.faults.get(command) {
+            None => vec![],
+            Some(faults) => faults
+                .iter()
+                .filter(|(o, _)| o.map_or(true, |o| o == occurrence))
+                .map(|(_, f)| f.clone())
+                .collect(),
+        }
+    }
+}
+
+impl Display for MockScript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (command, faults) in &self.faults {
+            for (occurrence, fault) in faults {
+                match occurrence {
+                    Some(o) => writeln!(f, "{command}[{o}]: {fault}")?,
+                    None => writeln!(f, "{command}[*]: {fault}")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Execution platform running a mock grpc server in the same process.
+#[derive(Clone)]
+pub struct MockPlatform {
+    pub service_info: SocketAddr,
+    pub domain: SchemeDomain,
+    pub script: MockScript,
+}
+
+impl MockPlatform {
+    pub fn new(domain: impl Into<SchemeDomain>) -> Self {
+        Self {
+            service_info: format!(
+                "{}:{}",
+                DEFAULT_PLATFORM_SERVICE_IP, DEFAULT_PLATFROM_SERVICE_PORT
+            )
+            .parse()
+            .unwrap(),
+            domain: domain.into(),
+            script: Default::default(),
+        }
+    }
+
+    pub fn set_server_info(&mut self, service_info: SocketAddr) {
+        self.service_info = service_info;
+    }
+
+    pub fn add_fault(
+        &mut self,
+        command: impl ToString,
+        occurrence: Option<usize>,
+        fault: MockFault,
+    ) {
+        self.script.add_fault(command, occurrence, fault)
+    }
+
+    /// Loads the domain in a new simulation environment and serves the platform interface.
+    /// Returns once the server has been spawned.
+    pub async fn serve(&self) {
+        let service = MockPlatformService::new(self.domain.clone(), self.script.clone()).await;
+        let server_info = self.service_info;
+        let mut process = ProcessInterface::new(
+            PROCESS_MOCK_PLATFORM_SERVER,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_info(format!("Serving mock platform on {server_info}"));
+        tokio::spawn(async move {
+            let server = Server::builder().add_service(PlatformInterfaceServer::new(service));
+            tokio::select! {
+                _ = process.recv() => {}
+                r = server.serve(server_info) => {
+                    if let Err(e) = r {
+                        process.log_error(format!("Mock platform server error: {e}"));
+                    }
+                }
+            }
+        });
+
+        //Leaves time for the server to bind its socket before the client tries to connect.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+#[async_trait]
+impl PlatformDescriptor for MockPlatform {
+    async fn start(&self, _: PlatformConfig) {
+        self.serve().await
+    }
+
+    async fn stop(&self) {
+        //The server is killed with the other processes of OMPAS.
+    }
+
+    async fn domain(&self) -> SchemeDomain {
+        self.domain.clone()
+    }
+
+    async fn module(&self) -> Option<LModule> {
+        None
+    }
+
+    async fn socket(&self) -> SocketAddr {
+        self.service_info
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ompas_interface::platform_interface::command_request::Request as CommandRequestKind;
+    use ompas_interface::platform_interface::command_response::Response as CommandResponseKind;
+    use ompas_interface::platform_interface::platform_interface_client::PlatformInterfaceClient;
+    use ompas_interface::platform_interface::platform_update::Update;
+    use ompas_interface::platform_interface::{
+        Atom, CommandAccepted, CommandCancelRequest, CommandCancelled, CommandExecutionRequest,
+        CommandRejected, CommandRequest, CommandResponse, CommandResult, InitGetUpdate,
+    };
+    use sompas_structs::lvalues::LValueS;
+    use tokio::sync::mpsc;
+    use tokio::sync::mpsc::UnboundedSender;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+    use tonic::Streaming;
+
+    const DOMAIN: &str = "(begin
+        (def-state-function value (:result int))
+        (def-facts (value 0))
+        (def-command set_value (:params (?v int)))
+        (def-command-om-model set_value
+            (:params (?v int))
+            (:body
+                (do
+                    (check (> ?v 0))
+                    (effect 'value ?v)))))";
+
+    #[test]
+    fn test_mock_script() {
+        let mut script = MockScript::default();
+        script.add_fault("pick", Some(1), MockFault::Fail);
+        script.add_fault("pick", None, MockFault::Delay(2.0));
+        script.add_fault("move", Some(0), MockFault::Reject);
+
+        assert_eq!(script.get_faults("pick", 0), vec![MockFault::Delay(2.0)]);
+        assert_eq!(
+            script.get_faults("pick", 1),
+            vec![MockFault::Fail, MockFault::Delay(2.0)]
+        );
+        assert_eq!(script.get_faults("move", 0), vec![MockFault::Reject]);
+        assert!(script.get_faults("move", 1).is_empty());
+        assert!(script.get_faults("drop", 0).is_empty());
+    }
+
+    fn execute(tx: &UnboundedSender<CommandRequest>, command_id: u64, command: &[LValueS]) {
+        let arguments: Vec<Atom> = command
+            .iter()
+            .map(|arg| arg.clone().try_into().unwrap())
+            .collect();
+        tx.send(CommandRequest {
+            request: Some(CommandRequestKind::Execution(CommandExecutionRequest {
+                arguments,
+                command_id,
+            })),
+        })
+        .unwrap();
+    }
+
+    fn set_value(v: i64) -> Vec<LValueS> {
+        vec![LValueS::Symbol("set_value".to_string()), LValueS::Int(v)]
+    }
+
+    async fn next_response(responses: &mut Streaming<CommandResponse>) -> CommandResponseKind {
+        responses
+            .message()
+            .await
+            .unwrap()
+            .unwrap()
+            .response
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mock_platform() {
+        let socket = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut platform = MockPlatform::new(DOMAIN);
+        platform.set_server_info(socket);
+        platform.add_fault("set_value", Some(1), MockFault::Fail);
+        platform.add_fault("set_value", Some(3), MockFault::Delay(3600.0));
+        platform.serve().await;
+
+        let mut client = PlatformInterfaceClient::connect(format!("http://{socket}"))
+            .await
+            .unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut responses = client
+            .send_commands(UnboundedReceiverStream::new(rx))
+            .await
+            .unwrap()
+            .into_inner();
+
+        //Success
+        execute(&tx, 0, &set_value(2));
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Accepted(CommandAccepted { command_id: 0 })
+        );
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Result(CommandResult {
+                command_id: 0,
+                result: true
+            })
+        );
+
+        //Injected failure
+        execute(&tx, 1, &set_value(3));
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Accepted(CommandAccepted { command_id: 1 })
+        );
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Result(CommandResult {
+                command_id: 1,
+                result: false
+            })
+        );
+
+        //Failure of the sim-model
+        execute(&tx, 2, &set_value(0));
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Accepted(CommandAccepted { command_id: 2 })
+        );
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Result(CommandResult {
+                command_id: 2,
+                result: false
+            })
+        );
+
+        //Unknown command
+        execute(&tx, 3, &[LValueS::Symbol("jump".to_string())]);
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Rejected(CommandRejected { command_id: 3 })
+        );
+
+        //Cancellation of a delayed command
+        execute(&tx, 4, &set_value(5));
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Accepted(CommandAccepted { command_id: 4 })
+        );
+        tx.send(CommandRequest {
+            request: Some(CommandRequestKind::Cancel(CommandCancelRequest {
+                command_id: 4,
+            })),
+        })
+        .unwrap();
+        assert_eq!(
+            next_response(&mut responses).await,
+            CommandResponseKind::Cancelled(CommandCancelled {
+                command_id: 4,
+                result: true
+            })
+        );
+
+        //Only the successful command has modified the state of the platform.
+        let mut updates = client
+            .get_updates(InitGetUpdate {})
+            .await
+            .unwrap()
+            .into_inner();
+        let value = loop {
+            let update = updates.message().await.unwrap().unwrap().update.unwrap();
+            if let Update::State(state) = update {
+                break state
+                    .state_variables
+                    .into_iter()
+                    .find(|sv| sv.state_function == "value")
+                    .unwrap()
+                    .value
+                    .unwrap();
+            }
+        };
+        assert_eq!(LValueS::try_from(&value).unwrap(), LValueS::Int(2));
+    }
+}
@@ -0,0 +1,382 @@
+use crate::model::acting_domain::model::ModelKind;
+use crate::ompas::manager::acting::ActingManager;
+use crate::ompas::manager::platform::mock::{
+    MockFault, MockScript, PROCESS_MOCK_PLATFORM_SERVICE_GET_UPDATES,
+    PROCESS_MOCK_PLATFORM_SERVICE_SEND_COMMANDS,
+};
+use crate::ompas::manager::platform::platform_declaration::PlatformDeclaration;
+use crate::ompas::manager::platform::scheme_domain::SchemeDomain;
+use crate::ompas::manager::state::partial_state::Fact;
+use crate::ompas::manager::state::state_update_manager::StateRule;
+use crate::ompas::manager::state::StateType;
+use crate::ompas::scheme::exec::ModExec;
+use crate::ompas::scheme::monitor::control::ModControl;
+use crate::ompas::scheme::monitor::ModMonitor;
+use async_trait::async_trait;
+use ompas_interface::platform_interface::command_request::Request as CommandRequestKind;
+use ompas_interface::platform_interface::platform_interface_server::PlatformInterface;
+use ompas_interface::platform_interface::{
+    Atom, CommandAccepted, CommandCancelled, CommandExecutionRequest, CommandRejected,
+    CommandRequest, CommandResponse, CommandResult, Expression, InitGetUpdate, Instance,
+    PlatformUpdate, StateUpdate, StateVariable, StateVariableType,
+};
+use ompas_language::interface::LOG_TOPIC_PLATFORM;
+use ompas_language::process::PROCESS_TOPIC_OMPAS;
+use ompas_middleware::logger::LogClient;
+use ompas_middleware::ProcessInterface;
+use sompas_core::{eval, eval_init, get_root_env};
+use sompas_modules::ModExtendedStd;
+use sompas_structs::lenv::ImportType::WithoutPrefix;
+use sompas_structs::lenv::LEnv;
+use sompas_structs::lswitch::{new_interruption_handler, InterruptionSender};
+use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{mpsc, RwLock};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+type ResponseSender = UnboundedSender<Result<CommandResponse, Status>>;
+
+/// Grpc service of the mock platform.
+/// Holds its own acting manager, in which the domain is loaded and the sim-models are evaluated.
+#[derive(Clone)]
+pub struct MockPlatformService {
+    acting_manager: ActingManager,
+    env: LEnv,
+    script: Arc<MockScript>,
+    occurrences: Arc<RwLock<HashMap<String, usize>>>,
+    interrupters: Arc<RwLock<HashMap<u64, InterruptionSender>>>,
+    log: LogClient,
+}
+
+impl MockPlatformService {
+    pub async fn new(domain: SchemeDomain, script: MockScript) -> Self {
+        let monitor = ModMonitor::new(PlatformDeclaration::Simu(domain), None).await;
+        let control = ModControl::new(&monitor);
+        let acting_manager = monitor.acting_manager.clone();
+        let log = LogClient::new("mock-platform", LOG_TOPIC_PLATFORM).await;
+
+        //The domain, its initial state included, is loaded in the acting manager of the platform
+        //by the initialisation of the environment in which the sim-models are evaluated.
+        let mut env: LEnv = get_root_env().await;
+        env.log = log.clone();
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        env.import_module(ModExec::new(&control).await, WithoutPrefix);
+        env.import_module(monitor, WithoutPrefix);
+        eval_init(&mut env).await;
+        env.set_new_top_symbols(acting_manager.domain_manager.get_exec_env().await);
+
+        Self {
+            acting_manager,
+            env,
+            script: Arc::new(script),
+            occurrences: Arc::new(Default::default()),
+            interrupters: Arc::new(Default::default()),
+            log,
+        }
+    }
+
+    async fn next_occurrence(&self, command: &str) -> usize {
+        let mut occurrences = self.occurrences.write().await;
+        let occurrence = occurrences.entry(command.to_string()).or_insert(0);
+        let r = *occurrence;
+        *occurrence += 1;
+        r
+    }
+
+    async fn get_state_variable(&self, key: LValueS) -> Option<StateVariable> {
+        let (r#type, fact): (StateVariableType, Fact) = match self
+            .acting_manager
+            .state_manager
+            .get_fact(&key, Some(StateType::Static))
+            .await
+        {
+            Some(fact) => (StateVariableType::Static, fact),
+            None => (
+                StateVariableType::Dynamic,
+                self.acting_manager
+                    .state_manager
+                    .get_fact(&key, Some(StateType::Dynamic))
+                    .await?,
+            ),
+        };
+        new_state_variable(key, fact, r#type)
+    }
+
+    /// Returns the instances and the full state of the platform.
+    async fn get_initial_updates(&self) -> Vec<PlatformUpdate> {
+        let snapshot = self.acting_manager.state_manager.get_snapshot().await;
+        let mut updates: Vec<PlatformUpdate> = vec![];
+        for (r#type, set) in &snapshot.instance.inner {
+            for object in &set.elements {
+                updates.push(
+                    Instance {
+                        r#type: r#type.to_string(),
+                        object: object.to_string(),
+                    }
+                    .into(),
+                )
+            }
+        }
+
+        let mut state_variables = vec![];
+        for (state, r#type) in [
+            (snapshot.r#static, StateVariableType::Static),
+            (snapshot.dynamic, StateVariableType::Dynamic),
+        ] {
+            for (key, fact) in state.inner {
+                if let Some(sv) = new_state_variable(key, fact, r#type) {
+                    state_variables.push(sv)
+                }
+            }
+        }
+        updates.push(StateUpdate { state_variables }.into());
+        updates
+    }
+
+    async fn execute(self, request: CommandExecutionRequest, tx: ResponseSender) {
+        let id = request.command_id;
+        let send = |response: CommandResponse| {
+            let _ = tx.send(Ok(response));
+        };
+
+        let mut command: Vec<LValue> = vec![];
+        for arg in &request.arguments {
+            match LValueS::try_from(arg) {
+                Ok(lv) => command.push(lv.into()),
+                Err(_) => {
+                    send(CommandRejected { command_id: id }.into());
+                    return;
+                }
+            }
+        }
+        let label = match command.first() {
+            Some(label) => label.to_string(),
+            None => {
+                send(CommandRejected { command_id: id }.into());
+                return;
+            }
+        };
+
+        let model = match self.acting_manager.domain_manager.get_command(&label).await {
+            Some(command) => command.get_model(&ModelKind::SimModel),
+            None => None,
+        };
+        let occurrence = self.next_occurrence(&label).await;
+        let faults = self.script.get_faults(&label, occurrence);
+
+        let model = match model {
+            Some(model) if !faults.contains(&MockFault::Reject) => model,
+            _ => {
+                self.log.info(format!(
+                    "Command {}({id}) rejected.",
+                    LValue::from(&command)
+                ));
+                send(CommandRejected { command_id: id }.into());
+                return;
+            }
+        };
+        send(CommandAccepted { command_id: id }.into());
+
+        let (tx_int, mut rx_int) = new_interruption_handler();
+        self.interrupters.write().await.insert(id, tx_int);
+
+        let delay: f64 = faults
+            .iter()
+            .map(|f| match f {
+                MockFault::Delay(d) => *d,
+                _ => 0.0,
+            })
+            .sum();
+        if delay > 0.0 {
+            tokio::select! {
+                _ = rx_int.recv() => {
+                    return;
+                }
+                _ = self.acting_manager.clock_manager.sleep(Duration::from_secs_f64(delay)) => {}
+            }
+        }
+
+        let result = if faults.contains(&MockFault::Fail) {
+            false
+        } else {
+            let mut env = self.env.clone();
+            env.insert(label, model);
+            !matches!(
+                eval(&LValue::from(&command), &mut env, Some(rx_int)).await,
+                Err(_) | Ok(LValue::Err(_))
+            )
+        };
+
+        //If the command has been cancelled in the meantime, the response has already been sent.
+        if self.interrupters.write().await.remove(&id).is_some() {
+            self.log.info(format!(
+                "Command {}({id}) {}.",
+                LValue::from(&command),
+                match result {
+                    true => "succeeded",
+                    false => "failed",
+                }
+            ));
+            send(
+                CommandResult {
+                    command_id: id,
+                    result,
+                }
+                .into(),
+            );
+        }
+    }
+
+    async fn cancel(&self, command_id: u64, tx: &ResponseSender) {
+        let result = match self.interrupters.write().await.remove(&command_id) {
+            Some(mut interrupter) => {
+                interrupter.interrupt();
+                true
+            }
+            None => false,
+        };
+        let _ = tx.send(Ok(CommandCancelled { command_id, result }.into()));
+    }
+}
+
+pub(crate) fn new_state_variable(
+    key: LValueS,
+    fact: Fact,
+    r#type: StateVariableType,
+) -> Option<StateVariable> {
+    let (state_function, parameters): (String, Vec<LValueS>) = match key {
+        LValueS::Symbol(s) => (s, vec![]),
+        LValueS::List(mut list) if !list.is_empty() => {
+            let sf = list.remove(0);
+            (sf.to_string(), list)
+        }
+        _ => return None,
+    };
+    let mut atoms: Vec<Atom> = vec![];
+    for p in parameters {
+        atoms.push(p.try_into().ok()?)
+    }
+    let value: Expression = fact.value.try_into().ok()?;
+    Some(StateVariable {
+        r#type: r#type as i32,
+        state_function,
+        parameters: atoms,
+        value: Some(value),
+    })
+}
+
+#[async_trait]
+impl PlatformInterface for MockPlatformService {
+    type GetUpdatesStream = UnboundedReceiverStream<Result<PlatformUpdate, Status>>;
+
+    async fn get_updates(
+        &self,
+        _: Request<InitGetUpdate>,
+    ) -> Result<Response<Self::GetUpdatesStream>, Status> {
+        let mut process = ProcessInterface::new(
+            PROCESS_MOCK_PLATFORM_SERVICE_GET_UPDATES,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_info("Received request for updates!");
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        let mut subscriber = self
+            .acting_manager
+            .state_manager
+            .new_subscriber(StateRule::All)
+            .await;
+        for update in self.get_initial_updates().await {
+            let _ = tx.send(Ok(update));
+        }
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = process.recv() => {
+                        break;
+                    }
+                    updated = subscriber.channel.recv() => {
+                        let updated = match updated {
+                            Some(updated) => updated,
+                            None => break,
+                        };
+                        let mut state_variables = vec![];
+                        for key in updated {
+                            if let Some(sv) = service.get_state_variable(key).await {
+                                state_variables.push(sv);
+                            }
+                        }
+                        if !state_variables.is_empty()
+                            && tx.send(Ok(StateUpdate { state_variables }.into())).is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+            }
+            service
+                .acting_manager
+                .state_manager
+                .remove_subscriber(&subscriber.id)
+                .await;
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    type SendCommandsStream = UnboundedReceiverStream<Result<CommandResponse, Status>>;
+
+    async fn send_commands(
+        &self,
+        request: Request<Streaming<CommandRequest>>,
+    ) -> Result<Response<Self::SendCommandsStream>, Status> {
+        let mut process = ProcessInterface::new(
+            PROCESS_MOCK_PLATFORM_SERVICE_SEND_COMMANDS,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_debug("Received request to execute stream of commands!");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut requests = request.into_inner();
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = process.recv() => {
+                        break;
+                    }
+                    msg = requests.message() => {
+                        match msg {
+                            Ok(Some(CommandRequest { request: Some(request) })) => match request {
+                                CommandRequestKind::Execution(execution) => {
+                                    tokio::spawn(service.clone().execute(execution, tx.clone()));
+                                }
+                                CommandRequestKind::Cancel(cancel) => {
+                                    service.cancel(cancel.command_id, &tx).await;
+                                }
+                            },
+                            Ok(Some(_)) => {}
+                            Ok(None) => break,
+                            Err(e) => {
+                                process.log_error(format!("Grpc error: {e}"));
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+}
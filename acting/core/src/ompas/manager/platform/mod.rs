@@ -1,9 +1,11 @@
 use crate::model::acting_domain::model::ModelKind;
 use crate::ompas::manager::acting::{ActingManager, ActionId};
+use crate::ompas::manager::clock::ClockManager;
 use crate::ompas::manager::domain::DomainManager;
 use crate::ompas::manager::platform::exec_platform::ExecPlatform;
 use crate::ompas::manager::platform::platform_config::PlatformConfig;
 use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::trace::Trace;
 use async_trait::async_trait;
 use ompas_language::process::{LOG_TOPIC_OMPAS, PROCESS_TOPIC_OMPAS};
 use ompas_middleware::ProcessInterface;
@@ -21,8 +23,10 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 pub mod exec_platform;
+pub mod mock;
 pub mod platform_config;
 pub mod platform_declaration;
+pub mod replay;
 pub mod scheme_domain;
 
 /// Trait that a platform needs to implement to be able to be used as execution platform in RAE.
@@ -42,6 +46,14 @@ pub trait PlatformDescriptor: Any + Send + Sync {
 
     ///Returns the server info in order to connect OMPAS to the platform using grpc services
     async fn socket(&self) -> SocketAddr;
+
+    ///Returns the recorded trace replayed by the platform, against which the decisions are checked
+    async fn trace(&self) -> Option<Trace> {
+        None
+    }
+
+    ///Sets the clock of the engine, used by platforms running in the same process to pace their events
+    fn set_clock_manager(&mut self, _clock_manager: ClockManager) {}
 }
 
 #[derive(Default, Clone)]
@@ -0,0 +1,423 @@
+//! Execution platform replaying a recorded trace.
+//! The updates of the platform are streamed at the date they have been recorded, and the commands
+//! receive the responses recorded for the command of the same id.
+//! Events are paced by the clock of the engine, so that a trace is replayed in virtual time as well.
+//! The decisions of the engine are checked against the trace by the *TraceManager*.
+use crate::ompas::manager::acting::ActingProcessId;
+use crate::ompas::manager::clock::ClockManager;
+use crate::ompas::manager::platform::mock::service::new_state_variable;
+use crate::ompas::manager::platform::platform_config::PlatformConfig;
+use crate::ompas::manager::platform::scheme_domain::SchemeDomain;
+use crate::ompas::manager::platform::PlatformDescriptor;
+use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::state::partial_state::Fact;
+use crate::ompas::manager::state::StateType;
+use crate::ompas::manager::trace::{Trace, TraceEvent};
+use async_trait::async_trait;
+use ompas_interface::platform_interface::command_request::Request as CommandRequestKind;
+use ompas_interface::platform_interface::platform_interface_server::{
+    PlatformInterface, PlatformInterfaceServer,
+};
+use ompas_interface::platform_interface::{
+    CommandAccepted, CommandCancelled, CommandExecutionRequest, CommandProgress, CommandRejected,
+    CommandRequest, CommandResponse, CommandResult, InitGetUpdate, Instance, PlatformUpdate,
+    Resource, ResourceKind, StateUpdate, StateVariableType,
+};
+use ompas_language::interface::{
+    DEFAULT_PLATFORM_SERVICE_IP, DEFAULT_PLATFROM_SERVICE_PORT, LOG_TOPIC_PLATFORM,
+};
+use ompas_language::process::PROCESS_TOPIC_OMPAS;
+use ompas_middleware::logger::LogClient;
+use ompas_middleware::ProcessInterface;
+use sompas_structs::lmodule::LModule;
+use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status, Streaming};
+
+pub const PROCESS_REPLAY_PLATFORM_SERVER: &str = "__PROCESS_REPLAY_PLATFORM_SERVER__";
+const PROCESS_REPLAY_PLATFORM_SERVICE_GET_UPDATES: &str =
+    "__PROCESS_REPLAY_PLATFORM_SERVICE_GET_UPDATES__";
+const PROCESS_REPLAY_PLATFORM_SERVICE_SEND_COMMANDS: &str =
+    "__PROCESS_REPLAY_PLATFORM_SERVICE_SEND_COMMANDS__";
+
+type ResponseSender = UnboundedSender<Result<CommandResponse, Status>>;
+
+/// Execution platform running a grpc server replaying a trace in the same process.
+#[derive(Clone)]
+pub struct ReplayPlatform {
+    pub service_info: SocketAddr,
+    pub domain: SchemeDomain,
+    pub trace: Trace,
+    pub clock_manager: ClockManager,
+}
+
+impl ReplayPlatform {
+    pub fn new(domain: impl Into<SchemeDomain>, trace: Trace) -> Self {
+        Self {
+            service_info: format!(
+                "{}:{}",
+                DEFAULT_PLATFORM_SERVICE_IP, DEFAULT_PLATFROM_SERVICE_PORT
+            )
+            .parse()
+            .unwrap(),
+            domain: domain.into(),
+            trace,
+            clock_manager: Default::default(),
+        }
+    }
+
+    pub fn set_server_info(&mut self, service_info: SocketAddr) {
+        self.service_info = service_info;
+    }
+
+    /// Serves the platform interface replaying the trace.
+    /// Returns once the server has been spawned.
+    pub async fn serve(&self) {
+        let service = ReplayPlatformService::new(&self.trace, self.clock_manager.clone()).await;
+        let server_info = self.service_info;
+        let mut process = ProcessInterface::new(
+            PROCESS_REPLAY_PLATFORM_SERVER,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_info(format!(
+            "Replaying a trace of {} events on {server_info}",
+            self.trace.entries.len()
+        ));
+        tokio::spawn(async move {
+            let server = Server::builder().add_service(PlatformInterfaceServer::new(service));
+            tokio::select! {
+                _ = process.recv() => {}
+                r = server.serve(server_info) => {
+                    if let Err(e) = r {
+                        process.log_error(format!("Replay platform server error: {e}"));
+                    }
+                }
+            }
+        });
+
+        //Leaves time for the server to bind its socket before the client tries to connect.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+#[async_trait]
+impl PlatformDescriptor for ReplayPlatform {
+    async fn start(&self, _: PlatformConfig) {
+        self.serve().await
+    }
+
+    async fn stop(&self) {
+        //The server is killed with the other processes of OMPAS.
+    }
+
+    async fn domain(&self) -> SchemeDomain {
+        self.domain.clone()
+    }
+
+    async fn module(&self) -> Option<LModule> {
+        None
+    }
+
+    async fn socket(&self) -> SocketAddr {
+        self.service_info
+    }
+
+    async fn trace(&self) -> Option<Trace> {
+        Some(self.trace.clone())
+    }
+
+    fn set_clock_manager(&mut self, clock_manager: ClockManager) {
+        self.clock_manager = clock_manager;
+    }
+}
+
+/// A command of the trace, with the responses of the platform and their delay since the request.
+#[derive(Clone)]
+struct RecordedCommand {
+    command: String,
+    responses: Vec<(f64, ProcessStatus)>,
+}
+
+/// Grpc service of the replay platform.
+#[derive(Clone)]
+pub struct ReplayPlatformService {
+    updates: Arc<Vec<(f64, PlatformUpdate)>>,
+    commands: Arc<HashMap<ActingProcessId, RecordedCommand>>,
+    clock_manager: ClockManager,
+    log: LogClient,
+}
+
+/// Waits until *delay* seconds have elapsed since *start*, according to the clock.
+async fn sleep_until(clock_manager: &ClockManager, start: f64, delay: f64) {
+    let remaining = delay - (clock_manager.now().as_secs() - start);
+    clock_manager
+        .sleep(Duration::from_secs_f64(remaining.max(0.0)))
+        .await
+}
+
+impl ReplayPlatformService {
+    pub async fn new(trace: &Trace, clock_manager: ClockManager) -> Self {
+        let start = trace.start().as_secs();
+        let mut updates = vec![];
+        let mut commands: HashMap<ActingProcessId, RecordedCommand> = HashMap::new();
+        let mut requests: HashMap<ActingProcessId, f64> = HashMap::new();
+
+        for entry in &trace.entries {
+            let date = entry.date.as_secs();
+            match &entry.event {
+                TraceEvent::CommandRequest { id, command } => {
+                    requests.insert(*id, date);
+                    commands.insert(
+                        *id,
+                        RecordedCommand {
+                            command: command.clone(),
+                            responses: vec![],
+                        },
+                    );
+                }
+                TraceEvent::CommandResponse { id, status } => {
+                    if let (Some(request), Some(command)) = (requests.get(id), commands.get_mut(id))
+                    {
+                        command.responses.push((date - request, *status));
+                    }
+                }
+                event if event.is_platform_update() => {
+                    if let Some(update) = new_platform_update(event) {
+                        updates.push((date - start, update))
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            updates: Arc::new(updates),
+            commands: Arc::new(commands),
+            clock_manager,
+            log: LogClient::new("replay-platform", LOG_TOPIC_PLATFORM).await,
+        }
+    }
+
+    fn execute(&self, request: CommandExecutionRequest, tx: ResponseSender) {
+        let id = request.command_id;
+        let mut command: Vec<LValue> = vec![];
+        for arg in &request.arguments {
+            if let Ok(lv) = LValueS::try_from(arg) {
+                command.push(lv.into())
+            }
+        }
+        let command = LValue::from(command).to_string();
+
+        let recorded = match self.commands.get(&(id as ActingProcessId)) {
+            Some(recorded) => recorded.clone(),
+            None => {
+                self.log.warn(format!(
+                    "Command {command}({id}) is not in the trace, it is rejected."
+                ));
+                let _ = tx.send(Ok(CommandRejected { command_id: id }.into()));
+                return;
+            }
+        };
+        if recorded.command != command {
+            self.log.warn(format!(
+                "Command {command}({id}) differs from the recorded command {}.",
+                recorded.command
+            ));
+        }
+
+        let clock_manager = self.clock_manager.clone();
+        tokio::spawn(async move {
+            let start = clock_manager.now().as_secs();
+            for (delay, status) in recorded.responses {
+                sleep_until(&clock_manager, start, delay).await;
+                if let Some(response) = new_command_response(id, status) {
+                    if tx.send(Ok(response)).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    fn cancel(&self, command_id: u64, tx: &ResponseSender) {
+        //A recorded cancellation is sent with the other responses of the command.
+        let recorded = self
+            .commands
+            .get(&(command_id as ActingProcessId))
+            .map(|c| {
+                c.responses
+                    .iter()
+                    .any(|(_, s)| matches!(s, ProcessStatus::Cancelled(_)))
+            })
+            .unwrap_or_default();
+        if !recorded {
+            let _ = tx.send(Ok(CommandCancelled {
+                command_id,
+                result: false,
+            }
+            .into()));
+        }
+    }
+}
+
+fn new_platform_update(event: &TraceEvent) -> Option<PlatformUpdate> {
+    match event {
+        TraceEvent::StateUpdate { state_type, facts } => {
+            let r#type = match state_type {
+                StateType::Static => StateVariableType::Static,
+                StateType::Dynamic => StateVariableType::Dynamic,
+                _ => return None,
+            };
+            let state_variables = facts
+                .iter()
+                .filter_map(|(k, v)| new_state_variable(k.clone(), Fact::from(v), r#type))
+                .collect();
+            Some(StateUpdate { state_variables }.into())
+        }
+        TraceEvent::Instance { object, r#type } => Some(
+            Instance {
+                r#type: r#type.clone(),
+                object: object.clone(),
+            }
+            .into(),
+        ),
+        TraceEvent::Resource { label, capacity } => Some(
+            Resource {
+                label: label.clone(),
+                resource_kind: ResourceKind::Divisible as i32,
+                quantity: *capacity as u64,
+            }
+            .into(),
+        ),
+        _ => None,
+    }
+}
+
+fn new_command_response(command_id: u64, status: ProcessStatus) -> Option<CommandResponse> {
+    match status {
+        ProcessStatus::Accepted => Some(CommandAccepted { command_id }.into()),
+        ProcessStatus::Rejected => Some(CommandRejected { command_id }.into()),
+        ProcessStatus::Running(progress) => Some(
+            CommandProgress {
+                command_id,
+                progress: progress.unwrap_or_default(),
+            }
+            .into(),
+        ),
+        ProcessStatus::Success => Some(
+            CommandResult {
+                command_id,
+                result: true,
+            }
+            .into(),
+        ),
+        ProcessStatus::Failure => Some(
+            CommandResult {
+                command_id,
+                result: false,
+            }
+            .into(),
+        ),
+        ProcessStatus::Cancelled(result) => Some(CommandCancelled { command_id, result }.into()),
+        //deadlines only apply to tasks
+        ProcessStatus::Pending | ProcessStatus::Planned | ProcessStatus::DeadlineMissed => None,
+    }
+}
+
+#[async_trait]
+impl PlatformInterface for ReplayPlatformService {
+    type GetUpdatesStream = UnboundedReceiverStream<Result<PlatformUpdate, Status>>;
+
+    async fn get_updates(
+        &self,
+        _: Request<InitGetUpdate>,
+    ) -> Result<Response<Self::GetUpdatesStream>, Status> {
+        let mut process = ProcessInterface::new(
+            PROCESS_REPLAY_PLATFORM_SERVICE_GET_UPDATES,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_info("Received request for updates!");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let updates = self.updates.clone();
+        let clock_manager = self.clock_manager.clone();
+
+        tokio::spawn(async move {
+            let start = clock_manager.now().as_secs();
+            for (delay, update) in updates.iter() {
+                tokio::select! {
+                    _ = process.recv() => {
+                        break;
+                    }
+                    _ = sleep_until(&clock_manager, start, *delay) => {
+                        if tx.send(Ok(update.clone())).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            process.log_info("All the recorded updates have been replayed.");
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    type SendCommandsStream = UnboundedReceiverStream<Result<CommandResponse, Status>>;
+
+    async fn send_commands(
+        &self,
+        request: Request<Streaming<CommandRequest>>,
+    ) -> Result<Response<Self::SendCommandsStream>, Status> {
+        let mut process = ProcessInterface::new(
+            PROCESS_REPLAY_PLATFORM_SERVICE_SEND_COMMANDS,
+            PROCESS_TOPIC_OMPAS,
+            LOG_TOPIC_PLATFORM,
+        )
+        .await;
+        process.log_debug("Received request to execute stream of commands!");
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut requests = request.into_inner();
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = process.recv() => {
+                        break;
+                    }
+                    msg = requests.message() => {
+                        match msg {
+                            Ok(Some(CommandRequest { request: Some(request) })) => match request {
+                                CommandRequestKind::Execution(execution) => {
+                                    service.execute(execution, tx.clone());
+                                }
+                                CommandRequestKind::Cancel(cancel) => {
+                                    service.cancel(cancel.command_id, &tx);
+                                }
+                            },
+                            Ok(Some(_)) => {}
+                            Ok(None) => break,
+                            Err(e) => {
+                                process.log_error(format!("Grpc error: {e}"));
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+}
@@ -0,0 +1,57 @@
+//! Preemption of the top-level tasks holding a resource awaited by a task of higher priority.
+//! Each top-level task registers the switch interrupting its current attempt.
+//! A preempted task is interrupted with this switch, and flagged so that it is retried
+//! instead of failing.
+use crate::ompas::manager::acting::ActingProcessId;
+use sompas_structs::lswitch::InterruptionSender;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct InnerPreemptionManager {
+    switches: HashMap<ActingProcessId, InterruptionSender>,
+    preempted: HashSet<ActingProcessId>,
+}
+
+#[derive(Clone, Default)]
+pub struct PreemptionManager {
+    inner: Arc<Mutex<InnerPreemptionManager>>,
+}
+
+impl PreemptionManager {
+    /// Registers the switch interrupting the current attempt of the task.
+    pub fn register(&self, task: ActingProcessId, switch: InterruptionSender) {
+        self.inner.lock().unwrap().switches.insert(task, switch);
+    }
+
+    pub fn unregister(&self, task: &ActingProcessId) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.switches.remove(task);
+        inner.preempted.remove(task);
+    }
+
+    /// Interrupts the current attempt of the task.
+    /// Returns false if the task is not preemptible or already preempted.
+    pub fn preempt(&self, task: &ActingProcessId) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.switches.remove(task) {
+            Some(mut switch) => {
+                inner.preempted.insert(*task);
+                switch.interrupt();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the task has been preempted, and clears the flag.
+    pub fn take_preempted(&self, task: &ActingProcessId) -> bool {
+        self.inner.lock().unwrap().preempted.remove(task)
+    }
+
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.switches.clear();
+        inner.preempted.clear();
+    }
+}
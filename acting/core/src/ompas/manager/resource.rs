@@ -71,9 +71,29 @@ impl ResourceHandler {
 pub type ClientId = usize;
 pub type Capacity = usize;
 
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ResourceKind {
+    /// Capacity acquired by the clients, and given back when they release it.
+    #[default]
+    Reusable,
+    /// Stock decreased by consumptions and increased by productions, up to the maximum capacity.
+    /// A consumable resource can not be acquired.
+    Consumable,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceKind::Reusable => write!(f, "reusable"),
+            ResourceKind::Consumable => write!(f, "consumable"),
+        }
+    }
+}
+
 pub struct Resource {
     label: String,
     id: ResourceId,
+    kind: ResourceKind,
     max_capacity: Capacity,
     capacity: Capacity,
     in_service: HashSet<ClientId>,
@@ -180,6 +200,15 @@ impl Resource {
         priority: WaiterPriority,
         kind: TicketKind,
     ) -> Result<WaitAcquire, LRuntimeError> {
+        if self.kind == ResourceKind::Consumable {
+            return Err(LRuntimeError::new(
+                "new_ticket",
+                format!(
+                    "{} is a consumable resource and can not be acquired",
+                    self.label
+                ),
+            ));
+        }
         let acquisition_id = self.n_acquisition;
         self.n_acquisition += 1;
         if match quantity {
@@ -231,7 +260,8 @@ impl Resource {
             .iter()
             .map(|id| (id, self.clients.get(id).unwrap().priority))
             .sorted_by(|(id1, p1), (id2, p2)| match p1.cmp(p2) {
-                Ordering::Equal => id1.cmp(id2),
+                //the oldest client is served first among the clients of same priority
+                Ordering::Equal => id2.cmp(id1),
                 other => other,
             })
             .map(|(id, _)| *id)
@@ -244,10 +274,14 @@ impl Resource {
 
         while let Some(ticket_id) = queue.pop() {
             let waiter = self.clients.get(&ticket_id).unwrap();
-            if waiter.kind == TicketKind::Direct && waiter.quantity <= self.capacity {
+            //the members of a group are served together by the ResourceManager
+            if waiter.kind == TicketKind::Direct
+                && waiter.group.is_empty()
+                && waiter.quantity <= self.capacity
+            {
                 let acquire: ResourceHandler = self.add_acquire(&ticket_id);
                 let waiter = self.clients.get(&ticket_id).unwrap();
-                match waiter.tx.send(acquire).await {
+                match waiter.tx.send(Ok(acquire)).await {
                     Ok(()) => {
                         println!("acquisition: {}", self);
                         break;
@@ -332,6 +366,8 @@ impl Resource {
                 kind: _kind,
                 quantity,
                 priority,
+                owner: None,
+                group: vec![],
                 tx,
             },
         );
@@ -347,15 +383,15 @@ impl Resource {
         })
     }
 
-    pub async fn remove_client(&mut self, mut wa: WaitAcquire) {
-        match self.clients.remove(&wa.client_id) {
-            None => {
-                let rh: ResourceHandler = wa.rx.recv().await.unwrap();
-                self._remove_client(&rh.client_id);
-            }
-            Some(_) => {}
-        }
+    /// Removes the client of the waiter, releasing the resource if the client has been served
+    /// before the waiter received its handler.
+    pub async fn remove_client(&mut self, wa: WaitAcquire) {
         self.remove_ticket(&wa.client_id);
+        if self.clients.remove(&wa.client_id).is_some()
+            && self.in_service.remove(&wa.client_id).is_some()
+        {
+            self.update_remaining_capacity().expect("");
+        }
     }
 
     pub fn update_priority(&mut self, client_id: &ClientId, priority: WaiterPriority) {
@@ -372,6 +408,35 @@ impl Resource {
     pub fn get_client_priority(&self, client_id: &ClientId) -> WaiterPriority {
         self.clients.get(client_id).unwrap().priority
     }
+
+    /// Returns the clients in service to preempt so that the waiting client can be served:
+    /// the clients of lower priority, from the lowest, until enough capacity is freed.
+    /// Returns no client if the waiter is already served or cannot be served by preemption.
+    pub fn get_preemptible(&self, client_id: &ClientId) -> Vec<ClientId> {
+        let waiter = match self.clients.get(client_id) {
+            Some(waiter) if !self.in_service.contains(client_id) => waiter,
+            _ => return vec![],
+        };
+        let mut capacity = self.capacity;
+        let mut preempted = vec![];
+        for id in self
+            .in_service
+            .iter()
+            .copied()
+            .filter(|id| self.clients[id].priority < waiter.priority)
+            .sorted_by_key(|id| (self.clients[id].priority, *id))
+        {
+            if capacity >= waiter.quantity {
+                break;
+            }
+            capacity += self.clients[&id].quantity;
+            preempted.push(id);
+        }
+        match capacity >= waiter.quantity {
+            true => preempted,
+            false => vec![],
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -396,7 +461,60 @@ pub struct Client {
     kind: TicketKind,
     quantity: usize,
     priority: WaiterPriority,
-    tx: Sender<ResourceHandler>,
+    owner: Option<ClientOwner>,
+    /// Clients of the atomic acquisition the client is part of, the client included.
+    group: Vec<(ResourceId, ClientId)>,
+    tx: Sender<Result<ResourceHandler, Deadlock>>,
+}
+
+/// Acquisition owning a client, and the top-level task it has been refined from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClientOwner {
+    pub task: usize,
+    pub acquire: usize,
+}
+
+/// Edge of the wait-for graph: a client of the waiting task waits for a resource held by another task.
+#[derive(Debug, Clone)]
+pub struct WaitFor {
+    pub waiter: ClientOwner,
+    pub resource_id: ResourceId,
+    pub client_id: ClientId,
+    pub resource: String,
+    pub holder: ClientOwner,
+}
+
+impl Display for WaitFor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "task {} (acquire {}) waits for {} held by task {} (acquire {})",
+            self.waiter.task,
+            self.waiter.acquire,
+            self.resource,
+            self.holder.task,
+            self.holder.acquire
+        )
+    }
+}
+
+/// Cycle of the wait-for graph, each task waiting for a resource held by the next one.
+#[derive(Debug, Clone)]
+pub struct Deadlock {
+    pub cycle: Vec<WaitFor>,
+}
+
+impl Deadlock {
+    /// Returns the youngest waiting acquisition of the cycle.
+    pub fn youngest(&self) -> &WaitFor {
+        self.cycle.iter().max_by_key(|w| w.waiter.acquire).unwrap()
+    }
+}
+
+impl Display for Deadlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "deadlock: {}", self.cycle.iter().join(", "))
+    }
 }
 
 #[derive(Clone, Default)]
@@ -421,7 +539,11 @@ impl ResourceManager {
                     Resource {
                         label: v.label.to_string(),
                         id: v.id,
-                        max_capacity: v.capacity,
+                        kind: v.kind,
+                        max_capacity: match v.kind {
+                            ResourceKind::Reusable => v.capacity,
+                            ResourceKind::Consumable => v.max_capacity,
+                        },
                         capacity: v.capacity,
                         in_service: Default::default(),
                         queue: vec![],
@@ -449,13 +571,14 @@ pub enum AcquireResponse {
 }
 
 pub struct WaitAcquire {
-    rx: Receiver<ResourceHandler>,
+    rx: Receiver<Result<ResourceHandler, Deadlock>>,
     client_id: usize,
     resource_id: usize,
 }
 
 impl WaitAcquire {
-    pub async fn recv(&mut self) -> ResourceHandler {
+    /// Waits for the resource, or for the failure of the acquisition if it is part of a deadlock.
+    pub async fn recv(&mut self) -> Result<ResourceHandler, Deadlock> {
         self.rx.recv().await.unwrap()
     }
 
@@ -486,16 +609,50 @@ impl ResourceManager {
     }
 
     pub async fn new_resource(&self, label: String, capacity: Option<Capacity>) {
+        let capacity: Capacity = capacity.unwrap_or(1);
+        self.add_resource(label, ResourceKind::Reusable, capacity, capacity)
+            .await
+    }
+
+    /// Declares a consumable resource, with a stock of *quantity* that can not exceed *max_capacity*.
+    pub async fn new_consumable(
+        &self,
+        label: String,
+        max_capacity: Capacity,
+        quantity: Option<Capacity>,
+    ) -> Result<(), LRuntimeError> {
+        let quantity = quantity.unwrap_or(max_capacity);
+        if quantity > max_capacity {
+            return Err(LRuntimeError::new(
+                "new_consumable",
+                format!(
+                    "the stock of {} ({}) exceeds its maximum capacity ({})",
+                    label, quantity, max_capacity
+                ),
+            ));
+        }
+        self.add_resource(label, ResourceKind::Consumable, max_capacity, quantity)
+            .await;
+        Ok(())
+    }
+
+    async fn add_resource(
+        &self,
+        label: String,
+        kind: ResourceKind,
+        max_capacity: Capacity,
+        capacity: Capacity,
+    ) {
         let id = get_and_update_id_counter(self.id.clone());
         let mut labels = self.labels.lock().await;
         labels.insert(label.clone(), id);
         drop(labels);
         let mut map = self.inner.lock().await;
-        let capacity: Capacity = capacity.unwrap_or(1);
         let resource = Resource {
             label,
             id,
-            max_capacity: capacity,
+            kind,
+            max_capacity,
             capacity,
             in_service: Default::default(),
             clients: Default::default(),
@@ -504,11 +661,11 @@ impl ResourceManager {
         };
         map.insert(id, resource);
         let value = self.max_capacity.load(atomic::Ordering::Relaxed);
-        if capacity > value {
+        if max_capacity > value {
             self.max_capacity
                 .compare_exchange(
                     value,
-                    capacity,
+                    max_capacity,
                     atomic::Ordering::Release,
                     atomic::Ordering::Acquire,
                 )
@@ -520,6 +677,69 @@ impl ResourceManager {
         }
     }
 
+    /// Removes the quantity from the stock of a consumable resource.
+    /// Returns the remaining stock, or an error if the stock is insufficient.
+    pub async fn consume(
+        &self,
+        label: &str,
+        quantity: Capacity,
+    ) -> Result<Capacity, LRuntimeError> {
+        let mut map = self.inner.lock().await;
+        let resource = self.get_consumable(&mut map, label, "consume").await?;
+        if quantity > resource.capacity {
+            return Err(LRuntimeError::new(
+                "consume",
+                format!(
+                    "insufficient stock of {}: {} required, {} available",
+                    label, quantity, resource.capacity
+                ),
+            ));
+        }
+        resource.capacity -= quantity;
+        Ok(resource.capacity)
+    }
+
+    /// Adds the quantity to the stock of a consumable resource.
+    /// Returns the new stock, or an error if it would exceed the maximum capacity.
+    pub async fn produce(
+        &self,
+        label: &str,
+        quantity: Capacity,
+    ) -> Result<Capacity, LRuntimeError> {
+        let mut map = self.inner.lock().await;
+        let resource = self.get_consumable(&mut map, label, "produce").await?;
+        if resource.capacity + quantity > resource.max_capacity {
+            return Err(LRuntimeError::new(
+                "produce",
+                format!(
+                    "the stock of {} would exceed its maximum capacity: {} + {} > {}",
+                    label, resource.capacity, quantity, resource.max_capacity
+                ),
+            ));
+        }
+        resource.capacity += quantity;
+        Ok(resource.capacity)
+    }
+
+    async fn get_consumable<'a>(
+        &self,
+        map: &'a mut HashMap<ResourceId, Resource>,
+        label: &str,
+        function: &str,
+    ) -> Result<&'a mut Resource, LRuntimeError> {
+        let id = self.get_id(label).await.ok_or_else(|| {
+            LRuntimeError::new(function, format!("Resource {} does not exist.", label))
+        })?;
+        let resource = map.get_mut(&id).unwrap();
+        match resource.kind {
+            ResourceKind::Consumable => Ok(resource),
+            ResourceKind::Reusable => Err(LRuntimeError::new(
+                function,
+                format!("{} is not a consumable resource", label),
+            )),
+        }
+    }
+
     pub async fn acquire(
         &self,
         label: &str,
@@ -531,7 +751,106 @@ impl ResourceManager {
         })?;
         let mut map = self.inner.lock().await;
         let resource: &mut Resource = map.get_mut(&id).unwrap();
-        resource._acquire(quantity, priority).await
+        let r = resource._acquire(quantity, priority).await;
+        Self::check_groups(&mut map).await;
+        r
+    }
+
+    /// Acquires all the resources atomically: the clients of the group are served together,
+    /// once each of them is first in the queue of its resource, and the resource has enough
+    /// capacity. Until then, the clients of lower priority are queued behind them.
+    pub async fn acquire_all(
+        &self,
+        resources: &[(String, Quantity)],
+        priority: WaiterPriority,
+    ) -> Result<Vec<WaitAcquire>, LRuntimeError> {
+        let mut ids = vec![];
+        for (label, _) in resources {
+            let id = self.get_id(label).await.ok_or_else(|| {
+                LRuntimeError::new("acquire_all", format!("Resource {} does not exist.", label))
+            })?;
+            if ids.contains(&id) {
+                return Err(LRuntimeError::new(
+                    "acquire_all",
+                    format!("Resource {} is acquired twice.", label),
+                ));
+            }
+            ids.push(id);
+        }
+        let mut map = self.inner.lock().await;
+        let mut waiters = vec![];
+        for (id, (_, quantity)) in ids.iter().zip(resources) {
+            let resource: &mut Resource = map.get_mut(id).unwrap();
+            match resource.new_ticket(*quantity, priority, TicketKind::Direct) {
+                Ok(waiter) => waiters.push(waiter),
+                Err(e) => {
+                    for waiter in waiters {
+                        map.get_mut(&waiter.resource_id)
+                            .unwrap()
+                            .remove_client(waiter)
+                            .await;
+                    }
+                    return Err(e.chain("acquire_all"));
+                }
+            }
+        }
+        let group: Vec<(ResourceId, ClientId)> = waiters
+            .iter()
+            .map(|w| (w.resource_id, w.client_id))
+            .collect();
+        for (resource_id, client_id) in &group {
+            map.get_mut(resource_id)
+                .unwrap()
+                .clients
+                .get_mut(client_id)
+                .unwrap()
+                .group = group.clone();
+        }
+        Self::check_groups(&mut map).await;
+        Ok(waiters)
+    }
+
+    /// Serves the groups whose clients are all first in the queue of their resource, and
+    /// whose resources have enough capacity.
+    async fn check_groups(map: &mut HashMap<ResourceId, Resource>) {
+        loop {
+            let servable: Vec<Vec<(ResourceId, ClientId)>> = map
+                .values()
+                .filter_map(|r| r.clients.get(r.queue.last()?))
+                .filter(|c| !c.group.is_empty())
+                .map(|c| c.group.clone())
+                .unique()
+                .filter(|group| {
+                    group.iter().all(|(resource_id, client_id)| {
+                        let r = &map[resource_id];
+                        r.queue.last() == Some(client_id)
+                            && r.clients[client_id].kind == TicketKind::Direct
+                            && r.clients[client_id].quantity <= r.capacity
+                    })
+                })
+                .collect();
+
+            if servable.is_empty() {
+                return;
+            }
+            for group in servable {
+                for (resource_id, client_id) in group {
+                    let resource = map.get_mut(&resource_id).unwrap();
+                    resource.remove_ticket(&client_id);
+                    let acquire = resource.add_acquire(&client_id);
+                    if resource.clients[&client_id]
+                        .tx
+                        .send(Ok(acquire))
+                        .await
+                        .is_err()
+                    {
+                        resource.remove_in_service(&client_id);
+                        resource.update_remaining_capacity().expect("");
+                    }
+                    resource.check_queue().await;
+                }
+            }
+        }
     }
 
     pub async fn reserve(
@@ -552,13 +871,9 @@ impl ResourceManager {
         let id = self.get_id(resource).await.ok_or_else(|| {
             LRuntimeError::new("acquire", format!("Resource {} does not exist.", resource))
         })?;
-        self.inner
-            .lock()
-            .await
-            .get_mut(&id)
-            .unwrap()
-            .check_queue()
-            .await;
+        let mut map = self.inner.lock().await;
+        map.get_mut(&id).unwrap().check_queue().await;
+        Self::check_groups(&mut map).await;
         Ok(())
     }
 
@@ -582,6 +897,7 @@ impl ResourceManager {
         resource.remove_in_service(&rh.client_id);
         resource.update_remaining_capacity()?;
         resource.check_queue().await;
+        Self::check_groups(&mut map).await;
         Ok(())
     }
 
@@ -589,6 +905,9 @@ impl ResourceManager {
         let mut map = self.inner.lock().await;
         let resource: &mut Resource = map.get_mut(&wa.resource_id).unwrap();
         resource.remove_client(wa).await;
+        //the removed waiter may have been blocking the queue
+        resource.check_queue().await;
+        Self::check_groups(&mut map).await;
     }
 
     pub async fn acquire_reservation(
@@ -615,10 +934,12 @@ impl ResourceManager {
             Quantity::Some(u) => *u,
         };
 
-        resource
+        let r = resource
             ._acquire_reservation(client_id, quantity)
             .await
-            .map_err(|e| e.chain("resource_manager.acquire_reservation"))
+            .map_err(|e| e.chain("resource_manager.acquire_reservation"));
+        Self::check_groups(&mut map).await;
+        r
     }
 
     pub async fn update_priority(
@@ -629,7 +950,8 @@ impl ResourceManager {
     ) {
         let mut map = self.inner.lock().await;
         let resource: &mut Resource = map.get_mut(resource_id).unwrap();
-        resource.update_priority(client_id, priority)
+        resource.update_priority(client_id, priority);
+        Self::check_groups(&mut map).await;
     }
 
     pub async fn get_client_quantity(
@@ -642,6 +964,114 @@ impl ResourceManager {
         resource.get_client_quantity(client_id)
     }
 
+    /// Sets the acquisition owning the client, used to build the wait-for graph.
+    pub async fn set_owner(
+        &self,
+        resource_id: &ResourceId,
+        client_id: &ClientId,
+        owner: ClientOwner,
+    ) {
+        if let Some(client) = self
+            .inner
+            .lock()
+            .await
+            .get_mut(resource_id)
+            .and_then(|r| r.clients.get_mut(client_id))
+        {
+            client.owner = Some(owner)
+        }
+    }
+
+    /// Returns the deadlock formed by the waiting client, if any.
+    /// The wait-for graph links the tasks waiting for a resource to the tasks holding it.
+    pub async fn detect_deadlock(
+        &self,
+        resource_id: &ResourceId,
+        client_id: &ClientId,
+    ) -> Option<Deadlock> {
+        let map = self.inner.lock().await;
+        let resource = map.get(resource_id)?;
+        if resource.in_service.contains(client_id) {
+            return None;
+        }
+        let task = resource.clients.get(client_id)?.owner?.task;
+
+        let mut graph: HashMap<usize, Vec<WaitFor>> = Default::default();
+        for r in map.values() {
+            for id in &r.queue {
+                let Some(waiter) = r.clients.get(id).and_then(|c| c.owner) else {
+                    continue;
+                };
+                for holder in r.in_service.iter().filter_map(|h| r.clients.get(h)?.owner) {
+                    if holder.task != waiter.task {
+                        graph.entry(waiter.task).or_default().push(WaitFor {
+                            waiter,
+                            resource_id: r.id,
+                            client_id: *id,
+                            resource: r.label.clone(),
+                            holder,
+                        })
+                    }
+                }
+            }
+        }
+
+        fn search(
+            task: usize,
+            target: usize,
+            graph: &HashMap<usize, Vec<WaitFor>>,
+            visited: &mut std::collections::HashSet<usize>,
+            path: &mut Vec<WaitFor>,
+        ) -> bool {
+            for edge in graph.get(&task).into_iter().flatten() {
+                path.push(edge.clone());
+                if edge.holder.task == target
+                    || (visited.insert(edge.holder.task)
+                        && search(edge.holder.task, target, graph, visited, path))
+                {
+                    return true;
+                }
+                path.pop();
+            }
+            false
+        }
+
+        let mut cycle = vec![];
+        search(task, task, &graph, &mut Default::default(), &mut cycle)
+            .then_some(Deadlock { cycle })
+    }
+
+    /// Fails the waiting client with the deadlock it is part of, removing it from the queue of the resource.
+    pub async fn fail_waiter(
+        &self,
+        resource_id: &ResourceId,
+        client_id: &ClientId,
+        deadlock: Deadlock,
+    ) {
+        let mut map = self.inner.lock().await;
+        if let Some(resource) = map.get_mut(resource_id) {
+            resource.remove_ticket(client_id);
+            if let Some(client) = resource.clients.remove(client_id) {
+                let _ = client.tx.send(Err(deadlock)).await;
+            }
+            resource.check_queue().await;
+        }
+        Self::check_groups(&mut map).await;
+    }
+
+    pub async fn get_preemptible(
+        &self,
+        resource_id: &ResourceId,
+        client_id: &ClientId,
+    ) -> Vec<ClientId> {
+        self.inner
+            .lock()
+            .await
+            .get(resource_id)
+            .map(|r| r.get_preemptible(client_id))
+            .unwrap_or_default()
+    }
+
     pub async fn get_client_priority(
         &self,
         resource_id: &ResourceId,
@@ -697,11 +1127,12 @@ impl ResourceManager {
             str.push_str(
                 format!(
                     "- {}:\n\
+                    \t- kind: {}\n\
                     \t- max_capacity: {}\n\
                     \t- capacity: {}\n\
                     \t- acquirers: {}\n\
                     \t- waiters: {} \n",
-                    label, r.max_capacity, r.capacity, acq_str, w_str,
+                    label, r.kind, r.max_capacity, r.capacity, acq_str, w_str,
                 )
                 .as_str(),
             );
@@ -742,3 +1173,191 @@ impl ResourceManager {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_preemptible() {
+        let resource_manager = ResourceManager::default();
+        resource_manager
+            .new_resource("r".to_string(), Some(2))
+            .await;
+        let low = resource_manager
+            .acquire("r", Quantity::Some(1), Execution(0))
+            .await
+            .unwrap();
+        let mid = resource_manager
+            .acquire("r", Quantity::Some(1), Execution(1))
+            .await
+            .unwrap();
+        let high = resource_manager
+            .acquire("r", Quantity::Some(1), Execution(5))
+            .await
+            .unwrap();
+        let id = high.get_resource_id();
+        assert_eq!(
+            resource_manager
+                .get_preemptible(&id, &high.get_client_id())
+                .await,
+            vec![low.get_client_id()]
+        );
+        assert!(resource_manager
+            .get_preemptible(&id, &mid.get_client_id())
+            .await
+            .is_empty());
+
+        let all = resource_manager
+            .acquire("r", Quantity::All, Execution(3))
+            .await
+            .unwrap();
+        assert_eq!(
+            resource_manager
+                .get_preemptible(&id, &all.get_client_id())
+                .await,
+            vec![low.get_client_id(), mid.get_client_id()]
+        );
+        let equal = resource_manager
+            .acquire("r", Quantity::Some(1), Execution(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            resource_manager
+                .get_preemptible(&id, &equal.get_client_id())
+                .await,
+            vec![low.get_client_id()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadlock() {
+        let resource_manager = ResourceManager::default();
+        for r in ["r1", "r2"] {
+            resource_manager.new_resource(r.to_string(), Some(1)).await;
+        }
+        let owner = |task, acquire| ClientOwner { task, acquire };
+        let acquire = |label: &'static str, owner: ClientOwner| {
+            let resource_manager = resource_manager.clone();
+            async move {
+                let wait = resource_manager
+                    .acquire(label, Quantity::All, Execution(0))
+                    .await
+                    .unwrap();
+                resource_manager
+                    .set_owner(&wait.get_resource_id(), &wait.get_client_id(), owner)
+                    .await;
+                wait
+            }
+        };
+        let mut a1 = acquire("r1", owner(1, 2)).await;
+        a1.recv().await.unwrap();
+        let b2 = acquire("r2", owner(10, 11)).await.recv().await.unwrap();
+        let mut a2 = acquire("r2", owner(1, 3)).await;
+        assert!(resource_manager
+            .detect_deadlock(&a2.get_resource_id(), &a2.get_client_id())
+            .await
+            .is_none());
+
+        let mut b1 = acquire("r1", owner(10, 12)).await;
+        let deadlock = resource_manager
+            .detect_deadlock(&b1.get_resource_id(), &b1.get_client_id())
+            .await
+            .unwrap();
+        assert_eq!(
+            deadlock.to_string(),
+            "deadlock: task 10 (acquire 12) waits for r1 held by task 1 (acquire 2), \
+             task 1 (acquire 3) waits for r2 held by task 10 (acquire 11)"
+        );
+        let youngest = deadlock.youngest().clone();
+        assert_eq!(youngest.waiter, owner(10, 12));
+
+        resource_manager
+            .fail_waiter(&youngest.resource_id, &youngest.client_id, deadlock)
+            .await;
+        assert!(b1.recv().await.is_err());
+        assert!(resource_manager
+            .detect_deadlock(&a2.get_resource_id(), &a2.get_client_id())
+            .await
+            .is_none());
+        resource_manager.release(b2).await.unwrap();
+        a2.recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_acquire_all() {
+        let resource_manager = ResourceManager::default();
+        for r in ["r1", "r2"] {
+            resource_manager.new_resource(r.to_string(), Some(1)).await;
+        }
+        let acquire = |label: &'static str| {
+            let resource_manager = resource_manager.clone();
+            async move {
+                resource_manager
+                    .acquire(label, Quantity::All, Execution(0))
+                    .await
+                    .unwrap()
+            }
+        };
+        let a = acquire("r1").await.recv().await.unwrap();
+        let mut group = resource_manager
+            .acquire_all(
+                &[
+                    ("r1".to_string(), Quantity::All),
+                    ("r2".to_string(), Quantity::All),
+                ],
+                Execution(0),
+            )
+            .await
+            .unwrap();
+        //r2 is free, but the client is queued behind the group
+        let mut b = acquire("r2").await;
+        assert!(!resource_manager.is_locked("r2").await.unwrap());
+
+        resource_manager.release(a).await.unwrap();
+        let mut handlers = vec![];
+        for waiter in &mut group {
+            handlers.push(waiter.recv().await.unwrap());
+        }
+        assert!(resource_manager.is_locked("r2").await.unwrap());
+        assert!(b.rx.try_recv().is_err());
+
+        for rh in handlers {
+            resource_manager.release(rh).await.unwrap();
+        }
+        b.recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_consumable() {
+        let resource_manager = ResourceManager::default();
+        resource_manager
+            .new_consumable("battery".to_string(), 10, Some(4))
+            .await
+            .unwrap();
+        assert!(resource_manager
+            .new_consumable("parts".to_string(), 2, Some(3))
+            .await
+            .is_err());
+        assert!(resource_manager
+            .acquire("battery", Quantity::Some(1), Execution(0))
+            .await
+            .is_err());
+
+        assert_eq!(resource_manager.consume("battery", 3).await.unwrap(), 1);
+        assert!(resource_manager.consume("battery", 2).await.is_err());
+        assert_eq!(resource_manager.produce("battery", 9).await.unwrap(), 10);
+        assert!(resource_manager.produce("battery", 1).await.is_err());
+
+        let snapshot = resource_manager.get_snapshot(None).await;
+        let key: LValueS = list![QUANTITY.into(), "battery".into()].try_into().unwrap();
+        assert_eq!(
+            snapshot.inner_dynamic.inner.get(&key).unwrap().value,
+            LValueS::from(10usize)
+        );
+
+        let copy = resource_manager.new_from_current().await;
+        copy.consume("battery", 5).await.unwrap();
+        assert_eq!(copy.produce("battery", 5).await.unwrap(), 10);
+    }
+}
@@ -10,6 +10,7 @@ pub const STATUS_RUNNING: &str = "running";
 pub const STATUS_SUCCESS: &str = "success";
 pub const STATUS_FAILURE: &str = "failure";
 pub const STATUS_CANCELLED: &str = "cancelled";
+pub const STATUS_DEADLINE_MISSED: &str = "deadline-missed";
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ProcessStatus {
@@ -22,11 +23,15 @@ pub enum ProcessStatus {
     Cancelled(bool),
     Planned,
     //True the action has been successfully stopped, false it was a failure to cancel
+    DeadlineMissed, //The task has been stopped because it did not end before its deadline
 }
 
 impl ProcessStatus {
     pub fn is_failed(&self) -> bool {
-        matches!(self, Self::Failure | Self::Rejected | Self::Cancelled(_))
+        matches!(
+            self,
+            Self::Failure | Self::Rejected | Self::Cancelled(_) | Self::DeadlineMissed
+        )
     }
 
     pub fn is_terminated(&self) -> bool {
@@ -56,6 +61,7 @@ impl Display for ProcessStatus {
             ProcessStatus::Failure => write!(f, "failure"),
             ProcessStatus::Pending => write!(f, "pending"),
             ProcessStatus::Planned => write!(f, "planned"),
+            ProcessStatus::DeadlineMissed => write!(f, "deadline missed"),
         }
     }
 }
@@ -6,7 +6,9 @@ use crate::ompas::manager::state::state_update_manager::{
     StateRule, StateUpdate, StateUpdateManager, StateUpdateSubscriber, SubscriberId,
 };
 use crate::ompas::manager::state::world_state_snapshot::{WorldState, WorldStateSnapshot};
+use crate::ompas::manager::trace::{TraceEvent, TraceManager};
 use im::hashmap::Entry;
+use serde::{Deserialize, Serialize};
 use sompas_structs::lruntimeerror;
 use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::LValue;
@@ -20,7 +22,7 @@ pub mod partial_state;
 pub mod state_update_manager;
 pub mod world_state_snapshot;
 
-#[derive(Clone, Debug, PartialOrd, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialOrd, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StateType {
     Static,
     Dynamic,
@@ -32,6 +34,7 @@ pub enum StateType {
 #[derive(Clone)]
 pub struct StateManager {
     clock_manager: ClockManager,
+    trace_manager: TraceManager,
     world_state: Arc<RwLock<WorldState>>,
     // r#static: Arc<RwLock<PartialState>>,
     // dynamic: Arc<RwLock<PartialState>>,
@@ -42,9 +45,10 @@ pub struct StateManager {
 }
 
 impl StateManager {
-    pub fn new(clock_manager: ClockManager, st: RefSymTable) -> Self {
+    pub fn new(clock_manager: ClockManager, trace_manager: TraceManager, st: RefSymTable) -> Self {
         Self {
             clock_manager,
+            trace_manager,
             world_state: Arc::new(Default::default()),
             // r#static: Arc::new(Default::default()),
             // dynamic: Arc::new(Default::default()),
@@ -60,6 +64,7 @@ impl From<WorldStateSnapshot> for StateManager {
     fn from(w: WorldStateSnapshot) -> Self {
         Self {
             clock_manager: Default::default(),
+            trace_manager: Default::default(),
             world_state: Arc::new(RwLock::new(WorldState {
                 r#static: w.r#static,
                 dynamic: w.dynamic,
@@ -135,6 +140,13 @@ impl StateManager {
         self.state_update_manager.write().await.new_subscriber(rule)
     }
 
+    pub async fn remove_subscriber(&self, subscriber_id: &SubscriberId) {
+        self.state_update_manager
+            .write()
+            .await
+            .remove_subscriber(subscriber_id)
+    }
+
     pub async fn update_subscriber_rule(&self, subscriber_id: &SubscriberId, rule: StateRule) {
         self.state_update_manager
             .write()
@@ -211,6 +223,16 @@ impl StateManager {
     }
 
     pub async fn update_state(&self, state: PartialState) {
+        if let Some(state_type) = &state._type {
+            self.trace_manager.record(TraceEvent::StateUpdate {
+                state_type: state_type.clone(),
+                facts: state
+                    .inner
+                    .iter()
+                    .map(|(k, f)| (k.clone(), f.value.clone()))
+                    .collect(),
+            });
+        }
         let mut updated = vec![];
         let time = self.clock_manager.now();
         let mut world_state = self.world_state.write().await;
@@ -0,0 +1,470 @@
+//! Persistent trace of the execution of the acting engine.
+//! Jobs, state updates, exchanges with the platform, decisions of the engine and plan updates are
+//! recorded with their date in a JSON lines file, one event per line.
+//! A recorded trace can be replayed with the *ReplayPlatform*, the decisions of the engine being
+//! checked against the recorded ones.
+use crate::ompas::manager::acting::interval::Timepoint;
+use crate::ompas::manager::acting::ActingProcessId;
+use crate::ompas::manager::clock::ClockManager;
+use crate::ompas::manager::monitoring::Divergence;
+use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::state::StateType;
+use serde::{Deserialize, Serialize};
+use sompas_structs::lruntimeerror;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalues::LValueS;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub const TRACE_FILE: &str = "trace.jsonl";
+/// Period at which the recorded trace is flushed to its file.
+const TRACE_FLUSH_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A job received by the main loop of the engine.
+    Job { kind: String, expr: String },
+    /// An update of the state, whatever its origin.
+    StateUpdate {
+        state_type: StateType,
+        facts: Vec<(LValueS, LValueS)>,
+    },
+    /// A new instance declared by the platform.
+    Instance { object: String, r#type: String },
+    /// A new resource declared by the platform.
+    Resource { label: String, capacity: usize },
+    /// A command sent to the platform.
+    CommandRequest {
+        id: ActingProcessId,
+        command: String,
+    },
+    /// A request to cancel a command sent to the platform.
+    CommandCancel { id: ActingProcessId },
+    /// A response of the platform to a command.
+    CommandResponse {
+        id: ActingProcessId,
+        status: ProcessStatus,
+    },
+    /// The method executed to refine a task.
+    Refinement {
+        task: ActingProcessId,
+        method: String,
+    },
+    /// The value chosen by an arbitrary.
+    Arbitrary { id: ActingProcessId, value: String },
+    /// An update of the acting tree by the planner.
+    PlanUpdate { models: usize, choices: usize },
+    /// A divergence between the planned chronicles and the observed state.
+    Divergence(Divergence),
+}
+
+impl TraceEvent {
+    /// Key of the decisions of the engine, used to compare a replay with the recorded trace.
+    /// Returns None if the event is not a decision of the engine.
+    fn decision_key(&self) -> Option<(&'static str, ActingProcessId)> {
+        match self {
+            TraceEvent::CommandRequest { id, .. } => Some(("command", *id)),
+            TraceEvent::Refinement { task, .. } => Some(("refinement", *task)),
+            TraceEvent::Arbitrary { id, .. } => Some(("arbitrary", *id)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the event is an input coming from the platform.
+    pub fn is_platform_update(&self) -> bool {
+        match self {
+            TraceEvent::StateUpdate { state_type, .. } => {
+                matches!(state_type, StateType::Static | StateType::Dynamic)
+            }
+            TraceEvent::Instance { .. } | TraceEvent::Resource { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraceEvent::Job { kind, expr } => write!(f, "job {kind} {expr}"),
+            TraceEvent::StateUpdate { state_type, facts } => {
+                write!(f, "{state_type:?} state update:")?;
+                for (k, v) in facts {
+                    write!(f, " {k} = {v};")?;
+                }
+                Ok(())
+            }
+            TraceEvent::Instance { object, r#type } => write!(f, "instance {object} - {type}"),
+            TraceEvent::Resource { label, capacity } => {
+                write!(f, "resource {label} of capacity {capacity}")
+            }
+            TraceEvent::CommandRequest { id, command } => write!(f, "command({id}) {command}"),
+            TraceEvent::CommandCancel { id } => write!(f, "cancel command({id})"),
+            TraceEvent::CommandResponse { id, status } => write!(f, "command({id}): {status}"),
+            TraceEvent::Refinement { task, method } => {
+                write!(f, "task({task}) refined by {method}")
+            }
+            TraceEvent::Arbitrary { id, value } => write!(f, "arbitrary({id}) = {value}"),
+            TraceEvent::PlanUpdate { models, choices } => {
+                write!(f, "plan update: {models} models, {choices} choices")
+            }
+            TraceEvent::Divergence(divergence) => write!(f, "divergence: {divergence}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub date: Timepoint,
+    #[serde(flatten)]
+    pub event: TraceEvent,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:.3}] {}", self.date.as_secs(), self.event)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Loads a trace recorded in a JSON lines file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LRuntimeError> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| {
+            lruntimeerror!(
+                "Trace::load",
+                format!("could not open {}: {}", path.display(), e)
+            )
+        })?;
+        let mut entries = vec![];
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| lruntimeerror!("Trace::load", e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line).map_err(|e| {
+                lruntimeerror!(
+                    "Trace::load",
+                    format!("{}:{}: invalid trace entry: {}", path.display(), i + 1, e)
+                )
+            })?)
+        }
+        Ok(Self { entries })
+    }
+
+    /// Date of the first entry of the trace.
+    pub fn start(&self) -> Timepoint {
+        self.entries.first().map(|e| e.date).unwrap_or_default()
+    }
+}
+
+/// A decision of the engine that differs from the recorded trace.
+#[derive(Debug, Clone)]
+pub struct ReplayDivergence {
+    pub date: Timepoint,
+    pub expected: Option<TraceEvent>,
+    pub got: TraceEvent,
+}
+
+impl Display for ReplayDivergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.expected {
+            Some(expected) => write!(
+                f,
+                "[{:.3}] expected {}, got {}",
+                self.date.as_secs(),
+                expected,
+                self.got
+            ),
+            None => write!(f, "[{:.3}] unexpected {}", self.date.as_secs(), self.got),
+        }
+    }
+}
+
+/// Compares the decisions of the engine with the ones of a recorded trace.
+/// Decisions are compared per acting process, in the order they have been taken.
+#[derive(Debug, Default)]
+pub struct TraceChecker {
+    expected: HashMap<(&'static str, ActingProcessId), VecDeque<TraceEvent>>,
+    checked: usize,
+    divergences: Vec<ReplayDivergence>,
+}
+
+impl TraceChecker {
+    pub fn new(trace: &Trace) -> Self {
+        let mut expected: HashMap<_, VecDeque<_>> = HashMap::new();
+        for entry in &trace.entries {
+            if let Some(key) = entry.event.decision_key() {
+                expected
+                    .entry(key)
+                    .or_default()
+                    .push_back(entry.event.clone());
+            }
+        }
+        Self {
+            expected,
+            checked: 0,
+            divergences: vec![],
+        }
+    }
+
+    /// Checks a decision against the trace, returning the divergence if any.
+    pub fn check(&mut self, date: Timepoint, event: &TraceEvent) -> Option<&ReplayDivergence> {
+        let key = event.decision_key()?;
+        self.checked += 1;
+        let expected = self.expected.get_mut(&key).and_then(|q| q.pop_front());
+        if expected.as_ref() == Some(event) {
+            return None;
+        }
+        self.divergences.push(ReplayDivergence {
+            date,
+            expected,
+            got: event.clone(),
+        });
+        self.divergences.last()
+    }
+
+    pub fn report(&self) -> ReplayReport {
+        ReplayReport {
+            checked: self.checked,
+            missing: self.expected.values().map(|q| q.len()).sum(),
+            divergences: self.divergences.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    /// Number of decisions checked.
+    pub checked: usize,
+    /// Number of recorded decisions that have not been taken during the replay.
+    pub missing: usize,
+    pub divergences: Vec<ReplayDivergence>,
+}
+
+impl ReplayReport {
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty() && self.missing == 0
+    }
+}
+
+impl Display for ReplayReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} decisions checked, {} divergences, {} recorded decisions not taken.",
+            self.checked,
+            self.divergences.len(),
+            self.missing
+        )?;
+        for d in &self.divergences {
+            writeln!(f, "- {d}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writer of the trace. The lines are buffered and flushed periodically, the remaining ones being
+/// flushed when the record stops or the writer is dropped.
+struct TraceRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+/// Records the trace of the engine and checks it against a reference trace in replay mode.
+#[derive(Clone)]
+pub struct TraceManager {
+    clock_manager: ClockManager,
+    recorder: Arc<Mutex<Option<TraceRecorder>>>,
+    checker: Arc<Mutex<Option<TraceChecker>>>,
+}
+
+impl Default for TraceManager {
+    fn default() -> Self {
+        Self::new(ClockManager::default())
+    }
+}
+
+impl TraceManager {
+    pub fn new(clock_manager: ClockManager) -> Self {
+        Self {
+            clock_manager,
+            recorder: Arc::new(Mutex::new(None)),
+            checker: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Starts to record the trace in a new file, stopping the previous record if any.
+    pub fn start_recording(&self, path: PathBuf) -> Result<(), LRuntimeError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| lruntimeerror!("start_recording", e.to_string()))?;
+        }
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|e| {
+                lruntimeerror!(
+                    "start_recording",
+                    format!("could not create {}: {}", path.display(), e)
+                )
+            })?;
+        self.stop_recording();
+        *self.recorder.lock().unwrap() = Some(TraceRecorder {
+            path,
+            writer: BufWriter::new(file),
+            last_flush: Instant::now(),
+        });
+        Ok(())
+    }
+
+    /// Stops the record of the trace, returning the path of the file if a record was running.
+    pub fn stop_recording(&self) -> Option<PathBuf> {
+        let mut recorder = self.recorder.lock().unwrap().take()?;
+        let _ = recorder.writer.flush();
+        Some(recorder.path)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.lock().unwrap().is_some()
+    }
+
+    /// Sets the trace against which the decisions of the engine are checked.
+    pub fn set_reference(&self, trace: &Trace) {
+        *self.checker.lock().unwrap() = Some(TraceChecker::new(trace));
+    }
+
+    pub fn get_report(&self) -> Option<ReplayReport> {
+        self.checker.lock().unwrap().as_ref().map(|c| c.report())
+    }
+
+    /// Records an event at the current date, and checks it against the reference trace if any.
+    pub fn record(&self, event: TraceEvent) {
+        let date = self.clock_manager.now();
+        if let Some(checker) = self.checker.lock().unwrap().as_mut() {
+            checker.check(date, &event);
+        }
+        if let Some(recorder) = self.recorder.lock().unwrap().as_mut() {
+            let entry = TraceEntry { date, event };
+            if let Ok(line) = serde_json::to_string(&entry) {
+                let _ = writeln!(recorder.writer, "{line}");
+                //Flushing periodically keeps most of the trace of a run that crashes.
+                if recorder.last_flush.elapsed() >= TRACE_FLUSH_PERIOD {
+                    let _ = recorder.writer.flush();
+                    recorder.last_flush = Instant::now();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ClockMode;
+
+    fn entry(secs: f64, event: TraceEvent) -> TraceEntry {
+        TraceEntry {
+            date: secs.into(),
+            event,
+        }
+    }
+
+    #[test]
+    fn test_trace_checker() {
+        let trace = Trace {
+            entries: vec![
+                entry(
+                    0.0,
+                    TraceEvent::Job {
+                        kind: "task".to_string(),
+                        expr: "(t_move r1 l1)".to_string(),
+                    },
+                ),
+                entry(
+                    0.1,
+                    TraceEvent::Refinement {
+                        task: 1,
+                        method: "(m_move r1 l1)".to_string(),
+                    },
+                ),
+                entry(
+                    0.2,
+                    TraceEvent::Arbitrary {
+                        id: 3,
+                        value: "l2".to_string(),
+                    },
+                ),
+                entry(
+                    0.3,
+                    TraceEvent::Refinement {
+                        task: 1,
+                        method: "(m_move_2 r1 l1)".to_string(),
+                    },
+                ),
+            ],
+        };
+
+        for entry in &trace.entries {
+            let line = serde_json::to_string(entry).unwrap();
+            let parsed: TraceEntry = serde_json::from_str(&line).unwrap();
+            assert_eq!(parsed.event, entry.event);
+            assert_eq!(parsed.date, entry.date);
+        }
+
+        let mut checker = TraceChecker::new(&trace);
+        let date = Timepoint::default();
+        assert!(checker.check(date, &trace.entries[1].event).is_none());
+        assert!(checker
+            .check(
+                date,
+                &TraceEvent::Arbitrary {
+                    id: 3,
+                    value: "l3".to_string()
+                }
+            )
+            .is_some());
+        let report = checker.report();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.missing, 1);
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn test_trace_recording() -> Result<(), LRuntimeError> {
+        let trace_manager = TraceManager::new(ClockManager::new(ClockMode::RealTime));
+        let path = std::env::temp_dir()
+            .join(format!("ompas_trace_{}", std::process::id()))
+            .join(TRACE_FILE);
+        trace_manager.start_recording(path.clone())?;
+        for id in 0..100 {
+            trace_manager.record(TraceEvent::CommandCancel { id });
+        }
+        assert_eq!(trace_manager.stop_recording(), Some(path.clone()));
+        assert!(!trace_manager.is_recording());
+
+        //All the buffered events are written when the record stops.
+        let trace = Trace::load(&path)?;
+        assert_eq!(trace.entries.len(), 100);
+        assert_eq!(
+            trace.entries[99].event,
+            TraceEvent::CommandCancel { id: 99 }
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
 use crate::model::process_ref::ProcessRef;
 use crate::ompas::error::RaeExecError;
-use crate::ompas::interface::job::{Job, JobType};
+use crate::ompas::interface::job::{split_task_priority, Job, JobType};
 use crate::ompas::interface::rae_command::OMPASJob;
 use crate::ompas::interface::trigger_collection::Response;
 use crate::ompas::interface::trigger_collection::TaskProcess;
 use crate::ompas::manager::acting::acting_var::AsCst;
-use crate::ompas::manager::acting::ActingManager;
+use crate::ompas::manager::acting::{ActingManager, ActingProcessId};
 use crate::ompas::manager::state::action_status::ProcessStatus;
+use crate::ompas::manager::trace::TraceEvent;
 use crate::ompas::scheme::exec::acting_context::ModActingContext;
 use futures::FutureExt;
 use ompas_language::process::{LOG_TOPIC_OMPAS, PROCESS_TOPIC_OMPAS};
@@ -17,7 +18,8 @@ use sompas_core::{eval, parse};
 use sompas_structs::lasynchandler::LAsyncHandle;
 use sompas_structs::lenv::LEnv;
 use sompas_structs::lfuture::FutureResult;
-use sompas_structs::lswitch::{new_interruption_handler, InterruptionSender};
+use sompas_structs::lruntimeerror::LResult;
+use sompas_structs::lswitch::{new_interruption_handler, InterruptionReceiver, InterruptionSender};
 use sompas_structs::lvalue::LValue;
 use tokio::sync::mpsc::UnboundedReceiver;
 
@@ -94,13 +96,18 @@ pub async fn exec_job(
     killers: &mut Vec<InterruptionSender>,
 ) {
     log.debug("new job received!");
+    acting_manager.trace_manager.record(TraceEvent::Job {
+        kind: format!("{:?}", job.r#type).to_lowercase(),
+        expr: job.expr.clone(),
+    });
 
     let mut pr: ProcessRef = ProcessRef::Id(0);
+    let mut preemptible: Option<ActingProcessId> = None;
 
     let job_type = job.r#type;
     let job_expr = &job.expr;
 
-    let job_lvalue: LValue = match parse(job_expr, &mut env).await {
+    let mut job_lvalue: LValue = match parse(job_expr, &mut env).await {
         Ok(l) => l,
         Err(e) => {
             job.sender.send(Err(e)).unwrap();
@@ -112,13 +119,26 @@ pub async fn exec_job(
         JobType::Task => {
             log.debug(format!("new triggered task: {}", job_expr));
             let vec: Vec<LValue> = job_lvalue.clone().try_into().unwrap();
+            let (task, priority) = match split_task_priority(&vec) {
+                Ok(r) => r,
+                Err(e) => {
+                    job.sender.send(Err(e)).unwrap();
+                    return;
+                }
+            };
+            job_lvalue = task.into();
             let mut vec_cst = vec![];
-            for e in vec {
+            for e in task {
                 vec_cst.push(e.as_cst().unwrap())
             }
             let id: ProcessRef = acting_manager
                 .new_high_level_task(job_lvalue.to_string(), vec_cst)
                 .await;
+            let task_id = acting_manager.get_id(id.clone()).await.unwrap();
+            if priority > 0 {
+                acting_manager.set_task_priority(&task_id, priority).await;
+            }
+            preemptible = Some(task_id);
 
             let mod_context: ModActingContext = ModActingContext::new(id.clone());
             pr = id;
@@ -185,7 +205,12 @@ pub async fn exec_job(
             }
         }*/
 
-        let result = eval(&job_lvalue, &mut env, Some(rx)).await;
+        let result = match preemptible {
+            Some(id) => {
+                eval_preemptible(&job_lvalue, &mut env, rx, id, &acting_manager_2, &log2).await
+            }
+            None => eval(&job_lvalue, &mut env, Some(rx)).await,
+        };
         match &result {
             Ok(lv) => log2.info(format!(
                 "result of job {}: {}",
@@ -223,3 +248,147 @@ pub async fn exec_job(
         }
     }
 }
+
+/// Evaluates a top-level task, retrying it each time it is preempted by a task of higher priority.
+/// The interruption of the job interrupts the current attempt.
+async fn eval_preemptible(
+    lv: &LValue,
+    env: &mut LEnv,
+    mut int: InterruptionReceiver,
+    id: ActingProcessId,
+    acting_manager: &ActingManager,
+    log: &LogClient,
+) -> LResult {
+    let preemption = &acting_manager.preemption_manager;
+    loop {
+        let (mut switch, rx) = new_interruption_handler();
+        preemption.register(id, switch.clone());
+        let result = {
+            let attempt = eval(lv, env, Some(rx));
+            tokio::pin!(attempt);
+            tokio::select! {
+                r = &mut attempt => r,
+                true = int.recv() => {
+                    switch.interrupt();
+                    attempt.await
+                }
+            }
+        };
+        //The task may have been preempted after its end.
+        if preemption.take_preempted(&id) && !acting_manager.get_status(&id).await.is_success() {
+            log.info(format!("({id}) Task preempted, retrying it."));
+            continue;
+        }
+        preemption.unregister(&id);
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::acting_domain::model::ActingModel;
+    use crate::model::process_ref::Label;
+    use crate::model::sym_domain::cst::Cst;
+    use crate::ompas::manager::acting::process::ProcessOrigin;
+    use crate::ompas::manager::acting::MethodModel;
+    use crate::ompas::manager::resource::{Quantity, WaiterPriority};
+    use sompas_core::{eval_init, get_root_env};
+    use sompas_modules::ModExtendedStd;
+    use sompas_structs::lenv::ImportType::WithoutPrefix;
+    use sompas_structs::lruntimeerror::LRuntimeError;
+    use std::time::{Duration, Instant};
+
+    /// Adds a top-level task refined by a method acquiring a resource.
+    /// Returns the id of the task and of the acquisition.
+    async fn new_acquiring_task(
+        acting_manager: &ActingManager,
+        name: &str,
+    ) -> (ActingProcessId, ActingProcessId) {
+        let pr = acting_manager
+            .new_high_level_task(name.to_string(), vec![Cst::Symbol(name.to_string())])
+            .await;
+        let task = acting_manager.get_id(pr).await.unwrap();
+        let model = ActingModel {
+            lv: LValue::Nil,
+            lv_om: LValue::Nil,
+            lv_expanded: None,
+            runtime_info: Default::default(),
+            chronicle: None,
+        };
+        let method = acting_manager
+            .new_executed_method(
+                &task,
+                format!("m_{name}"),
+                vec![],
+                MethodModel::ActingModel(model),
+            )
+            .await;
+        let acquire = acting_manager
+            .new_acquire(
+                Label::ResourceAcquisition(0),
+                &method,
+                ProcessOrigin::Execution,
+            )
+            .await;
+        (task, acquire)
+    }
+
+    #[tokio::test]
+    async fn test_eval_preemptible() -> Result<(), LRuntimeError> {
+        let acting_manager = ActingManager::default();
+        acting_manager
+            .resource_manager
+            .new_resource("r".to_string(), None)
+            .await;
+        let (low, low_acquire) = new_acquiring_task(&acting_manager, "low").await;
+        let (high, high_acquire) = new_acquiring_task(&acting_manager, "high").await;
+        acting_manager.set_task_priority(&high, 10).await;
+
+        //The task of low priority holds the resource while it runs.
+        let mut wait = acting_manager
+            .acquire(
+                &low_acquire,
+                "r".to_string(),
+                Quantity::All,
+                WaiterPriority::Execution(0),
+            )
+            .await?;
+        let held = wait.recv().await.unwrap();
+
+        let mut env = get_root_env().await;
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        eval_init(&mut env).await;
+        let lv = parse("(sleep 0.5)", &mut env).await?;
+        let (_tx, rx) = new_interruption_handler();
+        let acting_manager_2 = acting_manager.clone();
+        let start = Instant::now();
+        let job = tokio::spawn(async move {
+            let log = LogClient::new("test-preemption", LOG_TOPIC_OMPAS).await;
+            eval_preemptible(&lv, &mut env, rx, low, &acting_manager_2, &log).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        //The acquisition of higher priority preempts the running task, which releases the resource.
+        let mut wait = acting_manager
+            .acquire(
+                &high_acquire,
+                "r".to_string(),
+                Quantity::All,
+                WaiterPriority::Execution(0),
+            )
+            .await?;
+        assert_eq!(
+            acting_manager.preempt(&high_acquire, &wait).await,
+            vec![low]
+        );
+        acting_manager.resource_manager.release(held).await?;
+        assert!(wait.recv().await.is_ok());
+
+        //The preempted task is retried from the start, and completes.
+        assert_eq!(job.await.unwrap()?, LValue::Nil);
+        assert!(start.elapsed() >= Duration::from_millis(600));
+        assert!(!acting_manager.preemption_manager.preempt(&low));
+        Ok(())
+    }
+}
@@ -153,6 +153,12 @@ pub async fn arbitrary(env: &LEnv, args: &[LValue]) -> LResult {
 pub async fn __sleep__(env: &LEnv, n: LNumber) -> LAsyncHandle {
     let (tx, mut rx) = new_interruption_handler();
     let mode = *env.get_context::<RAEMode>(CTX_RAE_MODE).unwrap();
+    let clock_manager = env
+        .get_context::<ModExec>(MOD_EXEC)
+        .unwrap()
+        .acting_manager
+        .clock_manager
+        .clone();
     let f: FutureResult = Box::pin(async move {
         let duration = match mode {
             RAEMode::Exec => Duration::from_micros((f64::from(&n) * 1_000_000.0) as u64),
@@ -163,7 +169,7 @@ pub async fn __sleep__(env: &LEnv, n: LNumber) -> LAsyncHandle {
             _ = rx.recv() => {
                 Ok(interrupted!())
             }
-            _ = tokio::time::sleep(duration) => {
+            _ = clock_manager.sleep(duration) => {
                 Ok(LValue::Nil)
             }
         }
@@ -54,6 +54,7 @@ impl From<ModPlatform> for LModule {
         module.add_async_fn(START_PLATFORM, start_platform, DOC_START_PLATFORM, false);
         module.add_lambda(EXEC_COMMAND, LAMBDA_EXEC_COMMAND, DOC_EXEC_COMMAND);
         module.add_lambda(CTX_ACQUIRE, LAMBDA_CTX_ACQUIRE, DOC_CTX_ACQUIRE);
+        module.add_lambda(CTX_ACQUIRE_ALL, LAMBDA_CTX_ACQUIRE_ALL, DOC_CTX_ACQUIRE_ALL);
         module.add_lambda(
             CTX_EXEC_COMMAND,
             LAMBDA_CTX_EXEC_COMMAND,
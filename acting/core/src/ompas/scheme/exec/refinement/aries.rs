@@ -66,6 +66,7 @@ pub async fn aries_select(
     //     }
     // }
 
+    let _busy = acting_manager.clock_manager.busy();
     let result = ompas_lcp::run_planner(
         &ep,
         &OMPASLCPConfig {
@@ -0,0 +1,77 @@
+use crate::ompas::interface::select_mode::{LearningConfig, SelectMode, UPOMMode};
+use crate::ompas::manager::acting::process::task::Selected;
+use crate::ompas::manager::learning::{LearningKey, LearningManager, MethodStats};
+use crate::ompas::manager::state::world_state_snapshot::WorldStateSnapshot;
+use crate::ompas::scheme::exec::refinement::sampling::upom::{upom_select_with_priors, Prior};
+use sompas_structs::lenv::LEnv;
+use sompas_structs::lruntimeerror;
+use sompas_structs::lvalue::LValue;
+
+fn value(stats: &MethodStats, mode: UPOMMode) -> f64 {
+    match mode {
+        UPOMMode::Efficiency => stats.efficiency,
+        UPOMMode::Robustness => stats.success_rate(),
+    }
+}
+
+/// Selects the candidate with the best learned value, with a bonus for the candidates that have
+/// been rarely executed. Candidates that have never been executed in the abstracted state are
+/// tried first.
+/// If UPOM is configured, the learned values are the priors of UPOM.
+pub async fn learning_select(
+    task: &[LValue],
+    candidates: &[LValue],
+    state: &WorldStateSnapshot,
+    env: &LEnv,
+    learning: &LearningManager,
+    config: LearningConfig,
+) -> lruntimeerror::Result<Selected> {
+    if candidates.is_empty() {
+        return Ok(Selected::Generated(
+            LValue::Nil,
+            SelectMode::Learning(config),
+        ));
+    }
+    let stats: Vec<Option<MethodStats>> = candidates
+        .iter()
+        .map(|m| {
+            let LValue::List(method) = m else {
+                unreachable!()
+            };
+            learning.get_stats(&LearningKey::new(task, method, state))
+        })
+        .collect();
+
+    let selected = if let Some(upom) = config.get_upom() {
+        let priors: Vec<Option<Prior>> = stats
+            .iter()
+            .map(|s| {
+                s.map(|s| Prior {
+                    n: s.n,
+                    q: value(&s, upom.get_mode()),
+                })
+            })
+            .collect();
+        match upom_select_with_priors(task, candidates, state, env, upom, &priors).await? {
+            Selected::Generated(m, _) => m,
+            _ => unreachable!(),
+        }
+    } else {
+        let n_total: u64 = stats.iter().flatten().map(|s| s.n).sum();
+        let rank = |s: &MethodStats| {
+            value(s, config.get_mode())
+                + config.get_c() * ((n_total as f64).ln() / s.n as f64).sqrt()
+        };
+        match stats.iter().position(|s| s.is_none()) {
+            Some(i) => candidates[i].clone(),
+            None => candidates
+                .iter()
+                .zip(&stats)
+                .max_by(|(_, s1), (_, s2)| rank(&s1.unwrap()).total_cmp(&rank(&s2.unwrap())))
+                .map(|(m, _)| m.clone())
+                .unwrap_or(LValue::Nil),
+        }
+    };
+
+    Ok(Selected::Generated(selected, SelectMode::Learning(config)))
+}
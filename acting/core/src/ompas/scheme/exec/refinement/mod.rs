@@ -1,4 +1,5 @@
 pub mod aries;
+pub mod learning;
 
 mod sampling;
 
@@ -14,11 +15,13 @@ use crate::ompas::manager::acting::process::ProcessOrigin;
 use crate::ompas::manager::acting::{ActingManager, ActingProcessId, MethodModel};
 use crate::ompas::manager::clock::ClockManager;
 use crate::ompas::manager::domain::DomainManager;
+use crate::ompas::manager::learning::LearningKey;
 use crate::ompas::manager::planning::planner_manager_interface::FilterWatchedProcesses;
 use crate::ompas::manager::state::action_status::ProcessStatus;
 use crate::ompas::manager::state::world_state_snapshot::WorldStateSnapshot;
 use crate::ompas::scheme::exec::acting_context::ModActingContext;
 use crate::ompas::scheme::exec::refinement::aries::aries_select;
+use crate::ompas::scheme::exec::refinement::learning::learning_select;
 use crate::ompas::scheme::exec::refinement::sampling::c_choice::c_choice_select;
 use crate::ompas::scheme::exec::refinement::sampling::rae_plan::rae_plan_select;
 use crate::ompas::scheme::exec::refinement::sampling::upom::upom_select;
@@ -200,6 +203,22 @@ async fn check_refinement_trace(
         .set_executed_refinement(task_id, &method_id, rt)
         .await;
 
+    if acting_manager.learning_manager.is_enabled() {
+        let task: Vec<LValue> = acting_manager
+            .get_process_args(task_id)
+            .await
+            .into_iter()
+            .map(LValue::from)
+            .collect();
+        let LValue::List(method) = acting_manager.get_refinement_lv(&method_id).await else {
+            unreachable!()
+        };
+        let state = acting_manager.state_manager.get_snapshot().await;
+        acting_manager
+            .learning_manager
+            .start(method_id, LearningKey::new(&task, &method, &state));
+    }
+
     let program = acting_manager.get_om_lvalue(&method_id).await;
 
     log.trace(format!("({method_id}) program: \n{}", program.format(0)));
@@ -233,6 +252,10 @@ pub async fn set_success(env: &LEnv) -> LResult {
         .set_end(&task_id, None, ProcessStatus::Success)
         .await;
 
+    if let Some(method_id) = acting_manager.get_last_executed_refinement(&task_id).await {
+        learn_outcome(ctx, &method_id, true).await;
+    }
+
     Ok(LValue::Nil)
 }
 
@@ -253,6 +276,7 @@ pub async fn _retry(env: &LEnv, err: LValue) -> LResult {
     ));
     if let Some(refinement_id) = acting_manager.get_last_executed_refinement(&task_id).await {
         acting_manager.set_failed_method(&refinement_id).await;
+        learn_outcome(ctx, &refinement_id, false).await;
         let rt: RefinementTrace = select(task_id, env).await?;
         check_refinement_trace(ctx, &task_id, rt).await
     } else {
@@ -266,6 +290,20 @@ pub async fn _retry(env: &LEnv, err: LValue) -> LResult {
     }
 }
 
+/// Updates the statistics learned on the methods with the outcome of an executed method.
+async fn learn_outcome(ctx: &ModRefinement, method_id: &ActingProcessId, success: bool) {
+    let acting_manager = &ctx.acting_manager;
+    if acting_manager.learning_manager.is_enabled() {
+        let duration = acting_manager.get_execution_time(method_id).await.as_secs();
+        if let Err(e) = acting_manager
+            .learning_manager
+            .end(method_id, success, duration)
+        {
+            ctx.log.error(format!("({method_id}) {e}"));
+        }
+    }
+}
+
 pub async fn select(
     task_id: ActingProcessId,
     env: &LEnv,
@@ -347,7 +385,7 @@ pub async fn select(
                         .get_deliberation_reactivity_duration();
 
                     tokio::select! {
-                        _ = tokio::time::sleep(duration) => {
+                        _ = mod_refinement.clock_manager.sleep(duration) => {
                             log.info(format!("({}) Going to select without a plan.", task_id));
                         }
                         r = watcher.recv() => {
@@ -439,6 +477,16 @@ pub async fn select(
                     .await
                     .map_err(|e| e.chain("UPOM"))?
             }
+            SelectMode::Learning(config) => learning_select(
+                &task,
+                &candidates,
+                &state,
+                env,
+                &acting_manager.learning_manager,
+                config,
+            )
+            .await
+            .map_err(|e| e.chain("learning_select"))?,
         })
     } else {
         selected
@@ -85,6 +85,13 @@ pub struct MethodValue {
     q0: f64,
 }
 
+/// Value of a method learned on its previous executions, with the number of executions.
+#[derive(Copy, Clone, Debug)]
+pub struct Prior {
+    pub n: u64,
+    pub q: f64,
+}
+
 #[derive(Default, Clone)]
 pub struct StateValue {
     n_task: HashMap<LValueS, u64>,
@@ -293,13 +300,17 @@ pub async fn upom_task<T: Utility>(env: &LEnv, task: &[LValue]) -> LResult {
         }
         Entry::Vacant(o) => {
             //ctx.log("Newly addressed task");
-            o.insert(0);
+            let mut n_task = 0;
             for method in &methods {
                 // ctx.log(format!("Adding method {} to state key", method));
-                state_value
+                //The method may have been initialized with a prior.
+                n_task += state_value
                     .method_values
-                    .insert(method.clone(), MethodValue::default());
+                    .entry(method.clone())
+                    .or_default()
+                    .n;
             }
+            o.insert(n_task);
         }
     }
     drop(global);
@@ -431,13 +442,26 @@ pub async fn upom_select(
     state: &WorldStateSnapshot,
     env: &LEnv,
     config: UPOMConfig,
+) -> lruntimeerror::Result<Selected> {
+    upom_select_with_priors(task, candidates, state, env, config, &[]).await
+}
+
+/// Selects a method with UPOM, the values of the candidates being initialized with the priors.
+/// *priors* is either empty or contains the prior of each candidate, if any.
+pub async fn upom_select_with_priors(
+    task: &[LValue],
+    candidates: &[LValue],
+    state: &WorldStateSnapshot,
+    env: &LEnv,
+    config: UPOMConfig,
+    priors: &[Option<Prior>],
 ) -> lruntimeerror::Result<Selected> {
     match config.get_mode() {
         UPOMMode::Efficiency => {
-            _upom_select::<Efficiency>(task, candidates, state, env, config).await
+            _upom_select::<Efficiency>(task, candidates, state, env, config, priors).await
         }
         UPOMMode::Robustness => {
-            _upom_select::<Robustness>(task, candidates, state, env, config).await
+            _upom_select::<Robustness>(task, candidates, state, env, config, priors).await
         }
     }
 }
@@ -448,11 +472,13 @@ async fn _upom_select<T: Utility>(
     state: &WorldStateSnapshot,
     env: &LEnv,
     config: UPOMConfig,
+    priors: &[Option<Prior>],
 ) -> lruntimeerror::Result<Selected> {
     let global = GLOBAL_UPOM.clone();
     let mut new_env = env.clone();
     let ctx_exec = new_env.get_context::<ModExec>(MOD_EXEC).unwrap();
     let domain_manager = ctx_exec.domain.clone();
+    let clock = ctx_exec.acting_manager.clock_manager.clone();
     let mut domain = domain_manager.get_inner().await;
     upom_env(&mut new_env, &domain).await;
     let models_commands: im::HashMap<String, ModelCollection> = domain
@@ -478,6 +504,27 @@ async fn _upom_select<T: Utility>(
         .map(|m| LValueS::try_from(m).unwrap())
         .collect();
 
+    if !priors.is_empty() {
+        //The priors count as previous samples of the methods, until the task is sampled in this state.
+        let task_key: LValueS = LValue::from(task).try_into()?;
+        let mut global_lock = global.inner.write().await;
+        let state_value = global_lock.entry(state_key.clone()).or_default();
+        if !state_value.n_task.contains_key(&task_key) {
+            for (method, prior) in methods.iter().zip(priors) {
+                if let Some(prior) = prior {
+                    state_value.method_values.insert(
+                        method.clone(),
+                        MethodValue {
+                            n: prior.n,
+                            q: prior.q,
+                            q0: prior.q,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
     let mut m = candidates[0].clone();
     let mut q_max = 0.0;
     let global_lock = global.inner.read().await;
@@ -500,7 +547,7 @@ async fn _upom_select<T: Utility>(
     }
     drop(global_lock);
 
-    let timeout = tokio::time::sleep(Duration::from_secs_f64(config.get_timeout()));
+    let timeout = clock.sleep(Duration::from_secs_f64(config.get_timeout()));
     let task: LValue = task.into();
     let candidates = candidates.to_vec();
     let (tx, mut rx) = tokio::sync::oneshot::channel();
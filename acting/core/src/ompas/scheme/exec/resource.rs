@@ -1,16 +1,20 @@
 use crate::model::process_ref::{Label, ProcessRef};
+use crate::ompas::error::RaeExecError;
 use crate::ompas::manager::acting::inner::ActingProcessKind;
 use crate::ompas::manager::acting::process::ProcessOrigin;
 use crate::ompas::manager::acting::{ActingManager, ActingProcessId};
 use crate::ompas::manager::resource::{
-    Capacity, Quantity, ResourceHandler, ResourceManager, WaitAcquire, WaiterPriority,
+    Capacity, Quantity, ResourceHandler, ResourceKind, ResourceManager, WaitAcquire, WaiterPriority,
 };
 use crate::ompas::manager::state::action_status::ProcessStatus;
 use crate::ompas::manager::state::StateManager;
 use crate::ompas::scheme::exec::acting_context::{def_label, ModActingContext};
 use crate::ompas::scheme::exec::mode::RAEMode;
 use crate::ompas::scheme::exec::ModExec;
+use crate::{OMPAS_DEADLOCK_POLICY, OMPAS_PREEMPTION};
+use futures::future::try_join_all;
 use futures::FutureExt;
+use itertools::Itertools;
 use macro_rules_attribute::macro_rules_attribute;
 use ompas_language::exec::acting_context::MOD_ACTING_CONTEXT;
 use ompas_language::exec::mode::*;
@@ -30,6 +34,7 @@ use sompas_structs::lmodule::LModule;
 use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
 use sompas_structs::lswitch::new_interruption_handler;
 use sompas_structs::lvalue::LValue;
+use sompas_structs::lvalues::LValueS;
 use sompas_structs::{list, lruntimeerror};
 use std::borrow::Borrow;
 use std::convert::{TryFrom, TryInto};
@@ -83,11 +88,19 @@ impl From<ModResource> for LModule {
             DOC___ACQUIRE_IN_LIST__,
             false,
         );
+        module.add_async_fn(__ACQUIRE_ALL__, __acquire_all__, DOC___ACQUIRE_ALL__, false);
         module.add_async_fn(RELEASE, release, DOC_RELEASE, false);
+        module.add_async_fn(CONSUME, consume, (DOC_CONSUME, DOC_CONSUME_VERBOSE), false);
+        module.add_async_fn(PRODUCE, produce, (DOC_PRODUCE, DOC_PRODUCE_VERBOSE), false);
         module.add_async_fn(IS_LOCKED, is_locked, DOC_IS_LOCKED, false);
         module.add_async_fn(RESOURCES, resources, DOC_RESOURCES, false);
         module.add_lambda(ACQUIRE, LAMBDA_ACQUIRE, DOC_ACQUIRE);
         module.add_lambda(ACQUIRE_IN_LIST, LAMBDA_ACQUIRE_IN_LIST, DOC_ACQUIRE_IN_LIST);
+        module.add_lambda(
+            ACQUIRE_ALL,
+            LAMBDA_ACQUIRE_ALL,
+            (DOC_ACQUIRE_ALL, DOC_ACQUIRE_ALL_VERBOSE),
+        );
         module
     }
 }
@@ -95,10 +108,26 @@ impl From<ModResource> for LModule {
 #[async_scheme_fn]
 pub async fn new_resource(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeError> {
     let ctx = env.get_context::<ModResource>(MOD_RESOURCE)?;
+    declare_resource(
+        &ctx.resource_manager,
+        &ctx.state_manager,
+        args,
+        NEW_RESOURCE,
+    )
+    .await
+}
 
+/// Declares the resource described by *args*: a label, an optional capacity, and optionally
+/// the keyword :consumable followed by the initial stock of the resource.
+pub async fn declare_resource(
+    resource_manager: &ResourceManager,
+    state_manager: &StateManager,
+    args: &[LValue],
+    function: &str,
+) -> Result<(), LRuntimeError> {
     let label: String = args
         .first()
-        .ok_or_else(|| LRuntimeError::wrong_number_of_args(NEW_RESOURCE, args, 1..2))?
+        .ok_or_else(|| LRuntimeError::wrong_number_of_args(function, args, 1..4))?
         .try_into()?;
 
     let capacity: Capacity = match args.get(1) {
@@ -106,13 +135,62 @@ pub async fn new_resource(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeErr
         Some(lv) => lv.try_into()?,
     };
 
-    if LValue::Nil == ctx.state_manager.instance(&label, TYPE_OBJECT).await {
-        ctx.state_manager.add_instance(&label, TYPE_OBJECT).await
+    let kind = match args.get(2) {
+        None => ResourceKind::Reusable,
+        Some(lv) if lv == &LValue::from(CONSUMABLE) => ResourceKind::Consumable,
+        Some(lv) => {
+            return Err(lruntimeerror!(
+                function,
+                format!("expected {}, got {}", CONSUMABLE, lv)
+            ))
+        }
+    };
+
+    if LValue::Nil == state_manager.instance(&label, TYPE_OBJECT).await {
+        state_manager.add_instance(&label, TYPE_OBJECT).await
+    }
+
+    match kind {
+        ResourceKind::Reusable => resource_manager.new_resource(label, Some(capacity)).await,
+        ResourceKind::Consumable => {
+            let quantity: Option<Capacity> = match args.get(3) {
+                None => None,
+                Some(lv) => Some(lv.try_into()?),
+            };
+            resource_manager
+                .new_consumable(label.clone(), capacity, quantity)
+                .await?;
+            set_stock(state_manager, &label, quantity.unwrap_or(capacity)).await;
+        }
     }
+    Ok(())
+}
 
-    ctx.resource_manager
-        .new_resource(label, Some(capacity))
-        .await;
+/// Writes the stock of a consumable resource in the state, as the fact (quantity resource).
+async fn set_stock(state_manager: &StateManager, label: &str, quantity: Capacity) {
+    state_manager
+        .add_value_with_date(
+            LValueS::List(vec![QUANTITY.into(), label.into()]),
+            LValueS::from(quantity),
+        )
+        .await
+}
+
+/// Removes a quantity from the stock of a consumable resource.
+#[async_scheme_fn]
+pub async fn consume(env: &LEnv, label: String, quantity: Capacity) -> Result<(), LRuntimeError> {
+    let ctx = env.get_context::<ModResource>(MOD_RESOURCE)?;
+    let stock = ctx.resource_manager.consume(&label, quantity).await?;
+    set_stock(&ctx.state_manager, &label, stock).await;
+    Ok(())
+}
+
+/// Adds a quantity to the stock of a consumable resource.
+#[async_scheme_fn]
+pub async fn produce(env: &LEnv, label: String, quantity: Capacity) -> Result<(), LRuntimeError> {
+    let ctx = env.get_context::<ModResource>(MOD_RESOURCE)?;
+    let stock = ctx.resource_manager.produce(&label, quantity).await?;
+    set_stock(&ctx.state_manager, &label, stock).await;
     Ok(())
 }
 
@@ -175,8 +253,9 @@ pub async fn __acquire__(env: &LEnv, args: &[LValue]) -> Result<LAsyncHandle, LR
                     resources.remove_waiter(wait).await;
                     return Ok(LValue::Err(LValue::Nil.into()))
                 }
-                rh = wait.recv() => {
-                    rh
+                rh = wait.recv() => match rh {
+                    Ok(rh) => rh,
+                    Err(_) => return Ok(RaeExecError::Deadlock.into()),
                 }
             };
 
@@ -254,15 +333,37 @@ pub async fn __acquire__(env: &LEnv, args: &[LValue]) -> Result<LAsyncHandle, LR
             "({id}) Acquiring {label}; capacity = {quantity}; priority = {priority}"
         ));
 
+        if OMPAS_PREEMPTION.get() {
+            for task in acting_manager.preempt(&id, &wait).await {
+                log.info(format!("({id}) Preempting task {task} holding {label}"));
+            }
+        }
+
+        if let Some(deadlock) = acting_manager.resolve_deadlock(&wait).await {
+            log.warn(format!(
+                "({id}) {deadlock}; policy = {:?}",
+                OMPAS_DEADLOCK_POLICY.get()
+            ));
+        }
+
         let rh: ResourceHandler = tokio::select! {
             _ = rx.recv() => {
                 log.info(format!("Acquisition of {label} cancelled."));
                 resources.remove_waiter(wait).await;
                 return Ok(LValue::Err(LValue::Nil.into()))
             }
-            rh = wait.recv() => {
-                log.info(format!("({}) Acquired unlocked", id));
-                rh
+            rh = wait.recv() => match rh {
+                Ok(rh) => {
+                    log.info(format!("({}) Acquired unlocked", id));
+                    rh
+                }
+                Err(_) => {
+                    log.info(format!("({id}) Acquisition of {label} failed to break a deadlock."));
+                    acting_manager
+                        .set_end(&id, None, ProcessStatus::Failure)
+                        .await;
+                    return Ok(RaeExecError::Deadlock.into())
+                }
             }
         };
 
@@ -302,6 +403,226 @@ pub async fn __acquire__(env: &LEnv, args: &[LValue]) -> Result<LAsyncHandle, LR
     Ok(LAsyncHandle::new(f2, tx))
 }
 
+/// Acquires atomically a set of resources.
+/// Each argument is a resource, or a list (resource quantity), the optional priority being
+/// a list (:priority p).
+/// An acquisition process is created for each resource, all of them being released with the
+/// returned handle.
+#[async_scheme_fn]
+pub async fn __acquire_all__(env: &LEnv, args: &[LValue]) -> Result<LAsyncHandle, LRuntimeError> {
+    let ctx = env.get_context::<ModResource>(MOD_RESOURCE)?;
+
+    let resources = ctx.resource_manager.clone();
+
+    let (tx, mut rx) = new_interruption_handler();
+
+    let mut requests: Vec<(String, Quantity)> = vec![];
+    let mut priority = WaiterPriority::Execution(0);
+    for arg in args {
+        match arg {
+            LValue::List(l) if l.len() == 2 && l[0] == LValue::from(PRIORITY) => {
+                priority = WaiterPriority::Execution((&l[1]).try_into()?)
+            }
+            LValue::List(l) if l.len() == 2 => {
+                requests.push(((&l[0]).try_into()?, Quantity::Some((&l[1]).try_into()?)))
+            }
+            LValue::List(_) => {
+                return Err(lruntimeerror!(
+                    __ACQUIRE_ALL__,
+                    format!(
+                        "{arg} is neither a resource, a list (resource quantity) nor a priority"
+                    )
+                ))
+            }
+            lv => requests.push((lv.try_into()?, Quantity::All)),
+        }
+    }
+    if requests.is_empty() {
+        return Err(LRuntimeError::wrong_number_of_args(
+            ACQUIRE_ALL,
+            args,
+            1..usize::MAX,
+        ));
+    }
+    let labels = requests.iter().map(|(r, _)| r.as_str()).join(", ");
+
+    let mode = *env.get_context::<RAEMode>(CTX_RAE_MODE)?;
+    if mode == RAEMode::Simu {
+        let f: LFuture = (Box::pin(async move {
+            let mut waits = resources.acquire_all(&requests, priority).await?;
+
+            let rhs: Vec<ResourceHandler> = tokio::select! {
+                _ = rx.recv() => {
+                    for wait in waits {
+                        resources.remove_waiter(wait).await;
+                    }
+                    return Ok(LValue::Err(LValue::Nil.into()))
+                }
+                rhs = try_join_all(waits.iter_mut().map(|w| w.recv())) => match rhs {
+                    Ok(rhs) => rhs,
+                    Err(_) => return Ok(RaeExecError::Deadlock.into()),
+                }
+            };
+
+            let rc = resources.clone();
+            let (tx, mut rx) = new_interruption_handler();
+
+            let f: LFuture = (Box::pin(async move {
+                rx.recv().await;
+                for rh in rhs {
+                    rc.release(rh).await?;
+                }
+                Ok(LValue::Nil)
+            }) as FutureResult)
+                .shared();
+
+            let f2 = f.clone();
+
+            tokio::spawn(f);
+
+            Ok(LAsyncHandle::new(f2, tx).into())
+        }) as FutureResult)
+            .shared();
+
+        let f2 = f.clone();
+
+        tokio::spawn(f);
+
+        return Ok(LAsyncHandle::new(f2, tx));
+    };
+
+    let pr = &env
+        .get_context::<ModActingContext>(MOD_ACTING_CONTEXT)?
+        .process_ref;
+    let acting_manager = ctx.acting_manager.clone();
+
+    //one acquisition process per resource, labelled as consecutive acquisitions
+    let mut ids: Vec<ActingProcessId> = vec![];
+    for i in 0..requests.len() {
+        let id = match pr {
+            ProcessRef::Id(id) => {
+                let label =
+                    Label::ResourceAcquisition(acting_manager.get_number_acquire(*id).await);
+                acting_manager
+                    .new_acquire(label, id, ProcessOrigin::Execution)
+                    .await
+            }
+            ProcessRef::Relative(id, labels) => match labels[0] {
+                Label::ResourceAcquisition(s) => {
+                    let label = Label::ResourceAcquisition(s + i);
+                    match acting_manager
+                        .get_id(ProcessRef::Relative(*id, vec![label]))
+                        .await
+                    {
+                        Some(id) => id,
+                        None => {
+                            acting_manager
+                                .new_acquire(label, id, ProcessOrigin::Execution)
+                                .await
+                        }
+                    }
+                }
+                _ => panic!(),
+            },
+        };
+        ids.push(id)
+    }
+
+    let log = ctx.log.clone();
+
+    let f: LFuture = (Box::pin(async move {
+        for id in &ids {
+            acting_manager.set_start(id, None).await;
+        }
+
+        let mut waits: Vec<WaitAcquire> =
+            acting_manager.acquire_all(&ids, requests, priority).await?;
+
+        log.info(format!(
+            "({}) Acquiring atomically {labels}; priority = {priority}",
+            ids.iter().join(", ")
+        ));
+
+        for (id, wait) in ids.iter().zip(&waits) {
+            if let Some(deadlock) = acting_manager.resolve_deadlock(wait).await {
+                log.warn(format!(
+                    "({id}) {deadlock}; policy = {:?}",
+                    OMPAS_DEADLOCK_POLICY.get()
+                ));
+            }
+        }
+
+        let rhs: Result<Vec<ResourceHandler>, _> = tokio::select! {
+            _ = rx.recv() => {
+                log.info(format!("Acquisition of {labels} cancelled."));
+                for wait in waits {
+                    resources.remove_waiter(wait).await;
+                }
+                return Ok(LValue::Err(LValue::Nil.into()))
+            }
+            rhs = try_join_all(waits.iter_mut().map(|w| w.recv())) => rhs
+        };
+
+        let rhs = match rhs {
+            Ok(rhs) => rhs,
+            Err(_) => {
+                log.info(format!(
+                    "Acquisition of {labels} failed to break a deadlock."
+                ));
+                for wait in waits {
+                    resources.remove_waiter(wait).await;
+                }
+                for id in &ids {
+                    acting_manager
+                        .set_end(id, None, ProcessStatus::Failure)
+                        .await;
+                }
+                return Ok(RaeExecError::Deadlock.into());
+            }
+        };
+
+        for id in &ids {
+            acting_manager.set_s_acq(id, None).await;
+        }
+
+        let rc = resources.clone();
+        let (tx, mut rx) = new_interruption_handler();
+
+        let log2 = log.clone();
+        let labels2 = labels.clone();
+
+        let f: LFuture = (Box::pin(async move {
+            rx.recv().await;
+            for rh in rhs {
+                rc.release(rh).await?;
+            }
+            log2.info(format!("Released {labels2}"));
+            for id in &ids {
+                acting_manager
+                    .set_end(id, None, ProcessStatus::Success)
+                    .await;
+            }
+            Ok(LValue::Nil)
+        }) as FutureResult)
+            .shared();
+
+        let f2 = f.clone();
+
+        tokio::spawn(f);
+
+        log.info(format!("{labels} acquired."));
+
+        Ok(LAsyncHandle::new(f2, tx).into())
+    }) as FutureResult)
+        .shared();
+
+    let f2 = f.clone();
+
+    tokio::spawn(f);
+
+    Ok(LAsyncHandle::new(f2, tx))
+}
+
 /// Release the resource
 #[async_scheme_fn]
 pub async fn release(mut h: LAsyncHandle) -> LResult {
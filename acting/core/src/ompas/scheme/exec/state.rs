@@ -1,3 +1,4 @@
+use crate::ompas::error::RaeExecError;
 use crate::ompas::manager::clock::ClockManager;
 use crate::ompas::manager::domain::DomainManager;
 use crate::ompas::manager::event::EventManager;
@@ -30,6 +31,7 @@ pub struct ModState {
     pub state_manager: StateManager,
     pub event_manager: EventManager,
     pub domain: DomainManager,
+    pub clock_manager: ClockManager,
 }
 
 impl ModState {
@@ -38,16 +40,19 @@ impl ModState {
             state_manager: exec.acting_manager.state_manager.clone(),
             event_manager: exec.acting_manager.event_manager.clone(),
             domain: exec.acting_manager.domain_manager.clone(),
+            clock_manager: exec.acting_manager.clock_manager.clone(),
         }
     }
 
     pub fn new_from_snapshot(state: WorldStateSnapshot) -> Self {
         let state_manager: StateManager = state.into();
-        let event_manager = EventManager::new(state_manager.clone(), ClockManager::default());
+        let clock_manager = ClockManager::default();
+        let event_manager = EventManager::new(state_manager.clone(), clock_manager.clone());
         Self {
             state_manager,
             event_manager,
             domain: DomainManager::default(),
+            clock_manager,
         }
     }
 }
@@ -83,7 +88,11 @@ impl From<ModState> for LModule {
         module.add_macro(RUN_MONITORING, MACRO_RUN_MONITORING, DOC_RUN_MONITORING);
 
         //Lambdas
-        module.add_lambda(WAIT_FOR, LAMBDA_WAIT_FOR, DOC_WAIT_FOR);
+        module.add_lambda(
+            WAIT_FOR,
+            LAMBDA_WAIT_FOR,
+            (DOC_WAIT_FOR, DOC_WAIT_FOR_VERBOSE),
+        );
         module.add_lambda(MONITOR, LAMBDA_MONITOR, DOC_MONITOR);
         module
     }
@@ -197,7 +206,10 @@ async fn durative_effect(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeErro
             .state_manager
             .add_value_with_date(key.clone(), intermediate_result)
             .await;
-        tokio::time::sleep(Duration::from_micros((duration * 1_000_000.0) as u64)).await;
+        state
+            .clock_manager
+            .sleep(Duration::from_micros((duration * 1_000_000.0) as u64))
+            .await;
     }
     state.state_manager.add_value_with_date(key, value).await;
     Ok(())
@@ -333,11 +345,28 @@ async fn get_state(env: &LEnv, args: &[LValue]) -> LResult {
     Ok(state)
 }
 
+/// Waits until the expression becomes true.
+/// With a timeout, returns err::timeout if the expression is still false once the timeout elapses.
 #[async_scheme_fn]
-async fn __wait_for__(env: &LEnv, lv: LValue) -> Result<LAsyncHandle, LRuntimeError> {
+async fn __wait_for__(env: &LEnv, args: &[LValue]) -> Result<LAsyncHandle, LRuntimeError> {
+    let (lv, timeout) = match args {
+        [lv] => (lv.clone(), None),
+        [lv, timeout] => {
+            let timeout: f64 = timeout.try_into()?;
+            if timeout < 0.0 {
+                return Err(lruntimeerror!(
+                    WAIT_FOR,
+                    format!("expected a non-negative timeout, got {timeout}")
+                ));
+            }
+            (lv.clone(), Some(Duration::from_secs_f64(timeout)))
+        }
+        _ => return Err(LRuntimeError::wrong_number_of_args(WAIT_FOR, args, 1..3)),
+    };
     let (tx, mut rx) = new_interruption_handler();
     let ctx = env.get_context::<ModState>(MOD_STATE)?;
     let monitors = ctx.event_manager.clone();
+    let clock_manager = ctx.clock_manager.clone();
 
     let mut env = env.clone();
     let f: LFuture = (Box::pin(async move {
@@ -347,6 +376,12 @@ async fn __wait_for__(env: &LEnv, lv: LValue) -> Result<LAsyncHandle, LRuntimeEr
         } else {
             let handler = monitors.add_waiter(lv.clone()).await;
             let id = *handler.id();
+            let timer = async move {
+                match timeout {
+                    Some(timeout) => clock_manager.sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
             //println!("wait-for: waiting on {}", lv);
             tokio::select! {
                 _ = rx.recv() => {
@@ -354,6 +389,10 @@ async fn __wait_for__(env: &LEnv, lv: LValue) -> Result<LAsyncHandle, LRuntimeEr
                         monitors.remove_waiter(id).await;
                         Ok(interrupted!())
                 }
+                _ = timer => {
+                    monitors.remove_waiter(id).await;
+                    Ok(RaeExecError::Timeout.into())
+                }
                 _ = handler.recv() => {
                     //println!("success for waiter");
                     Ok(LValue::Nil)
@@ -367,3 +406,32 @@ async fn __wait_for__(env: &LEnv, lv: LValue) -> Result<LAsyncHandle, LRuntimeEr
 
     Ok(LAsyncHandle::new(f, tx))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sompas_core::{eval_init, get_root_env, parse};
+    use sompas_modules::ModExtendedStd;
+    use sompas_structs::lenv::ImportType::WithoutPrefix;
+
+    #[tokio::test]
+    async fn test_wait_for_timeout() -> Result<(), LRuntimeError> {
+        let mod_state = ModState::new_from_snapshot(WorldStateSnapshot::default());
+        let event_manager = mod_state.event_manager.clone();
+        let mut env = get_root_env().await;
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        env.import_module(mod_state, WithoutPrefix);
+        eval_init(&mut env).await;
+
+        //The condition never becomes true: the timeout error is returned and the waiter removed.
+        let lv = parse("(wait-for '(= 1 2) 0.1)", &mut env).await?;
+        let result = eval(&lv, &mut env, None).await?;
+        assert_eq!(result, LValue::from(RaeExecError::Timeout));
+        assert!(!event_manager.get_debug().await.contains("(= 1 2)"));
+
+        //The condition is already true: the timeout is not reached.
+        let lv = parse("(wait-for '(= 1 1) 0.1)", &mut env).await?;
+        assert_eq!(eval(&lv, &mut env, None).await?, LValue::Nil);
+        Ok(())
+    }
+}
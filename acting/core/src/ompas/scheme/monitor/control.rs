@@ -1,26 +1,34 @@
+use crate::model::process_ref::ProcessRef;
 use crate::ompas::interface::continuous_planning_mode::ContinuousPlanningMode;
-use crate::ompas::interface::job::{Job, JobType};
+use crate::ompas::interface::job::{split_task_priority, Job, JobTiming, JobType};
 use crate::ompas::interface::rae_command::OMPASJob;
-use crate::ompas::interface::select_mode::{AriesConfig, Planner, SelectMode};
+use crate::ompas::interface::select_mode::{AriesConfig, LearningConfig, Planner, SelectMode};
 use crate::ompas::interface::stat::BenchStat;
-use crate::ompas::interface::trigger_collection::{JobCollection, JobHandle, PendingJob, Response};
+use crate::ompas::interface::trigger_collection::{
+    JobCollection, JobHandle, PendingJob, Response, ScheduledJob,
+};
 use crate::ompas::manager::acting::filter::ProcessFilter;
 use crate::ompas::manager::acting::inner::ActingProcessKind;
 use crate::ompas::manager::acting::interval::Interval;
-use crate::ompas::manager::acting::ActingManager;
+use crate::ompas::manager::acting::{ActingManager, ActingProcessId};
 use crate::ompas::manager::deliberation::MAX_REACTIVITY;
 use crate::ompas::manager::event::{run_event_checker, run_fluent_checker};
+use crate::ompas::manager::monitoring::run_execution_monitor;
 use crate::ompas::manager::platform::platform_config::PlatformConfig;
 use crate::ompas::manager::platform::PlatformManager;
 use crate::ompas::manager::state::action_status::ProcessStatus;
 use crate::ompas::manager::state::state_update_manager::StateRule;
 use crate::ompas::manager::state::StateType;
+use crate::ompas::manager::trace::{Trace, TRACE_FILE};
 use crate::ompas::rae;
+use crate::ompas::scheme::exec::acting_context::ModActingContext;
 use crate::ompas::scheme::exec::ModExec;
 use crate::ompas::scheme::monitor::model::ModModel;
 use crate::ompas::scheme::monitor::ModMonitor;
 use crate::planning::planner::solver::PMetric;
+use crate::{OMPAS_LEARNING_FILE, OMPAS_TRACE};
 use ompas_language::continuous_planning::*;
+use ompas_language::exec::acting_context::MOD_ACTING_CONTEXT;
 use ompas_language::exec::state::{DYNAMIC, INNER_DYNAMIC, INNER_STATIC, INSTANCE, STATIC};
 use ompas_language::monitor::control::*;
 use ompas_language::monitor::model::MOD_MODEL;
@@ -30,11 +38,13 @@ use ompas_language::select::*;
 use ompas_language::supervisor::*;
 use ompas_middleware::logger::LogClient;
 use ompas_middleware::{Master, ProcessInterface};
+use sompas_core::debugger::{EnvFilter, DEBUGGER};
 use sompas_core::{eval_init, get_root_env};
 use sompas_macros::*;
 use sompas_modules::io::LogOutput;
 use sompas_modules::ModExtendedStd;
 use sompas_structs::kindlvalue::KindLValue;
+use sompas_structs::lasynchandler::LAsyncHandle;
 use sompas_structs::lenv::ImportType::WithoutPrefix;
 use sompas_structs::lenv::{LEnv, LEnvSymbols};
 use sompas_structs::lmodule::LModule;
@@ -130,6 +140,12 @@ impl From<ModControl> for LModule {
             (DOC_EXEC_TASK, DOC_EXEC_TASK_VERBOSE),
             false,
         );
+        module.add_async_fn(
+            EXEC_TIMED_TASK,
+            exec_timed_task,
+            (DOC_EXEC_TIMED_TASK, DOC_EXEC_TIMED_TASK_VERBOSE),
+            false,
+        );
         module.add_async_fn(_WAIT_TASK, _wait_task, DOC__WAIT_TASK, false);
         module.add_lambda(WAIT_TASK, LAMBDA_WAIT_TASK, DOC_WAIT_TASK);
         module.add_async_fn(GET_TASK_ID, get_task_id, DOC_GET_TASK_ID, false);
@@ -249,6 +265,18 @@ impl From<ModControl> for LModule {
         );
 
         module.add_async_fn(EXPORT_REPORT, export_report, DOC_EXPORT_REPORT, false);
+        module.add_async_fn(START_TRACE, start_trace, DOC_START_TRACE, false);
+        module.add_async_fn(STOP_TRACE, stop_trace, DOC_STOP_TRACE, false);
+        module.add_async_fn(CHECK_TRACE, check_trace, DOC_CHECK_TRACE, false);
+        module.add_async_fn(
+            GET_TRACE_REPORT,
+            get_trace_report,
+            DOC_GET_TRACE_REPORT,
+            false,
+        );
+        module.add_async_fn(DEBUG_PROCESS, debug_process, DOC_DEBUG_PROCESS, false);
+        module.add_async_fn(GET_LEARNING, get_learning, DOC_GET_LEARNING, false);
+        module.add_async_fn(GET_DIVERGENCES, get_divergences, DOC_GET_DIVERGENCES, false);
         module.add_async_fn(WAIT_END_ALL, wait_end_all, DOC_WAIT_END_ALL, false);
         module.add_async_fn(BENCH, bench, DOC_BENCH, false);
 
@@ -268,6 +296,12 @@ async fn start(env: &LEnv) -> Result<String, LRuntimeError> {
     let acting_manager = ctx.acting_manager.clone();
     acting_manager.set_plan_env(plan_env.clone()).await;
 
+    if OMPAS_TRACE.get() && !acting_manager.trace_manager.is_recording() {
+        let mut path = Master::get_run_dir();
+        path.push(TRACE_FILE);
+        acting_manager.trace_manager.start_recording(path)?;
+    }
+
     let pendings = ctx.jobs.move_pendings().await;
 
     let (tx, rx) = mpsc::unbounded_channel();
@@ -307,6 +341,15 @@ async fn start(env: &LEnv) -> Result<String, LRuntimeError> {
         .await
     });
 
+    let receiver_monitor_update_state = acting_manager
+        .state_manager
+        .new_subscriber(StateRule::All)
+        .await;
+    let acting_manager_3 = acting_manager.clone();
+    tokio::spawn(async move {
+        run_execution_monitor(receiver_monitor_update_state, acting_manager_3).await
+    });
+
     tokio::spawn(async move {
         rae(acting_manager_2, log_2, env, rx).await;
     });
@@ -381,7 +424,27 @@ async fn start(env: &LEnv) -> Result<String, LRuntimeError> {
     let _ = rx.await;
     tokio::time::sleep(Duration::from_millis(100)).await;
 
-    for PendingJob { id, r#type, lvalue } in pendings {
+    for PendingJob {
+        id,
+        r#type,
+        lvalue,
+        timing,
+    } in pendings
+    {
+        if let Some(timing) = timing {
+            log.info(format!("Scheduling pending job {}", lvalue));
+            let (scheduled, cancel) = ScheduledJob::new(timing);
+            ctx.jobs.set_scheduled(&id, scheduled.clone()).await;
+            tokio::spawn(run_timed_task(
+                lvalue,
+                scheduled,
+                cancel,
+                sender.clone(),
+                acting_manager.clone(),
+                log.clone(),
+            ));
+            continue;
+        }
         log.info(format!("Sending pending job {}", lvalue));
         let (tx, mut rx) = mpsc::unbounded_channel();
         let job = Job::new(tx, r#type, lvalue);
@@ -460,6 +523,7 @@ pub async fn exec_task(env: &LEnv, args: &[LValue]) -> Result<usize, LRuntimeErr
             format!("{} is not a task.", task),
         ));
     }
+    split_task_priority(args)?;
 
     match ctx.get_sender().await {
         None => Ok(ctx.jobs.add_pending_job(JobType::Task, args.into()).await),
@@ -480,12 +544,154 @@ pub async fn exec_task(env: &LEnv, args: &[LValue]) -> Result<usize, LRuntimeErr
     }
 }
 
+/// Sends a task to the agenda, that releases it in its time window.
+#[async_scheme_fn]
+pub async fn exec_timed_task(env: &LEnv, args: &[LValue]) -> Result<usize, LRuntimeError> {
+    if args.len() < 2 {
+        return Err(LRuntimeError::wrong_number_of_args(
+            EXEC_TIMED_TASK,
+            args,
+            2..usize::MAX,
+        ));
+    }
+    let ctx = env.get_context::<ModControl>(MOD_CONTROL)?;
+    let timing = JobTiming::try_from(&args[0])?;
+
+    let task = args[1].to_string();
+    if !ctx.acting_manager.domain_manager.is_task(&task).await {
+        return Err(LRuntimeError::new(
+            EXEC_TIMED_TASK,
+            format!("{} is not a task.", task),
+        ));
+    }
+
+    split_task_priority(&args[1..])?;
+
+    let task: LValue = args[1..].into();
+    match ctx.get_sender().await {
+        None => Ok(ctx.jobs.add_pending_timed_job(timing, task).await),
+        Some(sender) => {
+            let (scheduled, cancel) = ScheduledJob::new(timing);
+            let id = ctx.jobs.add_scheduled(scheduled.clone()).await;
+            tokio::spawn(run_timed_task(
+                task,
+                scheduled,
+                cancel,
+                sender,
+                ctx.acting_manager.clone(),
+                ctx.log.clone(),
+            ));
+            Ok(id)
+        }
+    }
+}
+
+/// Releases the occurrences of a timed task at their start, and watches their deadline.
+/// A task without start is released as soon as it is scheduled.
+async fn run_timed_task(
+    task: LValue,
+    scheduled: ScheduledJob,
+    mut cancel: tokio::sync::watch::Receiver<bool>,
+    sender: mpsc::UnboundedSender<OMPASJob>,
+    acting_manager: ActingManager,
+    log: LogClient,
+) {
+    let clock = acting_manager.clock_manager.clone();
+    let mut timing = scheduled.get_timing();
+    if timing.start.is_none() {
+        timing.start = Some(clock.now().as_secs());
+    }
+
+    let mut k = 0;
+    while let Some((release, deadline)) = timing.occurrence(k) {
+        k += 1;
+        let wait = release - clock.now().as_secs();
+        if wait > 0.0 {
+            tokio::select! {
+                _ = clock.sleep(Duration::from_secs_f64(wait)) => {}
+                _ = cancel.changed() => return,
+            }
+        }
+        if *cancel.borrow() {
+            return;
+        }
+
+        log.info(format!("Releasing timed task {}", task));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        if sender.send(Job::new_task(tx, task.clone()).into()).is_err() {
+            return;
+        }
+        let process = match rx.recv().await {
+            Some(Ok(Response::Process(process))) => process,
+            Some(Err(e)) => {
+                log.error(format!("Could not release timed task {}: {}", task, e));
+                continue;
+            }
+            _ => return,
+        };
+        if let Some(deadline) = deadline {
+            let id = acting_manager.get_id(process.get_ref()).await.unwrap();
+            acting_manager.set_deadline(&id, deadline.into()).await;
+            tokio::spawn(watch_deadline(
+                id,
+                process.get_handle(),
+                deadline,
+                timing.soft,
+                acting_manager.clone(),
+                log.clone(),
+            ));
+        }
+        scheduled.add_occurrence(process).await;
+    }
+}
+
+/// Stops the task if it is not terminated at its deadline.
+/// A soft deadline only logs that the task is late.
+async fn watch_deadline(
+    id: ActingProcessId,
+    mut handle: LAsyncHandle,
+    deadline: f64,
+    soft: bool,
+    acting_manager: ActingManager,
+    log: LogClient,
+) {
+    let clock = acting_manager.clock_manager.clone();
+    let wait = deadline - clock.now().as_secs();
+    if wait > 0.0 {
+        clock.sleep(Duration::from_secs_f64(wait)).await;
+    }
+    if acting_manager.get_status(&id).await.is_terminated() {
+        return;
+    }
+    if soft {
+        log.warn(format!(
+            "({}) Task is late on its deadline {:.3}",
+            id, deadline
+        ));
+    } else {
+        log.warn(format!(
+            "({}) Task stopped, it missed its deadline {:.3}",
+            id, deadline
+        ));
+        let _ = handle.interrupt().await;
+        acting_manager
+            .set_end(&id, None, ProcessStatus::DeadlineMissed)
+            .await;
+    }
+}
+
 #[async_scheme_fn]
 pub async fn _wait_task(env: &LEnv, task_id: usize) -> LResult {
     let ctx = env.get_context::<ModControl>(MOD_CONTROL)?;
     let trigger: Option<JobHandle> = ctx.jobs.get_job(task_id).await;
     match trigger {
         Some(JobHandle::Process(process)) => Ok(LValue::Handle(process.get_handle())),
+        Some(JobHandle::Scheduled(scheduled)) => match scheduled.get_last_occurrence().await {
+            Some(process) => Ok(LValue::Handle(process.get_handle())),
+            None => Ok(string!(format!(
+                "{task_id} is a timed task that has not been released yet."
+            ))),
+        },
         Some(JobHandle::Pending(_)) => Ok(string!(format!(
             "{task_id} is a pending task because ompas has been started yet."
         ))),
@@ -502,6 +708,15 @@ pub async fn get_task_id(env: &LEnv, task_id: usize) -> LResult {
     let trigger: Option<JobHandle> = ctx.jobs.get_job(task_id).await;
     match trigger {
         Some(JobHandle::Process(process)) => Ok(format!("{:?}", process.get_ref()).into()),
+        Some(JobHandle::Scheduled(scheduled)) => {
+            let refs: Vec<ProcessRef> = scheduled
+                .get_occurrences()
+                .await
+                .iter()
+                .map(|p| p.get_ref())
+                .collect();
+            Ok(format!("{:?}", refs).into())
+        }
         Some(JobHandle::Pending(_)) => Ok(string!(format!(
             "{task_id} is a pending task because ompas has been started yet."
         ))),
@@ -518,6 +733,17 @@ pub async fn cancel_task(env: &LEnv, task_id: usize) -> LResult {
     let trigger: Option<JobHandle> = ctx.jobs.get_job(task_id).await;
     match trigger {
         Some(JobHandle::Process(process)) => process.get_handle().interrupt().await,
+        Some(JobHandle::Scheduled(scheduled)) => {
+            scheduled.cancel();
+            let mut result = Ok(LValue::Nil);
+            for process in scheduled.get_occurrences().await {
+                let id = ctx.acting_manager.get_id(process.get_ref()).await.unwrap();
+                if !ctx.acting_manager.get_status(&id).await.is_terminated() {
+                    result = process.get_handle().interrupt().await;
+                }
+            }
+            result
+        }
         Some(JobHandle::Pending(_)) => Ok(string!(format!(
             "Cannot cancel {task_id} because it is still a pending task"
         ))),
@@ -631,17 +857,25 @@ pub async fn set_select(env: &LEnv, m: String) -> Result<(), LRuntimeError> {
         UPOM => SelectMode::Planning(Planner::UPOM(Default::default())),
         RAE_PLAN => SelectMode::Planning(Planner::RAEPlan(Default::default())),
         C_CHOICE => SelectMode::Planning(Planner::CChoice(Default::default())),
+        LEARNING => SelectMode::Learning(Default::default()),
+        LEARNING_UPOM => SelectMode::Learning(LearningConfig::new_with_upom(Default::default())),
         _ => {
             return Err(lruntimeerror!(
                 SET_SELECT,
                 format!(
-                    "Select mode is either {}, {}, or {}.",
-                    GREEDY, RANDOM, PLANNING,
+                    "Select mode is either {}, {}, {}, or {}.",
+                    GREEDY, RANDOM, PLANNING, LEARNING,
                 )
             ))
         }
     };
 
+    if let SelectMode::Learning(_) = select_mode {
+        ctx.acting_manager
+            .learning_manager
+            .enable(OMPAS_LEARNING_FILE.get_ref().into())?;
+    }
+
     ctx.acting_manager
         .deliberation_manager
         .set_select_mode(select_mode)
@@ -649,6 +883,18 @@ pub async fn set_select(env: &LEnv, m: String) -> Result<(), LRuntimeError> {
     Ok(())
 }
 
+#[async_scheme_fn]
+pub async fn get_learning(env: &LEnv) -> String {
+    let ctx = env.get_context::<ModControl>(MOD_CONTROL).unwrap();
+    ctx.acting_manager.learning_manager.to_string()
+}
+
+#[async_scheme_fn]
+pub async fn get_divergences(env: &LEnv) -> String {
+    let ctx = env.get_context::<ModControl>(MOD_CONTROL).unwrap();
+    ctx.acting_manager.execution_monitor.to_string()
+}
+
 #[async_scheme_fn]
 pub async fn set_continuous_planning(env: &LEnv, m: String) -> Result<(), LRuntimeError> {
     let ctx = env.get_context::<ModControl>(MOD_CONTROL).unwrap();
@@ -840,6 +1086,7 @@ pub async fn print_processes(env: &LEnv, args: &[LValue]) -> LResult {
             STATUS_SUCCESS => task_filter.status = Some(ProcessStatus::Success),
             STATUS_FAILURE => task_filter.status = Some(ProcessStatus::Failure),
             STATUS_CANCELLED => task_filter.status = Some(ProcessStatus::Cancelled(true)),
+            STATUS_DEADLINE_MISSED => task_filter.status = Some(ProcessStatus::DeadlineMissed),
 
             str => {
                 return Err(lruntimeerror!(
@@ -1228,6 +1475,111 @@ pub async fn export_report(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeEr
     Ok(())
 }
 
+#[async_scheme_fn]
+pub async fn start_trace(env: &LEnv, args: &[LValue]) -> Result<String, LRuntimeError> {
+    let path: PathBuf = match args {
+        [] => {
+            let mut path = Master::get_run_dir();
+            path.push(TRACE_FILE);
+            path
+        }
+        [path] => String::try_from(path)?.into(),
+        _ => return Err(LRuntimeError::wrong_number_of_args(START_TRACE, args, 0..1)),
+    };
+    env.get_context::<ModControl>(MOD_CONTROL)?
+        .acting_manager
+        .trace_manager
+        .start_recording(path.clone())?;
+    Ok(format!("Recording the trace in {}", path.display()))
+}
+
+#[async_scheme_fn]
+pub async fn stop_trace(env: &LEnv) -> LResult {
+    Ok(
+        match env
+            .get_context::<ModControl>(MOD_CONTROL)?
+            .acting_manager
+            .trace_manager
+            .stop_recording()
+        {
+            Some(path) => path.display().to_string().into(),
+            None => LValue::Nil,
+        },
+    )
+}
+
+#[async_scheme_fn]
+pub async fn check_trace(env: &LEnv, path: String) -> Result<String, LRuntimeError> {
+    let trace = Trace::load(&path)?;
+    env.get_context::<ModControl>(MOD_CONTROL)?
+        .acting_manager
+        .trace_manager
+        .set_reference(&trace);
+    Ok(format!(
+        "Checking the decisions against the {} events of {}",
+        trace.entries.len(),
+        path
+    ))
+}
+
+#[async_scheme_fn]
+pub async fn get_trace_report(env: &LEnv) -> Result<String, LRuntimeError> {
+    match env
+        .get_context::<ModControl>(MOD_CONTROL)?
+        .acting_manager
+        .trace_manager
+        .get_report()
+    {
+        Some(report) => Ok(report.to_string()),
+        None => Err(LRuntimeError::new(
+            GET_TRACE_REPORT,
+            "No trace is replayed, the decisions are not checked.",
+        )),
+    }
+}
+
+#[async_scheme_fn]
+pub async fn debug_process(env: &LEnv, id: usize) -> Result<String, LRuntimeError> {
+    let acting_manager = &env.get_context::<ModControl>(MOD_CONTROL)?.acting_manager;
+    if !acting_manager.contains(&id).await {
+        return Err(LRuntimeError::new(
+            DEBUG_PROCESS,
+            format!("{} is not an acting process", id),
+        ));
+    }
+    let method = match acting_manager.get_kind(&id).await {
+        ActingProcessKind::Method => id,
+        ActingProcessKind::Task => acting_manager
+            .get_last_executed_refinement(&id)
+            .await
+            .ok_or_else(|| {
+                LRuntimeError::new(DEBUG_PROCESS, format!("task {} is not refined yet", id))
+            })?,
+        kind => {
+            return Err(LRuntimeError::new(
+                DEBUG_PROCESS,
+                format!("{} is a {}, expected a method or a task", id, kind),
+            ))
+        }
+    };
+    //The evaluation of a method and of its subprocesses are in the context of the method.
+    let filter: EnvFilter =
+        Arc::new(
+            move |env: &LEnv| match env.get_context::<ModActingContext>(MOD_ACTING_CONTEXT) {
+                Ok(ctx) => match &ctx.process_ref {
+                    ProcessRef::Id(id) | ProcessRef::Relative(id, _) => *id == method,
+                },
+                Err(_) => false,
+            },
+        );
+    let label = format!("method {}", method);
+    DEBUGGER.attach(label.clone(), filter);
+    Ok(format!(
+        "Debugger attached to {}, that pauses at its next procedure call",
+        label
+    ))
+}
+
 #[async_scheme_fn]
 pub async fn wait_end_all(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeError> {
     let timeout: Option<i64> = if args.len() == 1 {
@@ -1266,7 +1618,7 @@ pub async fn wait_end_all(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeErr
                 _ = waiter => {
 
                 }
-                _ = tokio::time::sleep(Duration::from_secs(timeout as u64)) => {
+                _ = acting_manager.clock_manager.sleep(Duration::from_secs(timeout as u64)) => {
 
                 }
             }
@@ -1278,7 +1630,12 @@ pub async fn wait_end_all(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeErr
 
 #[async_scheme_fn]
 pub async fn bench(env: &LEnv, min: f64, max: f64, export: String) -> Result<(), LRuntimeError> {
-    tokio::time::sleep(Duration::from_secs_f64(min)).await;
+    let clock = env
+        .get_context::<ModControl>(MOD_CONTROL)?
+        .acting_manager
+        .clock_manager
+        .clone();
+    clock.sleep(Duration::from_secs_f64(min)).await;
     wait_end_all(env, &[(max - min).into()]).await?;
     let (file_name, format) = extract_file_name(&[export.into()])?;
     _export_report(env, file_name, format, Some((min, max))).await?;
@@ -1,3 +1,4 @@
+use crate::model::acting_domain::pddl;
 use crate::ompas::scheme::exec::state::ModState;
 use crate::ompas::scheme::monitor::model::ModModel;
 use crate::planning::conversion::context::ConversionContext;
@@ -42,6 +43,7 @@ impl From<ModDebugConversion> for LModule {
             DOC_EXPORT_TYPE_LATTICE,
             false,
         );
+        m.add_async_fn(EXPORT_PDDL, export_pddl, DOC_EXPORT_PDDL, false);
         m.add_async_fn(PRE_EVAL_TASK, pre_eval_task, DOC_PRE_EVAL_TASK, false);
         m.add_async_fn(PRE_EVAL_EXPR, pre_eval_expr, DOC_PRE_EVAL_EXPR, false);
         m.add_async_fn(ANNOTATE_TASK, annotate_task, DOC_ANNOTATE_TASK, false);
@@ -131,6 +133,51 @@ pub async fn export_type_lattice(env: &LEnv) -> Result<(), LRuntimeError> {
     Ok(())
 }
 
+/// Writes the PDDL or HDDL export of the domain and the current state in a directory,
+/// by default in the run directory.
+/// Returns a report listing the constructs that could not be expressed.
+#[async_scheme_fn]
+pub async fn export_pddl(env: &LEnv, args: &[LValue]) -> Result<String, LRuntimeError> {
+    let ctx = env.get_context::<ModModel>(MOD_MODEL)?;
+    let (path, tasks): (PathBuf, &[LValue]) = match args.first() {
+        Some(LValue::String(dir)) => (dir.as_str().into(), &args[1..]),
+        _ => {
+            let mut path: PathBuf = Master::get_run_dir();
+            path.push("pddl");
+            path.push(format!("pddl_{}", Master::get_string_date()));
+            (path, args)
+        }
+    };
+
+    let domain = ctx.domain_manager.get_inner().await;
+    let state = ctx.get_plan_state().await;
+    let export = pddl::export::export_pddl(&domain, &state, tasks);
+
+    let write = |name: &str, content: &str| -> Result<(), LRuntimeError> {
+        let mut file_path = path.clone();
+        file_path.push(format!("{}.{}", name, export.extension()));
+        fs::create_dir_all(&path)
+            .and_then(|_| fs::write(&file_path, content))
+            .map_err(|e| {
+                LRuntimeError::new(
+                    EXPORT_PDDL,
+                    format!("could not write {}: {}", file_path.display(), e),
+                )
+            })
+    };
+    write("domain", &export.domain)?;
+    write("problem", &export.problem)?;
+
+    let mut report = format!("Domain and problem exported in {}.", path.display());
+    if !export.unsupported.is_empty() {
+        report.push_str("\nConstructs that could not be expressed:");
+        for construct in &export.unsupported {
+            report.push_str(format!("\n- {}", construct).as_str());
+        }
+    }
+    Ok(report)
+}
+
 #[async_scheme_fn]
 pub async fn pre_eval_task(env: &LEnv, task: &[LValue]) -> Result<(), LRuntimeError> {
     let ctx = env.get_context::<ModModel>(MOD_MODEL)?;
@@ -169,6 +216,7 @@ pub async fn pre_eval_task(env: &LEnv, task: &[LValue]) -> Result<(), LRuntimeEr
         let mut p_env = PLEnv {
             env,
             unpure_bindings: Default::default(),
+            unrolling: Default::default(),
             pc,
         };
         let plv = p_eval(lv, &mut p_env).await?;
@@ -220,6 +268,7 @@ pub async fn annotate_task(env: &LEnv, task: &[LValue]) -> Result<(), LRuntimeEr
         let mut p_env = PLEnv {
             env,
             unpure_bindings: Default::default(),
+            unrolling: Default::default(),
             pc,
         };
         let lv = p_eval(lv, &mut p_env).await?;
@@ -249,6 +298,7 @@ pub async fn pre_eval_expr(env: &LEnv, lv: LValue) -> Result<(), LRuntimeError>
     let mut p_env = PLEnv {
         env: context.env.clone(),
         unpure_bindings: Default::default(),
+        unrolling: Default::default(),
         pc,
     };
     env.log = LogClient::new(P_EVAL, LOG_TOPIC_INTERPRETER).await;
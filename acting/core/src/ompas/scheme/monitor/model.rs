@@ -2,21 +2,23 @@ use crate::model::acting_domain::command::Command;
 use crate::model::acting_domain::method::Method;
 use crate::model::acting_domain::model::ModelKind;
 use crate::model::acting_domain::parameters::{try_domain_from_lvalue, Parameters};
+use crate::model::acting_domain::pddl::import::{pddl_to_sompas, PddlDomain, PddlProblem};
+use crate::model::acting_domain::pddl::read_pddl_file;
 use crate::model::acting_domain::state_function::StateFunction;
 use crate::model::acting_domain::task::Task;
 use crate::model::acting_domain::OMPASDomain;
 use crate::model::sym_domain::Domain;
 use crate::model::sym_table::r#ref::RefSymTable;
 use crate::ompas::manager::domain::DomainManager;
-use crate::ompas::manager::resource::{Capacity, ResourceManager};
+use crate::ompas::manager::resource::ResourceManager;
 use crate::ompas::manager::state::partial_state::{Fact, PartialState};
 use crate::ompas::manager::state::world_state_snapshot::WorldStateSnapshot;
 use crate::ompas::manager::state::{StateManager, StateType};
+use crate::ompas::scheme::exec::resource::declare_resource;
 use crate::ompas::scheme::monitor::ModMonitor;
 use crate::planning::conversion::context::ConversionContext;
 use ompas_language::exec::state::{DURATIVE_EFFECT, EFFECT};
 use ompas_language::monitor::model::*;
-use ompas_language::sym_table::TYPE_OBJECT;
 use ompas_middleware::logger::LogClient;
 use sompas_core::modules::list::{car, first};
 use sompas_core::{eval, expand, get_root_env, parse};
@@ -133,6 +135,7 @@ impl From<ModModel> for LModule {
         module.add_async_fn(ADD_RESOURCE, add_resource, DOC_ADD_RESOURCE, false);
         module.add_async_fn(ADD_RESOURCES, add_resources, DOC_ADD_RESOURCES, false);
         module.add_async_fn(ADD_INIT, add_init, DOC_ADD_INIT, false);
+        module.add_async_fn(IMPORT_PDDL, import_pddl, DOC_IMPORT_PDDL, false);
 
         // Remove functions
         module.add_async_fn(REMOVE_COMMAND, remove_command, DOC_REMOVE_COMMAND, false);
@@ -401,7 +404,7 @@ pub async fn add_function(
         .await?;
     Ok(())
 }
-use crate::model::acting_domain::event::{Event, Trigger, TriggerActivation};
+use crate::model::acting_domain::event::{Event, Trigger, TriggerActivation, TriggerKind};
 use ompas_language::exec::state::DURATIVE;
 
 #[function_name::named]
@@ -428,33 +431,37 @@ async fn generate_model(env: &LEnv, model: im::HashMap<LValue, LValue>) -> LResu
                 .get(&PRE_CONDITIONS.into())
                 .ok_or_else(|| LRuntimeError::new(function_name!(), "missing :pre-conditions"))?;
             let mut str_conds = "(do".to_string();
-            if let LValue::List(conds) = conds {
-                for cond in conds.iter() {
-                    str_conds.push_str(format!("(check {})", cond).as_str());
+            match conds {
+                LValue::List(conds) => {
+                    for cond in conds.iter() {
+                        str_conds.push_str(format!("(check {})", cond).as_str());
+                    }
                 }
-                str_conds.push(')');
-            } else {
-                return Err(LRuntimeError::default());
+                LValue::Nil => {}
+                _ => return Err(LRuntimeError::default()),
             }
+            str_conds.push(')');
             let effects = model
                 .get(&EFFECTS.into())
                 .ok_or_else(|| LRuntimeError::new("create_model", "missing :effects"))?;
             let mut str_effects = "(do".to_string();
-            if let LValue::List(effects) = effects {
-                for effect in effects.iter() {
-                    let mut expr = effect.to_string();
-                    if expr.contains(DURATIVE) {
-                        expr = expr.replace(DURATIVE, DURATIVE_EFFECT)
-                    } else {
-                        expr.remove(0);
-                        expr.insert_str(0, format!("({} ", EFFECT).as_str());
+            match effects {
+                LValue::List(effects) => {
+                    for effect in effects.iter() {
+                        let mut expr = effect.to_string();
+                        if expr.contains(DURATIVE) {
+                            expr = expr.replace(DURATIVE, DURATIVE_EFFECT)
+                        } else {
+                            expr.remove(0);
+                            expr.insert_str(0, format!("({} ", EFFECT).as_str());
+                        }
+                        str_effects.push_str(expr.as_str());
                     }
-                    str_effects.push_str(expr.as_str());
                 }
-                str_effects.push(')');
-            } else {
-                return Err(LRuntimeError::default());
+                LValue::Nil => {}
+                _ => return Err(LRuntimeError::default()),
             }
+            str_effects.push(')');
             let test = generate_test_type_expr(
                 env,
                 &[model
@@ -736,14 +743,16 @@ pub async fn add_method(env: &LEnv, map: im::HashMap<LValue, LValue>) -> Result<
         Some(conds) => {
             let test = generate_test_type_expr(env, &[parameters.clone()]).await?;
             let mut str_conds = "(do".to_string();
-            if let LValue::List(conds) = conds {
-                for cond in conds.iter() {
-                    str_conds.push_str(format!("(check {})", cond).as_str());
+            match conds {
+                LValue::List(conds) => {
+                    for cond in conds.iter() {
+                        str_conds.push_str(format!("(check {})", cond).as_str());
+                    }
                 }
-                str_conds.push(')');
-            } else {
-                return Err(LRuntimeError::default());
+                LValue::Nil => {}
+                _ => return Err(LRuntimeError::default()),
             }
+            str_conds.push(')');
             let expr = format!(
                 "(lambda {} (do {} {}))",
                 method.parameters.get_params_as_lvalue(),
@@ -833,7 +842,7 @@ pub async fn add_event(env: &LEnv, map: im::HashMap<LValue, LValue>) -> Result<(
             let trigger = car(&new_env, &[trigger.clone()])?;
             if let LValue::List(list) = trigger {
                 let activation: TriggerActivation = (&list[0]).try_into()?;
-                let conds = &list[1..];
+                let (kind, arming, conds) = parse_trigger_kind(&list[1..])?;
                 let test = generate_test_type_expr(env, &[parameters.clone()]).await?;
                 let mut str_conds = "(do".to_string();
 
@@ -850,7 +859,19 @@ pub async fn add_event(env: &LEnv, map: im::HashMap<LValue, LValue>) -> Result<(
                 );
                 let pre_conditions =
                     eval(&parse(&expr, &mut new_env).await?, &mut new_env, None).await?;
-                Trigger::new(activation, pre_conditions)
+                let arming = match arming {
+                    Some(arming) => {
+                        let expr = format!(
+                            "(lambda {} (do {} (check {})))",
+                            event_parameters.get_params_as_lvalue(),
+                            test,
+                            arming
+                        );
+                        Some(eval(&parse(&expr, &mut new_env).await?, &mut new_env, None).await?)
+                    }
+                    None => None,
+                };
+                Trigger::new(activation, pre_conditions).with_kind(kind, arming)
             } else {
                 return Err(LRuntimeError::new(ADD_EVENT, format!("Wrong format for {TRIGGER}, expected expression of the form (:trigger ({{{ONCE},{WHENEVER}}} {{:*}}))",)));
             }
@@ -879,6 +900,70 @@ pub async fn add_event(env: &LEnv, map: im::HashMap<LValue, LValue>) -> Result<(
     Ok(())
 }
 
+/// Splits the temporal keywords of a trigger from its conditions.
+/// Returns the kind of the trigger, the arming conditions given with :after, and the conditions.
+fn parse_trigger_kind(
+    args: &[LValue],
+) -> Result<(TriggerKind, Option<LValue>, Vec<LValue>), LRuntimeError> {
+    let mut kind = TriggerKind::Level;
+    let mut arming = None;
+    let mut conds = vec![];
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let keyword = match arg {
+            LValue::Symbol(s) => s.as_str(),
+            _ => "",
+        };
+        let new_kind = match keyword {
+            RISING => TriggerKind::Rising,
+            FALLING => TriggerKind::Falling,
+            FOR_DURATION | EVERY | WITHIN => {
+                let duration: f64 = iter
+                    .next()
+                    .ok_or_else(|| {
+                        lruntimeerror!(ADD_EVENT, format!("{keyword} expects a duration"))
+                    })?
+                    .try_into()?;
+                if duration <= 0.0 {
+                    return Err(lruntimeerror!(
+                        ADD_EVENT,
+                        format!("{keyword} expects a positive duration, got {duration}")
+                    ));
+                }
+                match keyword {
+                    FOR_DURATION => TriggerKind::For(duration),
+                    EVERY => TriggerKind::Every(duration),
+                    _ => TriggerKind::Within(duration),
+                }
+            }
+            AFTER => {
+                arming = Some(iter.next().cloned().ok_or_else(|| {
+                    lruntimeerror!(ADD_EVENT, format!("{AFTER} expects a condition"))
+                })?);
+                continue;
+            }
+            _ => {
+                conds.push(arg.clone());
+                continue;
+            }
+        };
+        if kind != TriggerKind::Level {
+            return Err(lruntimeerror!(
+                ADD_EVENT,
+                format!("a trigger has at most one temporal keyword, got {kind} and {new_kind}")
+            ));
+        }
+        kind = new_kind;
+    }
+    if arming.is_some() && !matches!(kind, TriggerKind::Within(_)) {
+        return Err(lruntimeerror!(
+            ADD_EVENT,
+            format!("{AFTER} is only allowed with {WITHIN}")
+        ));
+    }
+    Ok((kind, arming, conds))
+}
+
 pub enum ModelType {
     PDDL,
     OM,
@@ -1037,26 +1122,13 @@ pub async fn add_objects(env: &LEnv, args: Vec<Vec<LValue>>) -> Result<(), LRunt
 #[async_scheme_fn]
 pub async fn add_resource(env: &LEnv, args: &[LValue]) -> Result<(), LRuntimeError> {
     let ctx = env.get_context::<ModModel>(MOD_MODEL).unwrap();
-
-    let label: String = args
-        .first()
-        .ok_or_else(|| LRuntimeError::wrong_number_of_args(ADD_RESOURCE, args, 1..2))?
-        .try_into()?;
-
-    let capacity: Capacity = match args.get(1) {
-        None => 1,
-        Some(lv) => lv.try_into()?,
-    };
-
-    if LValue::Nil == ctx.state_manager.instance(&label, TYPE_OBJECT).await {
-        ctx.state_manager.add_instance(&label, TYPE_OBJECT).await
-    }
-
-    ctx.resource_manager
-        .new_resource(label, Some(capacity))
-        .await;
-
-    Ok(())
+    declare_resource(
+        &ctx.resource_manager,
+        &ctx.state_manager,
+        args,
+        ADD_RESOURCE,
+    )
+    .await
 }
 
 #[async_scheme_fn]
@@ -1089,6 +1161,42 @@ pub async fn add_init(env: &LEnv, body: LValue) -> Result<(), LRuntimeError> {
     Ok(())
 }
 
+/// Imports a PDDL or HDDL domain and an optional problem into the domain of OMPAS.
+/// Returns the initial tasks of the HDDL problem.
+#[async_scheme_fn]
+pub async fn import_pddl(env: &LEnv, args: &[LValue]) -> LResult {
+    let ctx = env.get_context::<ModModel>(MOD_MODEL)?;
+    let (domain, problem) = match args {
+        [domain] => (domain, None),
+        [domain, problem] => (domain, Some(problem)),
+        _ => return Err(LRuntimeError::wrong_number_of_args(IMPORT_PDDL, args, 1..2)),
+    };
+
+    let domain: PddlDomain = (&read_pddl_file(domain.to_string())?).try_into()?;
+    let problem: Option<PddlProblem> = match problem {
+        Some(problem) => Some((&read_pddl_file(problem.to_string())?).try_into()?),
+        None => None,
+    };
+
+    let mut env = env.clone();
+    for def in pddl_to_sompas(&domain, problem.as_ref())? {
+        eval(&parse(&def.to_string(), &mut env).await?, &mut env, None).await?;
+    }
+
+    match problem {
+        Some(problem) => {
+            if let Some(goal) = &problem.goal {
+                ctx.log.warn(format!(
+                    "goal {} of problem {} is not imported, only HTN tasks are",
+                    goal, problem.name
+                ));
+            }
+            Ok(problem.tasks.into())
+        }
+        None => Ok(LValue::Nil),
+    }
+}
+
 #[async_scheme_fn]
 pub async fn remove_command(env: &LEnv, label: String) {
     let ctx = env.get_context::<ModModel>(MOD_MODEL).unwrap();
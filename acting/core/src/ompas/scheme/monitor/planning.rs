@@ -189,6 +189,7 @@ pub async fn __plan(
 
     let subscriber = state_manager.new_subscriber(StateRule::All).await;
 
+    let _busy = acting_manager.clock_manager.busy();
     let result = ompas_lcp::run_planner(
         &ep,
         &OMPASLCPConfig {
@@ -6,7 +6,7 @@ use crate::model::chronicle::condition::Condition;
 use crate::model::chronicle::constraint::Constraint;
 use crate::model::chronicle::effect::{Effect, EffectOperation};
 use crate::model::chronicle::interval::Interval;
-use crate::model::chronicle::lit::{Lit, LitSet};
+use crate::model::chronicle::lit::{Lit, LitSet, StockOperation};
 use crate::model::chronicle::subtask::SubTask;
 use crate::model::chronicle::task_template::TaskTemplate;
 use crate::model::chronicle::{Chronicle, ChronicleKind};
@@ -61,14 +61,9 @@ impl HandleTable {
         );
     }
 
+    /// Adds the handle released at the timepoint, shared by the acquisitions of an acquire-all.
     pub fn add_resource_handle(&mut self, var_id: VarId) {
-        self.resources.insert(
-            var_id,
-            ResourceHandle {
-                drops: Default::default(),
-                releases: Default::default(),
-            },
-        );
+        self.resources.entry(var_id).or_default();
     }
 
     pub fn add_async_drop(&mut self, flow: &FlowId, drop: VarId) {
@@ -504,6 +499,88 @@ pub fn convert_into_chronicle(
                         let handle = fl.get_resource_handle(*release).unwrap();
                         ht.add_release(handle, interval.get_end());
                     }
+                    Lit::Stock(stock) => {
+                        let quantity_symbol = st.new_symbol(QUANTITY);
+                        let max_q_symbol = st.new_symbol(MAX_Q);
+                        let resource = stock.resource;
+                        let quantity = stock.quantity;
+                        let quantity_domain: Domain = Domain::IntRange(0, cv.max_capacity);
+
+                        let max_q_result = st.new_result();
+                        st.set_domain(st.get_domain_id(max_q_result), quantity_domain.clone());
+                        let new_q = st.new_result();
+                        st.set_domain(st.get_domain_id(new_q), quantity_domain.clone());
+
+                        ch.add_condition(Condition {
+                            interval: Interval::new_instantaneous(start),
+                            sv: vec![max_q_symbol, resource],
+                            value: max_q_result,
+                        });
+
+                        match OMPAS_RESOURCE_ENCODING.get() {
+                            ResourceEncoding::Addition => {
+                                //[start, end] quantity(resource) -= quantity, or += quantity
+                                ch.add_effect(Effect {
+                                    interval,
+                                    sv: vec![quantity_symbol, resource],
+                                    operation: match stock.operation {
+                                        StockOperation::Consume => {
+                                            EffectOperation::decrease(quantity)
+                                        }
+                                        StockOperation::Produce => {
+                                            EffectOperation::increase(quantity)
+                                        }
+                                    },
+                                });
+                                ch.add_condition(Condition {
+                                    interval: Interval::new_instantaneous(end),
+                                    sv: vec![quantity_symbol, resource],
+                                    value: new_q,
+                                });
+                            }
+                            ResourceEncoding::Assignment => {
+                                let current_q = st.new_result();
+                                st.set_domain(st.get_domain_id(current_q), quantity_domain);
+                                ch.add_condition(Condition {
+                                    interval: Interval::new_instantaneous(start),
+                                    sv: vec![quantity_symbol, resource],
+                                    value: current_q,
+                                });
+                                ch.add_constraint(Constraint::eq(
+                                    new_q,
+                                    match stock.operation {
+                                        StockOperation::Consume => {
+                                            Computation::sub(vec![current_q, quantity])
+                                        }
+                                        StockOperation::Produce => {
+                                            Computation::add(vec![current_q, quantity])
+                                        }
+                                    },
+                                ));
+                                ch.add_effect(Effect {
+                                    interval,
+                                    sv: vec![quantity_symbol, resource],
+                                    operation: EffectOperation::assign(new_q),
+                                });
+                            }
+                        }
+
+                        // 0 <= quantity(resource) <= max_q(resource)
+                        ch.add_constraint(Constraint::leq(st.new_int(0), new_q));
+                        ch.add_constraint(Constraint::leq(new_q, max_q_result));
+
+                        if duration.is_none() {
+                            let eps = st.new_symbol(EPSILON);
+                            ch.add_constraint(Constraint::eq(
+                                end,
+                                Computation::add(vec![start, eps]),
+                            ));
+                        }
+                        ch.add_constraint(Constraint::lt(start, end));
+
+                        let result_2 = ch.st.new_nil();
+                        st.union_var(result, result_2);
+                    }
                     Lit::Constraint(c) => match c.deref() {
                         Constraint::Arbitrary(set) => {
                             let constraint = match set {
@@ -1,10 +1,13 @@
 use ompas_language::exec::acting_context::{
-    CTX_ACQUIRE, CTX_ARBITRARY, CTX_EXEC_COMMAND, CTX_EXEC_TASK,
+    CTX_ACQUIRE, CTX_ACQUIRE_ALL, CTX_ARBITRARY, CTX_EXEC_COMMAND, CTX_EXEC_TASK,
 };
 use ompas_language::exec::platform::EXEC_COMMAND;
 use ompas_language::exec::refinement::EXEC_TASK;
-use ompas_language::exec::resource::ACQUIRE;
+use ompas_language::exec::resource::{ACQUIRE, ACQUIRE_ALL, PRIORITY};
 use ompas_language::exec::ARBITRARY;
+use sompas_language::kind::LIST;
+use sompas_language::list::CONS;
+use sompas_language::primitives::{QUASI_QUOTE, QUOTE};
 use sompas_structs::lvalue::LValue;
 
 #[derive(Clone)]
@@ -19,6 +22,28 @@ pub struct AnnotateStack {
     results: Vec<LValue>,
 }
 
+/// Returns true if the argument of an acquisition is its priority, as '(:priority p).
+/// A priority computed at runtime, as `(:priority ,p) or (list :priority p), is also recognized.
+fn is_priority(lv: &LValue) -> bool {
+    match lv {
+        LValue::List(l) => match l.first().map(|h| h.to_string()).as_deref() {
+            Some(PRIORITY) => true,
+            Some(QUOTE | QUASI_QUOTE) => l.get(1).is_some_and(is_priority),
+            Some(CONS | LIST) => l.get(1).is_some_and(is_priority_keyword),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns true if the value is the keyword :priority, quoted or not.
+fn is_priority_keyword(lv: &LValue) -> bool {
+    match lv {
+        LValue::List(l) if l.len() == 2 && l[0].to_string() == QUOTE => is_priority_keyword(&l[1]),
+        _ => lv.to_string() == PRIORITY,
+    }
+}
+
 pub fn annotate(lv: LValue) -> LValue {
     let mut n_acquire = -1;
     let mut n_arbitrary = -1;
@@ -57,6 +82,16 @@ pub fn annotate(lv: LValue) -> LValue {
                         n_acquire += 1;
                         ctx_expr(CTX_ACQUIRE, n_acquire, results.split_off(1))
                     }
+                    //(ctx-acquire-all <id of the first acquisition> <number of resources> args)
+                    ACQUIRE_ALL => {
+                        let args = results.split_off(1);
+                        let n = args.iter().filter(|a| !is_priority(a)).count() as i32;
+                        let mut list =
+                            vec![CTX_ACQUIRE_ALL.into(), (n_acquire + 1).into(), n.into()];
+                        n_acquire += n;
+                        list.extend(args);
+                        list.into()
+                    }
                     ARBITRARY => {
                         n_arbitrary += 1;
                         ctx_expr(CTX_ARBITRARY, n_arbitrary, results.split_off(1))
@@ -1,6 +1,6 @@
 use crate::model::chronicle::computation::Computation;
 use crate::model::chronicle::constraint::Constraint;
-use crate::model::chronicle::lit::{AcquireLit, Lit, LitSet};
+use crate::model::chronicle::lit::{AcquireLit, Lit, LitSet, StockLit, StockOperation};
 use crate::model::process_ref::Label;
 use crate::model::sym_domain::basic_type::BasicType;
 use crate::model::sym_domain::basic_type::BasicType::{Boolean, Float, True};
@@ -15,11 +15,11 @@ use crate::planning::conversion::flow_graph::flow::FlowId;
 use crate::planning::conversion::flow_graph::graph::FlowGraph;
 use function_name::named;
 use ompas_language::exec::acting_context::{
-    CTX_ACQUIRE, CTX_ARBITRARY, CTX_EXEC_COMMAND, CTX_EXEC_TASK,
+    CTX_ACQUIRE, CTX_ACQUIRE_ALL, CTX_ARBITRARY, CTX_EXEC_COMMAND, CTX_EXEC_TASK,
 };
 use ompas_language::exec::platform::EXEC_COMMAND;
 use ompas_language::exec::refinement::EXEC_TASK;
-use ompas_language::exec::resource::RELEASE;
+use ompas_language::exec::resource::{CONSUME, PRODUCE, RELEASE};
 use ompas_language::exec::state::{
     ASSERT, ASSERT_SHORT, DURATIVE_EFFECT, EFFECT, INSTANCE, INSTANCES, READ_STATE,
     READ_STATIC_STATE, WAIT_FOR,
@@ -30,6 +30,7 @@ use ompas_language::sym_table::{TYPE_OBJECT, TYPE_OBJECT_TYPE, TYPE_RESOURCE_HAN
 use sompas_language::basic_math::{ADD, DIV, EQ, GEQ, GT, LEQ, LT, MUL, NEQ, NOT, NOT_SHORT, SUB};
 use sompas_language::error::IS_ERR;
 use sompas_language::io::PRINT;
+use sompas_language::kind::LIST;
 use sompas_language::time::SLEEP;
 use sompas_language::utils::{AND, OR};
 use sompas_structs::lruntimeerror::LRuntimeError;
@@ -85,6 +86,8 @@ impl Default for ApplyConversionCollection {
         d.add_conversion(SLEEP, convert_sleep);
         d.add_conversion(ACQUIRE, convert_acquire);
         d.add_conversion(RELEASE, convert_release);
+        d.add_conversion(CONSUME, convert_consume);
+        d.add_conversion(PRODUCE, convert_produce);
         d.add_conversion(ADD, convert_add);
         d.add_conversion(SUB, convert_sub);
         d.add_conversion(MUL, convert_mul);
@@ -96,6 +99,7 @@ impl Default for ApplyConversionCollection {
         d.add_conversion(CTX_EXEC_TASK, convert_ctx_exec_task);
         d.add_conversion(CTX_EXEC_COMMAND, convert_ctx_exec_command);
         d.add_conversion(CTX_ACQUIRE, convert_ctx_acquire);
+        d.add_conversion(CTX_ACQUIRE_ALL, convert_ctx_acquire_all);
         d.add_conversion(PRINT, convert_print);
         d
     }
@@ -402,10 +406,32 @@ fn convert_div(fl: &mut FlowGraph, mut seq: Vec<FlowId>) -> Result<FlowId, LRunt
     Ok(fl.new_seq(seq))
 }
 
+/// The wait is followed by the expression that becomes true.
+/// With a timeout, the expression has to become true before the timeout elapses.
 fn convert_wait_for(fl: &mut FlowGraph, mut seq: Vec<FlowId>) -> Result<FlowId, LRuntimeError> {
+    let timeout = match seq.len() {
+        2 => None,
+        3 => seq.pop(),
+        _ => {
+            return Err(LRuntimeError::new(
+                WAIT_FOR,
+                "expected an expression and an optional timeout",
+            ))
+        }
+    };
     seq[0] = fl.new_assignment(fl.st.new_nil());
-    let last = fl.get_flow_result(*seq.last().unwrap());
-    fl.st.set_domain_of_var(last, True);
+    let wait_start = fl.get_flow_start(seq[0]);
+    let last = *seq.last().unwrap();
+    fl.st.set_domain_of_var(fl.get_flow_result(last), True);
+    if let Some(timeout) = timeout {
+        let bound = fl.new_instantaneous_assignment(Lit::constraint(Constraint::leq(
+            fl.get_flow_end(last),
+            Computation::add(vec![wait_start, fl.get_flow_result(timeout)]),
+        )));
+        fl.st.set_domain_of_var(fl.get_flow_result(bound), True);
+        seq.insert(0, timeout);
+        seq.push(bound);
+    }
     let nil = fl.st.new_nil();
     let result = fl.new_instantaneous_assignment(nil);
     seq.push(result);
@@ -520,6 +546,39 @@ fn convert_release(fl: &mut FlowGraph, seq: Vec<FlowId>) -> Result<FlowId, LRunt
     Ok(fl.new_seq(vec![rh, flow_release]))
 }
 
+fn convert_consume(fl: &mut FlowGraph, seq: Vec<FlowId>) -> Result<FlowId, LRuntimeError> {
+    convert_stock(fl, seq, StockOperation::Consume)
+}
+
+fn convert_produce(fl: &mut FlowGraph, seq: Vec<FlowId>) -> Result<FlowId, LRuntimeError> {
+    convert_stock(fl, seq, StockOperation::Produce)
+}
+
+fn convert_stock(
+    fl: &mut FlowGraph,
+    mut seq: Vec<FlowId>,
+    operation: StockOperation,
+) -> Result<FlowId, LRuntimeError> {
+    seq.remove(0);
+    if seq.len() != 2 {
+        return Err(LRuntimeError::new(
+            "convert_stock",
+            "expected a resource and a quantity",
+        ));
+    }
+
+    let flow_stock = fl.new_assignment(Lit::Stock(StockLit {
+        resource: fl.get_flow_result(seq[0]),
+        quantity: fl.get_flow_result(seq[1]),
+        operation,
+    }));
+    fl.st
+        .set_domain_of_var(fl.get_flow_result(flow_stock), Domain::nil());
+
+    seq.push(flow_stock);
+    Ok(fl.new_seq(seq))
+}
+
 fn convert_instance(fl: &mut FlowGraph, mut seq: Vec<FlowId>) -> Result<FlowId, LRuntimeError> {
     let results: Vec<VarId> = seq.iter().map(|f| fl.get_flow_result(*f)).collect();
     fl.st
@@ -614,6 +673,72 @@ fn convert_ctx_acquire(fl: &mut FlowGraph, mut seq: Vec<FlowId>) -> Result<FlowI
     Ok(flow_id)
 }
 
+//(ctx-acquire-all <id> <n> <resources>* <priority>?)
+//Each resource is converted into an acquisition labelled with its own id, the acquisitions
+//sharing the same handle and release time.
+fn convert_ctx_acquire_all(
+    fl: &mut FlowGraph,
+    mut seq: Vec<FlowId>,
+) -> Result<FlowId, LRuntimeError> {
+    let index = extract_index(fl, &mut seq);
+    let n = extract_index(fl, &mut seq);
+    seq.remove(0);
+    if n == 0 {
+        return Err(LRuntimeError::new(
+            CTX_ACQUIRE_ALL,
+            "acquire-all without resource",
+        ));
+    } else if n > seq.len() {
+        return Err(LRuntimeError::new(
+            CTX_ACQUIRE_ALL,
+            format!("expected {} resources, got {} arguments", n, seq.len()),
+        ));
+    }
+
+    //The n first arguments are the resources, the others set the priority.
+    let release_time = fl.st.new_timepoint();
+    let mut acquires: Vec<FlowId> = vec![];
+    for (i, flow) in seq[..n].iter().enumerate() {
+        //a resource given with its quantity, as (list ?r 2)
+        let lit = fl
+            .try_get_last_flow(*flow)
+            .and_then(|f| fl.try_get_flow_lit(f));
+        let (resource, capacity) = match lit {
+            Some(Lit::Apply(args))
+                if args.len() == 3
+                    && fl
+                        .st
+                        .get_domain_of_var(args[0])
+                        .as_cst()
+                        .is_some_and(|c| c.as_symbol() == Some(LIST)) =>
+            {
+                (args[1], Some(args[2]))
+            }
+            _ => (fl.get_flow_result(*flow), None),
+        };
+        let acquire = fl.new_assignment(Lit::Acquire(AcquireLit {
+            resource,
+            capacity,
+            release_time,
+        }));
+        fl.set_label(&acquire, Label::ResourceAcquisition(index + i));
+        acquires.push(acquire);
+    }
+
+    let handle = fl.get_flow_result(acquires[0]);
+    for acquire in &acquires[1..] {
+        fl.st.union_var(handle, fl.get_flow_result(*acquire));
+    }
+    fl.st.set_domain_of_var(
+        handle,
+        fl.st.get_type_as_domain(TYPE_RESOURCE_HANDLE).unwrap(),
+    );
+    fl.resource_handles.insert(handle, release_time);
+
+    seq.append(&mut acquires);
+    Ok(fl.new_seq(seq))
+}
+
 fn convert_print(fl: &mut FlowGraph, _: Vec<FlowId>) -> Result<FlowId, LRuntimeError> {
     let flow_id = fl.new_instantaneous_assignment(fl.st.new_nil());
     Ok(flow_id)
@@ -90,6 +90,34 @@ fn convert_core_operator(_: &LPrimitive, _: &mut FlowGraph) -> FlowId {
     todo!()
 }
 
+/// Converts the asynchronous execution of a flow, returning the flow of its handle.
+fn convert_async(async_flow: FlowId, fl: &mut FlowGraph) -> FlowId {
+    let st = fl.st.clone();
+    let r_async = st.get_domain_id(fl.get_flow_result(async_flow));
+
+    let handle_flow = fl.new_async(async_flow);
+
+    let handle = fl.get_flow_result(handle_flow);
+    let handle_domain = st.get_domain_id(fl.get_flow_result(handle_flow));
+
+    fl.handles.insert(handle, async_flow);
+
+    st.add_update(
+        vec![r_async],
+        Update::new(
+            handle_domain,
+            closure::composed_update(handle_domain, r_async),
+        ),
+    );
+
+    st.add_update(
+        vec![handle_domain],
+        Update::new(r_async, closure::in_composed_update(r_async, handle_domain)),
+    );
+
+    handle_flow
+}
+
 #[named]
 fn convert_list(
     list: &Arc<Vec<LValue>>,
@@ -221,29 +249,7 @@ fn convert_list(
                 let define_table = &mut define_table.clone();
                 let e = &list[1];
                 let async_flow = convert_lv(e, fl, define_table)?;
-                let r_async = st.get_domain_id(fl.get_flow_result(async_flow));
-
-                let handle_flow = fl.new_async(async_flow);
-
-                let handle = fl.get_flow_result(handle_flow);
-                let handle_domain = st.get_domain_id(fl.get_flow_result(handle_flow));
-
-                fl.handles.insert(handle, async_flow);
-
-                st.add_update(
-                    vec![r_async],
-                    Update::new(
-                        handle_domain,
-                        closure::composed_update(handle_domain, r_async),
-                    ),
-                );
-
-                st.add_update(
-                    vec![handle_domain],
-                    Update::new(r_async, closure::in_composed_update(r_async, handle_domain)),
-                );
-
-                handle_flow
+                convert_async(async_flow, fl)
             }
             LPrimitive::Await => {
                 let define_table = &mut define_table.clone();
@@ -288,7 +294,49 @@ fn convert_list(
                 fl.new_seq(vec![arg_err, flow])
             }
             LPrimitive::Race => {
-                todo!()
+                let define_table = &mut define_table.clone();
+
+                /*
+                The expression finishing first is unknown before execution.
+                The race is converted into a branching on a free boolean, the planner choosing which expression wins.
+                 */
+                let cond = st.new_result();
+                st.meet_to_domain(st.get_domain_id(cond), Boolean);
+                let cond_flow = fl.new_instantaneous_assignment(Lit::Atom(cond));
+
+                let true_flow = convert_lv(&list[1], fl, define_table)?;
+                let true_flow = fl.new_seq(vec![true_flow]);
+                let false_flow = convert_lv(&list[2], fl, define_table)?;
+                let false_flow = fl.new_seq(vec![false_flow]);
+                let true_result = st.get_domain_id(fl.get_flow_result(true_flow));
+                let false_result = st.get_domain_id(fl.get_flow_result(false_flow));
+
+                let branching = BranchingFlow {
+                    cond_flow,
+                    true_flow,
+                    false_flow,
+                };
+                let flow_branch = fl.new_branching(branching);
+
+                let result = st.get_domain_id(fl.get_flow_result(flow_branch));
+
+                st.add_update(
+                    vec![true_result, false_result],
+                    Update::new(
+                        result,
+                        closure::union_update(result, vec![true_result, false_result]),
+                    ),
+                );
+                st.add_update(
+                    vec![result],
+                    Update::new(true_result, closure::in_union_update(true_result, result)),
+                );
+                st.add_update(
+                    vec![result],
+                    Update::new(false_result, closure::in_union_update(false_result, result)),
+                );
+
+                convert_async(flow_branch, fl)
             }
             co => Err(LRuntimeError::new(
                 function_name!(),
@@ -1,18 +1,23 @@
 pub mod r#struct;
 use crate::planning::conversion::flow_graph::algo::p_eval::r#struct::{
     PBeginFrame, PCoreOperatorFrame, PDefineFrame, PDoFrame, PEvalStack, PIfFrame, PLDebug, PLEnv,
-    PLValue, PProcedureFrame, PResults, PScopeCollection, PStackFrame, PUnstack,
+    PLValue, PProcedureFrame, PResults, PScopeCollection, PStackFrame, PUnstack, PWhileFrame,
 };
 use crate::planning::conversion::flow_graph::algo::pre_processing::transform_lambda_expression;
 use anyhow::anyhow;
 use async_recursion::async_recursion;
-use sompas_core::{expand_quasi_quote, parse_into_lvalue};
+use sompas_core::{expand, expand_quasi_quote, parse_into_lvalue};
+use sompas_language::basic_math::{ADD, LEQ, NOT};
+use sompas_language::list::{CAR, CDR};
+use sompas_language::predicate::IS_NIL;
+use sompas_language::primitives::{FOR_FROM, FOR_IN, TRY_FINALLY};
+use sompas_language::utils::_LOOP_;
 use sompas_structs::kindlvalue::KindLValue;
 use sompas_structs::llambda::{LLambda, LambdaArgs};
 use sompas_structs::lprimitive::LPrimitive;
 use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
 use sompas_structs::lvalue::LValue;
-use sompas_structs::{list, lruntimeerror, string, wrong_type};
+use sompas_structs::{list, lruntimeerror, string, symbol, wrong_type};
 use std::convert::{TryFrom, TryInto};
 use std::ops::Deref;
 use std::sync::Arc;
@@ -20,6 +25,7 @@ use std::sync::Arc;
 pub const P_EVAL: &str = "p-eval";
 pub const P_EXPAND: &str = "p-expand";
 pub const P_PARSE: &str = "p-parse";
+pub const UNROLL_BOUND_REACHED: &str = "unroll-bound-reached";
 
 /// Pre evaluate a LValue
 /// Main function of the Scheme Interpreter
@@ -131,7 +137,7 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                                         LValue::Lambda(LLambda::new(
                                             params,
                                             body.clone(),
-                                            scopes.get_last().env.get_symbols(),
+                                            scopes.get_last().env.get_captured_symbols(),
                                         ))
                                         .into(),
                                     );
@@ -163,8 +169,11 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                                 LPrimitive::UnQuote => {
                                     panic!("unquote not allowed")
                                 }
-                                LPrimitive::DefMacro => {
-                                    panic!("defmacro not allowed")
+                                LPrimitive::DefMacro
+                                | LPrimitive::DefSyntax
+                                | LPrimitive::DefModule
+                                | LPrimitive::Import => {
+                                    panic!("{} not allowed", co)
                                 }
                                 LPrimitive::Begin => {
                                     scopes.new_scope();
@@ -217,19 +226,40 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                                 }
                                 LPrimitive::Race => {
                                     queue.push(PCoreOperatorFrame::Race);
-                                    queue.push(args[0].clone());
+                                    queue
+                                        .push_list(args.iter().map(|a| a.clone().into()).collect());
                                 }
                                 LPrimitive::Interrupt => {
                                     queue.push(PCoreOperatorFrame::Interrupt);
                                     queue.push(args[0].clone());
                                 }
+                                LPrimitive::While => {
+                                    queue.push(PWhileFrame {
+                                        cond: args[0].clone(),
+                                        body: args[1..].to_vec(),
+                                    });
+                                    queue.push(args[0].clone());
+                                }
+                                LPrimitive::For => {
+                                    queue.push(for_as_while(args));
+                                }
+                                LPrimitive::Try => {
+                                    queue.push(try_as_nominal(args));
+                                }
+                                LPrimitive::Raise => {
+                                    queue.push(PCoreOperatorFrame::Raise);
+                                    queue.push(args[0].clone());
+                                }
                             }
-                        } else if scopes
-                            .get_last()
-                            .env
-                            .get_macro(proc.to_string().as_str())
-                            .is_some()
-                        {
+                        } else if matches!(proc, LValue::Symbol(s) if s.as_str() == _LOOP_) {
+                            //A loop never ends by itself, only a prefix of its iterations is kept.
+                            let mut expr = vec![LPrimitive::Begin.into()];
+                            let bound = scopes.get_last().get_p_config().unroll_bound.max(1);
+                            for _ in 0..bound {
+                                expr.push(list![LPrimitive::Eval.into(), args[0].clone()]);
+                            }
+                            queue.push(LValue::from(expr));
+                        } else if scopes.get_last().env.is_macro(proc.to_string().as_str()) {
                             queue.push(PCoreOperatorFrame::Eval);
                             queue.push(PCoreOperatorFrame::Expand);
                             results.push(PLValue::pure(lv.clone()));
@@ -260,8 +290,12 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                     match &proc.lvalue {
                         LValue::Lambda(l) => {
                             let p_env = scopes.get_last();
-
-                            if args_pure {
+                            let mut unrolling = p_env.unrolling.clone();
+                            if !unrolling.unroll(l.to_string(), p_env.get_p_config().unroll_bound) {
+                                scopes.revert_scope();
+                                results.push(unroll_bound_reached());
+                                debug.log_last_result(&results);
+                            } else if args_pure {
                                 queue.push(PCoreOperatorFrame::Lambda);
                                 queue.push(l.get_body().clone());
                                 let temp_env = match l.get_new_env(
@@ -279,6 +313,7 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                                 };
                                 let mut p_temp_env = p_env.clone();
                                 p_temp_env.env = temp_env;
+                                p_temp_env.unrolling = unrolling;
                                 scopes.new_defined_scope(p_temp_env);
                             } else {
                                 let mut new_exps = vec![];
@@ -294,6 +329,7 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                                     transform_lambda_expression(&new_exps.into(), p_env).await?,
                                 );
                                 scopes.new_scope();
+                                scopes.get_last_mut().unrolling = unrolling;
                             }
                         }
                         LValue::Fn(fun) => {
@@ -422,6 +458,7 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                     } else {
                         scopes.new_scope();
                         let p_env = scopes.get_last_mut();
+                        p_env.unrolling.depth += 1;
                         let conseq = p_eval(&i.conseq, p_env).await?;
                         let alt = p_eval(&i.alt, p_env).await?;
                         results.push(PLValue::unpure(list![
@@ -435,6 +472,59 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
 
                     scopes.revert_scope()
                 }
+                PCoreOperatorFrame::While(w) => {
+                    let result = results.pop().unwrap();
+                    if result.is_pure() {
+                        match result.lvalue {
+                            //The loop only ends with an error or an interruption,
+                            //it is unrolled up to the bound as a loop on an unpure condition.
+                            LValue::True if w.cond == LValue::True => {
+                                scopes.new_scope();
+                                let p_env = scopes.get_last_mut();
+                                let bound = p_env.get_p_config().unroll_bound;
+                                let result =
+                                    if p_env.unrolling.unroll(w.as_lvalue().to_string(), bound) {
+                                        p_env.unrolling.depth += 1;
+                                        PLValue::unpure(p_eval(&w.unfold(), p_env).await?)
+                                    } else {
+                                        unroll_bound_reached()
+                                    };
+                                results.push(result);
+                                debug.log_last_result(&results);
+                                scopes.revert_scope()
+                            }
+                            LValue::True => queue.push(w.unfold()),
+                            LValue::Nil => {
+                                results.push(LValue::Nil.into());
+                                debug.log_last_result(&results);
+                            }
+                            lv => {
+                                let e = wrong_type!("eval", &lv, KindLValue::Bool);
+                                expression_error = w.as_lvalue();
+                                break Err(e.chain("while condition must return a boolean."));
+                            }
+                        }
+                    } else {
+                        //The number of iterations is unknown, the loop is unrolled up to the bound.
+                        scopes.new_scope();
+                        let p_env = scopes.get_last_mut();
+                        let bound = p_env.get_p_config().unroll_bound;
+                        let conseq = if p_env.unrolling.unroll(w.as_lvalue().to_string(), bound) {
+                            p_env.unrolling.depth += 1;
+                            p_eval(&w.unfold(), p_env).await?
+                        } else {
+                            unroll_bound_reached().lvalue
+                        };
+                        results.push(PLValue::unpure(list![
+                            LPrimitive::If.into(),
+                            result.lvalue_as_quote(),
+                            conseq,
+                            LValue::Nil
+                        ]));
+                        debug.log_last_result(&results);
+                        scopes.revert_scope()
+                    }
+                }
                 PCoreOperatorFrame::Do(mut df) => {
                     let result = results.pop().unwrap();
                     df.pure &= result.is_pure();
@@ -503,10 +593,13 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                     debug.log_last_result(&results);
                 }
                 PCoreOperatorFrame::Race => {
-                    let last_result = results.pop().unwrap();
+                    let mut r = results.pop_n(2);
+                    let second = r.pop().unwrap();
+                    let first = r.pop().unwrap();
                     results.push(PLValue::unpure(list![
                         LPrimitive::Race.into(),
-                        last_result.lvalue_as_quote()
+                        first.lvalue_as_quote(),
+                        second.lvalue_as_quote()
                     ]));
                     debug.log_last_result(&results);
                 }
@@ -518,6 +611,12 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
                     ]));
                     debug.log_last_result(&results);
                 }
+                PCoreOperatorFrame::Raise => {
+                    //As an err, the path raising the error is discarded.
+                    let last_result = results.pop().unwrap();
+                    results.push(PLValue::unpure(LValue::Err(last_result.lvalue.into_ref())));
+                    debug.log_last_result(&results);
+                }
                 PCoreOperatorFrame::Eval => {
                     //debug.print_last_result(&results);
                     let result = results.pop().unwrap();
@@ -615,6 +714,82 @@ pub async fn p_eval(lv: &LValue, root_env: &mut PLEnv) -> LResult {
     }
 }
 
+/// Result of a loop or recursive call unrolled beyond the bound.
+/// The path leading to it is then discarded during the conversion.
+fn unroll_bound_reached() -> PLValue {
+    PLValue::unpure(LValue::Err(
+        symbol!(UNROLL_BOUND_REACHED.to_string()).into_ref(),
+    ))
+}
+
+/// Rewrites a for loop into an equivalent while loop.
+fn for_as_while(args: &[LValue]) -> LValue {
+    let var = &args[0];
+    let mut body: Vec<LValue> = vec![LPrimitive::While.into()];
+    let init = if matches!(&args[1], LValue::Symbol(s) if s.as_str() == FOR_IN) {
+        let rest = symbol!(format!("__{}_rest__", var));
+        body.push(list![
+            symbol!(NOT.to_string()),
+            list![symbol!(IS_NIL.to_string()), rest.clone()]
+        ]);
+        body.push(list![
+            LPrimitive::Define.into(),
+            var.clone(),
+            list![symbol!(CAR.to_string()), rest.clone()]
+        ]);
+        body.push(list![
+            LPrimitive::Define.into(),
+            rest.clone(),
+            list![symbol!(CDR.to_string()), rest.clone()]
+        ]);
+        body.extend_from_slice(&args[3..]);
+        vec![list![LPrimitive::Define.into(), rest, args[2].clone()]]
+    } else {
+        debug_assert!(matches!(&args[1], LValue::Symbol(s) if s.as_str() == FOR_FROM));
+        let end = symbol!(format!("__{}_end__", var));
+        body.push(list![symbol!(LEQ.to_string()), var.clone(), end.clone()]);
+        body.extend_from_slice(&args[5..]);
+        body.push(list![
+            LPrimitive::Define.into(),
+            var.clone(),
+            list![symbol!(ADD.to_string()), var.clone(), 1.into()]
+        ]);
+        vec![
+            list![LPrimitive::Define.into(), var.clone(), args[2].clone()],
+            list![LPrimitive::Define.into(), end, args[4].clone()],
+        ]
+    };
+    let mut expr = vec![LPrimitive::Begin.into()];
+    expr.extend(init);
+    expr.push(body.into());
+    expr.into()
+}
+
+/// Rewrites a try into its nominal path, in which the body returns no error:
+/// the body followed by the finally clause, the catch clauses being dropped.
+fn try_as_nominal(args: &[LValue]) -> LValue {
+    let finally: Vec<LValue> = args[1..]
+        .iter()
+        .flat_map(|clause| match clause {
+            LValue::List(c) if matches!(&c[0], LValue::Symbol(s) if s.as_str() == TRY_FINALLY) => {
+                c[1..].to_vec()
+            }
+            _ => vec![],
+        })
+        .collect();
+    if finally.is_empty() {
+        return args[0].clone();
+    }
+    let r = symbol!("__try_r__".to_string());
+    let mut expr = vec![
+        LPrimitive::Begin.into(),
+        list![LPrimitive::Define.into(), r.clone(), args[0].clone()],
+    ];
+    expr.extend(finally);
+    expr.push(r);
+    expr.into()
+}
+
 fn unstack(
     current: LValue,
     e: LRuntimeError,
@@ -836,6 +1011,21 @@ pub async fn p_expand(
                         )
                         .into())
                     }
+                    LPrimitive::While | LPrimitive::For | LPrimitive::Try | LPrimitive::Raise => {
+                        let mut expanded_list = vec![co.into()];
+                        for e in &list[1..] {
+                            let result = p_expand(e, false, p_env).await?;
+                            if !result.is_pure() {
+                                return Ok(PLValue::unpure(lv.clone()));
+                            }
+                            expanded_list.push(result.lvalue)
+                        }
+                        return Ok(PLValue::pure(expanded_list.into()));
+                    }
+                    LPrimitive::DefSyntax | LPrimitive::DefModule | LPrimitive::Import => {
+                        //Syntaxes and modules are defined at the expansion, as in SOMPAS.
+                        return Ok(PLValue::pure(expand(lv, top_level, &mut p_env.env).await?));
+                    }
                     co => {
                         return if list.len() != 2 {
                             Err(LRuntimeError::wrong_number_of_args(P_EXPAND, list, 2..2))
@@ -853,6 +1043,14 @@ pub async fn p_expand(
                     }
                 }
             } else if let LValue::Symbol(sym) = &list[0] {
+                if let Some(expanded) = p_env
+                    .env
+                    .get_syntax(sym)
+                    .map(|r| r.transcribe(lv))
+                    .transpose()?
+                {
+                    return p_expand(&expanded, top_level, p_env).await;
+                }
                 match p_env.env.get_macro(sym) {
                     None => {}
                     Some(m) => {
@@ -896,3 +1094,210 @@ pub async fn p_parse(str: &str, env: &mut PLEnv) -> lruntimeerror::Result<PLValu
         Err(e) => Err(lruntimeerror!(P_PARSE, format!("Error in command: {}", e))),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::chronicle::computation::Computation;
+    use crate::model::chronicle::constraint::Constraint;
+    use crate::model::chronicle::lit::Lit;
+    use crate::model::process_ref::Label;
+    use crate::model::sym_table::r#ref::RefSymTable;
+    use crate::model::sym_table::SymTable;
+    use crate::planning::conversion::convert;
+    use crate::planning::conversion::flow_graph::algo::p_eval::r#struct::PConfig;
+    use sompas_core::{get_root_env, parse};
+    use sompas_modules::ModExtendedStd;
+    use sompas_structs::lenv::ImportType::WithoutPrefix;
+
+    const BOUND: usize = 2;
+
+    async fn new_p_env(unpure: &[&str]) -> PLEnv {
+        let mut env = get_root_env().await;
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        let mut p_env = PLEnv {
+            env,
+            unpure_bindings: Default::default(),
+            unrolling: Default::default(),
+            pc: PConfig {
+                unroll_bound: BOUND,
+                ..Default::default()
+            },
+        };
+        for symbol in unpure {
+            p_env.add_unpure(symbol.to_string());
+        }
+        p_env
+    }
+
+    /// Pre-evaluates the expression, *unpure* being the symbols whose value is unknown,
+    /// and checks that the result can be converted into a chronicle.
+    async fn pre_eval(expr: &str, unpure: &[&str]) -> Result<String, LRuntimeError> {
+        let mut p_env = new_p_env(unpure).await;
+        let lv = parse(expr, &mut p_env.env).await?;
+        let result = p_eval(&lv, &mut p_env).await?;
+
+        let st: RefSymTable = SymTable::default().into();
+        let model = convert(None, &lv, new_p_env(unpure).await, st).await?;
+        assert!(model.chronicle.is_some(), "{expr} is not converted");
+        Ok(result.to_string())
+    }
+
+    fn count(result: &str, pattern: &str) -> usize {
+        result.matches(pattern).count()
+    }
+
+    #[tokio::test]
+    async fn test_race() -> Result<(), LRuntimeError> {
+        let result = pre_eval("(race (exec-command 'a) (exec-command 'b))", &[]).await?;
+        assert_eq!(count(&result, "race"), 1);
+        assert_eq!(count(&result, "exec-command"), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_while() -> Result<(), LRuntimeError> {
+        //Pure condition: the number of iterations is known.
+        let result = pre_eval(
+            "(begin
+                (define i 0)
+                (while (< i 4)
+                    (exec-command 'move i)
+                    (define i (+ i 1))))",
+            &[],
+        )
+        .await?;
+        assert_eq!(count(&result, "exec-command"), 4);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 0);
+
+        //Unpure condition: the loop is unrolled up to the bound.
+        let result = pre_eval("(while c (exec-command 'move))", &["c"]).await?;
+        assert_eq!(count(&result, "exec-command"), BOUND);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 1);
+
+        //Condition always true: the loop is unrolled up to the bound.
+        let result = pre_eval("(while true (exec-command 'patrol))", &[]).await?;
+        assert_eq!(count(&result, "exec-command"), BOUND);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_for() -> Result<(), LRuntimeError> {
+        let result = pre_eval("(for x in '(a b c) (exec-command 'pick x))", &[]).await?;
+        assert_eq!(count(&result, "exec-command"), 3);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 0);
+
+        //The range does not depend on the bound.
+        let result = pre_eval("(for i from 1 to 4 (exec-command 'move i))", &[]).await?;
+        assert_eq!(count(&result, "exec-command"), 4);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 0);
+
+        let result = pre_eval("(for i from 1 to n (exec-command 'move i))", &["n"]).await?;
+        assert_eq!(count(&result, "exec-command"), BOUND);
+        assert_eq!(count(&result, UNROLL_BOUND_REACHED), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_loop() -> Result<(), LRuntimeError> {
+        let result = pre_eval("(loop (exec-command 'patrol))", &[]).await?;
+        assert_eq!(count(&result, "exec-command"), BOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try() -> Result<(), LRuntimeError> {
+        //Only the nominal path is kept: the body, then the finally clause.
+        let result = pre_eval("(try (exec-command 'a) (finally (exec-command 'b)))", &[]).await?;
+        assert_eq!(count(&result, "exec-command"), 2);
+        assert_eq!(count(&result, "(try"), 0);
+
+        let result = pre_eval(
+            "(try (exec-command 'a) (catch 'failure e (exec-command 'b)))",
+            &[],
+        )
+        .await?;
+        assert_eq!(count(&result, "exec-command"), 1);
+        assert_eq!(count(&result, "catch"), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_raise() -> Result<(), LRuntimeError> {
+        //The raised value is evaluated.
+        let mut p_env = new_p_env(&[]).await;
+        let lv = parse(
+            "(begin (define kind 'blocked) (raise kind))",
+            &mut p_env.env,
+        )
+        .await?;
+        let result = p_eval(&lv, &mut p_env).await?.to_string();
+        assert_eq!(count(&result, "[err blocked]"), 1);
+        assert_eq!(count(&result, "[err kind]"), 0);
+
+        //The path raising the error is discarded.
+        let result = pre_eval("(if c (exec-command 'a) (raise 'blocked))", &["c"]).await?;
+        assert_eq!(count(&result, "exec-command"), 1);
+        Ok(())
+    }
+
+    /// Returns true if the constraint bounds a timepoint by the sum of another timepoint and a duration.
+    fn is_bound(c: &Constraint) -> bool {
+        match c {
+            Constraint::Leq(_, Lit::Computation(c)) => matches!(c.as_ref(), Computation::Add(_)),
+            Constraint::Eq(a, b) => [a, b]
+                .iter()
+                .any(|l| matches!(l, Lit::Constraint(c) if is_bound(c))),
+            _ => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_for() -> Result<(), LRuntimeError> {
+        let mut bounds = vec![];
+        for expr in ["(wait-for c)", "(wait-for c 10)"] {
+            let mut p_env = new_p_env(&["c"]).await;
+            let lv = parse(expr, &mut p_env.env).await?;
+            let st: RefSymTable = SymTable::default().into();
+            let model = convert(None, &lv, p_env, st).await?;
+            let chronicle = model.chronicle.expect("wait-for is not converted");
+            bounds.push(
+                chronicle
+                    .get_constraints()
+                    .iter()
+                    .filter(|c| is_bound(c))
+                    .count(),
+            );
+        }
+        //Only the wait with a timeout bounds its end by its start and the timeout.
+        assert_eq!(bounds[1], bounds[0] + 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_acquire_all() -> Result<(), LRuntimeError> {
+        for expr in [
+            "(acquire-all r1 r2)",
+            "(acquire-all r1 r2 '(:priority 5))",
+            "(acquire-all r1 r2 `(:priority ,p))",
+        ] {
+            let mut p_env = new_p_env(&["r1", "r2", "p"]).await;
+            let lv = parse(expr, &mut p_env.env).await?;
+            let st: RefSymTable = SymTable::default().into();
+            let model = convert(None, &lv, p_env, st).await?;
+            let chronicle = model.chronicle.expect("acquire-all is not converted");
+            //One acquisition per resource, the priority is not acquired.
+            for (i, expected) in [true, true, false].into_iter().enumerate() {
+                assert_eq!(
+                    chronicle
+                        .get_acting_process_model(Label::ResourceAcquisition(i))
+                        .is_some(),
+                    expected,
+                    "{expr}"
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,13 @@
+use crate::OMPAS_UNROLL_BOUND;
 use im::{hashset, HashSet};
 use ompas_language::exec::acting_context::*;
 use ompas_language::exec::platform::EXEC_COMMAND;
 use ompas_language::exec::refinement::EXEC_TASK;
-use ompas_language::exec::resource::{ACQUIRE, RELEASE};
+use ompas_language::exec::resource::{ACQUIRE, ACQUIRE_ALL, CONSUME, PRODUCE, RELEASE};
 use ompas_language::exec::state::WAIT_FOR;
 use ompas_middleware::logger::LogClient;
 use sompas_language::time::SLEEP;
-use sompas_language::utils::{LOOP, _LOOP_};
+use sompas_language::utils::{_LOOP_, LOOP};
 use sompas_structs::lenv::LEnv;
 use sompas_structs::lprimitive::LPrimitive;
 use sompas_structs::lvalue::{LValue, Sym};
@@ -18,6 +19,7 @@ use std::sync::Arc;
 pub struct PLEnv {
     pub env: LEnv,
     pub unpure_bindings: im::HashMap<String, PLValue>,
+    pub unrolling: PUnrolling,
     pub pc: PConfig,
 }
 
@@ -43,6 +45,38 @@ impl PLEnv {
     }
 }
 
+/// Keeps track of the loops and recursive lambdas unrolled through branchings on unpure conditions,
+/// their number of iterations being unknown before execution.
+#[derive(Clone, Default)]
+pub struct PUnrolling {
+    /// Number of enclosing branchings on an unpure condition.
+    pub(crate) depth: usize,
+    /// Depth of the last unrolling and number of unrollings of each loop or lambda.
+    unrolled: im::HashMap<String, (usize, usize)>,
+}
+
+impl PUnrolling {
+    /// Registers an unrolling of the loop or lambda identified by key.
+    /// Nested evaluations at the same depth are not counted, as their number is known.
+    /// Returns false if the loop or lambda has already been unrolled bound times.
+    pub fn unroll(&mut self, key: String, bound: usize) -> bool {
+        let depth = self.depth;
+        match self.unrolled.get_mut(&key) {
+            Some((d, _)) if *d == depth => true,
+            Some((_, n)) if *n >= bound => false,
+            Some((d, n)) => {
+                *d = depth;
+                *n += 1;
+                true
+            }
+            None => {
+                self.unrolled.insert(key, (depth, 1));
+                bound > 0
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct PLValue {
     pub(crate) lvalue: LValue,
@@ -149,6 +183,8 @@ impl ParameterTable {
 pub struct PConfig {
     pub avoid: HashSet<String>,
     pub p_table: ParameterTable,
+    /// Maximum number of iterations of loops and recursive calls depending on unknown values.
+    pub unroll_bound: usize,
 }
 
 impl Default for PConfig {
@@ -157,6 +193,7 @@ impl Default for PConfig {
             avoid: hashset![
                 CTX_EXEC_COMMAND.to_string(),
                 CTX_ACQUIRE.to_string(),
+                CTX_ACQUIRE_ALL.to_string(),
                 CTX_EXEC_TASK.to_string(),
                 CTX_ARBITRARY.to_string(),
                 _LOOP_.to_string(),
@@ -166,9 +203,13 @@ impl Default for PConfig {
                 SLEEP.to_string(),
                 WAIT_FOR.to_string(),
                 ACQUIRE.to_string(),
-                RELEASE.to_string()
+                ACQUIRE_ALL.to_string(),
+                RELEASE.to_string(),
+                CONSUME.to_string(),
+                PRODUCE.to_string()
             ],
             p_table: Default::default(),
+            unroll_bound: OMPAS_UNROLL_BOUND.get(),
         }
     }
 }
@@ -205,6 +246,7 @@ pub enum PCoreOperatorFrame {
     Begin(PBeginFrame),
     Do(PDoFrame),
     Define(PDefineFrame),
+    While(PWhileFrame),
     Lambda,
     Await,
     Eval,
@@ -215,6 +257,7 @@ pub enum PCoreOperatorFrame {
     Parse,
     Async,
     Interrupt,
+    Raise,
     Race,
     Interruptible,
     Uninterruptible,
@@ -251,6 +294,11 @@ impl PUnstack for PCoreOperatorFrame {
                     results.pop().unwrap().lvalue_as_quote()
                 )
             }
+            PCoreOperatorFrame::While(mut w) => {
+                let mut list = vec![LPrimitive::While.into(), results.pop().unwrap().lvalue];
+                list.append(&mut w.body);
+                list.into()
+            }
             PCoreOperatorFrame::Lambda => results.pop().unwrap().lvalue,
             PCoreOperatorFrame::Await => {
                 list!(LPrimitive::Await.into(), results.pop().unwrap().lvalue)
@@ -291,8 +339,14 @@ impl PUnstack for PCoreOperatorFrame {
             PCoreOperatorFrame::Interrupt => {
                 list!(LPrimitive::Interrupt.into(), results.pop().unwrap().lvalue)
             }
+            PCoreOperatorFrame::Raise => {
+                list!(LPrimitive::Raise.into(), results.pop().unwrap().lvalue)
+            }
             PCoreOperatorFrame::Race => {
-                list!(LPrimitive::Race.into(), results.pop().unwrap().lvalue)
+                let mut r = results.pop_n(2).drain(..).map(|plv| plv.lvalue).collect();
+                let mut list = vec![LPrimitive::Race.into()];
+                list.append(&mut r);
+                list.into()
             }
         })
     }
@@ -337,6 +391,33 @@ impl From<PDoFrame> for PStackFrame {
     }
 }
 
+pub struct PWhileFrame {
+    pub(crate) cond: LValue,
+    pub(crate) body: Vec<LValue>,
+}
+
+impl PWhileFrame {
+    /// Returns the expression evaluating the body once before looping again.
+    pub fn unfold(&self) -> LValue {
+        let mut list = vec![LPrimitive::Do.into()];
+        list.append(&mut self.body.clone());
+        list.push(self.as_lvalue());
+        list.into()
+    }
+
+    pub fn as_lvalue(&self) -> LValue {
+        let mut list = vec![LPrimitive::While.into(), self.cond.clone()];
+        list.append(&mut self.body.clone());
+        list.into()
+    }
+}
+
+impl From<PWhileFrame> for PStackFrame {
+    fn from(w: PWhileFrame) -> Self {
+        Self::CoreOperator(PCoreOperatorFrame::While(w))
+    }
+}
+
 pub struct PDefineFrame {
     pub(crate) symbol: Arc<Sym>,
 }
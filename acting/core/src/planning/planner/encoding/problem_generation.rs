@@ -134,6 +134,7 @@ pub async fn generate_acting_model(
     let p_env = PLEnv {
         env: env.clone(),
         unpure_bindings: Default::default(),
+        unrolling: Default::default(),
         pc: pc.clone(),
     };
 
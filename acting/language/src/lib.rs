@@ -120,7 +120,8 @@ pub mod exec {
         pub const NEW_RESOURCE: &str = "new-resource";
         pub const DOC_NEW_RESOURCE: &str = "Declare a new resource with an optional capacity.";
         pub const DOC_NEW_RESOURCE_VERBOSE: &str = "Example: (new-resource r1); atomic resource\n\
-        (new-resource battery 50); divisible resource";
+        (new-resource battery 50); divisible resource\n\
+        (new-resource fuel 100 :consumable 80); consumable resource with a stock of 80";
 
         pub const __ACQUIRE__: &str = "__acquire__";
         pub const DOC___ACQUIRE__: &str = "Acquire a resource with an optional quantity.";
@@ -128,9 +129,23 @@ pub mod exec {
         pub const __ACQUIRE_IN_LIST__: &str = "__acquire_in_list__";
         pub const DOC___ACQUIRE_IN_LIST__: &str = "Acquire on of the element of the list.";
 
+        pub const __ACQUIRE_ALL__: &str = "__acquire_all__";
+        pub const DOC___ACQUIRE_ALL__: &str =
+            "Acquire atomically all the resources, each with an optional quantity.";
+
         pub const RELEASE: &str = "release";
         pub const DOC_RELEASE: &str = "Release the resource acquired behind the resource handle.";
 
+        pub const CONSUME: &str = "consume";
+        pub const DOC_CONSUME: &str =
+            "Remove a quantity from the stock of a consumable resource. Fails if the stock is insufficient.";
+        pub const DOC_CONSUME_VERBOSE: &str = "Example: (consume fuel 10)";
+
+        pub const PRODUCE: &str = "produce";
+        pub const DOC_PRODUCE: &str =
+            "Add a quantity to the stock of a consumable resource. Fails if it exceeds its maximum capacity.";
+        pub const DOC_PRODUCE_VERBOSE: &str = "Example: (produce fuel 10)";
+
         pub const IS_LOCKED: &str = "locked?";
         pub const DOC_IS_LOCKED: &str = "Return true if a resource is locked.";
 
@@ -150,9 +165,21 @@ pub mod exec {
         pub const LAMBDA_ACQUIRE_IN_LIST: &str = "(lambda __args__
     (u!
         (await-interrupt (enr (cons '__acquire_in_list__ __args__)))))";
+        pub const ACQUIRE_ALL: &str = "acquire-all";
+        pub const DOC_ACQUIRE_ALL: &str =
+            "Wrapper around __acquire_all__ to make it a blocking interruptible.";
+        pub const DOC_ACQUIRE_ALL_VERBOSE: &str =
+            "Each argument is a resource, or a list (resource quantity). \
+            The resources are released together with the returned handle.\n\
+            The optional priority is the last argument.\n\
+            Example: (acquire-all ?r (list ?m 2) '(:priority 5))";
+        pub const LAMBDA_ACQUIRE_ALL: &str = "(lambda __args__
+    (u!
+        (await-interrupt (enr (cons '__acquire_all__ __args__)))))";
 
         //Keywords
         pub const PRIORITY: &str = ":priority";
+        pub const CONSUMABLE: &str = ":consumable";
         pub const LOCKED: &str = "locked";
         pub const MAX_Q: &str = "max-q";
         pub const QUANTITY: &str = "quantity";
@@ -202,14 +229,18 @@ pub mod exec {
         pub const DOC_INSTANCES: &str = "Return all elements of a type.";
         //pub const AWAIT: &str = "rae-await";
         pub const __WAIT_FOR__: &str = "__wait_for__";
-        pub const DOC___WAIT_FOR__: &str = "Wait until a dynamic expression becomes true.";
+        pub const DOC___WAIT_FOR__: &str =
+            "Wait until a dynamic expression becomes true, or until the optional timeout in seconds elapses.";
 
         pub const WAIT_FOR: &str = "wait-for";
         pub const DOC_WAIT_FOR: &str =
             "Wrapper around __wait_for__ to have a blocking interruptible.";
-        pub const LAMBDA_WAIT_FOR: &str = "(lambda (e)
+        pub const DOC_WAIT_FOR_VERBOSE: &str =
+            "Returns nil once the expression is true, or err::timeout if the timeout elapses before.\n\
+            Example: (wait-for `(robot.at ,?r dock) 60)";
+        pub const LAMBDA_WAIT_FOR: &str = "(lambda __args__
     (u! 
-        (await-interrupt (__wait_for__ e))))";
+        (await-interrupt (enr (cons '__wait_for__ (quote-list __args__))))))";
 
         pub const MONITOR: &str = "monitor";
         pub const DOC_MONITOR: &str = "Wait until an dynamic expression becomes false.";
@@ -258,6 +289,16 @@ pub mod exec {
             )
         )";
 
+        pub const CTX_ACQUIRE_ALL: &str = "ctx-acquire-all";
+        pub const DOC_CTX_ACQUIRE_ALL: &str =
+            "Evaluates an acquire-all in an assigned context, the second argument being the number of resources";
+        pub const LAMBDA_CTX_ACQUIRE_ALL: &str = "(lambda args
+            (begin
+                (def-label 'acquire (car args))
+                (enr (cons acquire-all (cdr (cdr args))))
+            )
+        )";
+
         pub const CTX_EXEC_COMMAND: &str = "ctx-exec-command";
         pub const DOC_CTX_EXEC_COMMAND: &str = "Evaluates a command in an assigned context";
         pub const LAMBDA_CTX_EXEC_COMMAND: &str = "(lambda args
@@ -429,6 +470,8 @@ pub mod exec {
 
     pub const ERR_NO_APPLICABLE_METHOD: &str = "err::no-applicable-method";
     pub const ERR_ACTION_FAILURE: &str = "err::action_failure";
+    pub const ERR_DEADLOCK: &str = "err::deadlock";
+    pub const ERR_TIMEOUT: &str = "err::timeout";
 
     pub const MACRO_SIM_BLOCK: &str = "(defmacro sim_block (lambda (body)
 `(begin
@@ -439,6 +482,8 @@ pub mod exec {
 
     pub const DEFINE_ERR_NO_APPLICABLE_METHOD: &str = "(define err::no-applicable-method 0)";
     pub const DEFINE_ERR_ACTION_FAILURE: &str = "(define err::action_failure 1)";
+    pub const DEFINE_ERR_DEADLOCK: &str = "(define err::deadlock 3)";
+    pub const DEFINE_ERR_TIMEOUT: &str = "(define err::timeout 4)";
     pub const MOD_RAE_DESCRIPTION: &str = "rae-description";
 
     pub const RAE_SUCCESS: &str = "success";
@@ -471,6 +516,9 @@ pub mod monitor {
         pub const DOC_EXPORT_TYPE_LATTICE: &str =
             "Exports in a google-chrome page the lattice in a dot form";
 
+        pub const EXPORT_PDDL: &str = "export-pddl";
+        pub const DOC_EXPORT_PDDL: &str = "Exports the domain and the current state in PDDL, or in HDDL if tasks are defined, and reports the constructs that could not be expressed. Takes as optional arguments the output directory and the initial tasks of the HDDL problem.";
+
         pub const PRE_EVAL_TASK: &str = "pre-eval-task";
         pub const DOC_PRE_EVAL_TASK: &str = "Pre evaluate a task";
 
@@ -630,6 +678,10 @@ pub mod monitor {
 
         pub const EVENT_NEW_INSTANCE: &str = "new-instance";
 
+        pub const IMPORT_PDDL: &str = "import-pddl";
+        pub const DOC_IMPORT_PDDL: &str =
+            "Imports a PDDL or HDDL domain and an optional problem. Returns the initial tasks of the problem.";
+
         pub const REMOVE_COMMAND: &str = "remove-command";
         pub const DOC_REMOVE_COMMAND: &str = "Removes command from the domain definition.";
 
@@ -771,7 +823,15 @@ pub mod monitor {
 
         pub const DEF_EVENT: &str = "def-event";
         pub const DOC_DEF_EVENT: &str = "Wrapper around add-event";
-        pub const DOC_DEF_EVENT_VERBOSE: &str= "Example: (def-event on_new_package (new-instance (?p package)) (lambda (?p) (t_process_package ?p)))";
+        pub const DOC_DEF_EVENT_VERBOSE: &str= "Example: (def-event on_new_package (new-instance (?p package)) (lambda (?p) (t_process_package ?p)))\n\
+        The trigger is (:trigger ({once,whenever} [temporal] conditions...)), where temporal is one of:\n\
+        - :rising, :falling: fires when the conditions become true, or false;\n\
+        - :for d: fires once the conditions have held for d seconds;\n\
+        - :every d: fires every d seconds while the conditions hold;\n\
+        - :within d [:after c]: fires if the conditions have not become true d seconds after c became true,\n\
+        or after the event is watched if c is omitted.\n\
+        Example: (def-event dock_watchdog (:params (?r robot)) \
+        (:trigger (whenever :within 60 :after (departed ?r) (at-dock ?r))) (:body (t_recover ?r)))";
         pub const MACRO_DEF_EVENT: &str = "(lambda attributes
         (let ((label (car attributes))
                 (attributes (cdr attributes)))
@@ -876,7 +936,8 @@ pub mod monitor {
         pub const DEF_RESOURCES: &str = "def-resources";
         pub const DOC_DEF_RESOURCES: &str =
             "Wrapper to ease the definition of new objects. Objects are defined with their types.";
-        pub const DOC_DEF_RESOURCES_VERBOSE: &str = "Example: (def-resources (b1 4) (b2 5) b3)";
+        pub const DOC_DEF_RESOURCES_VERBOSE: &str = "Example: (def-resources (b1 4) (b2 5) b3 (fuel 100 :consumable 80))\n\
+        A consumable resource has a stock, by default its maximum capacity, that is modified with consume and produce.";
         pub const MACRO_DEF_RESOURCES: &str = "(lambda args
     (cons 'add-resources (quote-list args)))";
 
@@ -898,6 +959,14 @@ pub mod monitor {
 
         pub const ONCE: &str = "once";
         pub const WHENEVER: &str = "whenever";
+
+        //Temporal keywords of the triggers
+        pub const RISING: &str = ":rising";
+        pub const FALLING: &str = ":falling";
+        pub const FOR_DURATION: &str = ":for";
+        pub const EVERY: &str = ":every";
+        pub const WITHIN: &str = ":within";
+        pub const AFTER: &str = ":after";
     }
 
     pub mod log {
@@ -946,7 +1015,26 @@ pub mod monitor {
 
         pub const EXEC_TASK: &str = "exec-task";
         pub const DOC_EXEC_TASK: &str = "Sends to the system a new task to address.";
-        pub const DOC_EXEC_TASK_VERBOSE: &str = "Example: (exec-task t_dumber robot0)";
+        pub const DOC_EXEC_TASK_VERBOSE: &str = "Example: (exec-task t_dumber robot0)\n\
+             A priority can be given as last argument, inherited by the resource acquisitions of the task: \
+             (exec-task t_dumber robot0 (:priority 5))";
+
+        pub const EXEC_TIMED_TASK: &str = "exec-timed-task";
+        pub const DOC_EXEC_TIMED_TASK: &str =
+            "Sends to the system a task to address in a time window, possibly recurring.";
+        pub const DOC_EXEC_TIMED_TASK_VERBOSE: &str =
+            "Example: (exec-timed-task '(:start 10 :deadline 40 :every 60 :until 600) t_dumber robot0)\n\
+             Times are in seconds since the start of OMPAS.\n\
+             \t:start: earliest start of the task\n\
+             \t:deadline: the task is stopped if it is not terminated at this time\n\
+             \t:every: period of the recurrence, the deadline being shifted for each occurrence\n\
+             \t:until: the task is not released after this time\n\
+             \t:soft: if true, the task is not stopped at its deadline, only its lateness is reported";
+        pub const TIMING_START: &str = ":start";
+        pub const TIMING_DEADLINE: &str = ":deadline";
+        pub const TIMING_EVERY: &str = ":every";
+        pub const TIMING_UNTIL: &str = ":until";
+        pub const TIMING_SOFT: &str = ":soft";
 
         pub const EXEC_COMMAND: &str = "exec-command";
         pub const DOC_EXEC_COMMAND: &str = "Sends to RAE a new command to execute";
@@ -974,7 +1062,7 @@ pub mod monitor {
 
         pub const SET_SELECT: &str = "set-select";
         pub const DOC_SET_SELECT: &str =
-            "Set the select engine: greedy, aries, upom, c_choice, etc.";
+            "Set the select engine: greedy, aries, upom, c_choice, learning, learning-upom, etc.";
 
         pub const SET_CONTINUOUS_PLANNING: &str = "set-continuous-planning";
         pub const DOC_SET_CONTINUOUS_PLANNING: &str =
@@ -1070,6 +1158,34 @@ pub mod monitor {
         pub const DOC_EXPORT_REPORT: &str =
             "Exports the acting tree and the state of the current run.";
 
+        pub const START_TRACE: &str = "start-trace";
+        pub const DOC_START_TRACE: &str =
+            "Starts to record the trace of the execution in a JSON lines file, by default trace.jsonl in the run directory.";
+
+        pub const STOP_TRACE: &str = "stop-trace";
+        pub const DOC_STOP_TRACE: &str =
+            "Stops the record of the trace of the execution and returns the path of the file.";
+
+        pub const CHECK_TRACE: &str = "check-trace";
+        pub const DOC_CHECK_TRACE: &str =
+            "Checks the decisions of the engine against a recorded trace, when the trace is replayed by an external platform.";
+
+        pub const GET_TRACE_REPORT: &str = "get-trace-report";
+        pub const DOC_GET_TRACE_REPORT: &str =
+            "Returns the decisions of the engine that differ from the replayed trace.";
+
+        pub const DEBUG_PROCESS: &str = "debug-process";
+        pub const DOC_DEBUG_PROCESS: &str =
+            "Attaches the debugger of the REPL to a method, or to the current method of a task, identified by its acting process id. The method pauses at its next procedure call.";
+
+        pub const GET_LEARNING: &str = "get-learning";
+        pub const DOC_GET_LEARNING: &str =
+            "Returns the statistics learned on the executions of the methods, used by the learning select mode.";
+
+        pub const GET_DIVERGENCES: &str = "get-divergences";
+        pub const DOC_GET_DIVERGENCES: &str =
+            "Returns the divergences detected between the conditions and effects of the planned chronicles and the observed state.";
+
         pub const WAIT_END_ALL: &str = "wait-end-all";
         pub const DOC_WAIT_END_ALL: &str = "Wait that all current high-level tasks are terminated.";
 
@@ -1086,6 +1202,7 @@ pub mod supervisor {
     pub const STATUS_SUCCESS: &str = "success";
     pub const STATUS_FAILURE: &str = "failure";
     pub const STATUS_CANCELLED: &str = "cancelled";
+    pub const STATUS_DEADLINE_MISSED: &str = "deadline-missed";
 
     pub const TASK: &str = "task";
     pub const ROOT_TASK: &str = "root_task";
@@ -1111,6 +1228,7 @@ pub mod select {
     pub const PLANNING: &str = "planning";
     pub const HEURISTIC: &str = "heuristic";
     pub const LEARNING: &str = "learning";
+    pub const LEARNING_UPOM: &str = "learning-upom";
     pub const RANDOM: &str = "random";
     pub const COST: &str = "cost";
     pub const ARIES: &str = "aries";
@@ -1134,6 +1252,7 @@ pub mod process {
     pub const PROCESS_STOP_OMPAS: &str = "__PROCESS_STOP_OMPAS__";
     pub const PROCESS_CHECK_FLUENT: &str = "__PROCESS_CHECK_FLUENT__";
     pub const PROCESS_CHECK_EVENT: &str = "__PROCESS_CHECK_EVENT__";
+    pub const PROCESS_MONITOR_EXECUTION: &str = "__PROCESS_MONITOR_EXECUTION__";
 }
 
 pub mod interface {
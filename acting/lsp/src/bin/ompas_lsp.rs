@@ -0,0 +1,13 @@
+use ompas_lsp::env::OmpasEnv;
+use ompas_lsp::server::OmpasLanguageServer;
+use tower_lsp::{LspService, Server};
+
+/// Language server communicating with the editor on the standard input and output.
+#[tokio::main]
+async fn main() {
+    let env = OmpasEnv::new().await;
+    let (service, socket) = LspService::new(|client| OmpasLanguageServer::new(client, env));
+    Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+        .serve(service)
+        .await;
+}
@@ -0,0 +1,435 @@
+//! Analysis of a domain file: its definitions, the files it loads, and the diagnostics of its content.
+use crate::reader::{read, Node, NodeKind, Pos, ReadError, Span};
+use ompas_language::monitor::model::*;
+use sompas_language::io::LOAD;
+use sompas_language::primitives::{DEFINE, FN_LAMBDA};
+use sompas_language::utils::{LET, LET_STAR};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Attributes of the definitions that do not contain expressions to evaluate.
+const DATA_ATTRIBUTES: &[&str] = &[NAME, TASK, PARAMETERS, RESULT, MODEL_TYPE];
+
+/// Definitions whose arguments are data, and not expressions to evaluate.
+const DATA_DEFINITIONS: &[&str] = &[
+    DEF_TYPES,
+    DEF_OBJECTS,
+    DEF_RESOURCES,
+    DEF_FACTS,
+    DEF_STATIC_FACTS,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DefinitionKind {
+    Task,
+    Method,
+    Command,
+    StateFunction,
+    Function,
+    Lambda,
+    Env,
+    Event,
+    Type,
+    Object,
+    Resource,
+    Variable,
+}
+
+impl Display for DefinitionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DefinitionKind::Task => "task",
+                DefinitionKind::Method => "method",
+                DefinitionKind::Command => "command",
+                DefinitionKind::StateFunction => "state function",
+                DefinitionKind::Function => "function",
+                DefinitionKind::Lambda => "lambda",
+                DefinitionKind::Env => "env",
+                DefinitionKind::Event => "event",
+                DefinitionKind::Type => "type",
+                DefinitionKind::Object => "object",
+                DefinitionKind::Resource => "resource",
+                DefinitionKind::Variable => "variable",
+            }
+        )
+    }
+}
+
+/// Symbol defined in a domain file.
+/// *signature* is the definition without its body, as `(def-task go2 (:params (?r room)))`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    pub span: Span,
+    pub signature: String,
+}
+
+/// Symbol called in an expression of a domain file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub name: String,
+    pub span: Span,
+}
+
+/// File loaded with `(load path)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Load {
+    pub path: PathBuf,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub text: String,
+    pub forms: Vec<Node>,
+    pub errors: Vec<ReadError>,
+    pub definitions: Vec<Definition>,
+    /// Calls of symbols that are not bound locally, by a lambda or a let.
+    pub calls: Vec<Call>,
+    pub loads: Vec<Load>,
+}
+
+impl Document {
+    /// Reads and analyses the text of a file. The paths of the loaded files are resolved relatively
+    /// to the directory of the file, if any.
+    pub fn new(text: String, dir: Option<&Path>) -> Self {
+        let (forms, errors) = read(&text);
+        let mut document = Self {
+            text,
+            forms,
+            errors,
+            ..Default::default()
+        };
+        let forms = std::mem::take(&mut document.forms);
+        let mut bound = vec![];
+        for form in &forms {
+            document.analyse(form, dir, &mut bound);
+        }
+        document.forms = forms;
+        document
+    }
+
+    /// Returns the source of the top level form.
+    pub fn source(&self, form: &Node) -> &str {
+        &self.text[form.bytes.clone()]
+    }
+
+    /// Returns the atom at the position.
+    pub fn atom_at(&self, pos: Pos) -> Option<&Node> {
+        self.forms.iter().find_map(|f| f.atom_at(pos))
+    }
+
+    /// Returns the atom ending at the position, or an empty prefix, used for completion.
+    pub fn prefix_at(&self, pos: Pos) -> String {
+        match self.atom_at(pos) {
+            Some(Node {
+                kind: NodeKind::Atom(a),
+                span,
+                ..
+            }) if span.start.line == pos.line => a
+                .chars()
+                .take((pos.character - span.start.character) as usize)
+                .collect(),
+            _ => String::new(),
+        }
+    }
+
+    pub fn get_definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.iter().find(|d| d.name == name)
+    }
+
+    fn define(&mut self, name: &Node, kind: DefinitionKind, form: &Node) {
+        if let Some(n) = name.as_atom() {
+            if self.get_definition(n).is_some() {
+                return;
+            }
+            let signature = match (kind, form.as_list()) {
+                (
+                    DefinitionKind::Task
+                    | DefinitionKind::Method
+                    | DefinitionKind::Command
+                    | DefinitionKind::StateFunction
+                    | DefinitionKind::Function,
+                    Some(list),
+                ) => {
+                    let attributes: Vec<&str> = list
+                        .iter()
+                        .skip(2)
+                        .filter(|a| matches!(a.head(), Some(TASK | PARAMETERS | RESULT)))
+                        .map(|a| self.source(a))
+                        .collect();
+                    let def = list[0].as_atom().unwrap_or_default();
+                    format!("({} {} {})", def, n, attributes.join(" "))
+                }
+                _ => format!("{} {}", kind, n),
+            };
+            self.definitions.push(Definition {
+                name: n.to_string(),
+                kind,
+                span: name.span,
+                signature,
+            })
+        }
+    }
+
+    /// Collects the definitions, loads and calls of the expression.
+    /// *bound* contains the symbols bound by the enclosing lambdas and lets.
+    fn analyse(&mut self, node: &Node, dir: Option<&Path>, bound: &mut Vec<String>) {
+        let list = match &node.kind {
+            NodeKind::List(list) if !list.is_empty() => list,
+            _ => return,
+        };
+        let head = match list[0].as_atom() {
+            Some(head) => head,
+            None => {
+                for e in list {
+                    self.analyse(e, dir, bound)
+                }
+                return;
+            }
+        };
+
+        let kind = match head {
+            DEF_TASK => Some(DefinitionKind::Task),
+            DEF_METHOD => Some(DefinitionKind::Method),
+            DEF_COMMAND => Some(DefinitionKind::Command),
+            DEF_STATE_FUNCTION => Some(DefinitionKind::StateFunction),
+            DEF_FUNCTION => Some(DefinitionKind::Function),
+            DEF_LAMBDA => Some(DefinitionKind::Lambda),
+            DEF_ENV => Some(DefinitionKind::Env),
+            DEF_EVENT => Some(DefinitionKind::Event),
+            DEFINE => Some(DefinitionKind::Variable),
+            _ => None,
+        };
+        if let (Some(kind), Some(name)) = (kind, list.get(1)) {
+            self.define(name, kind, node);
+        }
+
+        match head {
+            DEF_TYPES | DEF_OBJECTS | DEF_RESOURCES => {
+                for e in &list[1..] {
+                    let names: Vec<&Node> = match &e.kind {
+                        NodeKind::Atom(_) => vec![e],
+                        NodeKind::List(l) => match head {
+                            //the last element is the type of the objects
+                            DEF_OBJECTS => l.iter().take(l.len().saturating_sub(1)).collect(),
+                            //the second element is the capacity of the resource
+                            DEF_RESOURCES => l.iter().take(1).collect(),
+                            _ => l.iter().collect(),
+                        },
+                        _ => vec![],
+                    };
+                    let kind = match head {
+                        DEF_TYPES => DefinitionKind::Type,
+                        DEF_OBJECTS => DefinitionKind::Object,
+                        _ => DefinitionKind::Resource,
+                    };
+                    for name in names {
+                        self.define(name, kind, e)
+                    }
+                }
+            }
+            LOAD => {
+                let path = match list.get(1).map(|p| &p.kind) {
+                    Some(NodeKind::Atom(p) | NodeKind::String(p)) => PathBuf::from(p),
+                    _ => return,
+                };
+                let path = match dir {
+                    Some(dir) if path.is_relative() => dir.join(path),
+                    _ => path,
+                };
+                let path = path.canonicalize().unwrap_or(path);
+                self.loads.push(Load {
+                    path,
+                    span: node.span,
+                })
+            }
+            FN_LAMBDA => {
+                let n = bound.len();
+                match list.get(1).map(|p| &p.kind) {
+                    Some(NodeKind::Atom(p)) => bound.push(p.clone()),
+                    Some(NodeKind::List(params)) => {
+                        bound.extend(params.iter().filter_map(|p| p.as_atom().map(String::from)))
+                    }
+                    _ => {}
+                }
+                for e in list.iter().skip(2) {
+                    self.analyse(e, dir, bound)
+                }
+                bound.truncate(n);
+            }
+            LET | LET_STAR => {
+                let n = bound.len();
+                //named let
+                let (bindings, body) = match list.get(1).map(|b| &b.kind) {
+                    Some(NodeKind::Atom(name)) => {
+                        bound.push(name.clone());
+                        (list.get(2), 3)
+                    }
+                    _ => (list.get(1), 2),
+                };
+                for binding in bindings.and_then(|b| b.as_list()).unwrap_or_default() {
+                    if let Some([var, value]) = binding.as_list() {
+                        self.analyse(value, dir, bound);
+                        if let Some(var) = var.as_atom() {
+                            bound.push(var.to_string())
+                        }
+                    }
+                }
+                for e in list.iter().skip(body) {
+                    self.analyse(e, dir, bound)
+                }
+                bound.truncate(n);
+            }
+            head if DATA_DEFINITIONS.contains(&head) || DATA_ATTRIBUTES.contains(&head) => {}
+            head => {
+                //attributes of a definition, as (:body ...), are not calls
+                if !head.starts_with(':') && !bound.iter().any(|b| b == head) {
+                    self.calls.push(Call {
+                        name: head.to_string(),
+                        span: list[0].span,
+                    })
+                }
+                for e in &list[1..] {
+                    self.analyse(e, dir, bound)
+                }
+            }
+        }
+    }
+
+    /// Returns the calls of symbols that are neither defined in the document nor known.
+    pub fn undefined_calls<'a>(&'a self, known: &'a HashSet<String>) -> Vec<&'a Call> {
+        self.calls
+            .iter()
+            .filter(|c| {
+                !c.name.starts_with('?')
+                    && c.name.parse::<f64>().is_err()
+                    && !known.contains(&c.name)
+                    && self.get_definition(&c.name).is_none()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const DOMAIN: &str = "(def-types robot room)
+(def-objects (r1 r2 robot))
+(def-task go2 (:params (?r robot) (?to room)))
+(def-method m_go2
+  (:task go2)
+  (:params (?r robot) (?to room))
+  (:body
+    (let ((from (at ?r)))
+      (move ?r from ?to)
+      (go2 ?r ?to))))";
+
+    fn names<'a>(calls: impl IntoIterator<Item = &'a Call>) -> Vec<&'a str> {
+        calls.into_iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_definitions() {
+        let document = Document::new(DOMAIN.to_string(), None);
+        assert!(document.errors.is_empty());
+
+        let go2 = document.get_definition("go2").unwrap();
+        assert_eq!(go2.kind, DefinitionKind::Task);
+        assert_eq!(
+            go2.signature,
+            "(def-task go2 (:params (?r robot) (?to room)))"
+        );
+        assert_eq!(
+            document.get_definition("m_go2").unwrap().signature,
+            "(def-method m_go2 (:task go2) (:params (?r robot) (?to room)))"
+        );
+        assert_eq!(
+            document.get_definition("robot").unwrap().kind,
+            DefinitionKind::Type
+        );
+        assert_eq!(
+            document.get_definition("r2").unwrap().kind,
+            DefinitionKind::Object
+        );
+        assert!(document.get_definition("?r").is_none());
+
+        //The definition of the symbol under the cursor, as in a go-to-definition.
+        let call = document
+            .atom_at(Pos {
+                line: 9,
+                character: 8,
+            })
+            .and_then(|a| a.as_atom())
+            .unwrap();
+        assert_eq!(call, "go2");
+        assert_eq!(
+            document.get_definition(call).unwrap().span.start,
+            Pos {
+                line: 2,
+                character: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_undefined_calls() {
+        let document = Document::new(DOMAIN.to_string(), None);
+        //The variables bound by a let and the parameters are not calls.
+        assert_eq!(
+            names(&document.calls),
+            vec!["def-task", "def-method", "at", "move", "go2"]
+        );
+
+        let known: HashSet<String> = ["def-task", "def-method", "at"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let undefined = document.undefined_calls(&known);
+        assert_eq!(names(undefined.iter().copied()), vec!["move"]);
+        assert_eq!(
+            undefined[0].span.start,
+            Pos {
+                line: 8,
+                character: 7
+            }
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = std::env::temp_dir().join("ompas-lsp-test-load");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("robot.scm");
+        fs::write(
+            &path,
+            "(def-command move (:params (?r robot) (?from room) (?to room)))",
+        )
+        .unwrap();
+
+        let document = Document::new(DOMAIN.to_string() + "\n(load \"robot.scm\")", Some(&dir));
+        assert_eq!(document.loads.len(), 1);
+        assert_eq!(document.loads[0].path, path.canonicalize().unwrap());
+
+        //The definitions of the loaded file are known by the document that loads it.
+        let loaded = Document::new(
+            fs::read_to_string(&document.loads[0].path).unwrap(),
+            Some(&dir),
+        );
+        let definition = loaded.get_definition("move").unwrap();
+        assert_eq!(definition.kind, DefinitionKind::Command);
+        let mut known: HashSet<String> = ["def-task", "def-method", "at"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        known.extend(loaded.definitions.iter().map(|d| d.name.clone()));
+        assert!(document.undefined_calls(&known).is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+//! Environment of OMPAS, used to check the domain files and to document their symbols.
+use ompas_core::ompas::scheme::exec::ModExec;
+use ompas_core::ompas::scheme::monitor::control::ModControl;
+use ompas_core::ompas::scheme::monitor::ModMonitor;
+use sompas_core::{eval_init, get_root_env, parse};
+use sompas_modules::ModExtendedStd;
+use sompas_structs::documentation::{Doc, DocCollection};
+use sompas_structs::lenv::ImportType::WithoutPrefix;
+use sompas_structs::lenv::LEnv;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use std::collections::HashSet;
+
+/// Environment in which the domain files are loaded by the REPL of OMPAS,
+/// completed with the primitives available in the bodies of the methods.
+pub struct OmpasEnv {
+    env: LEnv,
+    symbols: HashSet<String>,
+    documentation: DocCollection,
+}
+
+impl OmpasEnv {
+    pub async fn new() -> Self {
+        let monitor = ModMonitor::default();
+        let exec = ModExec::new(&ModControl::new(&monitor)).await;
+        let mut env = get_root_env().await;
+        env.import_module(ModExtendedStd::default(), WithoutPrefix);
+        env.import_module(exec, WithoutPrefix);
+        env.import_module(monitor, WithoutPrefix);
+        eval_init(&mut env).await;
+
+        let mut symbols: HashSet<String> = env.keys().into_iter().collect();
+        symbols.extend(env.macros());
+        let documentation = env.get_documentation();
+        Self {
+            env,
+            symbols,
+            documentation,
+        }
+    }
+
+    /// Returns the symbols defined in the environment: primitives, functions, lambdas and macros.
+    pub fn symbols(&self) -> &HashSet<String> {
+        &self.symbols
+    }
+
+    pub fn get_doc(&self, symbol: &str) -> Option<&Doc> {
+        self.documentation.get_doc(symbol)
+    }
+
+    /// Parses and expands an expression of a domain file, as the REPL does before evaluating it.
+    pub async fn check(&self, expr: &str) -> Result<(), LRuntimeError> {
+        let mut env = self.env.clone();
+        parse(expr, &mut env).await.map(|_| ())
+    }
+}
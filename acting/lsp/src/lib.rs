@@ -0,0 +1,7 @@
+//! Language server of the OMPAS domain files.
+//! It reports the syntax errors, the expressions that cannot be expanded and the undefined symbols,
+//! completes and documents the symbols, and finds their definitions across the loaded files.
+pub mod document;
+pub mod env;
+pub mod reader;
+pub mod server;
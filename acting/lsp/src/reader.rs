@@ -0,0 +1,326 @@
+//! Reader of s-expressions keeping the position of each expression in the source,
+//! used to locate diagnostics, definitions and symbols in the domain files.
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+/// Position in a text, lines and characters starting at 0.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pos {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: Pos,
+    pub end: Pos,
+}
+
+impl Span {
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.start <= pos && pos <= self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind {
+    Atom(String),
+    String(String),
+    List(Vec<Node>),
+    /// Expression prefixed by a quote, a quasiquote or an unquote.
+    Quote(char, Box<Node>),
+}
+
+/// Expression read in the source, with its span and the range of its bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub bytes: Range<usize>,
+}
+
+impl Node {
+    pub fn as_atom(&self) -> Option<&str> {
+        match &self.kind {
+            NodeKind::Atom(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Node]> {
+        match &self.kind {
+            NodeKind::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    /// Returns the first element of the list, if it is an atom.
+    pub fn head(&self) -> Option<&str> {
+        self.as_list()?.first()?.as_atom()
+    }
+
+    /// Returns the innermost atom whose span contains the position.
+    pub fn atom_at(&self, pos: Pos) -> Option<&Node> {
+        if !self.span.contains(pos) {
+            return None;
+        }
+        match &self.kind {
+            NodeKind::Atom(_) => Some(self),
+            NodeKind::String(_) => None,
+            NodeKind::List(l) => l.iter().find_map(|n| n.atom_at(pos)),
+            NodeKind::Quote(_, n) => n.atom_at(pos),
+        }
+    }
+}
+
+/// Error of the reader: an unbalanced parenthesis or an unterminated string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.start.line + 1,
+            self.span.start.character + 1,
+            self.message
+        )
+    }
+}
+
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+    pos: Pos,
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn next(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.character = 0;
+        } else {
+            self.pos.character += c.len_utf16() as u32;
+        }
+        Some(c)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|(_, c)| *c)
+    }
+}
+
+/// Open list, or quote waiting for the expression it applies to.
+enum Open {
+    List(Pos, usize, Vec<Node>),
+    Quote(char, Pos, usize),
+}
+
+/// Reads all the expressions of the text.
+/// The reader recovers from errors, so that the expressions before and after an error are returned.
+pub fn read(text: &str) -> (Vec<Node>, Vec<ReadError>) {
+    let mut reader = Reader {
+        chars: text.char_indices().peekable(),
+        text,
+        pos: Pos::default(),
+        offset: 0,
+    };
+    let mut nodes = vec![];
+    let mut errors = vec![];
+    let mut open: Vec<Open> = vec![];
+
+    while let Some(c) = reader.peek() {
+        let start = reader.pos;
+        let start_offset = reader.offset;
+        let node = match c {
+            c if c.is_whitespace() => {
+                reader.next();
+                continue;
+            }
+            ';' => {
+                while !matches!(reader.next(), Some('\n') | None) {}
+                continue;
+            }
+            '(' => {
+                reader.next();
+                open.push(Open::List(start, start_offset, vec![]));
+                continue;
+            }
+            '\'' | '`' | ',' => {
+                reader.next();
+                if c == ',' && reader.peek() == Some('@') {
+                    reader.next();
+                }
+                open.push(Open::Quote(c, start, start_offset));
+                continue;
+            }
+            ')' => {
+                reader.next();
+                //Quotes not followed by an expression are ignored.
+                while let Some(Open::Quote(..)) = open.last() {
+                    open.pop();
+                }
+                match open.pop() {
+                    Some(Open::List(list_start, list_offset, list)) => Node {
+                        kind: NodeKind::List(list),
+                        span: Span {
+                            start: list_start,
+                            end: reader.pos,
+                        },
+                        bytes: list_offset..reader.offset,
+                    },
+                    _ => {
+                        errors.push(ReadError {
+                            message: "unexpected closing parenthesis".to_string(),
+                            span: Span {
+                                start,
+                                end: reader.pos,
+                            },
+                        });
+                        continue;
+                    }
+                }
+            }
+            '"' => {
+                reader.next();
+                let mut string = String::new();
+                let mut terminated = false;
+                while let Some(c) = reader.next() {
+                    match c {
+                        '"' => {
+                            terminated = true;
+                            break;
+                        }
+                        '\\' => {
+                            if let Some(c) = reader.next() {
+                                string.push(c)
+                            }
+                        }
+                        c => string.push(c),
+                    }
+                }
+                if !terminated {
+                    errors.push(ReadError {
+                        message: "unterminated string".to_string(),
+                        span: Span {
+                            start,
+                            end: reader.pos,
+                        },
+                    });
+                }
+                Node {
+                    kind: NodeKind::String(string),
+                    span: Span {
+                        start,
+                        end: reader.pos,
+                    },
+                    bytes: start_offset..reader.offset,
+                }
+            }
+            _ => {
+                while let Some(c) = reader.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"' | ';') {
+                        break;
+                    }
+                    reader.next();
+                }
+                Node {
+                    kind: NodeKind::Atom(reader.text[start_offset..reader.offset].to_string()),
+                    span: Span {
+                        start,
+                        end: reader.pos,
+                    },
+                    bytes: start_offset..reader.offset,
+                }
+            }
+        };
+
+        //The expression completes the pending quotes, then goes in the open list, if any.
+        let mut node = node;
+        loop {
+            match open.pop() {
+                Some(Open::Quote(q, start, offset)) => {
+                    node = Node {
+                        span: Span {
+                            start,
+                            end: node.span.end,
+                        },
+                        bytes: offset..node.bytes.end,
+                        kind: NodeKind::Quote(q, Box::new(node)),
+                    }
+                }
+                Some(Open::List(start, offset, mut list)) => {
+                    list.push(node);
+                    open.push(Open::List(start, offset, list));
+                    break;
+                }
+                None => {
+                    nodes.push(node);
+                    break;
+                }
+            }
+        }
+    }
+
+    for o in open {
+        if let Open::List(start, _, _) = o {
+            errors.push(ReadError {
+                message: "unclosed parenthesis".to_string(),
+                span: Span {
+                    start,
+                    end: Pos {
+                        line: start.line,
+                        character: start.character + 1,
+                    },
+                },
+            })
+        }
+    }
+
+    (nodes, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let (nodes, errors) = read("(def-task go2\n  (:params (?r room))) ; comment\n'(a \"b\")");
+        assert!(errors.is_empty());
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].head(), Some("def-task"));
+        let atom = nodes[0]
+            .atom_at(Pos {
+                line: 1,
+                character: 13,
+            })
+            .unwrap();
+        assert_eq!(atom.as_atom(), Some("?r"));
+        assert_eq!(
+            atom.span.start,
+            Pos {
+                line: 1,
+                character: 12
+            }
+        );
+        assert!(matches!(nodes[1].kind, NodeKind::Quote('\'', _)));
+
+        let (nodes, errors) = read("(begin (a)) )\n(b");
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            vec![
+                "1:13: unexpected closing parenthesis",
+                "2:1: unclosed parenthesis"
+            ]
+        );
+    }
+}
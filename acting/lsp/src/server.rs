@@ -0,0 +1,332 @@
+//! Language server of the OMPAS domain files.
+use crate::document::{Definition, DefinitionKind, Document};
+use crate::env::OmpasEnv;
+use crate::reader::{Pos, Span};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use tokio::sync::RwLock;
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer};
+
+pub const SERVER_NAME: &str = "ompas-lsp";
+const DIAGNOSTIC_SOURCE: &str = "ompas";
+
+impl From<Position> for Pos {
+    fn from(p: Position) -> Self {
+        Self {
+            line: p.line,
+            character: p.character,
+        }
+    }
+}
+
+impl From<Pos> for Position {
+    fn from(p: Pos) -> Self {
+        Self {
+            line: p.line,
+            character: p.character,
+        }
+    }
+}
+
+impl From<Span> for Range {
+    fn from(s: Span) -> Self {
+        Self {
+            start: s.start.into(),
+            end: s.end.into(),
+        }
+    }
+}
+
+impl From<DefinitionKind> for CompletionItemKind {
+    fn from(kind: DefinitionKind) -> Self {
+        match kind {
+            DefinitionKind::Task | DefinitionKind::Method | DefinitionKind::Command => {
+                CompletionItemKind::METHOD
+            }
+            DefinitionKind::StateFunction | DefinitionKind::Function | DefinitionKind::Lambda => {
+                CompletionItemKind::FUNCTION
+            }
+            DefinitionKind::Type => CompletionItemKind::CLASS,
+            DefinitionKind::Object | DefinitionKind::Resource => CompletionItemKind::CONSTANT,
+            DefinitionKind::Event => CompletionItemKind::EVENT,
+            DefinitionKind::Env | DefinitionKind::Variable => CompletionItemKind::VARIABLE,
+        }
+    }
+}
+
+pub struct OmpasLanguageServer {
+    client: Client,
+    env: OmpasEnv,
+    /// Documents opened in the editor.
+    documents: RwLock<HashMap<Url, Document>>,
+}
+
+impl OmpasLanguageServer {
+    pub fn new(client: Client, env: OmpasEnv) -> Self {
+        Self {
+            client,
+            env,
+            documents: Default::default(),
+        }
+    }
+
+    /// Returns the document and the documents it loads, recursively.
+    /// The documents that are not opened in the editor are read from the disk.
+    async fn with_loaded(&self, uri: &Url) -> Vec<(Url, Document)> {
+        let documents = self.documents.read().await;
+        let mut result: Vec<(Url, Document)> = vec![];
+        let mut queue = vec![uri.clone()];
+        while let Some(uri) = queue.pop() {
+            if result.iter().any(|(u, _)| *u == uri) {
+                continue;
+            }
+            let document = match documents.get(&uri) {
+                Some(d) => d.clone(),
+                None => match uri.to_file_path().ok().and_then(|p| {
+                    let text = fs::read_to_string(&p).ok()?;
+                    Some(Document::new(text, p.parent()))
+                }) {
+                    Some(d) => d,
+                    None => continue,
+                },
+            };
+            queue.extend(
+                document
+                    .loads
+                    .iter()
+                    .filter_map(|l| Url::from_file_path(&l.path).ok()),
+            );
+            result.push((uri, document));
+        }
+        result
+    }
+
+    /// Returns the definition of the symbol in the document, the documents it loads,
+    /// or any document opened in the editor.
+    async fn find_definition(&self, uri: &Url, name: &str) -> Option<(Url, Definition)> {
+        let mut documents = self.with_loaded(uri).await;
+        for (u, d) in self.documents.read().await.iter() {
+            documents.push((u.clone(), d.clone()))
+        }
+        documents
+            .into_iter()
+            .find_map(|(u, d)| d.get_definition(name).map(|def| (u, def.clone())))
+    }
+
+    async fn diagnose(&self, uri: Url, version: Option<i32>) {
+        let documents = self.with_loaded(&uri).await;
+        let document = match documents.first() {
+            Some((u, d)) if *u == uri => d,
+            _ => return,
+        };
+        let mut diagnostics = vec![];
+        let error = |span: Span, message: String| Diagnostic {
+            range: span.into(),
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some(DIAGNOSTIC_SOURCE.to_string()),
+            message,
+            ..Default::default()
+        };
+
+        for e in &document.errors {
+            diagnostics.push(error(e.span, e.message.clone()));
+        }
+        if document.errors.is_empty() {
+            for form in &document.forms {
+                if let Err(e) = self.env.check(document.source(form)).await {
+                    //the error is reported on the first line of the form
+                    let span = match form.as_list().and_then(|l| l.first()) {
+                        Some(head) => Span {
+                            start: form.span.start,
+                            end: head.span.end,
+                        },
+                        None => form.span,
+                    };
+                    diagnostics.push(error(span, e.to_string()));
+                }
+            }
+        }
+        for load in &document.loads {
+            if !load.path.exists() {
+                diagnostics.push(error(
+                    load.span,
+                    format!("cannot find {}", load.path.display()),
+                ));
+            }
+        }
+
+        let mut known: HashSet<String> = self.env.symbols().clone();
+        for (_, d) in documents.iter().skip(1) {
+            known.extend(d.definitions.iter().map(|d| d.name.clone()))
+        }
+        for (_, d) in self.documents.read().await.iter() {
+            known.extend(d.definitions.iter().map(|d| d.name.clone()))
+        }
+        for call in document.undefined_calls(&known) {
+            diagnostics.push(Diagnostic {
+                range: call.span.into(),
+                severity: Some(DiagnosticSeverity::WARNING),
+                source: Some(DIAGNOSTIC_SOURCE.to_string()),
+                message: format!("{} is not defined", call.name),
+                ..Default::default()
+            })
+        }
+
+        self.client
+            .publish_diagnostics(uri, diagnostics, version)
+            .await;
+    }
+
+    async fn update(&self, uri: Url, text: String, version: Option<i32>) {
+        let dir = uri
+            .to_file_path()
+            .ok()
+            .and_then(|p| p.parent().map(|p| p.to_path_buf()));
+        let document = Document::new(text, dir.as_deref());
+        self.documents.write().await.insert(uri.clone(), document);
+        self.diagnose(uri, version).await;
+    }
+
+    async fn get_atom(&self, uri: &Url, pos: Position) -> Option<(String, Span)> {
+        let documents = self.documents.read().await;
+        let atom = documents.get(uri)?.atom_at(pos.into())?;
+        Some((atom.as_atom()?.to_string(), atom.span))
+    }
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for OmpasLanguageServer {
+    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                    TextDocumentSyncKind::FULL,
+                )),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                completion_provider: Some(CompletionOptions::default()),
+                definition_provider: Some(OneOf::Left(true)),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: SERVER_NAME.to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client
+            .log_message(MessageType::INFO, format!("{} initialized", SERVER_NAME))
+            .await;
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let document = params.text_document;
+        self.update(document.uri, document.text, Some(document.version))
+            .await
+    }
+
+    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+        //the documents are fully synchronized, the last change contains the whole text
+        if let Some(change) = params.content_changes.pop() {
+            let document = params.text_document;
+            self.update(document.uri, change.text, Some(document.version))
+                .await
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.write().await.remove(&uri);
+        self.client.publish_diagnostics(uri, vec![], None).await;
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let params = params.text_document_position_params;
+        let uri = params.text_document.uri;
+        let (name, _) = match self.get_atom(&uri, params.position).await {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        Ok(self
+            .find_definition(&uri, &name)
+            .await
+            .map(|(u, def)| GotoDefinitionResponse::Scalar(Location::new(u, def.span.into()))))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let params = params.text_document_position_params;
+        let uri = params.text_document.uri;
+        let (name, span) = match self.get_atom(&uri, params.position).await {
+            Some(atom) => atom,
+            None => return Ok(None),
+        };
+        let value = match self.find_definition(&uri, &name).await {
+            Some((u, def)) => format!(
+                "```scheme\n{}\n```\n{} defined in {}",
+                def.signature,
+                def.kind,
+                u.path()
+            ),
+            None => match self.env.get_doc(&name) {
+                Some(doc) => format!("**{}**\n\n{:?}", name, doc),
+                None => return Ok(None),
+            },
+        };
+        Ok(Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(span.into()),
+        }))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let params = params.text_document_position;
+        let uri = params.text_document.uri;
+        let prefix = match self.documents.read().await.get(&uri) {
+            Some(d) => d.prefix_at(params.position.into()),
+            None => return Ok(None),
+        };
+
+        let mut items: Vec<CompletionItem> = vec![];
+        for (_, d) in self.with_loaded(&uri).await {
+            for def in d.definitions {
+                if def.name.starts_with(&prefix) && !items.iter().any(|i| i.label == def.name) {
+                    items.push(CompletionItem {
+                        label: def.name,
+                        kind: Some(def.kind.into()),
+                        detail: Some(def.signature),
+                        ..Default::default()
+                    })
+                }
+            }
+        }
+        let mut symbols: Vec<&String> = self
+            .env
+            .symbols()
+            .iter()
+            .filter(|s| s.starts_with(&prefix))
+            .collect();
+        symbols.sort();
+        for symbol in symbols {
+            items.push(CompletionItem {
+                label: symbol.clone(),
+                kind: Some(CompletionItemKind::FUNCTION),
+                detail: self.env.get_doc(symbol).map(|d| d.to_string()),
+                ..Default::default()
+            })
+        }
+        Ok(Some(CompletionResponse::Array(items)))
+    }
+}
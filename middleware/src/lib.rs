@@ -1,7 +1,7 @@
 extern crate core;
 
 use crate::logger::{
-    EndSignal, FileDescriptor, LogClient, LogMessage, LogTopicId, Logger, END_SIGNAL,
+    EndSignal, FileDescriptor, LogClient, LogMessage, LogStream, LogTopicId, Logger, END_SIGNAL,
 };
 use chrono::{DateTime, Local};
 use env_param::EnvParam;
@@ -413,6 +413,15 @@ impl Master {
 
     //pub async fn get_log_topic_id() {}
 
+    /// Returns a stream of the lines written in the log topic, if it exists.
+    pub async fn stream_log_topic(topic: impl Display) -> Option<LogStream> {
+        MASTER.logger.stream_log_topic(topic).await
+    }
+
+    pub async fn get_log_topics() -> Vec<String> {
+        MASTER.logger.get_topic_labels().await
+    }
+
     pub async fn start_display_log_topic(topic: impl Display) {
         MASTER.logger.start_display_log_topic(topic).await;
     }
@@ -18,6 +18,7 @@ use tokio::sync::{broadcast, mpsc, RwLock};
 use tokio::time::sleep;
 
 const DEFAULT_MAX_LOG_LEVEL: Level = Level::Info;
+const LOG_STREAM_SIZE: usize = 1024;
 pub const END_SIGNAL: EndSignal = EndSignal {};
 pub const PROCESS_LOGGER: &str = "__PROCESS_LOGGER__";
 
@@ -50,6 +51,7 @@ pub struct Logger {
     max_log_level: Arc<RwLock<Level>>,
     end_receiver: Arc<broadcast::Receiver<EndSignal>>,
     sender_log: Arc<mpsc::UnboundedSender<LogMessage>>,
+    log_stream: broadcast::Sender<(LogTopicId, Arc<String>)>,
 }
 
 impl Logger {
@@ -58,6 +60,7 @@ impl Logger {
 
         let (tx, rx) = mpsc::unbounded_channel();
         let (tx_end_logger, rx_end) = broadcast::channel(TOKIO_CHANNEL_SIZE);
+        let (log_stream, _) = broadcast::channel(LOG_STREAM_SIZE);
 
         let logger = Self {
             collection: Default::default(),
@@ -67,6 +70,7 @@ impl Logger {
             max_log_level: Arc::new(RwLock::new(DEFAULT_MAX_LOG_LEVEL)),
             end_receiver: Arc::new(rx_end),
             sender_log: Arc::new(tx),
+            log_stream,
         };
 
         let logger2 = logger.clone();
@@ -90,9 +94,9 @@ impl Logger {
 
     async fn log_to_file(&self, message: LogMessage) {
         if self.enabled(message.level).await {
-            let topic = message.topic;
+            let topic_id = message.topic;
             let mut topics = self.collection.inner.write().await;
-            if let Some(topic) = topics.get_mut(&topic) {
+            if let Some(topic) = topics.get_mut(&topic_id) {
                 let string = format!(
                     "[{:^.3},{:^16}] {:^6}: {}\n",
                     self.system_start.elapsed().unwrap().as_secs_f64(),
@@ -102,6 +106,8 @@ impl Logger {
                 );
 
                 topic.file.write_all(string.as_bytes()).expect("");
+                //No error if no one is streaming the topic.
+                let _ = self.log_stream.send((topic_id, Arc::new(string)));
             } else {
                 drop(topics);
                 let topic_id = self.subscribe_to_topic(LOG_TOPIC_ROOT).await;
@@ -236,6 +242,24 @@ impl Logger {
         }
     }
 
+    pub(crate) async fn stream_log_topic(&self, topic: impl Display) -> Option<LogStream> {
+        let topic = self.get_topic_id(topic).await?;
+        Some(LogStream {
+            topic,
+            receiver: self.log_stream.subscribe(),
+        })
+    }
+
+    pub(crate) async fn get_topic_labels(&self) -> Vec<String> {
+        self.collection
+            .topic_id
+            .read()
+            .await
+            .keys()
+            .cloned()
+            .collect()
+    }
+
     pub(crate) async fn stop_display_log_topic(&self, topic: impl Display) {
         if let Some(topic) = self.get_topic_id(topic).await {
             let mut topics = self.collection.inner.write().await;
@@ -358,6 +382,26 @@ impl Logger {
     }
 }
 
+/// Receives the lines written in a log topic from the moment it has been created.
+pub struct LogStream {
+    topic: LogTopicId,
+    receiver: broadcast::Receiver<(LogTopicId, Arc<String>)>,
+}
+
+impl LogStream {
+    /// Returns the next line of the topic.
+    /// Lines missed because the stream lagged behind are skipped.
+    pub async fn recv(&mut self) -> Option<Arc<String>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok((topic, line)) if topic == self.topic => return Some(line),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct LogTopicCollection {
     inner: Arc<RwLock<HashMap<LogTopicId, LogTopic>>>,
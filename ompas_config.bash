@@ -22,9 +22,37 @@ export OMPAS_CHRONICLE_DEBUG=full
 # Frequency at which continuous planning will be updated
 export OMPAS_DELIBERATION_FREQUENCY=1
 
+# maximum number of iterations of loops and recursive calls unrolled when converting methods into chronicles
+export OMPAS_UNROLL_BOUND=3
+
+# source of time of the acting engine
+# - real-time: wall-clock time
+# - virtual: discrete-event time, jumping to the next pending timer when every task is blocked
+export OMPAS_CLOCK_MODE=real-time
+# period (in µs) during which the runtime must stay idle before the virtual time advances
+export OMPAS_VIRTUAL_CLOCK_QUIESCENCE=500
+
+# record the trace of the execution (jobs, state updates, commands and decisions) in trace.jsonl of the run directory
+export OMPAS_TRACE=false
+
+# file in which the statistics on the executions of the methods are saved by the learning select mode
+export OMPAS_LEARNING_FILE=/tmp/ompas_learning.json
+
+# interrupt the tasks holding a resource awaited by a task of higher priority, and retry them afterwards
+export OMPAS_PREEMPTION=false
+
+# resolution of the deadlocks between acquisitions: none (the acquisitions keep waiting), fail (the youngest acquisition fails) or preempt (its task is retried)
+export OMPAS_DEADLOCK_POLICY=none
+
+# resolution of the divergences between the planned conditions and effects and the observed state: none, replan or retry (the top-level task is retried)
+export OMPAS_DIVERGENCE_POLICY=none
+
 # print the plan formatted for the acting tree
 export OMPAS_PLAN_OUTPUT=true
 
+# repair the previous plan in continuous planning: only the tasks affected by the updates are replanned
+export OMPAS_PLAN_REPAIR=false
+
 # print the continuous planning deliberation phases
 export OMPAS_DEBUG_CONTINUOUS_PLANNING=true
 
@@ -3,6 +3,7 @@ use ompas_middleware::{LogLevel, Master};
 use sompas_modules::io::LogOutput;
 use sompas_modules::ModExtendedStd;
 use sompas_repl::lisp_interpreter::{LispInterpreter, LispInterpreterConfig};
+use sompas_repl::server::{ReplServerAddress, ReplServerConfig, REPL_TOKEN_ENV_VAR};
 use std::fs;
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -18,6 +19,18 @@ struct Opt {
 
     #[structopt(short = "r", long = "root")]
     root: bool,
+
+    /// Serves remote sessions on a tcp address (127.0.0.1:8257) or a unix socket (unix:/tmp/scheme.sock).
+    #[structopt(long = "listen")]
+    listen: Option<ReplServerAddress>,
+
+    /// Token of the remote sessions, read from SOMPAS_REPL_TOKEN if not given.
+    #[structopt(long = "token", requires = "listen")]
+    token: Option<String>,
+
+    /// Does not read expressions on stdin, the interpreter being only driven by remote sessions.
+    #[structopt(long = "headless", requires = "listen")]
+    headless: bool,
 }
 
 #[tokio::main]
@@ -31,10 +44,28 @@ async fn main() {
     }
 
     //test_lib_model(&opt);
-    lisp_interpreter(opt.log, opt.root).await;
+    let server = opt.listen.map(|address| {
+        let token = opt
+            .token
+            .or_else(|| std::env::var(REPL_TOKEN_ENV_VAR).ok())
+            .filter(|token| !token.is_empty())
+            .unwrap_or_else(|| {
+                panic!(
+                    "--listen requires a non-empty --token or {}",
+                    REPL_TOKEN_ENV_VAR
+                )
+            });
+        ReplServerConfig::new(address, token)
+    });
+    lisp_interpreter(opt.log, opt.root, !opt.headless, server).await;
 }
 
-pub async fn lisp_interpreter(log: Option<PathBuf>, root: bool) {
+pub async fn lisp_interpreter(
+    log: Option<PathBuf>,
+    root: bool,
+    repl: bool,
+    server: Option<ReplServerConfig>,
+) {
     let mut li = LispInterpreter::new().await;
 
     if !root {
@@ -49,7 +80,11 @@ pub async fn lisp_interpreter(log: Option<PathBuf>, root: bool) {
         li.import_namespace(mod_extended_std);
     }
 
-    li.set_config(LispInterpreterConfig::new(true));
+    let mut config = LispInterpreterConfig::new(repl);
+    if let Some(server) = server {
+        config = config.with_server(server);
+    }
+    li.set_config(config);
 
     li.run(log.map(|p| FileDescriptor::AbsolutePath(fs::canonicalize(p).unwrap())))
         .await;
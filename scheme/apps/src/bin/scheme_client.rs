@@ -0,0 +1,196 @@
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use sompas_repl::server::{
+    is_complete_expression, ReplServerAddress, END_OF_RESPONSE, ERROR_PREFIX, EXIT_EXPRESSION,
+    INFO_PREFIX, LOG_PREFIX, QUIT_COMMAND, REPL_TOKEN_ENV_VAR, RESULT_PREFIX,
+};
+use std::process;
+use structopt::StructOpt;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, UnixStream};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Scheme client",
+    about = "Client of the remote REPL of a running Scheme or OMPAS interpreter"
+)]
+struct Opt {
+    /// Address of the server: a tcp address (127.0.0.1:8257) or a unix socket (unix:/tmp/ompas.sock).
+    address: ReplServerAddress,
+
+    /// Token of the session, read from SOMPAS_REPL_TOKEN if not given.
+    #[structopt(short = "t", long = "token")]
+    token: Option<String>,
+
+    /// Log topics streamed during the session.
+    #[structopt(short = "l", long = "log")]
+    log: Vec<String>,
+
+    /// Expressions evaluated in order before exiting, instead of starting the interactive mode.
+    #[structopt(short = "e", long = "eval")]
+    eval: Vec<String>,
+}
+
+type Reader = Box<dyn AsyncRead + Unpin + Send>;
+type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+#[tokio::main]
+async fn main() {
+    let opt: Opt = Opt::from_args();
+    let token = opt
+        .token
+        .clone()
+        .or_else(|| std::env::var(REPL_TOKEN_ENV_VAR).ok())
+        .unwrap_or_else(|| {
+            eprintln!(
+                "a token is required, with --token or {}",
+                REPL_TOKEN_ENV_VAR
+            );
+            process::exit(1)
+        });
+
+    let (reader, mut writer) = connect(&opt.address).await.unwrap_or_else(|e| {
+        eprintln!("could not connect to {}: {}", opt.address, e);
+        process::exit(1)
+    });
+    let mut responses = spawn_reader(reader);
+
+    if !request(&mut writer, &mut responses, format!(":auth {}", token)).await {
+        process::exit(1)
+    }
+    for topic in &opt.log {
+        request(&mut writer, &mut responses, format!(":log {}", topic)).await;
+    }
+
+    if !opt.eval.is_empty() {
+        let mut success = true;
+        for expr in &opt.eval {
+            if !is_complete_expression(expr) {
+                eprintln!("incomplete expression: {}", expr);
+                process::exit(1)
+            }
+            success &= request(&mut writer, &mut responses, expr.to_string()).await;
+        }
+        let _ = send(&mut writer, format!(":{}", QUIT_COMMAND)).await;
+        process::exit(if success { 0 } else { 1 })
+    }
+
+    repl(writer, responses).await;
+}
+
+async fn connect(address: &ReplServerAddress) -> std::io::Result<(Reader, Writer)> {
+    Ok(match address {
+        ReplServerAddress::Tcp(addr) => {
+            let (r, w) = TcpStream::connect(addr).await?.into_split();
+            (Box::new(r), Box::new(w))
+        }
+        ReplServerAddress::Unix(path) => {
+            let (r, w) = UnixStream::connect(path).await?.into_split();
+            (Box::new(r), Box::new(w))
+        }
+    })
+}
+
+/// Prints the lines received from the server.
+/// For each complete response, sends on the channel whether it contained no error.
+fn spawn_reader(reader: Reader) -> UnboundedReceiver<bool> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        let mut success = true;
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line == END_OF_RESPONSE {
+                if tx.send(success).is_err() {
+                    break;
+                }
+                success = true;
+            } else if let Some(l) = line.strip_prefix(RESULT_PREFIX) {
+                println!("LI>> {}", l);
+            } else if let Some(l) = line.strip_prefix(ERROR_PREFIX) {
+                success = false;
+                eprintln!("{}", l);
+            } else if let Some(l) = line
+                .strip_prefix(INFO_PREFIX)
+                .or_else(|| line.strip_prefix(LOG_PREFIX))
+            {
+                println!("{}", l);
+            } else {
+                println!("{}", line);
+            }
+        }
+    });
+    rx
+}
+
+async fn send(writer: &mut Writer, msg: String) -> std::io::Result<()> {
+    writer.write_all(msg.as_bytes()).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await
+}
+
+/// Sends the request and waits for its response, exiting if the connection is closed.
+async fn request(
+    writer: &mut Writer,
+    responses: &mut UnboundedReceiver<bool>,
+    msg: String,
+) -> bool {
+    let received = match send(writer, msg).await {
+        Ok(()) => responses.recv().await,
+        Err(_) => None,
+    };
+    match received {
+        Some(success) => success,
+        None => {
+            eprintln!("connection closed by the server");
+            process::exit(1)
+        }
+    }
+}
+
+async fn repl(mut writer: Writer, mut responses: UnboundedReceiver<bool>) {
+    let mut rl = DefaultEditor::new().unwrap();
+    let mut buffer = String::new();
+    loop {
+        let prompt = if buffer.is_empty() { ">> " } else { ".. " };
+        match rl.readline(prompt) {
+            Ok(line) => {
+                if buffer.is_empty() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let _ = rl.add_history_entry(line.as_str());
+                    if line.trim() == format!(":{}", QUIT_COMMAND) || line.trim() == EXIT_EXPRESSION
+                    {
+                        break;
+                    }
+                    if line.trim().starts_with(':') {
+                        request(&mut writer, &mut responses, line).await;
+                        continue;
+                    }
+                } else {
+                    buffer.push('\n');
+                }
+                buffer.push_str(&line);
+                if is_complete_expression(&buffer) {
+                    let expr = std::mem::take(&mut buffer);
+                    request(&mut writer, &mut responses, expr).await;
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                println!("CTRL-C");
+                break;
+            }
+            Err(ReadlineError::Eof) => {
+                println!("CTRL-D");
+                break;
+            }
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
+            }
+        }
+    }
+    let _ = send(&mut writer, format!(":{}", QUIT_COMMAND)).await;
+}
@@ -0,0 +1,292 @@
+//! Step debugger of the evaluator.
+//!
+//! Evaluations pause before a procedure call when a breakpoint is set on the name of the procedure,
+//! or when their environment matches a filter to which the debugger is attached.
+//! A paused evaluation waits for the commands of a frontend, as the REPL, that can inspect its
+//! bindings and call stack, and resume it step by step, or interrupt it.
+use crate::structs::EvalStack;
+use lazy_static::lazy_static;
+use sompas_structs::lenv::LEnv;
+use sompas_structs::lruntimeerror;
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalue::LValue;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::{mpsc, oneshot, Notify};
+
+lazy_static! {
+    /// Debugger shared by all the evaluations of the process.
+    pub static ref DEBUGGER: Debugger = Default::default();
+}
+
+/// Predicate on the environment of an evaluation, used to attach the debugger to it.
+pub type EnvFilter = Arc<dyn Fn(&LEnv) -> bool + Send + Sync>;
+
+/// Way a paused evaluation is resumed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Pauses at the next procedure call.
+    In,
+    /// Pauses at the next procedure call that is not nested in the current one.
+    Over,
+    /// Pauses at the next procedure call once the current lambda has returned.
+    Out,
+    /// Runs until the next breakpoint.
+    Continue,
+}
+
+pub enum DebugCommand {
+    Resume(Step),
+    Interrupt,
+    Lookup(String, oneshot::Sender<Option<LValue>>),
+}
+
+/// Evaluation paused before the call of *expr*.
+/// *stack* contains the procedures of the lambdas being evaluated, from the outermost call,
+/// and *bindings* the parameters and local definitions of the current lambda call.
+#[derive(Clone)]
+pub struct PausedEval {
+    pub id: usize,
+    pub reason: String,
+    pub expr: LValue,
+    pub stack: Vec<LValue>,
+    pub bindings: Vec<(String, LValue)>,
+    tx: UnboundedSender<DebugCommand>,
+}
+
+impl PausedEval {
+    fn send(&self, command: DebugCommand) -> Result<(), LRuntimeError> {
+        self.tx.send(command).map_err(|_| {
+            lruntimeerror!(
+                "debugger",
+                format!("evaluation #{} is not paused anymore", self.id)
+            )
+        })
+    }
+
+    pub fn resume(&self, step: Step) -> Result<(), LRuntimeError> {
+        self.send(DebugCommand::Resume(step))?;
+        DEBUGGER.remove_paused(self.id);
+        Ok(())
+    }
+
+    /// Resumes the evaluation, that returns an interruption error instead of continuing.
+    pub fn interrupt(&self) -> Result<(), LRuntimeError> {
+        self.send(DebugCommand::Interrupt)?;
+        DEBUGGER.remove_paused(self.id);
+        Ok(())
+    }
+
+    /// Returns the value of the symbol in the environment of the paused evaluation.
+    pub async fn lookup(&self, symbol: impl Into<String>) -> Result<Option<LValue>, LRuntimeError> {
+        let (tx, rx) = oneshot::channel();
+        self.send(DebugCommand::Lookup(symbol.into(), tx))?;
+        rx.await
+            .map_err(|_| lruntimeerror!("debugger", "evaluation resumed during the lookup"))
+    }
+}
+
+impl Display for PausedEval {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} paused before {} ({})",
+            self.id, self.expr, self.reason
+        )
+    }
+}
+
+struct Attachment {
+    label: String,
+    filter: EnvFilter,
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    active: AtomicBool,
+    next_id: AtomicUsize,
+    breakpoints: RwLock<HashSet<String>>,
+    attachments: Mutex<Vec<Attachment>>,
+    paused: Mutex<Vec<PausedEval>>,
+    notify: Notify,
+}
+
+impl Debugger {
+    /// Returns true if a breakpoint or an attachment is set.
+    /// Evaluations that are not stepping only check the debugger when it is active.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    fn update_active(&self) {
+        let active = !self.breakpoints.read().unwrap().is_empty()
+            || !self.attachments.lock().unwrap().is_empty();
+        self.active.store(active, Ordering::Relaxed);
+    }
+
+    pub fn add_breakpoint(&self, name: impl Into<String>) {
+        self.breakpoints.write().unwrap().insert(name.into());
+        self.update_active();
+    }
+
+    /// Removes a breakpoint. Returns false if no breakpoint was set on the name.
+    pub fn remove_breakpoint(&self, name: &str) -> bool {
+        let removed = self.breakpoints.write().unwrap().remove(name);
+        self.update_active();
+        removed
+    }
+
+    pub fn clear_breakpoints(&self) {
+        self.breakpoints.write().unwrap().clear();
+        self.update_active();
+    }
+
+    pub fn breakpoints(&self) -> Vec<String> {
+        let mut breakpoints: Vec<String> =
+            self.breakpoints.read().unwrap().iter().cloned().collect();
+        breakpoints.sort();
+        breakpoints
+    }
+
+    /// Attaches the debugger to the next evaluation calling a procedure in an environment
+    /// matching the filter. The attachment is removed once an evaluation has paused on it.
+    pub fn attach(&self, label: impl Into<String>, filter: EnvFilter) {
+        self.attachments.lock().unwrap().push(Attachment {
+            label: label.into(),
+            filter,
+        });
+        self.update_active();
+    }
+
+    /// Removes an attachment that has not been hit yet. Returns false if there was none.
+    pub fn detach(&self, label: &str) -> bool {
+        let mut attachments = self.attachments.lock().unwrap();
+        let n = attachments.len();
+        attachments.retain(|a| a.label != label);
+        let removed = attachments.len() != n;
+        drop(attachments);
+        self.update_active();
+        removed
+    }
+
+    pub fn attachments(&self) -> Vec<String> {
+        self.attachments
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|a| a.label.clone())
+            .collect()
+    }
+
+    /// Returns the paused evaluations, from the first one to have paused.
+    pub fn paused(&self) -> Vec<PausedEval> {
+        self.paused.lock().unwrap().clone()
+    }
+
+    /// Waits until an evaluation is paused, and returns the first one to have paused.
+    pub async fn wait_paused(&self) -> PausedEval {
+        loop {
+            if let Some(p) = self.paused.lock().unwrap().first() {
+                return p.clone();
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Returns the reason to pause before the call of the procedure, if any.
+    fn breakpoint_hit(&self, proc: &LValue, env: &LEnv) -> Option<String> {
+        let name = proc.to_string();
+        if self.breakpoints.read().unwrap().contains(&name) {
+            return Some(format!("breakpoint on {}", name));
+        }
+        let mut attachments = self.attachments.lock().unwrap();
+        let index = attachments.iter().position(|a| (a.filter)(env))?;
+        let attachment = attachments.remove(index);
+        drop(attachments);
+        self.update_active();
+        Some(format!("attached to {}", attachment.label))
+    }
+
+    fn add_paused(&self, paused: PausedEval) {
+        self.paused.lock().unwrap().push(paused);
+        self.notify.notify_one();
+    }
+
+    fn remove_paused(&self, id: usize) {
+        self.paused.lock().unwrap().retain(|p| p.id != id);
+    }
+}
+
+/// State of the debugger local to an evaluation.
+/// *step* is the way the evaluation has been resumed, with the call depth at which it was paused.
+#[derive(Default)]
+pub(crate) struct EvalDebugger {
+    step: Option<(Step, usize)>,
+    pub(crate) interrupted: bool,
+}
+
+impl EvalDebugger {
+    pub fn is_enabled(&self) -> bool {
+        self.step.is_some() || DEBUGGER.is_active()
+    }
+
+    /// Pauses the evaluation before the call if it is stepping or hits a breakpoint,
+    /// and waits for a command resuming it.
+    pub async fn on_call(&mut self, call: &[LValue], queue: &EvalStack, env: &LEnv) {
+        let depth = queue.call_depth();
+        let reason = match self.step {
+            Some((Step::In, _)) => Some("step in".to_string()),
+            Some((Step::Over, d)) if depth <= d => Some("step over".to_string()),
+            Some((Step::Out, d)) if depth < d => Some("step out".to_string()),
+            _ => None,
+        };
+        if let Some(reason) = reason.or_else(|| DEBUGGER.breakpoint_hit(&call[0], env)) {
+            self.pause(reason, call.into(), queue.call_stack(), env, depth)
+                .await
+        }
+    }
+
+    async fn pause(
+        &mut self,
+        reason: String,
+        expr: LValue,
+        stack: Vec<LValue>,
+        env: &LEnv,
+        depth: usize,
+    ) {
+        let (tx, mut rx): (_, UnboundedReceiver<DebugCommand>) = mpsc::unbounded_channel();
+        let id = DEBUGGER.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut bindings: Vec<(String, LValue)> = env.local_bindings().into_iter().collect();
+        bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        DEBUGGER.add_paused(PausedEval {
+            id,
+            reason,
+            expr,
+            stack,
+            bindings,
+            tx,
+        });
+
+        let step = loop {
+            match rx.recv().await {
+                Some(DebugCommand::Lookup(symbol, reply)) => {
+                    let _ = reply.send(env.get_symbol(&symbol));
+                }
+                Some(DebugCommand::Resume(step)) => break step,
+                Some(DebugCommand::Interrupt) => {
+                    self.interrupted = true;
+                    break Step::Continue;
+                }
+                None => break Step::Continue,
+            }
+        };
+        DEBUGGER.remove_paused(id);
+        self.step = match step {
+            Step::Continue => None,
+            step => Some((step, depth)),
+        };
+    }
+}
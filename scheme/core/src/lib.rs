@@ -1,11 +1,13 @@
 extern crate core;
 
+use crate::debugger::EvalDebugger;
 use crate::modules::ModStd;
 #[cfg(not(feature = "opt"))]
 use crate::structs::LDebug;
 use crate::structs::{
-    BeginFrame, CoreOperatorFrame, DefineFrame, DoFrame, EvalStack, IfFrame, Interruptibility,
-    ProcedureFrame, Results, ScopeCollection, StackFrame, StackKind, Unstack,
+    BeginFrame, CoreOperatorFrame, DefineFrame, DoFrame, EvalStack, FinallyEndFrame, FinallyFrame,
+    ForFrame, IfFrame, Interruptibility, LambdaFrame, Mark, ProcedureFrame, Results,
+    ScopeCollection, StackFrame, StackKind, TryFrame, Unstack, WhileFrame,
 };
 use anyhow::anyhow;
 use aries_planning::parsing::sexpr::SExpr;
@@ -17,9 +19,10 @@ use sompas_language::primitives::*;
 use sompas_language::{primitives, FALSE};
 use sompas_structs::kindlvalue::KindLValue;
 use sompas_structs::lasynchandler::LAsyncHandle;
-use sompas_structs::lenv::{ImportType, LEnv};
+use sompas_structs::lenv::{ImportSelection, ImportType, LEnv};
 use sompas_structs::lfuture::{FutureResult, LFuture};
 use sompas_structs::llambda::{LLambda, LambdaArgs};
+use sompas_structs::lmodule::LModule;
 use sompas_structs::lnumber::LNumber;
 use sompas_structs::lprimitive::LPrimitive;
 use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
@@ -34,6 +37,7 @@ use std::ops::Deref;
 use std::sync::Arc;
 use std::time::SystemTime;
 
+pub mod debugger;
 pub mod modules;
 pub mod structs;
 pub mod test_utils;
@@ -391,8 +395,148 @@ pub async fn expand(x: &LValue, top_level: bool, env: &mut LEnv) -> LResult {
                             Ok(expanded.into())
                         }
                     }
+                    LPrimitive::While => {
+                        return if list.len() < 2 {
+                            Err(LRuntimeError::wrong_number_of_args(
+                                EXPAND,
+                                list.as_slice(),
+                                2..usize::MAX,
+                            )
+                            .chain(format!("{} must have at least a condition.", WHILE)))
+                        } else {
+                            let mut expanded = vec![LPrimitive::While.into()];
+                            for e in &list[1..] {
+                                expanded.push(expand(e, false, env).await?);
+                            }
+                            Ok(expanded.into())
+                        }
+                    }
+                    LPrimitive::For => {
+                        let well_formed = matches!(list.get(1), Some(LValue::Symbol(_)))
+                            && match list.get(2) {
+                                Some(LValue::Symbol(s)) if s.as_str() == FOR_IN => list.len() >= 4,
+                                Some(LValue::Symbol(s)) if s.as_str() == FOR_FROM => {
+                                    list.len() >= 6
+                                        && matches!(&list[4], LValue::Symbol(s) if s.as_str() == FOR_TO)
+                                }
+                                _ => false,
+                            };
+                        return if !well_formed {
+                            Err(anyhow!(
+                                "{}: expected (for x in <list> body...) or (for i from <start> to <end> body...)",
+                                x
+                            )
+                            .into())
+                        } else {
+                            let mut expanded = vec![LPrimitive::For.into()];
+                            for e in &list[1..] {
+                                expanded.push(expand(e, false, env).await?);
+                            }
+                            Ok(expanded.into())
+                        };
+                    }
+                    LPrimitive::Try => {
+                        if list.len() < 2 {
+                            return Err(LRuntimeError::wrong_number_of_args(
+                                EXPAND,
+                                list.as_slice(),
+                                2..usize::MAX,
+                            )
+                            .chain(format!("{} must have an expression.", TRY)));
+                        }
+                        let mut expanded = vec![LPrimitive::Try.into()];
+                        expanded.push(expand(&list[1], false, env).await?);
+                        let clauses = &list[2..];
+                        for (i, clause) in clauses.iter().enumerate() {
+                            let clause = match clause {
+                                LValue::List(c) => c.as_slice(),
+                                _ => &[],
+                            };
+                            match clause.first() {
+                                Some(LValue::Symbol(s))
+                                    if s.as_str() == TRY_CATCH
+                                        && clause.len() >= 4
+                                        && matches!(clause[2], LValue::Symbol(_)) =>
+                                {
+                                    let handler = match clause.len() {
+                                        4 => clause[3].clone(),
+                                        _ => {
+                                            let mut begin = vec![LPrimitive::Begin.into()];
+                                            begin.extend_from_slice(&clause[3..]);
+                                            begin.into()
+                                        }
+                                    };
+                                    expanded.push(list![
+                                        clause[0].clone(),
+                                        expand(&clause[1], false, env).await?,
+                                        clause[2].clone(),
+                                        expand(&handler, false, env).await?
+                                    ]);
+                                }
+                                Some(LValue::Symbol(s))
+                                    if s.as_str() == TRY_FINALLY && i == clauses.len() - 1 =>
+                                {
+                                    let mut finally = vec![clause[0].clone()];
+                                    for e in &clause[1..] {
+                                        finally.push(expand(e, false, env).await?);
+                                    }
+                                    expanded.push(finally.into());
+                                }
+                                _ => {
+                                    return Err(anyhow!(
+                                        "{}: expected (catch <kind> <var> body...) clauses, and a last (finally body...) clause",
+                                        x
+                                    )
+                                    .into())
+                                }
+                            }
+                        }
+                        return Ok(expanded.into());
+                    }
+                    LPrimitive::Raise => {
+                        return if list.len() != 2 {
+                            Err(wrong_n_args!("expand", list, 2)
+                                .chain(format!("{} must have one arg", RAISE)))
+                        } else {
+                            let mut expanded = vec![LPrimitive::Raise.into()];
+                            expanded.push(expand(&list[1], top_level, env).await?);
+                            Ok(expanded.into())
+                        }
+                    }
+                    LPrimitive::DefSyntax | LPrimitive::DefModule | LPrimitive::Import => {
+                        if !top_level {
+                            return Err(anyhow!("{}: {} only allowed at top level", x, co).into());
+                        }
+                        match co {
+                            LPrimitive::DefSyntax => match list.as_slice() {
+                                [_, LValue::Symbol(sym), rules] => {
+                                    env.add_syntax(sym.to_string(), rules.try_into()?)
+                                }
+                                _ => {
+                                    return Err(anyhow!(
+                                        "{}: expected ({} <symbol> ({} ...))",
+                                        x,
+                                        DEFINE_SYNTAX,
+                                        SYNTAX_RULES
+                                    )
+                                    .into())
+                                }
+                            },
+                            LPrimitive::DefModule => {
+                                let module = define_module(x, env).await?;
+                                env.add_module(module);
+                            }
+                            _ => import(x, env)?,
+                        }
+                        return Ok(LValue::Nil);
+                    }
                 }
             } else if let LValue::Symbol(sym) = &list[0] {
+                if let Some(expanded) = env.get_syntax(sym).map(|r| r.transcribe(x)).transpose()? {
+                    env.log
+                        .trace(format!("In expand: syntax expanded: {}", expanded));
+                    return expand(&expanded, top_level, env).await;
+                }
                 match env.get_macro(sym) {
                     None => {}
                     Some(m) => {
@@ -420,6 +564,276 @@ pub async fn expand(x: &LValue, top_level: bool, env: &mut LEnv) -> LResult {
     }
 }
 
+/// Defines a module from the expression (define-module <name> [(export <symbol>...)] <body>...).
+/// The body is expanded and evaluated in a new layer of the environment, whose definitions, macros
+/// and syntaxes form the module.
+/// The lambdas and the syntaxes of the module capture the definitions of the whole module, so that
+/// they refer to each other whatever the prefix under which they are imported.
+async fn define_module(x: &LValue, env: &LEnv) -> Result<LModule, LRuntimeError> {
+    let list = match x {
+        LValue::List(list) => list.as_slice(),
+        _ => &[],
+    };
+    let label = match list.get(1) {
+        Some(LValue::Symbol(label)) => label.to_string(),
+        _ => {
+            return Err(anyhow!(
+                "{}: expected ({} <symbol> [({} <symbol>...)] body...)",
+                x,
+                DEFINE_MODULE,
+                MODULE_EXPORT
+            )
+            .into())
+        }
+    };
+    let mut body = &list[2..];
+    let exports: Option<Vec<String>> = match body.first() {
+        Some(LValue::List(export)) if matches!(&export[0], LValue::Symbol(s) if s.as_str() == MODULE_EXPORT) =>
+        {
+            body = &body[1..];
+            Some(
+                export[1..]
+                    .iter()
+                    .map(|s| match s {
+                        LValue::Symbol(s) => Ok(s.to_string()),
+                        lv => Err(wrong_type!(DEFINE_MODULE, lv, KindLValue::Symbol)),
+                    })
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        _ => None,
+    };
+
+    let mut m_env = env.clone();
+    m_env.new_local_symbols();
+    for e in body {
+        let e = expand(e, true, &mut m_env).await?;
+        let result = eval(&e, &mut m_env, None).await?;
+        if let LValue::Err(_) = result {
+            return Err(anyhow!("{}: error in the definition of module {}", result, label).into());
+        }
+    }
+
+    let exported = |sym: &String| exports.as_ref().is_none_or(|e| e.contains(sym));
+    let doc = format!("Defined in module {}.", label);
+    let mut module = LModule::new((), &label, format!("Module {} defined in Scheme.", label));
+    let mut defined = vec![];
+    let symbols = m_env.get_captured_symbols();
+    let mut bindings: Vec<(String, LValue)> = m_env
+        .local_bindings()
+        .into_iter()
+        .map(|(sym, lv)| match lv {
+            LValue::Lambda(l) => (
+                sym,
+                LLambda::new(l.get_params(), l.get_body().clone(), symbols.clone()).into(),
+            ),
+            lv => (sym, lv),
+        })
+        .collect();
+    bindings.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (sym, lv) in &bindings {
+        if exported(sym) {
+            module.add_value(sym, lv.clone(), doc.as_str());
+            defined.push(sym.clone());
+        }
+    }
+    let mut macros: Vec<String> = m_env.macros().into_iter().collect();
+    macros.sort();
+    for sym in macros {
+        if !exported(&sym) {
+            continue;
+        }
+        if let Some(m) = m_env.get_macro(&sym) {
+            if env.get_macro(&sym) != Some(m) {
+                module.add_macro_lambda(&sym, m.clone(), doc.as_str());
+                defined.push(sym);
+            }
+        } else if let Some(rules) = m_env.get_syntax(&sym) {
+            if env.get_syntax(&sym) != Some(rules) {
+                //The templates refer to the definitions of the module, exported or not.
+                let mut rules = rules.clone();
+                rules.close_over(bindings.iter().cloned());
+                module.add_syntax(&sym, rules, doc.as_str());
+                defined.push(sym);
+            }
+        }
+    }
+    if let Some(undefined) = exports.iter().flatten().find(|e| !defined.contains(e)) {
+        return Err(anyhow!(
+            "{}: {} is exported but not defined in the module",
+            x,
+            undefined
+        )
+        .into());
+    }
+    Ok(module)
+}
+
+/// Imports a module defined in Scheme from the expression (import <name> <clause>...),
+/// the clauses being (prefix [<prefix>]), (only <symbol>...) and (except <symbol>...).
+fn import(x: &LValue, env: &mut LEnv) -> Result<(), LRuntimeError> {
+    let error = || {
+        anyhow!(
+            "{}: expected ({} <module> [({} [<symbol>])] [({}|{} <symbol>...)])",
+            x,
+            IMPORT,
+            IMPORT_PREFIX,
+            IMPORT_ONLY,
+            IMPORT_EXCEPT
+        )
+        .into()
+    };
+    let list = match x {
+        LValue::List(list) => list.as_slice(),
+        _ => &[],
+    };
+    let label = match list.get(1) {
+        Some(LValue::Symbol(label)) => label.to_string(),
+        _ => return Err(error()),
+    };
+    let module = match env.get_module(&label) {
+        Some(module) => module.clone(),
+        None => return Err(anyhow!("{}: module {} is not defined", x, label).into()),
+    };
+    let mut selection = ImportSelection::default();
+    for clause in &list[2..] {
+        let clause = match clause {
+            LValue::List(clause) => clause.as_slice(),
+            _ => return Err(error()),
+        };
+        let mut symbols = vec![];
+        for s in &clause[1..] {
+            match s {
+                LValue::Symbol(s) => symbols.push(s.to_string()),
+                _ => return Err(error()),
+            }
+        }
+        match &clause[0] {
+            LValue::Symbol(s) if s.as_str() == IMPORT_PREFIX && symbols.len() <= 1 => {
+                selection.prefix = Some(symbols.pop().unwrap_or_else(|| label.clone()))
+            }
+            LValue::Symbol(s) if s.as_str() == IMPORT_ONLY => selection.only = Some(symbols),
+            LValue::Symbol(s) if s.as_str() == IMPORT_EXCEPT => selection.except = symbols,
+            _ => return Err(error()),
+        }
+    }
+    env.import_module(module, ImportType::Select(selection));
+    Ok(())
+}
+
+/// Binds the variable of a for loop to its next element, and pushes the evaluation of the body.
+/// Returns false if there is no element left.
+fn next_for_iteration(
+    mut f: ForFrame,
+    scopes: &mut ScopeCollection,
+    queue: &mut EvalStack,
+    interruptibility: Interruptibility,
+) -> bool {
+    if f.body.is_empty() {
+        return false;
+    }
+    let Some(element) = f.elements.next() else {
+        return false;
+    };
+    scopes.get_last_mut().insert(f.var.as_ref(), element);
+    f.i = 0;
+    let first = f.body[0].clone();
+    queue.push(StackFrame::new(
+        CoreOperatorFrame::ForBody(f),
+        interruptibility,
+    ));
+    queue.push(StackFrame::new_lvalue(first, interruptibility));
+    true
+}
+
+/// Handles an error returned or raised by the body of a try with its first matching catch clause,
+/// the variable of the clause being bound to the content of the error.
+/// If no clause matches, the error is the result of the try.
+fn catch_error(
+    t: TryFrame,
+    error: LValue,
+    raised: bool,
+    scopes: &mut ScopeCollection,
+    queue: &mut EvalStack,
+    interruptibility: Interruptibility,
+) {
+    let content = match &error {
+        LValue::Err(e) => e.deref().clone(),
+        _ => unreachable!("only errors are caught"),
+    };
+    match t.catches.into_iter().find(|c| c.matches(&content)) {
+        Some(c) => {
+            queue.push(StackFrame::new(
+                FinallyFrame {
+                    finally: t.finally,
+                    mark: t.mark,
+                },
+                interruptibility,
+            ));
+            scopes.new_scope();
+            scopes.get_last_mut().insert(c.var.as_ref(), content);
+            queue.push(StackFrame::new(
+                CoreOperatorFrame::CatchEnd,
+                interruptibility,
+            ));
+            queue.push(StackFrame::new_lvalue(c.handler, interruptibility));
+        }
+        None => finally(t.finally, error, raised, queue),
+    }
+}
+
+/// Pushes the uninterruptible evaluation of the finally clause of a try, before its result.
+fn finally(finally: Vec<LValue>, result: LValue, raised: bool, queue: &mut EvalStack) {
+    queue.push(StackFrame::uninterruptible(FinallyEndFrame {
+        n: finally.len(),
+        result,
+        raised,
+    }));
+    queue.push_list(
+        finally
+            .into_iter()
+            .map(|e| StackFrame::new_lvalue(e, Interruptibility::Unininterruptible))
+            .collect(),
+    );
+}
+
+/// Raises an error up to the innermost handler, whose mark is returned,
+/// the results and the scopes being restored as they were when it has been installed.
+/// An error that no handler catches is the result of the evaluation.
+fn raise(
+    error: LValue,
+    results: &mut Results,
+    scopes: &mut ScopeCollection,
+    queue: &mut EvalStack,
+) -> Mark {
+    let handler = match queue.pop_handler() {
+        Some(handler) => handler,
+        None => {
+            results.truncate(0);
+            scopes.revert_to(1);
+            results.push(error);
+            return Mark::default();
+        }
+    };
+    let interruptibility = handler.interruptibily;
+    match handler.kind {
+        StackKind::CoreOperator(CoreOperatorFrame::TryBody(t)) => {
+            let mark = t.mark;
+            results.truncate(mark.results);
+            scopes.revert_to(mark.scopes);
+            catch_error(t, error, true, scopes, queue, interruptibility);
+            mark
+        }
+        StackKind::CoreOperator(CoreOperatorFrame::Finally(f)) => {
+            results.truncate(f.mark.results);
+            scopes.revert_to(f.mark.scopes);
+            finally(f.finally, error, true, queue);
+            f.mark
+        }
+        _ => unreachable!("a handler is the body or the finally clause of a try"),
+    }
+}
+
 /// Expand quasiquote expressions
 pub fn expand_quasi_quote(x: &LValue, env: &LEnv) -> LResult {
     match x {
@@ -483,6 +897,7 @@ pub async fn eval(
     let mut scopes: ScopeCollection = ScopeCollection::new(root_env);
     let mut results: Results = Default::default();
     let mut expression_error: LValue = LValue::Nil;
+    let mut debugger = EvalDebugger::default();
 
     //let mut n = 0;
     let result: LResult = loop {
@@ -496,6 +911,13 @@ pub async fn eval(
             interrupted = r.is_interrupted();
         }
 
+        if let StackKind::NonEvaluated(LValue::List(list)) = &current.kind {
+            if !matches!(list[0], LValue::Primitive(_)) && debugger.is_enabled() {
+                debugger.on_call(list, &queue, scopes.get_last()).await;
+            }
+        }
+        interrupted |= debugger.interrupted;
+
         let interruptibility = current.interruptibily;
 
         if interrupted && interruptibility == Interruptibility::Interruptible {
@@ -516,9 +938,12 @@ pub async fn eval(
                             scopes.revert_scope();
                         }
                         CoreOperatorFrame::Begin(b) => {
-                            let mut r = results.pop_n(b.n);
+                            results.pop_n(b.n);
+                            scopes.revert_scope();
+                            results.push(error.clone());
+                        }
+                        CoreOperatorFrame::BeginEnd => {
                             scopes.revert_scope();
-                            results.push(r.pop().unwrap());
                         }
                         CoreOperatorFrame::Do(_) => {
                             scopes.revert_scope();
@@ -529,7 +954,7 @@ pub async fn eval(
                             results.pop();
                             results.push(error.clone());
                         }
-                        CoreOperatorFrame::Lambda => {
+                        CoreOperatorFrame::Lambda(_) => {
                             scopes.revert_scope();
                         }
                         CoreOperatorFrame::Await => {
@@ -571,6 +996,40 @@ pub async fn eval(
                             results.pop();
                             results.push(error.clone())
                         }
+                        CoreOperatorFrame::While(_)
+                        | CoreOperatorFrame::WhileBody(_)
+                        | CoreOperatorFrame::ForBody(_) => {
+                            results.pop();
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::For(f) => {
+                            results.pop_n(f.n);
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::Try(t) => {
+                            results.pop_n(t.catches.len());
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::TryBody(t) => {
+                            //The error of the interruption is not caught.
+                            results.pop();
+                            finally(t.finally, error.clone(), false, &mut queue);
+                        }
+                        CoreOperatorFrame::CatchEnd => {
+                            scopes.revert_scope();
+                        }
+                        CoreOperatorFrame::Finally(f) => {
+                            results.pop();
+                            finally(f.finally, error.clone(), false, &mut queue);
+                        }
+                        CoreOperatorFrame::FinallyEnd(f) => {
+                            results.pop_n(f.n);
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::Raise => {
+                            results.pop();
+                            results.push(error.clone())
+                        }
                     }
                 }
             }
@@ -658,7 +1117,7 @@ pub async fn eval(
                                     results.push(LValue::Lambda(LLambda::new(
                                         params,
                                         body.clone(),
-                                        scopes.get_last().get_symbols(),
+                                        scopes.get_last().get_captured_symbols(),
                                     )));
                                 }
                                 LPrimitive::If => {
@@ -686,15 +1145,23 @@ pub async fn eval(
                                 LPrimitive::UnQuote => {
                                     panic!("unquote not allowed")
                                 }
-                                LPrimitive::DefMacro => {
-                                    panic!("defmacro not allowed")
+                                LPrimitive::DefMacro
+                                | LPrimitive::DefSyntax
+                                | LPrimitive::DefModule
+                                | LPrimitive::Import => {
+                                    panic!("{} not allowed", co)
                                 }
                                 LPrimitive::Begin => {
                                     scopes.new_scope();
-                                    let stack = BeginFrame { n: args.len() };
+                                    let (last, firsts) = args.split_last().unwrap();
+                                    let stack = BeginFrame {
+                                        n: firsts.len(),
+                                        last: last.clone(),
+                                    };
                                     queue.push(StackFrame::new(stack, interruptibility));
                                     queue.push_list(
-                                        args.iter()
+                                        firsts
+                                            .iter()
                                             .map(|a| {
                                                 StackFrame::new_lvalue(a.clone(), interruptibility)
                                             })
@@ -714,6 +1181,88 @@ pub async fn eval(
                                         interruptibility,
                                     ));
                                 }
+                                LPrimitive::While => {
+                                    let stack = WhileFrame {
+                                        cond: args[0].clone(),
+                                        body: args[1..].to_vec(),
+                                        i: 0,
+                                    };
+                                    queue.push(StackFrame::new(stack, interruptibility));
+                                    queue.push(StackFrame::new_lvalue(
+                                        args[0].clone(),
+                                        interruptibility,
+                                    ));
+                                }
+                                LPrimitive::For => {
+                                    let var = match &args[0] {
+                                        LValue::Symbol(s) => s.clone(),
+                                        lve => {
+                                            let err =
+                                                Err(wrong_type!("eval", &lve, KindLValue::Symbol));
+                                            expression_error = StackFrame::new(
+                                                StackKind::NonEvaluated(lv),
+                                                current.interruptibily,
+                                            )
+                                            .unstack(&mut results);
+                                            break err;
+                                        }
+                                    };
+                                    let (bounds, body) = match &args[1] {
+                                        LValue::Symbol(s) if s.as_str() == FOR_IN => {
+                                            (vec![args[2].clone()], args[3..].to_vec())
+                                        }
+                                        _ => (
+                                            vec![args[2].clone(), args[4].clone()],
+                                            args[5..].to_vec(),
+                                        ),
+                                    };
+                                    let stack = ForFrame {
+                                        var,
+                                        n: bounds.len(),
+                                        elements: Default::default(),
+                                        body,
+                                        i: 0,
+                                    };
+                                    queue.push(StackFrame::new(stack, interruptibility));
+                                    queue.push_list(
+                                        bounds
+                                            .into_iter()
+                                            .map(|b| StackFrame::new_lvalue(b, interruptibility))
+                                            .collect(),
+                                    );
+                                }
+                                LPrimitive::Try => {
+                                    let t = match TryFrame::new(args) {
+                                        Ok(t) => t,
+                                        Err(e) => {
+                                            expression_error = StackFrame::new(
+                                                StackKind::NonEvaluated(lv),
+                                                current.interruptibily,
+                                            )
+                                            .unstack(&mut results);
+                                            break Err(e);
+                                        }
+                                    };
+                                    let kinds = t
+                                        .catches
+                                        .iter()
+                                        .map(|c| {
+                                            StackFrame::new_lvalue(c.kind.clone(), interruptibility)
+                                        })
+                                        .collect();
+                                    queue.push(StackFrame::new(t, interruptibility));
+                                    queue.push_list(kinds);
+                                }
+                                LPrimitive::Raise => {
+                                    queue.push(StackFrame::new(
+                                        CoreOperatorFrame::Raise,
+                                        interruptibility,
+                                    ));
+                                    queue.push(StackFrame::new_lvalue(
+                                        args[0].clone(),
+                                        interruptibility,
+                                    ));
+                                }
                                 LPrimitive::Async => {
                                     let result =
                                         async_eval(args[0].clone(), scopes.get_last().clone());
@@ -825,11 +1374,7 @@ pub async fn eval(
                                 }
                             }
                             //debug_f.println("core_op");
-                        } else if scopes
-                            .get_last()
-                            .get_macro(proc.to_string().as_str())
-                            .is_some()
-                        {
+                        } else if scopes.get_last().is_macro(proc.to_string().as_str()) {
                             queue.push(StackFrame::new(CoreOperatorFrame::Eval, interruptibility));
                             queue
                                 .push(StackFrame::new(CoreOperatorFrame::Expand, interruptibility));
@@ -837,7 +1382,10 @@ pub async fn eval(
                         } else {
                             scopes.new_scope();
                             queue.push(StackFrame::new(
-                                ProcedureFrame { n: list.len() },
+                                ProcedureFrame {
+                                    n: list.len(),
+                                    label: proc.clone(),
+                                },
                                 interruptibility,
                             ));
                             queue.push_list(
@@ -866,12 +1414,22 @@ pub async fn eval(
                                 break Err(e);
                             }
                         };
-                        queue.push(StackFrame::new(CoreOperatorFrame::Lambda, interruptibility));
+                        scopes.revert_scope();
+                        //Tail call: the frames that would only revert their scope once the lambda
+                        //returns are dropped, so the stack does not grow.
+                        for _ in 0..queue.pop_tail_frames(interruptibility) {
+                            scopes.revert_scope();
+                        }
+                        queue.push(StackFrame::new(
+                            LambdaFrame {
+                                label: pro.label.clone(),
+                            },
+                            interruptibility,
+                        ));
                         queue.push(StackFrame::new_lvalue(
                             l.get_body().clone(),
                             interruptibility,
                         ));
-                        scopes.revert_scope();
                         scopes.new_defined_scope(temp_env);
                     }
                     LValue::Fn(fun) => {
@@ -971,12 +1529,149 @@ pub async fn eval(
                         }
                     }
                 }
-                CoreOperatorFrame::Begin(b) => {
+                CoreOperatorFrame::While(w) => {
+                    let result = results.pop().unwrap();
+                    match result {
+                        LValue::True if w.body.is_empty() => {
+                            tokio::task::yield_now().await;
+                            let cond = w.cond.clone();
+                            queue.push(StackFrame::new(w, interruptibility));
+                            queue.push(StackFrame::new_lvalue(cond, interruptibility));
+                        }
+                        LValue::True => {
+                            let next = w.body[0].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::WhileBody(w),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        }
+                        LValue::Nil => {
+                            results.push(LValue::Nil);
+                        }
+                        lv => {
+                            let e = wrong_type!("eval", &lv, KindLValue::Bool);
+                            let mut list = vec![LPrimitive::While.into(), lv];
+                            list.append(&mut w.body.clone());
+                            expression_error = list.into();
+                            break Err(e.chain("while condition must return a boolean."));
+                        }
+                    }
+                }
+                CoreOperatorFrame::WhileBody(mut w) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        results.push(result);
+                    } else {
+                        w.i += 1;
+                        if w.i < w.body.len() {
+                            let next = w.body[w.i].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::WhileBody(w),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        } else {
+                            //Gives a chance to other tasks to run, and to interrupt the loop.
+                            tokio::task::yield_now().await;
+                            w.i = 0;
+                            let cond = w.cond.clone();
+                            queue.push(StackFrame::new(w, interruptibility));
+                            queue.push(StackFrame::new_lvalue(cond, interruptibility));
+                        }
+                    }
+                }
+                CoreOperatorFrame::For(mut f) => {
+                    let mut values = results.pop_n(f.n);
+                    if let Err(e) = f.set_elements(&values) {
+                        let mut list = vec![LPrimitive::For.into(), LValue::Symbol(f.var)];
+                        list.append(&mut values);
+                        list.append(&mut f.body);
+                        expression_error = list.into();
+                        break Err(e.chain("for expects a list, or integer bounds."));
+                    }
+                    if !next_for_iteration(f, &mut scopes, &mut queue, interruptibility) {
+                        results.push(LValue::Nil);
+                    }
+                }
+                CoreOperatorFrame::ForBody(mut f) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        results.push(result);
+                    } else {
+                        f.i += 1;
+                        if f.i < f.body.len() {
+                            let next = f.body[f.i].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::ForBody(f),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        } else {
+                            //Gives a chance to other tasks to run, and to interrupt the loop.
+                            tokio::task::yield_now().await;
+                            if !next_for_iteration(f, &mut scopes, &mut queue, interruptibility) {
+                                results.push(LValue::Nil);
+                            }
+                        }
+                    }
+                }
+                CoreOperatorFrame::Try(mut t) => {
+                    let kinds = results.pop_n(t.catches.len());
+                    for (c, kind) in t.catches.iter_mut().zip(kinds) {
+                        c.kind = kind;
+                    }
+                    t.mark = Mark {
+                        results: results.len(),
+                        scopes: scopes.len(),
+                    };
+                    let body = std::mem::take(&mut t.body);
+                    queue.push(StackFrame::new(
+                        CoreOperatorFrame::TryBody(t),
+                        interruptibility,
+                    ));
+                    queue.push(StackFrame::new_lvalue(body, interruptibility));
+                }
+                CoreOperatorFrame::TryBody(t) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        catch_error(t, result, false, &mut scopes, &mut queue, interruptibility);
+                    } else {
+                        finally(t.finally, result, false, &mut queue);
+                    }
+                }
+                CoreOperatorFrame::CatchEnd => {
                     scopes.revert_scope();
-                    let mut r = results.pop_n(b.n);
-                    results.push(r.pop().unwrap());
                 }
-                CoreOperatorFrame::Lambda => {
+                CoreOperatorFrame::Finally(f) => {
+                    let result = results.pop().unwrap();
+                    finally(f.finally, result, false, &mut queue);
+                }
+                CoreOperatorFrame::FinallyEnd(f) => {
+                    results.pop_n(f.n);
+                    if f.raised {
+                        raise(f.result, &mut results, &mut scopes, &mut queue);
+                    } else {
+                        results.push(f.result);
+                    }
+                }
+                CoreOperatorFrame::Raise => {
+                    let error = match results.pop().unwrap() {
+                        LValue::Err(e) => LValue::Err(e),
+                        lv => LValue::Err(lv.into_ref()),
+                    };
+                    raise(error, &mut results, &mut scopes, &mut queue);
+                }
+                CoreOperatorFrame::Begin(b) => {
+                    results.pop_n(b.n);
+                    //The last expression is evaluated in tail position.
+                    queue.push(StackFrame::new(
+                        CoreOperatorFrame::BeginEnd,
+                        interruptibility,
+                    ));
+                    queue.push(StackFrame::new_lvalue(b.last, interruptibility));
+                }
+                CoreOperatorFrame::Lambda(_) => {
                     scopes.revert_scope();
                 }
                 CoreOperatorFrame::Await => {
@@ -1076,7 +1771,8 @@ pub async fn eval(
                 }
                 CoreOperatorFrame::EvalEnd
                 | CoreOperatorFrame::EnrEnd
-                | CoreOperatorFrame::IfEnd => {
+                | CoreOperatorFrame::IfEnd
+                | CoreOperatorFrame::BeginEnd => {
                     scopes.revert_scope();
                 }
             },
@@ -1120,9 +1816,10 @@ pub async fn eval(
     let mut scopes: ScopeCollection = ScopeCollection::new(root_env);
     let mut results: Results = Default::default();
     let mut expression_error: LValue = LValue::Nil;
+    let mut debugger = EvalDebugger::default();
 
     let result: LResult = loop {
-        let current = match queue.pop() {
+        let mut current = match queue.pop() {
             Some(lv) => lv,
             None => break Ok(results.pop().unwrap()),
         };
@@ -1134,6 +1831,13 @@ pub async fn eval(
             }
         }
 
+        if let StackKind::NonEvaluated(LValue::List(list)) = &current.kind {
+            if !matches!(list[0], LValue::Primitive(_)) && debugger.is_enabled() {
+                debugger.on_call(list, &queue, scopes.get_last()).await;
+            }
+        }
+        interrupted |= debugger.interrupted;
+
         let interruptibility = current.interruptibily;
 
         if interrupted && interruptibility == Interruptibility::Interruptible {
@@ -1155,9 +1859,12 @@ pub async fn eval(
                             scopes.revert_scope();
                         }
                         CoreOperatorFrame::Begin(b) => {
-                            let mut r = results.pop_n(b.n);
+                            results.pop_n(b.n);
+                            scopes.revert_scope();
+                            results.push(error.clone());
+                        }
+                        CoreOperatorFrame::BeginEnd => {
                             scopes.revert_scope();
-                            results.push(r.pop().unwrap());
                         }
                         CoreOperatorFrame::Do(_) => {
                             scopes.revert_scope();
@@ -1168,7 +1875,7 @@ pub async fn eval(
                             results.pop();
                             results.push(error.clone());
                         }
-                        CoreOperatorFrame::Lambda => {
+                        CoreOperatorFrame::Lambda(_) => {
                             scopes.revert_scope();
                         }
                         CoreOperatorFrame::Await => {
@@ -1210,6 +1917,43 @@ pub async fn eval(
                             results.pop();
                             results.push(error.clone())
                         }
+                        CoreOperatorFrame::While(_)
+                        | CoreOperatorFrame::WhileBody(_)
+                        | CoreOperatorFrame::ForBody(_) => {
+                            results.pop();
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::For(f) => {
+                            results.pop_n(f.n);
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::Try(t) => {
+                            results.pop_n(t.catches.len());
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::TryBody(t) => {
+                            //The error of the interruption is not caught.
+                            results.pop();
+                            finally(t.finally, error.clone(), false, &mut queue);
+                            continue;
+                        }
+                        CoreOperatorFrame::CatchEnd => {
+                            scopes.revert_scope();
+                            continue;
+                        }
+                        CoreOperatorFrame::Finally(f) => {
+                            results.pop();
+                            finally(f.finally, error.clone(), false, &mut queue);
+                            continue;
+                        }
+                        CoreOperatorFrame::FinallyEnd(f) => {
+                            results.pop_n(f.n);
+                            results.push(error.clone())
+                        }
+                        CoreOperatorFrame::Raise => {
+                            results.pop();
+                            results.push(error.clone())
+                        }
                     }
                     debug.log_last_result(&results);
                 }
@@ -1296,7 +2040,7 @@ pub async fn eval(
                                     results.push(LValue::Lambda(LLambda::new(
                                         params,
                                         body.clone(),
-                                        scopes.get_last().get_symbols(),
+                                        scopes.get_last().get_captured_symbols(),
                                     )));
                                     debug.log_last_result(&results);
                                 }
@@ -1327,15 +2071,23 @@ pub async fn eval(
                                 LPrimitive::UnQuote => {
                                     panic!("unquote not allowed")
                                 }
-                                LPrimitive::DefMacro => {
-                                    panic!("defmacro not allowed")
+                                LPrimitive::DefMacro
+                                | LPrimitive::DefSyntax
+                                | LPrimitive::DefModule
+                                | LPrimitive::Import => {
+                                    panic!("{} not allowed", co)
                                 }
                                 LPrimitive::Begin => {
                                     scopes.new_scope();
-                                    let stack = BeginFrame { n: args.len() };
+                                    let (last, firsts) = args.split_last().unwrap();
+                                    let stack = BeginFrame {
+                                        n: firsts.len(),
+                                        last: last.clone(),
+                                    };
                                     queue.push(StackFrame::new(stack, interruptibility));
                                     queue.push_list(
-                                        args.iter()
+                                        firsts
+                                            .iter()
                                             .map(|a| {
                                                 StackFrame::new_lvalue(a.clone(), interruptibility)
                                             })
@@ -1355,6 +2107,80 @@ pub async fn eval(
                                         interruptibility,
                                     ));
                                 }
+                                LPrimitive::While => {
+                                    let stack = WhileFrame {
+                                        cond: args[0].clone(),
+                                        body: args[1..].to_vec(),
+                                        i: 0,
+                                    };
+                                    queue.push(StackFrame::new(stack, interruptibility));
+                                    queue.push(StackFrame::new_lvalue(
+                                        args[0].clone(),
+                                        interruptibility,
+                                    ));
+                                }
+                                LPrimitive::For => {
+                                    let var = match &args[0] {
+                                        LValue::Symbol(s) => s.clone(),
+                                        lve => {
+                                            let err =
+                                                Err(wrong_type!("eval", &lve, KindLValue::Symbol));
+                                            expression_error = current.unstack(&mut results);
+                                            break err;
+                                        }
+                                    };
+                                    let (bounds, body) = match &args[1] {
+                                        LValue::Symbol(s) if s.as_str() == FOR_IN => {
+                                            (vec![args[2].clone()], args[3..].to_vec())
+                                        }
+                                        _ => (
+                                            vec![args[2].clone(), args[4].clone()],
+                                            args[5..].to_vec(),
+                                        ),
+                                    };
+                                    let stack = ForFrame {
+                                        var,
+                                        n: bounds.len(),
+                                        elements: Default::default(),
+                                        body,
+                                        i: 0,
+                                    };
+                                    queue.push(StackFrame::new(stack, interruptibility));
+                                    queue.push_list(
+                                        bounds
+                                            .into_iter()
+                                            .map(|b| StackFrame::new_lvalue(b, interruptibility))
+                                            .collect(),
+                                    );
+                                }
+                                LPrimitive::Try => {
+                                    let t = match TryFrame::new(args) {
+                                        Ok(t) => t,
+                                        Err(e) => {
+                                            expression_error = current.unstack(&mut results);
+                                            break Err(e);
+                                        }
+                                    };
+                                    let kinds = t
+                                        .catches
+                                        .iter()
+                                        .map(|c| {
+                                            StackFrame::new_lvalue(c.kind.clone(), interruptibility)
+                                        })
+                                        .collect();
+                                    queue.push(StackFrame::new(t, interruptibility));
+                                    queue.push_list(kinds);
+                                }
+                                LPrimitive::Raise => {
+                                    queue.push(StackFrame::new(
+                                        CoreOperatorFrame::Raise,
+                                        interruptibility,
+                                    ));
+                                    queue.push(StackFrame::new_lvalue(
+                                        args[0].clone(),
+                                        interruptibility,
+                                    ));
+                                }
                                 LPrimitive::Async => {
                                     let result =
                                         async_eval(args[0].clone(), scopes.get_last().clone());
@@ -1512,11 +2338,7 @@ pub async fn eval(
                                     debug.log_last_result(&results);
                                 }
                             }
-                        } else if scopes
-                            .get_last()
-                            .get_macro(proc.to_string().as_str())
-                            .is_some()
-                        {
+                        } else if scopes.get_last().is_macro(proc.to_string().as_str()) {
                             queue.push(StackFrame::new(CoreOperatorFrame::Eval, interruptibility));
                             queue
                                 .push(StackFrame::new(CoreOperatorFrame::Expand, interruptibility));
@@ -1528,7 +2350,10 @@ pub async fn eval(
                         } else {
                             scopes.new_scope();
                             queue.push(StackFrame::new(
-                                ProcedureFrame { n: list.len() },
+                                ProcedureFrame {
+                                    n: list.len(),
+                                    label: proc.clone(),
+                                },
                                 interruptibility,
                             ));
                             queue.push_list(
@@ -1557,12 +2382,24 @@ pub async fn eval(
                                 break Err(e);
                             }
                         };
-                        queue.push(StackFrame::new(CoreOperatorFrame::Lambda, interruptibility));
+                        scopes.revert_scope();
+                        //Tail call: the frames that would only revert their scope once the lambda
+                        //returns are dropped, so the stack does not grow.
+                        let n = queue.pop_tail_frames(interruptibility);
+                        for _ in 0..n {
+                            scopes.revert_scope();
+                        }
+                        debug.drop_tail_frames(n);
+                        queue.push(StackFrame::new(
+                            LambdaFrame {
+                                label: pro.label.clone(),
+                            },
+                            interruptibility,
+                        ));
                         queue.push(StackFrame::new_lvalue(
                             l.get_body().clone(),
                             interruptibility,
                         ));
-                        scopes.revert_scope();
                         scopes.new_defined_scope(temp_env);
                     }
                     LValue::Fn(fun) => {
@@ -1669,13 +2506,158 @@ pub async fn eval(
                         }
                     }
                 }
-                CoreOperatorFrame::Begin(b) => {
+                CoreOperatorFrame::While(w) => {
+                    let result = results.pop().unwrap();
+                    match result {
+                        LValue::True if w.body.is_empty() => {
+                            tokio::task::yield_now().await;
+                            let cond = w.cond.clone();
+                            queue.push(StackFrame::new(w, interruptibility));
+                            queue.push(StackFrame::new_lvalue(cond, interruptibility));
+                        }
+                        LValue::True => {
+                            let next = w.body[0].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::WhileBody(w),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        }
+                        LValue::Nil => {
+                            results.push(LValue::Nil);
+                            debug.log_last_result(&results);
+                        }
+                        lv => {
+                            let e = wrong_type!("eval", &lv, KindLValue::Bool);
+                            let mut list = vec![LPrimitive::While.into(), lv];
+                            list.append(&mut w.body.clone());
+                            expression_error = list.into();
+                            break Err(e.chain("while condition must return a boolean."));
+                        }
+                    }
+                }
+                CoreOperatorFrame::WhileBody(mut w) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        results.push(result);
+                        debug.log_last_result(&results);
+                    } else {
+                        w.i += 1;
+                        if w.i < w.body.len() {
+                            let next = w.body[w.i].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::WhileBody(w),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        } else {
+                            //Gives a chance to other tasks to run, and to interrupt the loop.
+                            tokio::task::yield_now().await;
+                            w.i = 0;
+                            let cond = w.cond.clone();
+                            queue.push(StackFrame::new(w, interruptibility));
+                            queue.push(StackFrame::new_lvalue(cond, interruptibility));
+                        }
+                    }
+                }
+                CoreOperatorFrame::For(mut f) => {
+                    let mut values = results.pop_n(f.n);
+                    if let Err(e) = f.set_elements(&values) {
+                        let mut list = vec![LPrimitive::For.into(), LValue::Symbol(f.var)];
+                        list.append(&mut values);
+                        list.append(&mut f.body);
+                        expression_error = list.into();
+                        break Err(e.chain("for expects a list, or integer bounds."));
+                    }
+                    if !next_for_iteration(f, &mut scopes, &mut queue, interruptibility) {
+                        results.push(LValue::Nil);
+                        debug.log_last_result(&results);
+                    }
+                }
+                CoreOperatorFrame::ForBody(mut f) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        results.push(result);
+                        debug.log_last_result(&results);
+                    } else {
+                        f.i += 1;
+                        if f.i < f.body.len() {
+                            let next = f.body[f.i].clone();
+                            queue.push(StackFrame::new(
+                                CoreOperatorFrame::ForBody(f),
+                                interruptibility,
+                            ));
+                            queue.push(StackFrame::new_lvalue(next, interruptibility));
+                        } else {
+                            //Gives a chance to other tasks to run, and to interrupt the loop.
+                            tokio::task::yield_now().await;
+                            if !next_for_iteration(f, &mut scopes, &mut queue, interruptibility) {
+                                results.push(LValue::Nil);
+                                debug.log_last_result(&results);
+                            }
+                        }
+                    }
+                }
+                CoreOperatorFrame::Try(mut t) => {
+                    let kinds = results.pop_n(t.catches.len());
+                    for (c, kind) in t.catches.iter_mut().zip(kinds) {
+                        c.kind = kind;
+                    }
+                    t.mark = Mark {
+                        results: results.len(),
+                        scopes: scopes.len(),
+                        debug: debug.len(),
+                    };
+                    let body = std::mem::take(&mut t.body);
+                    queue.push(StackFrame::new(
+                        CoreOperatorFrame::TryBody(t),
+                        interruptibility,
+                    ));
+                    queue.push(StackFrame::new_lvalue(body, interruptibility));
+                }
+                CoreOperatorFrame::TryBody(t) => {
+                    let result = results.pop().unwrap();
+                    if matches!(result, LValue::Err(_)) {
+                        catch_error(t, result, false, &mut scopes, &mut queue, interruptibility);
+                    } else {
+                        finally(t.finally, result, false, &mut queue);
+                    }
+                }
+                CoreOperatorFrame::CatchEnd => {
                     scopes.revert_scope();
-                    let mut r = results.pop_n(b.n);
-                    results.push(r.pop().unwrap());
-                    debug.log_last_result(&results);
                 }
-                CoreOperatorFrame::Lambda => {
+                CoreOperatorFrame::Finally(f) => {
+                    let result = results.pop().unwrap();
+                    finally(f.finally, result, false, &mut queue);
+                }
+                CoreOperatorFrame::FinallyEnd(f) => {
+                    results.pop_n(f.n);
+                    if f.raised {
+                        let mark = raise(f.result, &mut results, &mut scopes, &mut queue);
+                        debug.truncate(mark.debug);
+                    } else {
+                        results.push(f.result);
+                        debug.log_last_result(&results);
+                    }
+                }
+                CoreOperatorFrame::Raise => {
+                    let error = match results.pop().unwrap() {
+                        LValue::Err(e) => LValue::Err(e),
+                        lv => LValue::Err(lv.into_ref()),
+                    };
+                    let mark = raise(error, &mut results, &mut scopes, &mut queue);
+                    debug.truncate(mark.debug);
+                }
+                CoreOperatorFrame::Begin(b) => {
+                    results.pop_n(b.n);
+                    //The last expression is evaluated in tail position.
+                    queue.push(StackFrame::new(
+                        CoreOperatorFrame::BeginEnd,
+                        interruptibility,
+                    ));
+                    queue.push(StackFrame::new_lvalue(b.last, interruptibility));
+                }
+                CoreOperatorFrame::Lambda(_) => {
                     scopes.revert_scope();
                     debug.log_last_result(&results);
                 }
@@ -1782,7 +2764,8 @@ pub async fn eval(
                 }
                 CoreOperatorFrame::EvalEnd
                 | CoreOperatorFrame::EnrEnd
-                | CoreOperatorFrame::IfEnd => {
+                | CoreOperatorFrame::IfEnd
+                | CoreOperatorFrame::BeginEnd => {
                     scopes.revert_scope();
                     debug.log_last_result(&results);
                 }
@@ -1836,3 +2819,312 @@ impl DebugF {
         let _ = stdout().flush();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn eval_str(expr: &str) -> LResult {
+        let mut env = get_root_env().await;
+        let lv = parse(expr, &mut env).await?;
+        eval(&lv, &mut env, None).await
+    }
+
+    #[tokio::test]
+    async fn test_while() -> Result<(), LRuntimeError> {
+        let result = eval_str(
+            "(begin
+                (define i 0)
+                (define acc 0)
+                (while (< i 5)
+                    (define acc (+ acc i))
+                    (define i (+ i 1)))
+                acc)",
+        )
+        .await?;
+        assert_eq!(result, LValue::from(10));
+
+        let result = eval_str("(while true (err 1))").await?;
+        assert!(matches!(result, LValue::Err(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_while_interruption() -> Result<(), LRuntimeError> {
+        let mut env = get_root_env().await;
+        let lv = parse("(while true nil)", &mut env).await?;
+        let mut handle = async_eval(lv, env);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(handle.interrupt().await?, interrupted!());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_debugger() -> Result<(), LRuntimeError> {
+        use crate::debugger::{Step, DEBUGGER};
+        let mut env = get_root_env().await;
+        let lv = parse(
+            "(begin
+                (define debugged (lambda (x) (+ x 1)))
+                (debugged 1))",
+            &mut env,
+        )
+        .await?;
+        DEBUGGER.add_breakpoint("debugged");
+        let handle = async_eval(lv, env);
+
+        let paused = DEBUGGER.wait_paused().await;
+        DEBUGGER.remove_breakpoint("debugged");
+        assert_eq!(paused.expr.to_string(), "(debugged 1)");
+        assert!(paused.stack.is_empty());
+        paused.resume(Step::In)?;
+
+        let paused = DEBUGGER.wait_paused().await;
+        assert_eq!(paused.expr.to_string(), "(+ x 1)");
+        assert_eq!(paused.stack, vec![LValue::from("debugged")]);
+        assert_eq!(paused.bindings, vec![("x".to_string(), LValue::from(1))]);
+        assert_eq!(paused.lookup("x").await?, Some(LValue::from(1)));
+        paused.resume(Step::Continue)?;
+
+        assert_eq!(handle.get_future().await?, LValue::from(2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_for() -> Result<(), LRuntimeError> {
+        let result = eval_str(
+            "(begin
+                (define acc nil)
+                (for x in '(1 2 3)
+                    (define acc (cons x acc)))
+                acc)",
+        )
+        .await?;
+        assert_eq!(result, LValue::from(vec![3, 2, 1]));
+
+        let result = eval_str(
+            "(begin
+                (define acc 0)
+                (for i from 1 to 4
+                    (define acc (+ acc i)))
+                acc)",
+        )
+        .await?;
+        assert_eq!(result, LValue::from(10));
+
+        //The values of a range are produced lazily: a huge range is not built before iterating.
+        let result = eval_str(
+            "(try
+                (for i from 0 to 9223372036854775807
+                    (if (= i 3) (raise 'stop)))
+                (catch 'stop e e))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from("stop"));
+
+        assert!(eval_str("(for x '(1 2 3) x)").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tail_call() -> Result<(), LRuntimeError> {
+        let result = eval_str(
+            "(begin
+                (define f (lambda (n)
+                    (if (= n 0) 'done (f (- n 1)))))
+                (f 1000000))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from("done"));
+
+        //tail positions of begin and do
+        let result = eval_str(
+            "(begin
+                (define f (lambda (n acc)
+                    (begin
+                        (define m (- n 1))
+                        (if (= n 0)
+                            acc
+                            (do nil (f m (+ acc 1)))))))
+                (f 100000 0))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from(100000));
+
+        //mutual recursion
+        let result = eval_str(
+            "(begin
+                (define ping (lambda (n) (if (= n 0) 'ping (pong (- n 1)))))
+                (define pong (lambda (n) (if (= n 0) 'pong (ping (- n 1)))))
+                (ping 100000))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from("ping"));
+
+        //inside a spawned evaluation
+        let result = eval_str(
+            "(begin
+                (define f (lambda (n)
+                    (if (= n 0) 'done (f (- n 1)))))
+                (await (async (f 100000))))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from("done"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try() -> Result<(), LRuntimeError> {
+        //The first clause whose kind matches handles the error.
+        let result = eval_str(
+            "(try (begin (raise '(blocked r1)) 1)
+                (catch 'failure e 2)
+                (catch 'blocked e (car (cdr e)))
+                (catch _ e 3))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from("r1"));
+
+        //The errors returned by the body are caught too.
+        let result = eval_str("(try (err 1) (catch 1 e (+ e 1)))").await?;
+        assert_eq!(result, LValue::from(2));
+
+        //The finally clause is evaluated, an error not caught being raised again.
+        let result = eval_str(
+            "(begin
+                (define n 0)
+                (try (try (raise 'blocked) (finally (define n (+ n 1))))
+                    (catch 'blocked e n)))",
+        )
+        .await?;
+        assert_eq!(result, LValue::from(1));
+        let result = eval_str("(try (begin (raise 'blocked) 1) (catch 'failure e 2))").await?;
+        assert_eq!(result, LValue::Err(LValue::from("blocked").into_ref()));
+
+        assert_eq!(eval_str("(unwind-protect 1 2)").await?, LValue::from(1));
+        //The expansion does not depend on a binding of finally.
+        let (_, result) = eval_in_env(&["(define finally 3)", "(unwind-protect 1 2)"]).await?;
+        assert_eq!(result, LValue::from(1));
+        assert!(eval_str("(try 1 (finally 2) (catch _ e 3))").await.is_err());
+        Ok(())
+    }
+
+    /// Evaluates the expressions in the same environment, and returns the environment.
+    async fn eval_in_env(exprs: &[&str]) -> Result<(LEnv, LValue), LRuntimeError> {
+        let mut env = get_root_env().await;
+        let mut result = LValue::Nil;
+        for expr in exprs {
+            let lv = parse(expr, &mut env).await?;
+            result = eval(&lv, &mut env, None).await?;
+        }
+        Ok((env, result))
+    }
+
+    #[tokio::test]
+    async fn test_define_syntax() -> Result<(), LRuntimeError> {
+        let my_or = "(define-syntax my-or
+            (syntax-rules ()
+                ((_) nil)
+                ((_ e) e)
+                ((_ e rest ...) ((lambda (t) (if t t (my-or rest ...))) e))))";
+        //The t bound by the template does not capture the t of the form.
+        let (_, result) = eval_in_env(&[my_or, "(begin (define t 5) (my-or nil t))"]).await?;
+        assert_eq!(result, LValue::from(5));
+
+        let unless = "(define-syntax unless
+            (syntax-rules ()
+                ((_ c body ...) (if c nil (begin body ...)))))";
+        let (_, result) = eval_in_env(&[unless, "(unless (= 1 2) 1 2 3)"]).await?;
+        assert_eq!(result, LValue::from(3));
+
+        assert!(eval_in_env(&[unless, "(unless)"]).await.is_err());
+        assert!(
+            eval_str("(if true (define-syntax f (syntax-rules () ((_) 1))))")
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_modules() -> Result<(), LRuntimeError> {
+        let geometry = "(define-module geometry
+            (export area fact twice sq)
+            (define square (lambda (x) (* x x)))
+            (define area (lambda (r) (* 3 (square r))))
+            (define fact (lambda (n) (if (= n 0) 1 (* n (fact (- n 1))))))
+            (define-syntax twice (syntax-rules () ((_ e) (begin e e))))
+            (define-syntax sq (syntax-rules () ((_ e) (square e)))))";
+        let (env, result) = eval_in_env(&[
+            geometry,
+            "(import geometry (prefix g))",
+            "(list (g::area 2) (g::fact 5) (g::twice 1) (g::sq 3))",
+        ])
+        .await?;
+        assert_eq!(result, list![12.into(), 120.into(), 1.into(), 9.into()]);
+        //The definitions that are not exported are not imported.
+        assert!(env.get_symbol("g::square").is_none());
+        assert!(env.get_symbol("square").is_none());
+        assert!(env.get_symbol("area").is_none());
+        //An exported syntax refers to the private definitions of the module,
+        //and not to the ones where it is used.
+        let (_, result) = eval_in_env(&[
+            geometry,
+            "(import geometry (only sq))",
+            "(define square (lambda (x) x))",
+            "(sq 3)",
+        ])
+        .await?;
+        assert_eq!(result, LValue::from(9));
+
+        let (env, _) = eval_in_env(&[geometry, "(import geometry (only area))"]).await?;
+        assert!(env.get_symbol("area").is_some());
+        assert!(env.get_symbol("fact").is_none());
+        let (env, _) = eval_in_env(&[geometry, "(import geometry (except area) (prefix))"]).await?;
+        assert!(env.get_symbol("geometry::fact").is_some());
+        assert!(env.get_symbol("geometry::area").is_none());
+
+        //Two modules can define the same symbol.
+        let (_, result) = eval_in_env(&[
+            "(define-module a (define go2 (lambda () 'a)))",
+            "(define-module b (define go2 (lambda () 'b)))",
+            "(import a (prefix a))",
+            "(import b (prefix b))",
+            "(list (a::go2) (b::go2))",
+        ])
+        .await?;
+        assert_eq!(result, list!["a".into(), "b".into()]);
+
+        assert!(eval_str("(import geometry)").await.is_err());
+        assert!(eval_str("(define-module m (export f) (define g 1))")
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    static CLEANED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+    fn cleanup(_: &LEnv, _: &[LValue]) -> LResult {
+        CLEANED.store(true, std::sync::atomic::Ordering::SeqCst);
+        Ok(LValue::Nil)
+    }
+
+    #[tokio::test]
+    async fn test_finally_interruption() -> Result<(), LRuntimeError> {
+        let mut env = get_root_env().await;
+        env.insert(
+            "cleanup",
+            LValue::Fn(sompas_structs::function::LFn::new(
+                cleanup,
+                "cleanup".to_string(),
+            )),
+        );
+        let lv = parse("(try (while true nil) (finally (cleanup)))", &mut env).await?;
+        let mut handle = async_eval(lv, env);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(handle.interrupt().await?, interrupted!());
+        assert!(CLEANED.load(std::sync::atomic::Ordering::SeqCst));
+        Ok(())
+    }
+}
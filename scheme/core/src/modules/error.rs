@@ -15,6 +15,7 @@ impl From<ModError> for LModule {
         module.add_macro(DO_T, MACRO_DO_T, DOC_DO_T);
         module.add_fn(IS_ERR, is_err, DOC_IS_ERR, true);
         module.add_fn(IS_INTERRUPTED, is_interrupted, DOC_IS_INTERRUPTED, true);
+        module.add_macro(UNWIND_PROTECT, MACRO_UNWIND_PROTECT, DOC_UNWIND_PROTECT);
         module
     }
 }
@@ -33,6 +33,21 @@ impl From<ModPrimitive> for LModule {
         module.add_doc(INTERRUPTIBLE, DOC_INTERRUPTIBLE, PRIMITIVE);
         module.add_doc(UNINTERRUPTIBLE_SHORT, DOC_UNINTERRUPTIBLE_SHORT, PRIMITIVE);
         module.add_doc(UNINTERRUPTIBLE_SHORT, DOC_INTERRUPTIBLE_SHORT, PRIMITIVE);
+        module.add_doc(WHILE, (DOC_WHILE, DOC_WHILE_VERBOSE), PRIMITIVE);
+        module.add_doc(FOR, (DOC_FOR, DOC_FOR_VERBOSE), PRIMITIVE);
+        module.add_doc(TRY, (DOC_TRY, DOC_TRY_VERBOSE), PRIMITIVE);
+        module.add_doc(RAISE, (DOC_RAISE, DOC_RAISE_VERBOSE), PRIMITIVE);
+        module.add_doc(
+            DEFINE_SYNTAX,
+            (DOC_DEFINE_SYNTAX, DOC_DEFINE_SYNTAX_VERBOSE),
+            PRIMITIVE,
+        );
+        module.add_doc(
+            DEFINE_MODULE,
+            (DOC_DEFINE_MODULE, DOC_DEFINE_MODULE_VERBOSE),
+            PRIMITIVE,
+        );
+        module.add_doc(IMPORT, (DOC_IMPORT, DOC_IMPORT_VERBOSE), PRIMITIVE);
         module
     }
 }
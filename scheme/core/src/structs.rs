@@ -1,12 +1,15 @@
 use ompas_middleware::logger::LogClient;
 #[cfg(not(feature = "opt"))]
 use ompas_middleware::LogLevel::Debug;
+use sompas_language::primitives::{CATCH_ANY, TRY_CATCH, TRY_FINALLY};
 use sompas_structs::lenv::LEnv;
-use sompas_structs::list;
 use sompas_structs::lprimitive::LPrimitive;
+use sompas_structs::lruntimeerror::LRuntimeError;
 use sompas_structs::lvalue::{LValue, Sym};
+use sompas_structs::{list, symbol};
 use std::fmt::Display;
 use std::mem::take;
+use std::ops::RangeInclusive;
 use std::sync::Arc;
 
 pub trait Unstack {
@@ -19,8 +22,12 @@ pub enum Interruptibility {
     Unininterruptible,
 }
 
+/// Frame of a procedure call.
+/// *n* is the number of evaluated elements of the call, the procedure included,
+/// and *label* the unevaluated procedure, as written in the call.
 pub struct ProcedureFrame {
     pub(crate) n: usize,
+    pub(crate) label: LValue,
 }
 
 impl Unstack for ProcedureFrame {
@@ -39,9 +46,10 @@ pub enum CoreOperatorFrame {
     If(IfFrame),
     IfEnd,
     Begin(BeginFrame),
+    BeginEnd,
     Do(DoFrame),
     Define(DefineFrame),
-    Lambda,
+    Lambda(LambdaFrame),
     Await,
     Interrupt,
     Eval,
@@ -50,6 +58,16 @@ pub enum CoreOperatorFrame {
     EnrEnd,
     Expand,
     Parse,
+    While(WhileFrame),
+    WhileBody(WhileFrame),
+    For(ForFrame),
+    ForBody(ForFrame),
+    Try(TryFrame),
+    TryBody(TryFrame),
+    CatchEnd,
+    Finally(FinallyFrame),
+    FinallyEnd(FinallyEndFrame),
+    Raise,
 }
 
 impl Unstack for CoreOperatorFrame {
@@ -67,8 +85,10 @@ impl Unstack for CoreOperatorFrame {
                 let mut r = results.pop_n(b.n);
                 let mut list = vec![LPrimitive::Begin.into()];
                 list.append(&mut r);
+                list.push(take(&mut b.last));
                 list.into()
             }
+            CoreOperatorFrame::BeginEnd => results.pop().unwrap(),
             CoreOperatorFrame::Do(df) => {
                 let mut list = vec![LPrimitive::Do.into()];
                 list.append(&mut df.results);
@@ -83,7 +103,7 @@ impl Unstack for CoreOperatorFrame {
                     results.pop().unwrap()
                 )
             }
-            CoreOperatorFrame::Lambda => results.pop().unwrap(),
+            CoreOperatorFrame::Lambda(_) => results.pop().unwrap(),
             CoreOperatorFrame::Await => {
                 list!(LPrimitive::Await.into(), results.pop().unwrap())
             }
@@ -111,6 +131,77 @@ impl Unstack for CoreOperatorFrame {
             CoreOperatorFrame::IfEnd => {
                 list!(LPrimitive::If.into(), results.pop().unwrap())
             }
+            CoreOperatorFrame::While(w) => {
+                let mut list = vec![LPrimitive::While.into(), results.pop().unwrap()];
+                list.append(&mut w.body);
+                list.into()
+            }
+            CoreOperatorFrame::WhileBody(w) => {
+                let mut list = vec![LPrimitive::While.into(), take(&mut w.cond)];
+                list.append(&mut w.body);
+                list[w.i + 2] = results.pop().unwrap();
+                list.into()
+            }
+            CoreOperatorFrame::For(f) => {
+                let mut list = vec![LPrimitive::For.into(), f.var.clone().into()];
+                list.append(&mut results.pop_n(f.n));
+                list.append(&mut f.body);
+                list.into()
+            }
+            CoreOperatorFrame::ForBody(f) => {
+                let mut list = vec![LPrimitive::For.into(), f.var.clone().into()];
+                list.append(&mut f.elements.remaining());
+                let i = list.len() + f.i;
+                list.append(&mut f.body);
+                list[i] = results.pop().unwrap();
+                list.into()
+            }
+            CoreOperatorFrame::Try(t) => {
+                let kinds = results.pop_n(t.catches.len());
+                for (c, kind) in t.catches.iter_mut().zip(kinds) {
+                    c.kind = kind;
+                }
+                t.as_lvalue()
+            }
+            CoreOperatorFrame::TryBody(t) => {
+                t.body = results.pop().unwrap();
+                t.as_lvalue()
+            }
+            CoreOperatorFrame::CatchEnd => results.pop().unwrap(),
+            CoreOperatorFrame::Finally(f) => {
+                let mut finally = vec![symbol!(TRY_FINALLY.to_string())];
+                finally.append(&mut f.finally);
+                list![
+                    LPrimitive::Try.into(),
+                    results.pop().unwrap(),
+                    finally.into()
+                ]
+            }
+            CoreOperatorFrame::FinallyEnd(f) => {
+                let mut finally = vec![symbol!(TRY_FINALLY.to_string())];
+                finally.append(&mut results.pop_n(f.n));
+                list![LPrimitive::Try.into(), take(&mut f.result), finally.into()]
+            }
+            CoreOperatorFrame::Raise => {
+                list!(LPrimitive::Raise.into(), results.pop().unwrap())
+            }
+        }
+    }
+}
+
+impl CoreOperatorFrame {
+    /// Returns true if the only purpose of the frame is to revert the scope it has opened,
+    /// the result of the expression evaluated on top of it being its own result.
+    /// Such frames can be safely dropped before a tail call.
+    pub fn is_scope_end(&self) -> bool {
+        match self {
+            CoreOperatorFrame::Lambda(_)
+            | CoreOperatorFrame::IfEnd
+            | CoreOperatorFrame::BeginEnd
+            | CoreOperatorFrame::EvalEnd
+            | CoreOperatorFrame::EnrEnd => true,
+            CoreOperatorFrame::Do(df) => df.rest.is_empty(),
+            _ => false,
         }
     }
 }
@@ -132,8 +223,12 @@ impl From<IfFrame> for StackKind {
     }
 }
 
+/// Frame of a begin.
+/// *n* is the number of expressions evaluated before *last*, whose results are discarded.
+/// *last* is evaluated in tail position.
 pub struct BeginFrame {
     pub(crate) n: usize,
+    pub(crate) last: LValue,
 }
 
 impl From<BeginFrame> for StackKind {
@@ -153,6 +248,228 @@ impl From<DoFrame> for StackKind {
     }
 }
 
+/// Frame of a while loop.
+/// *i* is the index of the expression of the body being evaluated.
+pub struct WhileFrame {
+    pub(crate) cond: LValue,
+    pub(crate) body: Vec<LValue>,
+    pub(crate) i: usize,
+}
+
+impl From<WhileFrame> for StackKind {
+    fn from(w: WhileFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::While(w))
+    }
+}
+
+/// Frame of a for loop.
+/// *n* is the number of evaluated arguments: a list, or the two bounds of a range,
+/// *elements* the remaining elements of the iteration,
+/// and *i* the index of the expression of the body being evaluated.
+pub struct ForFrame {
+    pub(crate) var: Arc<Sym>,
+    pub(crate) n: usize,
+    pub(crate) elements: ForElements,
+    pub(crate) body: Vec<LValue>,
+    pub(crate) i: usize,
+}
+
+impl ForFrame {
+    /// Sets the elements of the iteration from the evaluated arguments of the loop.
+    pub fn set_elements(&mut self, values: &[LValue]) -> Result<(), LRuntimeError> {
+        self.elements = match values {
+            [list] => ForElements::List(Vec::<LValue>::try_from(list)?.into_iter()),
+            [start, end] => ForElements::Range(i64::try_from(start)?..=i64::try_from(end)?),
+            _ => unreachable!("a for loop iterates over a list or a range"),
+        };
+        Ok(())
+    }
+}
+
+/// Remaining elements of a for loop.
+/// The values of a range are produced one at a time, so that the loop runs in constant memory.
+pub enum ForElements {
+    List(std::vec::IntoIter<LValue>),
+    Range(RangeInclusive<i64>),
+}
+
+impl ForElements {
+    /// Returns the remaining elements as the evaluated arguments of the loop.
+    pub fn remaining(&self) -> Vec<LValue> {
+        match self {
+            ForElements::List(list) => vec![list.as_slice().into()],
+            ForElements::Range(range) => vec![(*range.start()).into(), (*range.end()).into()],
+        }
+    }
+}
+
+impl Default for ForElements {
+    fn default() -> Self {
+        Self::List(vec![].into_iter())
+    }
+}
+
+impl Iterator for ForElements {
+    type Item = LValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            ForElements::List(list) => list.next(),
+            ForElements::Range(range) => range.next().map(LValue::from),
+        }
+    }
+}
+
+impl From<ForFrame> for StackKind {
+    fn from(f: ForFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::For(f))
+    }
+}
+
+/// Lengths of the stacks of an evaluation when a handler of errors is installed,
+/// to which they are restored when an error is raised up to it.
+#[derive(Default, Copy, Clone)]
+pub struct Mark {
+    pub(crate) results: usize,
+    pub(crate) scopes: usize,
+    #[cfg(not(feature = "opt"))]
+    pub(crate) debug: usize,
+}
+
+/// Catch clause of a try: the kind of the errors it handles, first unevaluated,
+/// the variable bound to the content of the error, and the handler.
+pub struct CatchClause {
+    pub(crate) kind: LValue,
+    pub(crate) var: Arc<Sym>,
+    pub(crate) handler: LValue,
+}
+
+impl CatchClause {
+    /// Returns true if the content of an error is of the kind of the clause.
+    pub fn matches(&self, content: &LValue) -> bool {
+        match (&self.kind, content) {
+            (LValue::Symbol(s), _) if s.as_str() == CATCH_ANY => true,
+            (kind, LValue::List(list)) if list.first() == Some(kind) => true,
+            (kind, content) => kind == content,
+        }
+    }
+}
+
+/// Frame of a try.
+/// The kinds of the catch clauses are evaluated before the body,
+/// *mark* being set once they are.
+pub struct TryFrame {
+    pub(crate) body: LValue,
+    pub(crate) catches: Vec<CatchClause>,
+    pub(crate) finally: Vec<LValue>,
+    pub(crate) mark: Mark,
+}
+
+impl TryFrame {
+    /// Builds the frame from the expanded arguments of a try.
+    pub fn new(args: &[LValue]) -> Result<Self, LRuntimeError> {
+        let mut frame = Self {
+            body: args[0].clone(),
+            catches: vec![],
+            finally: vec![],
+            mark: Default::default(),
+        };
+        for clause in &args[1..] {
+            match clause {
+                LValue::List(c) => match (c.first(), c.get(2)) {
+                    (Some(LValue::Symbol(s)), Some(LValue::Symbol(var)))
+                        if s.as_str() == TRY_CATCH && c.len() == 4 =>
+                    {
+                        frame.catches.push(CatchClause {
+                            kind: c[1].clone(),
+                            var: var.clone(),
+                            handler: c[3].clone(),
+                        })
+                    }
+                    (Some(LValue::Symbol(s)), _) if s.as_str() == TRY_FINALLY => {
+                        frame.finally = c[1..].to_vec()
+                    }
+                    _ => {
+                        return Err(LRuntimeError::new(
+                            "try",
+                            format!("{clause}: malformed clause."),
+                        ))
+                    }
+                },
+                _ => {
+                    return Err(LRuntimeError::new(
+                        "try",
+                        format!("{clause}: malformed clause."),
+                    ))
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    pub fn as_lvalue(&mut self) -> LValue {
+        let mut list = vec![LPrimitive::Try.into(), take(&mut self.body)];
+        for c in &mut self.catches {
+            list.push(list![
+                symbol!(TRY_CATCH.to_string()),
+                take(&mut c.kind),
+                c.var.clone().into(),
+                take(&mut c.handler)
+            ]);
+        }
+        if !self.finally.is_empty() {
+            let mut finally = vec![symbol!(TRY_FINALLY.to_string())];
+            finally.append(&mut self.finally);
+            list.push(finally.into());
+        }
+        list.into()
+    }
+}
+
+impl From<TryFrame> for StackKind {
+    fn from(t: TryFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::Try(t))
+    }
+}
+
+/// Frame of the finally clause of a try, waiting for the result of a catch handler.
+pub struct FinallyFrame {
+    pub(crate) finally: Vec<LValue>,
+    pub(crate) mark: Mark,
+}
+
+impl From<FinallyFrame> for StackKind {
+    fn from(f: FinallyFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::Finally(f))
+    }
+}
+
+/// Frame ending a try once the *n* expressions of its finally clause are evaluated.
+/// Its result is an error raised again if *raised* is true.
+pub struct FinallyEndFrame {
+    pub(crate) n: usize,
+    pub(crate) result: LValue,
+    pub(crate) raised: bool,
+}
+
+impl From<FinallyEndFrame> for StackKind {
+    fn from(f: FinallyEndFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::FinallyEnd(f))
+    }
+}
+
+/// Frame of the body of a lambda being evaluated.
+/// *label* is the procedure of the call, as written in the call.
+pub struct LambdaFrame {
+    pub(crate) label: LValue,
+}
+
+impl From<LambdaFrame> for StackKind {
+    fn from(l: LambdaFrame) -> Self {
+        Self::CoreOperator(CoreOperatorFrame::Lambda(l))
+    }
+}
+
 pub struct DefineFrame {
     pub(crate) symbol: Arc<Sym>,
 }
@@ -272,6 +589,11 @@ impl<'a> ScopeCollection<'a> {
         self.inner.pop();
     }
 
+    /// Reverts the scopes until there are *n* left.
+    pub fn revert_to(&mut self, n: usize) {
+        self.inner.truncate(n - 1);
+    }
+
     pub fn get_last(&self) -> &LEnv {
         if self.inner.is_empty() {
             self.root_env
@@ -315,6 +637,65 @@ impl EvalStack {
     pub fn pop(&mut self) -> Option<StackFrame> {
         self.inner.pop()
     }
+
+    /// Pops the frames on top of the stack that only end a scope, for a tail call.
+    /// Stops at the first frame with a different interruptibility.
+    /// Returns the number of popped frames, i.e. the number of scopes to revert.
+    pub fn pop_tail_frames(&mut self, i: Interruptibility) -> usize {
+        let mut n = 0;
+        while let Some(StackFrame {
+            interruptibily,
+            kind: StackKind::CoreOperator(co),
+        }) = self.inner.last()
+        {
+            if *interruptibily != i || !co.is_scope_end() {
+                break;
+            }
+            self.inner.pop();
+            n += 1;
+        }
+        n
+    }
+
+    /// Pops the frames up to the innermost handler of errors,
+    /// i.e. the body or the finally clause of a try, and returns it.
+    pub fn pop_handler(&mut self) -> Option<StackFrame> {
+        while let Some(f) = self.inner.pop() {
+            if matches!(
+                f.kind,
+                StackKind::CoreOperator(
+                    CoreOperatorFrame::TryBody(_) | CoreOperatorFrame::Finally(_)
+                )
+            ) {
+                return Some(f);
+            }
+        }
+        None
+    }
+
+    /// Returns the number of lambda bodies being evaluated.
+    pub fn call_depth(&self) -> usize {
+        self.inner
+            .iter()
+            .filter(|f| {
+                matches!(
+                    f.kind,
+                    StackKind::CoreOperator(CoreOperatorFrame::Lambda(_))
+                )
+            })
+            .count()
+    }
+
+    /// Returns the procedures of the lambda bodies being evaluated, from the outermost call.
+    pub fn call_stack(&self) -> Vec<LValue> {
+        self.inner
+            .iter()
+            .filter_map(|f| match &f.kind {
+                StackKind::CoreOperator(CoreOperatorFrame::Lambda(l)) => Some(l.label.clone()),
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 #[derive(Default)]
@@ -345,6 +726,10 @@ impl Results {
     pub fn pop_n(&mut self, n: usize) -> Vec<LValue> {
         self.inner.split_off(self.inner.len() - n)
     }
+
+    pub fn truncate(&mut self, n: usize) {
+        self.inner.truncate(n)
+    }
 }
 
 #[derive(Default)]
@@ -376,6 +761,17 @@ impl LDebug {
         self.inner.pop()
     }
 
+    pub fn truncate(&mut self, n: usize) {
+        self.inner.truncate(n)
+    }
+
+    /// Drops the *n* entries below the last one, whose frames have been dropped by a tail call.
+    pub fn drop_tail_frames(&mut self, n: usize) {
+        let last = self.inner.pop();
+        self.inner.truncate(self.inner.len().saturating_sub(n));
+        self.inner.extend(last);
+    }
+
     pub fn log_last_result(&mut self, results: &Results) {
         let debug = self.inner.pop().unwrap();
         let last_result = results.last().unwrap();
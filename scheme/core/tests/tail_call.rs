@@ -0,0 +1,76 @@
+// Memory used by tail-recursive loops.
+// The allocator of this test binary keeps track of the peak of allocated memory,
+// so this file holds a single test to not be disturbed by other ones.
+
+use sompas_core::{eval, get_root_env, parse};
+use sompas_structs::lruntimeerror::LRuntimeError;
+use sompas_structs::lvalue::LValue;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct PeakAllocator {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let current = self.current.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            self.peak.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        self.current.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAllocator = PeakAllocator {
+    current: AtomicUsize::new(0),
+    peak: AtomicUsize::new(0),
+};
+
+/// Returns the result of the evaluation of *expr* in a new environment,
+/// and the peak of memory allocated during the evaluation.
+async fn eval_peak(expr: &str) -> Result<(LValue, usize), LRuntimeError> {
+    let mut env = get_root_env().await;
+    let lv = parse(expr, &mut env).await?;
+    let start = ALLOCATOR.current.load(Ordering::Relaxed);
+    ALLOCATOR.peak.store(start, Ordering::Relaxed);
+    let result = eval(&lv, &mut env, None).await?;
+    let peak = ALLOCATOR.peak.load(Ordering::Relaxed);
+    Ok((result, peak.saturating_sub(start)))
+}
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_tail_call_constant_memory() -> Result<(), LRuntimeError> {
+    //The peak of memory of a long loop is the one of a loop of a thousand iterations.
+    for (lambda, call, n) in [
+        ("(if (= n 0) 'done (f (- n 1)))", "(f {n})", 1_000_000),
+        ("(if (= n 0) 'done (begin nil (f (- n 1))))", "(f {n})", 100_000),
+        ("(if (= n 0) 'done (f (- n 1)))", "(await (async (f {n})))", 100_000),
+    ] {
+        let expr = |n: usize| {
+            let call = call.replace("{n}", &n.to_string());
+            format!(
+                "(begin
+                    (define f (lambda (n) {lambda}))
+                    {call})"
+            )
+        };
+        let (result, small) = eval_peak(&expr(1_000)).await?;
+        assert_eq!(result, LValue::from("done"));
+        let (result, large) = eval_peak(&expr(n)).await?;
+        assert_eq!(result, LValue::from("done"));
+        assert!(
+            large <= small + 64 * 1024,
+            "{lambda}: {small} bytes for 1000 iterations, {large} bytes for {n} iterations"
+        );
+    }
+    Ok(())
+}
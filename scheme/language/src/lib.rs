@@ -85,6 +85,13 @@ pub fn get_primitives() -> Vec<&'static str> {
         INTERRUPTIBLE,
         UNINTERRUPTIBLE_SHORT,
         INTERRUPTIBLE_SHORT,
+        WHILE,
+        FOR,
+        TRY,
+        RAISE,
+        DEFINE_SYNTAX,
+        DEFINE_MODULE,
+        IMPORT,
     ]
 }
 
@@ -185,6 +192,97 @@ pub mod primitives {
 
     pub const ERR: &str = "err";
     pub const DOC_ERR: &str = "Create an LValue::Err.";
+
+    pub const WHILE: &str = "while";
+    pub const DOC_WHILE: &str =
+        "Evaluate a sequence of expressions as long as a condition is true. \
+    Stops at the first expression returning an error and returns it, returns nil otherwise.";
+    pub const DOC_WHILE_VERBOSE: &str = "Example:\n\
+\t>> (begin (define i 0) (while (< i 3) (define i (+ i 1))) i)\n\
+\tLI>> 3";
+
+    pub const FOR: &str = "for";
+    pub const DOC_FOR: &str = "Evaluate a sequence of expressions for each element of a list, \
+    or for each integer of an inclusive range, the variable being bound in the current scope. \
+    Stops at the first expression returning an error and returns it, returns nil otherwise.";
+    pub const DOC_FOR_VERBOSE: &str = "Example:\n\
+\t>> (for x in '(1 2 3) (print x))\n\
+\t>> (for i from 1 to 3 (print i))";
+
+    pub const FOR_IN: &str = "in";
+    pub const FOR_FROM: &str = "from";
+    pub const FOR_TO: &str = "to";
+
+    pub const TRY: &str = "try";
+    pub const DOC_TRY: &str = "Evaluate an expression, and handle the error it returns or raises \
+    with the first catch clause whose kind matches it, the variable being bound to the content \
+    of the error. The kind of a clause matches an error equal to it, or a list starting with it, \
+    and _ matches any error. The expressions of the finally clause are evaluated uninterruptibly \
+    once the try ends, even on interruption. An error that is not caught is returned, or raised \
+    again if it has been raised.";
+    pub const DOC_TRY_VERBOSE: &str = "Example:\n\
+\t>> (try (raise '(blocked r1))\n\
+\t\t(catch 'blocked e (cadr e))\n\
+\t\t(catch _ e nil)\n\
+\t\t(finally (print \"done\")))\n\
+\tdone\n\
+\tLI>> r1";
+
+    pub const TRY_CATCH: &str = "catch";
+    pub const TRY_FINALLY: &str = "finally";
+    pub const CATCH_ANY: &str = "_";
+
+    pub const RAISE: &str = "raise";
+    pub const DOC_RAISE: &str = "Raise an error, stopping the evaluation up to the innermost try. \
+    The error is the result of the evaluation if no try handles it.";
+    pub const DOC_RAISE_VERBOSE: &str = "Example:\n\
+\t>> (begin (raise 'blocked) (print \"unreachable\"))\n\
+\tLI>> (err blocked)";
+
+    pub const DEFINE_SYNTAX: &str = "define-syntax";
+    pub const DOC_DEFINE_SYNTAX: &str = "Define a new hygienic macro from syntax rules, \
+    can only be done at the top level. The form is rewritten with the template of the first \
+    rule whose pattern matches it. The symbols bound by a template are renamed at each expansion, \
+    so that they never capture nor shadow the symbols of the form.";
+    pub const DOC_DEFINE_SYNTAX_VERBOSE: &str = "Example:\n\
+\t>> (define-syntax unless\n\
+\t\t(syntax-rules ()\n\
+\t\t\t((_ c body ...) (if c nil (begin body ...)))))\n\
+\t>> (define-syntax my-or\n\
+\t\t(syntax-rules ()\n\
+\t\t\t((_) nil)\n\
+\t\t\t((_ e rest ...) (let ((t e)) (if t t (my-or rest ...))))))";
+
+    pub const SYNTAX_RULES: &str = "syntax-rules";
+    pub const SYNTAX_ELLIPSIS: &str = "...";
+    pub const SYNTAX_WILDCARD: &str = "_";
+
+    pub const DEFINE_MODULE: &str = "define-module";
+    pub const DOC_DEFINE_MODULE: &str = "Define a new module, can only be done at the top level. \
+    The body is evaluated in its own environment, and its definitions, macros and syntaxes are \
+    not visible until the module is imported. An optional export clause restricts the \
+    definitions that can be imported.";
+    pub const DOC_DEFINE_MODULE_VERBOSE: &str = "Example:\n\
+\t>> (define-module geometry\n\
+\t\t(export area)\n\
+\t\t(define square (lambda (x) (* x x)))\n\
+\t\t(define area (lambda (r) (* 3.14 (square r)))))";
+
+    pub const MODULE_EXPORT: &str = "export";
+
+    pub const IMPORT: &str = "import";
+    pub const DOC_IMPORT: &str = "Import the definitions of a module in the environment, \
+    can only be done at the top level. The clause (prefix <p>) imports them as <p>::<symbol>, \
+    (prefix) using the name of the module, (only <symbol>...) imports only the listed ones and \
+    (except <symbol>...) all but the listed ones.";
+    pub const DOC_IMPORT_VERBOSE: &str = "Example:\n\
+\t>> (import geometry (prefix g) (only area))\n\
+\t>> (g::area 1)\n\
+\tLI>> 3.14";
+
+    pub const IMPORT_PREFIX: &str = "prefix";
+    pub const IMPORT_ONLY: &str = "only";
+    pub const IMPORT_EXCEPT: &str = "except";
 }
 
 pub mod env {
@@ -338,35 +436,6 @@ pub mod utils {
                 ,(cadar exprs)
                 ,(cons cond (cdr exprs))))))";
 
-    pub const FOR: &str = "for";
-    pub const DOC_FOR: &str = "Not yet implemented";
-    /*pub const MACRO_FOR: &str = "(defmacro for (lambda args \
-    (let ((_i_ (get-list args 0)) \
-            (_list_ (get args 2)) \
-            (_body_ (get args 3))) \
-        `(let ((_f_loop_ (lambda args \
-            (if (null? args) \
-                nil \
-                (let ((,_i_ (car args))) \
-                    (begin \
-                        ,_body_ \
-                        (_f_loop_ (cdr args)))))))) \
-            (_f_loop_ ,_list_)))))";*/
-
-    pub const WHILE: &str = "while";
-    pub const DOC_WHILE: &str = "Not yet implemented.";
-    /*pub const MACRO_WHILE: &str = "(defmacro while
-    (lambda (c b)
-        `(begin
-            (define __loop__
-                (lambda nil
-                    (if ,c
-                        (begin
-                            ,b
-                            (__loop__))
-                    nil)))
-            (__loop__))))";*/
-
     pub const LOOP: &str = "loop";
     pub const DOC_LOOP: &str = "Evaluate infinitely an expression.";
     pub const MACRO_LOOP: &str = "(lambda (_body_)
@@ -381,15 +450,24 @@ pub mod utils {
 
     pub const LET: &str = "let";
     pub const DOC_LET: &str = "Abstract variable binding in functional programming.";
-    pub const DOC_LET_VERBOSE: &str = "Example: (let ((a 1) (b 2)) (+ a b)) => 3";
-    pub const MACRO_LET: &str = "(lambda (bindings body)
-        (begin
-            (define unzipped (unzip bindings))
-            (define keys (car unzipped))
-            (define values (cadr unzipped))
-            (cons `(lambda ,keys
-                        ,body)
-                    values)))";
+    pub const DOC_LET_VERBOSE: &str = "Example: (let ((a 1) (b 2)) (+ a b)) => 3\n\
+    Named let, binding a procedure that can be called in the body to loop:\n\
+    (let iter ((i 0) (acc 0)) (if (> i 3) acc (iter (+ i 1) (+ acc i)))) => 6";
+    pub const MACRO_LET: &str = "(lambda args
+        (if (symbol? (car args))
+            (begin
+                (define name (car args))
+                (define unzipped (unzip (cadr args)))
+                `(begin
+                    (define ,name (lambda ,(car unzipped) ,(cons 'begin (cddr args))))
+                    ,(cons name (cadr unzipped))))
+            (begin
+                (define unzipped (unzip (car args)))
+                (define keys (car unzipped))
+                (define values (cadr unzipped))
+                (cons `(lambda ,keys
+                            ,(cadr args))
+                        values))))";
 
     pub const LET_STAR: &str = "let*";
     pub const DOC_LET_STAR: &str = "Abstract variable binding in functional programming.\
@@ -735,6 +813,12 @@ pub mod error {
         "Return true if the expression is the result of an interruption.";
 
     pub const INTERRUPTED: &str = "interrupted";
+
+    pub const UNWIND_PROTECT: &str = "unwind-protect";
+    pub const MACRO_UNWIND_PROTECT: &str = "(lambda args
+    `(try ,(car args) ,(cons 'finally (cdr args))))";
+    pub const DOC_UNWIND_PROTECT: &str = "Evaluate an expression, then the cleanup expressions, \
+    even if the evaluation is interrupted. Shortcut for (try <expr> (finally <cleanup>...)).";
 }
 
 pub mod map {
@@ -890,6 +974,124 @@ pub mod string {
     pub const CONCATENATE: &str = "concatenate";
     pub const DOC_CONCATENATE: &str =
         "Creates a string from a list of LValue by concatening their equivalent in string.";
+
+    pub const STRING_LENGTH: &str = "string-length";
+    pub const DOC_STRING_LENGTH: &str = "Returns the number of characters of a string.";
+
+    pub const STRING_REF: &str = "string-ref";
+    pub const DOC_STRING_REF: &str =
+        "Returns the character of a string at an index, as a string of one character.";
+    pub const DOC_STRING_REF_VERBOSE: &str = "Example:\n\
+\t>> (string-ref \"robot\" 1)\n\
+\tLI>> o";
+
+    pub const SUBSTRING: &str = "substring";
+    pub const DOC_SUBSTRING: &str = "Returns the characters of a string from a start index, \
+    up to an optional end index excluded.";
+    pub const DOC_SUBSTRING_VERBOSE: &str = "Example:\n\
+\t>> (substring \"robot_1\" 0 5)\n\
+\tLI>> robot";
+
+    pub const STRING_SPLIT: &str = "string-split";
+    pub const DOC_STRING_SPLIT: &str = "Splits a string on a separator, or on whitespaces \
+    if no separator is given.";
+    pub const DOC_STRING_SPLIT_VERBOSE: &str = "Example:\n\
+\t>> (string-split \"r1,l1,l2\" \",\")\n\
+\tLI>> (r1 l1 l2)";
+
+    pub const STRING_JOIN: &str = "string-join";
+    pub const DOC_STRING_JOIN: &str = "Joins a list of LValue in a string, \
+    with an optional separator.";
+    pub const DOC_STRING_JOIN_VERBOSE: &str = "Example:\n\
+\t>> (string-join '(r1 l1 l2) \",\")\n\
+\tLI>> r1,l1,l2";
+
+    pub const STRING_TRIM: &str = "string-trim";
+    pub const DOC_STRING_TRIM: &str = "Removes the leading and trailing whitespaces of a string.";
+
+    pub const STRING_UPCASE: &str = "string-upcase";
+    pub const DOC_STRING_UPCASE: &str = "Converts a string to upper case.";
+
+    pub const STRING_DOWNCASE: &str = "string-downcase";
+    pub const DOC_STRING_DOWNCASE: &str = "Converts a string to lower case.";
+
+    pub const STRING_REPLACE: &str = "string-replace";
+    pub const DOC_STRING_REPLACE: &str =
+        "Replaces all the occurrences of a pattern in a string by another string.";
+
+    pub const STRING_CONTAINS: &str = "string-contains?";
+    pub const DOC_STRING_CONTAINS: &str = "Returns true if a string contains another one.";
+
+    pub const STRING_STARTS_WITH: &str = "string-starts-with?";
+    pub const DOC_STRING_STARTS_WITH: &str = "Returns true if a string starts with a prefix.";
+
+    pub const STRING_ENDS_WITH: &str = "string-ends-with?";
+    pub const DOC_STRING_ENDS_WITH: &str = "Returns true if a string ends with a suffix.";
+
+    pub const FORMAT: &str = "format";
+    pub const DOC_FORMAT: &str = "Formats a string, replacing each placeholder {} by the next \
+    argument, and each placeholder {i} by the argument of index i. {{ and }} are escaped braces.";
+    pub const DOC_FORMAT_VERBOSE: &str = "Example:\n\
+\t>> (format \"{} moves to {}, {0} is ready\" r1 l2)\n\
+\tLI>> r1 moves to l2, r1 is ready";
+
+    pub const STRING_TO_NUMBER: &str = "string->number";
+    pub const DOC_STRING_TO_NUMBER: &str = "Parses a number from a string.";
+
+    pub const NUMBER_TO_STRING: &str = "number->string";
+    pub const DOC_NUMBER_TO_STRING: &str = "Converts a number to a string.";
+
+    pub const STRING_TO_SYMBOL: &str = "string->symbol";
+    pub const DOC_STRING_TO_SYMBOL: &str = "Converts a string to a symbol.";
+
+    pub const SYMBOL_TO_STRING: &str = "symbol->string";
+    pub const DOC_SYMBOL_TO_STRING: &str = "Converts a symbol to a string.";
+
+    pub const STRING_TO_LIST: &str = "string->list";
+    pub const DOC_STRING_TO_LIST: &str =
+        "Returns the list of the characters of a string, as strings of one character.";
+
+    pub const LIST_TO_STRING: &str = "list->string";
+    pub const DOC_LIST_TO_STRING: &str =
+        "Creates a string from a list of characters, as strings of one character.";
+
+    pub const CHAR_TO_INTEGER: &str = "char->integer";
+    pub const DOC_CHAR_TO_INTEGER: &str = "Returns the unicode code point of a character.";
+
+    pub const INTEGER_TO_CHAR: &str = "integer->char";
+    pub const DOC_INTEGER_TO_CHAR: &str = "Returns the character of a unicode code point.";
+}
+
+pub mod regex {
+    pub const MOD_REGEX: &str = "regex";
+    pub const DOC_MOD_REGEX: &str = "Collection of functions using regular expressions, \
+    with the syntax of the rust crate regex.";
+
+    pub const REGEX_MATCH: &str = "regex-match?";
+    pub const DOC_REGEX_MATCH: &str =
+        "Returns true if a regular expression matches a part of a string.";
+
+    pub const REGEX_FIND_ALL: &str = "regex-find-all";
+    pub const DOC_REGEX_FIND_ALL: &str =
+        "Returns the list of the parts of a string matched by a regular expression.";
+    pub const DOC_REGEX_FIND_ALL_VERBOSE: &str = "Example:\n\
+\t>> (regex-find-all \"[0-9]+\" \"r1 at 3 12\")\n\
+\tLI>> (1 3 12)";
+
+    pub const REGEX_REPLACE: &str = "regex-replace";
+    pub const DOC_REGEX_REPLACE: &str = "Replaces all the parts of a string matched by a regular \
+    expression. The replacement can refer to the groups of the match with $1, $2...";
+    pub const DOC_REGEX_REPLACE_VERBOSE: &str = "Example:\n\
+\t>> (regex-replace \"([a-z]+)([0-9]+)\" \"r1 r2\" \"$2$1\")\n\
+\tLI>> 1r 2r";
+
+    pub const REGEX_CAPTURES: &str = "regex-captures";
+    pub const DOC_REGEX_CAPTURES: &str = "Returns the list of the groups of the first match \
+    of a regular expression in a string, the first one being the whole match, and nil for a \
+    group that did not participate. Returns nil if there is no match.";
+    pub const DOC_REGEX_CAPTURES_VERBOSE: &str = "Example:\n\
+\t>> (regex-captures \"([a-z]+)([0-9]+)\" \"robot r1\")\n\
+\tLI>> (r1 r 1)";
 }
 
 pub mod advanced_math {
@@ -964,6 +1166,55 @@ pub mod io {
 
     pub const GET_ENV_VAR: &str = "get-env-var";
     pub const DOC_GET_ENV_VAR: &str = "Get the current value of an environment variable.";
+
+    pub const JSON_PARSE: &str = "json-parse";
+    pub const DOC_JSON_PARSE: &str = "Parse a JSON string into a LValue.";
+    pub const DOC_JSON_PARSE_VERBOSE: &str = "Objects are mapped to maps with string keys, arrays to lists, \
+                                             null and false to nil, true to true and numbers to ints or floats.\n\
+                                             As nil is also the empty list, null, false and [] are all parsed as nil, \
+                                             while {} is parsed as an empty map.\n\
+                                             Return an error with the line and column of the first syntax error.";
+
+    pub const JSON_STRINGIFY: &str = "json-stringify";
+    pub const DOC_JSON_STRINGIFY: &str = "Encode a LValue as a JSON string.";
+    pub const DOC_JSON_STRINGIFY_VERBOSE: &str = "Takes the LValue and an optional boolean to pretty print the output.\n\
+                                                 Maps are encoded as objects and need string, symbol or number keys, \
+                                                 lists as arrays, nil as null and symbols as strings.\n\
+                                                 As false and the empty list are nil, they are encoded as null, \
+                                                 and the keys of the maps are encoded as strings.\n\
+                                                 Functions, lambdas and non-finite floats cannot be encoded.";
+
+    pub const JSON_READ: &str = "json-read";
+    pub const DOC_JSON_READ: &str = "Read a JSON file and parse its content into a LValue.";
+
+    pub const JSON_WRITE: &str = "json-write";
+    pub const DOC_JSON_WRITE: &str = "Write a LValue to a file as pretty printed JSON.";
+    pub const DOC_JSON_WRITE_VERBOSE: &str =
+        "Takes two arguments: the name of the file and the LValue.";
+
+    pub const YAML_PARSE: &str = "yaml-parse";
+    pub const DOC_YAML_PARSE: &str = "Parse a YAML string into a LValue.";
+    pub const DOC_YAML_PARSE_VERBOSE: &str = "Mappings are mapped to maps, sequences to lists, \
+                                             null and false to nil, true to true and numbers to ints or floats.\n\
+                                             As nil is also the empty list, null, false and [] are all parsed as nil, \
+                                             while {} is parsed as an empty map.\n\
+                                             Tags are ignored and only the tagged value is kept.\n\
+                                             Return an error with the line and column of the first syntax error.";
+
+    pub const YAML_STRINGIFY: &str = "yaml-stringify";
+    pub const DOC_YAML_STRINGIFY: &str = "Encode a LValue as a YAML string.";
+    pub const DOC_YAML_STRINGIFY_VERBOSE: &str = "Maps are encoded as mappings, lists as sequences, \
+                                                 nil as null and symbols as strings.\n\
+                                                 As false and the empty list are nil, they are encoded as null.\n\
+                                                 Functions, lambdas and non-finite floats cannot be encoded.";
+
+    pub const YAML_READ: &str = "yaml-read";
+    pub const DOC_YAML_READ: &str = "Read a YAML file and parse its content into a LValue.";
+
+    pub const YAML_WRITE: &str = "yaml-write";
+    pub const DOC_YAML_WRITE: &str = "Write a LValue to a file as YAML.";
+    pub const DOC_YAML_WRITE_VERBOSE: &str =
+        "Takes two arguments: the name of the file and the LValue.";
 }
 
 pub mod first_order_logic {
@@ -4,6 +4,7 @@ use sompas_language::io::*;
 use sompas_macros::{async_scheme_fn, scheme_fn};
 use sompas_structs::lenv::LEnv;
 use sompas_structs::lmodule::LModule;
+use sompas_structs::lnumber::LNumber;
 use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
 use sompas_structs::lvalue::{LValue, Sym};
 use sompas_structs::{lruntimeerror, string};
@@ -62,6 +63,44 @@ impl From<ModIO> for LModule {
         module.add_fn(SET_CURRENT_DIR, set_current_dir, DOC_SET_CURRENT_DIR, false);
         module.add_async_fn(GET_ENV_VAR, get_env_var, DOC_GET_ENV_VAR, false);
         module.add_macro(LOAD, MACRO_LOAD, DOC_LOAD);
+        module.add_fn(
+            JSON_PARSE,
+            json_parse,
+            (DOC_JSON_PARSE, DOC_JSON_PARSE_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            JSON_STRINGIFY,
+            json_stringify,
+            (DOC_JSON_STRINGIFY, DOC_JSON_STRINGIFY_VERBOSE),
+            true,
+        );
+        module.add_fn(JSON_READ, json_read, DOC_JSON_READ, false);
+        module.add_fn(
+            JSON_WRITE,
+            json_write,
+            (DOC_JSON_WRITE, DOC_JSON_WRITE_VERBOSE),
+            false,
+        );
+        module.add_fn(
+            YAML_PARSE,
+            yaml_parse,
+            (DOC_YAML_PARSE, DOC_YAML_PARSE_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            YAML_STRINGIFY,
+            yaml_stringify,
+            (DOC_YAML_STRINGIFY, DOC_YAML_STRINGIFY_VERBOSE),
+            true,
+        );
+        module.add_fn(YAML_READ, yaml_read, DOC_YAML_READ, false);
+        module.add_fn(
+            YAML_WRITE,
+            yaml_write,
+            (DOC_YAML_WRITE, DOC_YAML_WRITE_VERBOSE),
+            false,
+        );
 
         module
     }
@@ -160,3 +199,318 @@ pub async fn get_env_var(env: &LEnv, var: String) -> LResult {
         Err(e) => Err(LRuntimeError::new("", e.to_string())),
     }
 }
+
+/*
+JSON & YAML
+ */
+
+/// Converts a JSON value into a LValue.
+/// Integers that fit in an i64 are kept as ints, all other numbers become floats.
+/// null, false and the empty array all become nil, the empty object an empty map.
+pub fn json_to_lvalue(value: serde_json::Value) -> LValue {
+    match value {
+        serde_json::Value::Null => LValue::Nil,
+        serde_json::Value::Bool(b) => b.into(),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        serde_json::Value::String(s) => string!(s),
+        serde_json::Value::Array(a) => a
+            .into_iter()
+            .map(json_to_lvalue)
+            .collect::<Vec<LValue>>()
+            .into(),
+        serde_json::Value::Object(o) => LValue::Map(
+            o.into_iter()
+                .map(|(k, v)| (string!(k), json_to_lvalue(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Converts a LValue into a JSON value.
+/// nil, that is also false and the empty list, becomes null, and symbols become strings.
+/// Returns an error for values that have no JSON counterpart.
+pub fn lvalue_to_json(lv: &LValue) -> Result<serde_json::Value, LRuntimeError> {
+    Ok(match lv {
+        LValue::Nil => serde_json::Value::Null,
+        LValue::True => serde_json::Value::Bool(true),
+        LValue::Number(LNumber::Int(i)) => (*i).into(),
+        LValue::Number(LNumber::Float(f)) => serde_json::Number::from_f64(*f)
+            .ok_or_else(|| {
+                lruntimeerror!(JSON_STRINGIFY, format!("{f} cannot be encoded in JSON."))
+            })?
+            .into(),
+        LValue::String(s) => s.to_string().into(),
+        LValue::Symbol(s) => s.to_string().into(),
+        LValue::List(l) => l
+            .iter()
+            .map(lvalue_to_json)
+            .collect::<Result<Vec<_>, _>>()?
+            .into(),
+        LValue::Map(m) => {
+            let mut object = serde_json::Map::new();
+            for (k, v) in m {
+                let key = match k {
+                    LValue::String(_) | LValue::Symbol(_) | LValue::Number(_) => k.to_string(),
+                    _ => {
+                        return Err(lruntimeerror!(
+                            JSON_STRINGIFY,
+                            format!("{k} cannot be used as a JSON object key.")
+                        ))
+                    }
+                };
+                object.insert(key, lvalue_to_json(v)?);
+            }
+            object.into()
+        }
+        _ => {
+            return Err(lruntimeerror!(
+                JSON_STRINGIFY,
+                format!("{lv} of kind {} cannot be encoded in JSON.", lv.get_kind())
+            ))
+        }
+    })
+}
+
+/// Converts a YAML value into a LValue.
+/// Tags are dropped and only the tagged value is converted.
+/// null, false and the empty sequence all become nil, the empty mapping an empty map.
+pub fn yaml_to_lvalue(value: serde_yaml::Value) -> LValue {
+    match value {
+        serde_yaml::Value::Null => LValue::Nil,
+        serde_yaml::Value::Bool(b) => b.into(),
+        serde_yaml::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        serde_yaml::Value::String(s) => string!(s),
+        serde_yaml::Value::Sequence(s) => s
+            .into_iter()
+            .map(yaml_to_lvalue)
+            .collect::<Vec<LValue>>()
+            .into(),
+        serde_yaml::Value::Mapping(m) => LValue::Map(
+            m.into_iter()
+                .map(|(k, v)| (yaml_to_lvalue(k), yaml_to_lvalue(v)))
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(t) => yaml_to_lvalue(t.value),
+    }
+}
+
+/// Converts a LValue into a YAML value.
+/// nil, that is also false and the empty list, becomes null, and symbols become strings.
+/// Returns an error for values that have no YAML counterpart.
+pub fn lvalue_to_yaml(lv: &LValue) -> Result<serde_yaml::Value, LRuntimeError> {
+    Ok(match lv {
+        LValue::Nil => serde_yaml::Value::Null,
+        LValue::True => serde_yaml::Value::Bool(true),
+        LValue::Number(LNumber::Int(i)) => (*i).into(),
+        LValue::Number(LNumber::Float(f)) => {
+            if !f.is_finite() {
+                return Err(lruntimeerror!(
+                    YAML_STRINGIFY,
+                    format!("{f} cannot be encoded in YAML.")
+                ));
+            }
+            (*f).into()
+        }
+        LValue::String(s) => s.to_string().into(),
+        LValue::Symbol(s) => s.to_string().into(),
+        LValue::List(l) => {
+            serde_yaml::Value::Sequence(l.iter().map(lvalue_to_yaml).collect::<Result<_, _>>()?)
+        }
+        LValue::Map(m) => serde_yaml::Value::Mapping(
+            m.iter()
+                .map(|(k, v)| Ok((lvalue_to_yaml(k)?, lvalue_to_yaml(v)?)))
+                .collect::<Result<_, LRuntimeError>>()?,
+        ),
+        _ => {
+            return Err(lruntimeerror!(
+                YAML_STRINGIFY,
+                format!("{lv} of kind {} cannot be encoded in YAML.", lv.get_kind())
+            ))
+        }
+    })
+}
+
+fn read_to_string(label: &str, file_name: &str) -> Result<String, LRuntimeError> {
+    std::fs::read_to_string(file_name)
+        .map_err(|e| lruntimeerror!(label, format!("{}: {}", file_name, e)))
+}
+
+fn write_string(label: &str, file_name: &str, contents: &str) -> Result<(), LRuntimeError> {
+    std::fs::write(file_name, contents)
+        .map_err(|e| lruntimeerror!(label, format!("{}: {}", file_name, e)))
+}
+
+/// Parses a JSON string.
+/// Syntax errors are reported with their line and column.
+#[scheme_fn]
+pub fn json_parse(s: String) -> LResult {
+    serde_json::from_str(&s)
+        .map(json_to_lvalue)
+        .map_err(|e| lruntimeerror!(JSON_PARSE, e.to_string()))
+}
+
+/// Encodes a LValue as a JSON string.
+///
+/// # Example:
+/// ```lisp
+/// (json-stringify <lvalue> [<pretty>])
+#[scheme_fn]
+pub fn json_stringify(args: &[LValue]) -> LResult {
+    let (lv, pretty) = match args {
+        [lv] => (lv, false),
+        [lv, pretty] => (lv, *pretty != LValue::Nil),
+        _ => {
+            return Err(LRuntimeError::wrong_number_of_args(
+                JSON_STRINGIFY,
+                args,
+                1..3,
+            ))
+        }
+    };
+    let value = lvalue_to_json(lv)?;
+    let s = if pretty {
+        serde_json::to_string_pretty(&value)
+    } else {
+        serde_json::to_string(&value)
+    }
+    .map_err(|e| lruntimeerror!(JSON_STRINGIFY, e.to_string()))?;
+    Ok(string!(s))
+}
+
+#[scheme_fn]
+pub fn json_read(file_name: String) -> LResult {
+    let contents = read_to_string(JSON_READ, &file_name)?;
+    serde_json::from_str(&contents)
+        .map(json_to_lvalue)
+        .map_err(|e| lruntimeerror!(JSON_READ, format!("{}: {}", file_name, e)))
+}
+
+#[scheme_fn]
+pub fn json_write(file_name: String, lv: &LValue) -> Result<(), LRuntimeError> {
+    let contents = serde_json::to_string_pretty(&lvalue_to_json(lv)?)
+        .map_err(|e| lruntimeerror!(JSON_WRITE, e.to_string()))?;
+    write_string(JSON_WRITE, &file_name, &contents)
+}
+
+/// Parses a YAML string.
+/// Syntax errors are reported with their line and column.
+#[scheme_fn]
+pub fn yaml_parse(s: String) -> LResult {
+    serde_yaml::from_str(&s)
+        .map(yaml_to_lvalue)
+        .map_err(|e| lruntimeerror!(YAML_PARSE, e.to_string()))
+}
+
+#[scheme_fn]
+pub fn yaml_stringify(lv: &LValue) -> LResult {
+    serde_yaml::to_string(&lvalue_to_yaml(lv)?)
+        .map(|s| string!(s))
+        .map_err(|e| lruntimeerror!(YAML_STRINGIFY, e.to_string()))
+}
+
+#[scheme_fn]
+pub fn yaml_read(file_name: String) -> LResult {
+    let contents = read_to_string(YAML_READ, &file_name)?;
+    serde_yaml::from_str(&contents)
+        .map(yaml_to_lvalue)
+        .map_err(|e| lruntimeerror!(YAML_READ, format!("{}: {}", file_name, e)))
+}
+
+#[scheme_fn]
+pub fn yaml_write(file_name: String, lv: &LValue) -> Result<(), LRuntimeError> {
+    let contents = serde_yaml::to_string(&lvalue_to_yaml(lv)?)
+        .map_err(|e| lruntimeerror!(YAML_WRITE, e.to_string()))?;
+    write_string(YAML_WRITE, &file_name, &contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let env = LEnv::default();
+        let lv = json_parse(
+            &env,
+            &[string!(
+                r#"{"robot": "r1", "pos": [1, -2.5], "busy": true, "task": null}"#
+            )],
+        )
+        .unwrap();
+        let expected: im::HashMap<LValue, LValue> = im::hashmap! {
+            string!("robot") => string!("r1"),
+            string!("pos") => vec![LValue::from(1), LValue::from(-2.5)].into(),
+            string!("busy") => LValue::True,
+            string!("task") => LValue::Nil,
+        };
+        assert_eq!(lv, LValue::Map(expected));
+
+        let s = json_stringify(&env, std::slice::from_ref(&lv)).unwrap();
+        assert_eq!(json_parse(&env, &[s]).unwrap(), lv);
+        let f: LValue = 0.1.into();
+        let s = json_stringify(&env, std::slice::from_ref(&f)).unwrap();
+        assert_eq!(json_parse(&env, &[s]).unwrap(), f);
+
+        let err = json_parse(&env, &[string!("{\n  \"x\": [1,\n}")]).unwrap_err();
+        assert!(err.to_string().contains("line 3"));
+        assert!(json_stringify(&env, &[f64::INFINITY.into()]).is_err());
+    }
+
+    #[test]
+    fn test_json_lossy() {
+        let env = LEnv::default();
+        //null, false and [] cannot be distinguished, as nil is also false and the empty list.
+        for s in ["null", "false", "[]"] {
+            assert_eq!(json_parse(&env, &[string!(s)]).unwrap(), LValue::Nil);
+        }
+        assert_eq!(
+            json_stringify(&env, &[LValue::Nil]).unwrap(),
+            string!("null")
+        );
+        //The empty object is kept as an empty map.
+        let lv = json_parse(&env, &[string!("{}")]).unwrap();
+        assert_eq!(lv, LValue::Map(Default::default()));
+        assert_eq!(
+            json_stringify(&env, std::slice::from_ref(&lv)).unwrap(),
+            string!("{}")
+        );
+        //Symbols and keys are encoded as strings.
+        let lv = LValue::Map(im::hashmap! {LValue::from(1) => LValue::from("r1")});
+        assert_eq!(
+            json_stringify(&env, &[lv]).unwrap(),
+            string!(r#"{"1":"r1"}"#)
+        );
+    }
+
+    #[test]
+    fn test_yaml() {
+        let env = LEnv::default();
+        let lv = yaml_parse(&env, &[string!("robot: r1\npos:\n  - 1\n  - 2.5\n")]).unwrap();
+        let expected: im::HashMap<LValue, LValue> = im::hashmap! {
+            string!("robot") => string!("r1"),
+            string!("pos") => vec![LValue::from(1), LValue::from(2.5)].into(),
+        };
+        assert_eq!(lv, LValue::Map(expected));
+
+        let s = yaml_stringify(&env, std::slice::from_ref(&lv)).unwrap();
+        assert_eq!(yaml_parse(&env, &[s]).unwrap(), lv);
+
+        let err = yaml_parse(&env, &[string!("robot: r1\n  pos: [1")]).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        //null, false and [] cannot be distinguished, as nil is also false and the empty list.
+        for s in ["~", "false", "[]"] {
+            assert_eq!(yaml_parse(&env, &[string!(s)]).unwrap(), LValue::Nil);
+        }
+        let lv = yaml_parse(&env, &[string!("{}")]).unwrap();
+        assert_eq!(lv, LValue::Map(Default::default()));
+        let s = yaml_stringify(&env, std::slice::from_ref(&lv)).unwrap();
+        assert_eq!(yaml_parse(&env, &[s]).unwrap(), lv);
+    }
+}
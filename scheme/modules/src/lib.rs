@@ -12,6 +12,7 @@ use sompas_structs::lmodule::LModule;
 pub mod advanced_math;
 pub mod first_order_logic;
 pub mod io;
+pub mod regex;
 pub mod sort;
 pub mod string;
 pub mod time;
@@ -0,0 +1,111 @@
+use ::regex::Regex;
+use sompas_language::regex::*;
+use sompas_macros::scheme_fn;
+use sompas_structs::lmodule::LModule;
+use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
+use sompas_structs::lvalue::LValue;
+use sompas_structs::{lruntimeerror, string};
+
+#[derive(Default)]
+pub struct ModRegex {}
+
+impl From<ModRegex> for LModule {
+    fn from(m: ModRegex) -> Self {
+        let mut module = LModule::new(m, MOD_REGEX, DOC_MOD_REGEX);
+        module.add_fn(REGEX_MATCH, regex_match, DOC_REGEX_MATCH, true);
+        module.add_fn(
+            REGEX_FIND_ALL,
+            regex_find_all,
+            (DOC_REGEX_FIND_ALL, DOC_REGEX_FIND_ALL_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            REGEX_REPLACE,
+            regex_replace,
+            (DOC_REGEX_REPLACE, DOC_REGEX_REPLACE_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            REGEX_CAPTURES,
+            regex_captures,
+            (DOC_REGEX_CAPTURES, DOC_REGEX_CAPTURES_VERBOSE),
+            true,
+        );
+        module
+    }
+}
+
+fn compile(label: &str, re: &str) -> Result<Regex, LRuntimeError> {
+    Regex::new(re).map_err(|e| lruntimeerror!(label, e.to_string()))
+}
+
+#[scheme_fn]
+pub fn regex_match(re: String, s: String) -> Result<bool, LRuntimeError> {
+    Ok(compile(REGEX_MATCH, &re)?.is_match(&s))
+}
+
+#[scheme_fn]
+pub fn regex_find_all(re: String, s: String) -> Result<Vec<LValue>, LRuntimeError> {
+    Ok(compile(REGEX_FIND_ALL, &re)?
+        .find_iter(&s)
+        .map(|m| string!(m.as_str()))
+        .collect())
+}
+
+#[scheme_fn]
+pub fn regex_replace(re: String, s: String, replacement: String) -> LResult {
+    Ok(string!(
+        compile(REGEX_REPLACE, &re)?.replace_all(&s, replacement.as_str())
+    ))
+}
+
+#[scheme_fn]
+pub fn regex_captures(re: String, s: String) -> LResult {
+    Ok(match compile(REGEX_CAPTURES, &re)?.captures(&s) {
+        Some(captures) => captures
+            .iter()
+            .map(|m| match m {
+                Some(m) => string!(m.as_str()),
+                None => LValue::Nil,
+            })
+            .collect::<Vec<_>>()
+            .into(),
+        None => LValue::Nil,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sompas_structs::lenv::LEnv;
+
+    fn s(s: &str) -> LValue {
+        string!(s)
+    }
+
+    #[test]
+    fn test_regex() {
+        let env = LEnv::default();
+        assert_eq!(
+            regex_match(&env, &[s("^r[0-9]+$"), s("r12")]).unwrap(),
+            LValue::True
+        );
+        assert_eq!(
+            regex_find_all(&env, &[s("[0-9]+"), s("r1 at 3 12")]).unwrap(),
+            vec![s("1"), s("3"), s("12")].into()
+        );
+        assert_eq!(
+            regex_replace(&env, &[s("([a-z]+)([0-9]+)"), s("r1 r2"), s("$2$1")]).unwrap(),
+            s("1r 2r")
+        );
+        assert_eq!(
+            regex_captures(&env, &[s("([a-z]+)([0-9]+)(x)?"), s("robot r1")]).unwrap(),
+            vec![s("r1"), s("r"), s("1"), LValue::Nil].into()
+        );
+        assert_eq!(
+            regex_captures(&env, &[s("[0-9]"), s("robot")]).unwrap(),
+            LValue::Nil
+        );
+        assert!(regex_match(&env, &[s("("), s("r1")]).is_err());
+    }
+}
@@ -1,6 +1,13 @@
+use crate::regex::ModRegex;
 use sompas_language::string::*;
 use sompas_macros::scheme_fn;
+use sompas_structs::lenv::ImportType::WithoutPrefix;
 use sompas_structs::lmodule::LModule;
+use sompas_structs::lnumber::LNumber;
+use sompas_structs::lruntimeerror::{LResult, LRuntimeError};
+use sompas_structs::lvalue::LValue;
+use sompas_structs::{lruntimeerror, string, symbol};
+use std::convert::TryFrom;
 
 /*
 LANGUAGE
@@ -13,6 +20,78 @@ impl From<ModString> for LModule {
     fn from(m: ModString) -> Self {
         let mut module = LModule::new(m, MOD_STRING, DOC_MOD_STRING);
         module.add_fn(CONCATENATE, concatenate, DOC_CONCATENATE, true);
+        module.add_fn(STRING_LENGTH, string_length, DOC_STRING_LENGTH, true);
+        module.add_fn(
+            STRING_REF,
+            string_ref,
+            (DOC_STRING_REF, DOC_STRING_REF_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            SUBSTRING,
+            substring,
+            (DOC_SUBSTRING, DOC_SUBSTRING_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            STRING_SPLIT,
+            string_split,
+            (DOC_STRING_SPLIT, DOC_STRING_SPLIT_VERBOSE),
+            true,
+        );
+        module.add_fn(
+            STRING_JOIN,
+            string_join,
+            (DOC_STRING_JOIN, DOC_STRING_JOIN_VERBOSE),
+            true,
+        );
+        module.add_fn(STRING_TRIM, string_trim, DOC_STRING_TRIM, true);
+        module.add_fn(STRING_UPCASE, string_upcase, DOC_STRING_UPCASE, true);
+        module.add_fn(STRING_DOWNCASE, string_downcase, DOC_STRING_DOWNCASE, true);
+        module.add_fn(STRING_REPLACE, string_replace, DOC_STRING_REPLACE, true);
+        module.add_fn(STRING_CONTAINS, string_contains, DOC_STRING_CONTAINS, true);
+        module.add_fn(
+            STRING_STARTS_WITH,
+            string_starts_with,
+            DOC_STRING_STARTS_WITH,
+            true,
+        );
+        module.add_fn(
+            STRING_ENDS_WITH,
+            string_ends_with,
+            DOC_STRING_ENDS_WITH,
+            true,
+        );
+        module.add_fn(FORMAT, fn_format, (DOC_FORMAT, DOC_FORMAT_VERBOSE), true);
+        module.add_fn(
+            STRING_TO_NUMBER,
+            string_to_number,
+            DOC_STRING_TO_NUMBER,
+            true,
+        );
+        module.add_fn(
+            NUMBER_TO_STRING,
+            number_to_string,
+            DOC_NUMBER_TO_STRING,
+            true,
+        );
+        module.add_fn(
+            STRING_TO_SYMBOL,
+            string_to_symbol,
+            DOC_STRING_TO_SYMBOL,
+            true,
+        );
+        module.add_fn(
+            SYMBOL_TO_STRING,
+            symbol_to_string,
+            DOC_SYMBOL_TO_STRING,
+            true,
+        );
+        module.add_fn(STRING_TO_LIST, string_to_list, DOC_STRING_TO_LIST, true);
+        module.add_fn(LIST_TO_STRING, list_to_string, DOC_LIST_TO_STRING, true);
+        module.add_fn(CHAR_TO_INTEGER, char_to_integer, DOC_CHAR_TO_INTEGER, true);
+        module.add_fn(INTEGER_TO_CHAR, integer_to_char, DOC_INTEGER_TO_CHAR, true);
+        module.add_submodule(ModRegex::default(), WithoutPrefix);
         module
     }
 }
@@ -26,3 +105,318 @@ pub fn concatenate(strings: Vec<String>) -> String {
     }
     str
 }
+
+#[scheme_fn]
+pub fn string_length(s: String) -> usize {
+    s.chars().count()
+}
+
+#[scheme_fn]
+pub fn string_ref(s: String, i: usize) -> LResult {
+    match s.chars().nth(i) {
+        Some(c) => Ok(string!(c.to_string())),
+        None => Err(lruntimeerror!(
+            STRING_REF,
+            format!("index {} out of \"{}\"", i, s)
+        )),
+    }
+}
+
+/// Returns the substring between two indexes of characters, the end being optional.
+#[scheme_fn]
+pub fn substring(args: &[LValue]) -> LResult {
+    let (s, start, end): (String, usize, Option<usize>) = match args {
+        [s, start] => (s.try_into()?, start.try_into()?, None),
+        [s, start, end] => (s.try_into()?, start.try_into()?, Some(end.try_into()?)),
+        _ => return Err(LRuntimeError::wrong_number_of_args(SUBSTRING, args, 2..3)),
+    };
+    let chars: Vec<char> = s.chars().collect();
+    let end = end.unwrap_or(chars.len());
+    if start > end || end > chars.len() {
+        return Err(lruntimeerror!(
+            SUBSTRING,
+            format!("indexes {}..{} out of \"{}\"", start, end, s)
+        ));
+    }
+    Ok(string!(chars[start..end].iter().collect::<String>()))
+}
+
+/// Splits a string on a separator, or on whitespaces if there is no separator.
+#[scheme_fn]
+pub fn string_split(args: &[LValue]) -> Result<Vec<LValue>, LRuntimeError> {
+    Ok(match args {
+        [s] => String::try_from(s)?
+            .split_whitespace()
+            .map(|e| string!(e))
+            .collect(),
+        [s, sep] => String::try_from(s)?
+            .split(String::try_from(sep)?.as_str())
+            .map(|e| string!(e))
+            .collect(),
+        _ => {
+            return Err(LRuntimeError::wrong_number_of_args(
+                STRING_SPLIT,
+                args,
+                1..2,
+            ))
+        }
+    })
+}
+
+/// Joins the elements of a list, with an optional separator.
+#[scheme_fn]
+pub fn string_join(args: &[LValue]) -> LResult {
+    let (list, sep): (Vec<LValue>, String) = match args {
+        [list] => (list.try_into()?, String::new()),
+        [list, sep] => (list.try_into()?, sep.try_into()?),
+        _ => return Err(LRuntimeError::wrong_number_of_args(STRING_JOIN, args, 1..2)),
+    };
+    Ok(string!(list
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(&sep)))
+}
+
+#[scheme_fn]
+pub fn string_trim(s: String) -> LValue {
+    string!(s.trim())
+}
+
+#[scheme_fn]
+pub fn string_upcase(s: String) -> LValue {
+    string!(s.to_uppercase())
+}
+
+#[scheme_fn]
+pub fn string_downcase(s: String) -> LValue {
+    string!(s.to_lowercase())
+}
+
+#[scheme_fn]
+pub fn string_replace(s: String, from: String, to: String) -> LValue {
+    string!(s.replace(&from, &to))
+}
+
+#[scheme_fn]
+pub fn string_contains(s: String, pattern: String) -> bool {
+    s.contains(&pattern)
+}
+
+#[scheme_fn]
+pub fn string_starts_with(s: String, prefix: String) -> bool {
+    s.starts_with(&prefix)
+}
+
+#[scheme_fn]
+pub fn string_ends_with(s: String, suffix: String) -> bool {
+    s.ends_with(&suffix)
+}
+
+/// Formats a string, replacing the placeholders {} by the next argument, and {i} by the argument of index i.
+#[scheme_fn]
+pub fn fn_format(args: &[LValue]) -> LResult {
+    let (fmt, args) = match args.split_first() {
+        Some((fmt, args)) => (String::try_from(fmt)?, args),
+        None => {
+            return Err(LRuntimeError::wrong_number_of_args(
+                FORMAT,
+                args,
+                1..usize::MAX,
+            ))
+        }
+    };
+    let mut result = String::new();
+    let mut next = 0;
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut index = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => index.push(c),
+                        None => {
+                            return Err(lruntimeerror!(
+                                FORMAT,
+                                format!("\"{}\": unclosed placeholder", fmt)
+                            ))
+                        }
+                    }
+                }
+                let i = if index.is_empty() {
+                    next += 1;
+                    next - 1
+                } else {
+                    index.trim().parse::<usize>().map_err(|_| {
+                        lruntimeerror!(FORMAT, format!("\"{}\": invalid placeholder", fmt))
+                    })?
+                };
+                match args.get(i) {
+                    Some(arg) => result.push_str(arg.to_string().as_str()),
+                    None => {
+                        return Err(lruntimeerror!(
+                            FORMAT,
+                            format!("\"{}\": no argument for placeholder {}", fmt, i)
+                        ))
+                    }
+                }
+            }
+            '}' => return Err(lruntimeerror!(FORMAT, format!("\"{}\": unmatched }}", fmt))),
+            c => result.push(c),
+        }
+    }
+    Ok(string!(result))
+}
+
+#[scheme_fn]
+pub fn string_to_number(s: String) -> LResult {
+    let trimmed = s.trim();
+    match trimmed.parse::<i64>() {
+        Ok(i) => Ok(i.into()),
+        Err(_) => match trimmed.parse::<f64>() {
+            Ok(f) => Ok(f.into()),
+            Err(_) => Err(lruntimeerror!(
+                STRING_TO_NUMBER,
+                format!("\"{}\" is not a number", s)
+            )),
+        },
+    }
+}
+
+#[scheme_fn]
+pub fn number_to_string(n: LNumber) -> LValue {
+    string!(n.to_string())
+}
+
+#[scheme_fn]
+pub fn string_to_symbol(s: String) -> LResult {
+    if s.is_empty() || s.chars().any(|c| c.is_whitespace()) {
+        return Err(lruntimeerror!(
+            STRING_TO_SYMBOL,
+            format!("\"{}\" is not a valid symbol", s)
+        ));
+    }
+    Ok(symbol!(s))
+}
+
+#[scheme_fn]
+pub fn symbol_to_string(s: String) -> LValue {
+    string!(s)
+}
+
+#[scheme_fn]
+pub fn string_to_list(s: String) -> Vec<LValue> {
+    s.chars().map(|c| string!(c.to_string())).collect()
+}
+
+#[scheme_fn]
+pub fn list_to_string(chars: Vec<LValue>) -> LResult {
+    let chars = chars
+        .iter()
+        .map(String::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(string!(chars.concat()))
+}
+
+#[scheme_fn]
+pub fn char_to_integer(c: String) -> Result<i64, LRuntimeError> {
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c as i64),
+        _ => Err(lruntimeerror!(
+            CHAR_TO_INTEGER,
+            format!("\"{}\" is not a character", c)
+        )),
+    }
+}
+
+#[scheme_fn]
+pub fn integer_to_char(i: i64) -> LResult {
+    match u32::try_from(i).ok().and_then(char::from_u32) {
+        Some(c) => Ok(string!(c.to_string())),
+        None => Err(lruntimeerror!(
+            INTEGER_TO_CHAR,
+            format!("{} is not a unicode code point", i)
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sompas_structs::lenv::LEnv;
+
+    fn s(s: &str) -> LValue {
+        string!(s)
+    }
+
+    #[test]
+    fn test_split_join() {
+        let env = LEnv::default();
+        let split = string_split(&env, &[s("r1,l1,,l2"), s(",")]).unwrap();
+        assert_eq!(split, vec![s("r1"), s("l1"), s(""), s("l2")].into());
+        let split = string_split(&env, &[s(" r1  l1\n")]).unwrap();
+        assert_eq!(split, vec![s("r1"), s("l1")].into());
+        assert_eq!(string_join(&env, &[split, s(", ")]).unwrap(), s("r1, l1"));
+    }
+
+    #[test]
+    fn test_substring() {
+        let env = LEnv::default();
+        assert_eq!(
+            substring(&env, &[s("robot_1"), 0.into(), 5.into()]).unwrap(),
+            s("robot")
+        );
+        assert_eq!(substring(&env, &[s("été"), 1.into()]).unwrap(), s("té"));
+        assert!(substring(&env, &[s("r1"), 1.into(), 3.into()]).is_err());
+        assert_eq!(string_ref(&env, &[s("été"), 2.into()]).unwrap(), s("é"));
+        assert_eq!(string_length(&env, &[s("été")]).unwrap(), 3.into());
+    }
+
+    #[test]
+    fn test_format() {
+        let env = LEnv::default();
+        let result = fn_format(
+            &env,
+            &[
+                s("{} moves to {}, {0} is ready {{}}"),
+                "r1".into(),
+                "l2".into(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(result, s("r1 moves to l2, r1 is ready {}"));
+        assert!(fn_format(&env, &[s("{} {}"), "r1".into()]).is_err());
+        assert!(fn_format(&env, &[s("{0")]).is_err());
+    }
+
+    #[test]
+    fn test_conversions() {
+        let env = LEnv::default();
+        assert_eq!(string_to_number(&env, &[s(" 12 ")]).unwrap(), 12.into());
+        assert_eq!(string_to_number(&env, &[s("1.5")]).unwrap(), 1.5.into());
+        assert!(string_to_number(&env, &[s("r1")]).is_err());
+        assert_eq!(number_to_string(&env, &[12.into()]).unwrap(), s("12"));
+        assert_eq!(
+            string_to_symbol(&env, &[s("r1")]).unwrap(),
+            LValue::from("r1")
+        );
+        assert_eq!(symbol_to_string(&env, &["r1".into()]).unwrap(), s("r1"));
+        assert_eq!(char_to_integer(&env, &[s("a")]).unwrap(), 97.into());
+        assert_eq!(integer_to_char(&env, &[97.into()]).unwrap(), s("a"));
+        assert_eq!(
+            list_to_string(&env, &[string_to_list(&env, &[s("r1")]).unwrap()]).unwrap(),
+            s("r1")
+        );
+    }
+}
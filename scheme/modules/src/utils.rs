@@ -79,10 +79,6 @@ impl From<ModUtils> for LModule {
         module.add_macro(COND, MACRO_COND, (DOC_COND, DOC_COND_VERBOSE));
         module.add_lambda(UNZIP, LAMBDA_UNZIP, (DOC_UNZIP, DOC_UNZIP_VERBOSE));
 
-        //todo:
-        module.add_doc(FOR, DOC_FOR, "Lambda");
-        //todo:
-        module.add_doc(WHILE, DOC_WHILE, "Lambda");
         module.add_macro(LOOP, MACRO_LOOP, DOC_LOOP);
         module.add_macro(LET, MACRO_LET, (DOC_LET, DOC_LET_VERBOSE));
         module.add_macro(
@@ -582,6 +578,48 @@ mod test {
         test_expression(test_lambda).await
     }
 
+    #[tokio::test]
+    async fn test_macro_named_let() -> lruntimeerror::Result<()> {
+        let test_lambda = TestExpression {
+            inner: Expr::_macro(LET, MACRO_LET),
+            dependencies: vec![
+                Expr::_macro(CAAR, MACRO_CAAR),
+                Expr::_macro(CADAR, MACRO_CADAR),
+                Expr::_macro(CADR, MACRO_CADR),
+                Expr::_macro(CDDR, MACRO_CDDR),
+                Expr::_lambda(UNZIP, LAMBDA_UNZIP),
+            ],
+            expression: "(let iter ((i 0)
+                                    (acc 0))
+                              (if (> i 3) acc (iter (+ i 1) (+ acc i))))",
+            expected: "(begin
+                            (define iter (lambda (i acc) (begin (if (> i 3) acc (iter (+ i 1) (+ acc i))))))
+                            (iter 0 0))",
+            result: "6",
+        };
+        test_expression(test_lambda).await?;
+
+        //The body of a named let is a sequence of expressions.
+        let test_lambda = TestExpression {
+            inner: Expr::_macro(LET, MACRO_LET),
+            dependencies: vec![
+                Expr::_macro(CAAR, MACRO_CAAR),
+                Expr::_macro(CADAR, MACRO_CADAR),
+                Expr::_macro(CADR, MACRO_CADR),
+                Expr::_macro(CDDR, MACRO_CDDR),
+                Expr::_lambda(UNZIP, LAMBDA_UNZIP),
+            ],
+            expression: "(let iter ((i 0))
+                              (define j (* i 2))
+                              (if (> j 4) j (iter (+ i 1))))",
+            expected: "(begin
+                            (define iter (lambda (i) (begin (define j (* i 2)) (if (> j 4) j (iter (+ i 1))))))
+                            (iter 0))",
+            result: "6",
+        };
+        test_expression(test_lambda).await
+    }
+
     #[tokio::test]
     async fn test_macro_let_star() -> lruntimeerror::Result<()> {
         let test_lambda = TestExpression {
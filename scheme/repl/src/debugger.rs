@@ -0,0 +1,176 @@
+//! Commands of the REPL to drive the debugger of the evaluator.
+//!
+//! Lines starting with ':' are debugger commands. When an evaluation is paused, the REPL enters
+//! a debug session with its own prompt, until the evaluation is resumed.
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use sompas_core::debugger::{PausedEval, Step, DEBUGGER};
+
+pub const DEBUG_PROMPT: &str = "(debug)>> ";
+
+const HELP_TOP_LEVEL: &str = "debugger commands:
+    :break <name>   pause before the calls of a procedure
+    :delete <name>  remove a breakpoint (all breakpoints without name)
+    :breakpoints    list the breakpoints and attachments
+    :paused         list the paused evaluations
+    :debug [id]     enter the debug session of a paused evaluation
+    :help           print this help";
+
+const HELP_SESSION: &str = "debug session commands:
+    :step (:s)       pause at the next procedure call
+    :next (:n)       pause at the next call that is not nested in the current one
+    :finish (:f)     pause at the next call once the current lambda has returned
+    :continue (:c)   run until the next breakpoint
+    :interrupt (:i)  interrupt the evaluation
+    :env             print the bindings of the current lambda call
+    :stack (:where)  print the call stack
+    :print <symbol>  print the value of a symbol (:p)
+and all the top level commands except :debug.";
+
+/// Result of a debugger command in a debug session.
+enum SessionAction {
+    Stay,
+    Leave,
+}
+
+/// Handles a debugger command entered at the top level prompt, without the leading ':'.
+pub async fn top_level_command(rl: &mut DefaultEditor, command: &str) {
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("debug"), id) => {
+            let paused = DEBUGGER.paused();
+            let paused = match id {
+                None => paused.into_iter().next(),
+                Some(id) => paused.into_iter().find(|p| p.id.to_string() == id),
+            };
+            match paused {
+                Some(p) => debug_session(rl, p).await,
+                None => println!("no paused evaluation"),
+            }
+        }
+        (Some(c), arg) => common_command(c, arg),
+        (None, _) => println!("{}", HELP_TOP_LEVEL),
+    }
+}
+
+/// Prints the paused evaluations, if any, as a reminder before the top level prompt.
+pub fn print_paused_reminder() {
+    let n = DEBUGGER.paused().len();
+    if n > 0 {
+        println!("{} evaluation(s) paused, enter :debug to inspect them.", n)
+    }
+}
+
+/// Drives a paused evaluation until it is resumed.
+pub async fn debug_session(rl: &mut DefaultEditor, paused: PausedEval) {
+    println!("{}", paused);
+    loop {
+        let line = match rl.readline(DEBUG_PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                resume(&paused, None);
+                return;
+            }
+            Err(ReadlineError::Eof) => {
+                resume(&paused, Some(Step::Continue));
+                return;
+            }
+            Err(err) => {
+                println!("Error: {:?}", err);
+                return;
+            }
+        };
+        let _ = rl.add_history_entry(line.clone());
+        let command = line.trim();
+        let command = command.strip_prefix(':').unwrap_or(command);
+        if let SessionAction::Leave = session_command(&paused, command).await {
+            return;
+        }
+    }
+}
+
+async fn session_command(paused: &PausedEval, command: &str) -> SessionAction {
+    let mut words = command.split_whitespace();
+    let step = match (words.next(), words.next()) {
+        (Some("step" | "s"), _) => Some(Step::In),
+        (Some("next" | "n"), _) => Some(Step::Over),
+        (Some("finish" | "f"), _) => Some(Step::Out),
+        (Some("continue" | "c"), _) => Some(Step::Continue),
+        (Some("interrupt" | "i"), _) => None,
+        (Some("env"), _) => {
+            if paused.bindings.is_empty() {
+                println!("no local binding");
+            }
+            for (symbol, value) in &paused.bindings {
+                println!("{}: {}", symbol, value);
+            }
+            return SessionAction::Stay;
+        }
+        (Some("stack" | "where"), _) => {
+            for (i, label) in paused.stack.iter().enumerate() {
+                println!("{}: {}", i, label);
+            }
+            println!("{}: {}", paused.stack.len(), paused.expr);
+            return SessionAction::Stay;
+        }
+        (Some("print" | "p"), Some(symbol)) => {
+            match paused.lookup(symbol).await {
+                Ok(Some(value)) => println!("{}", value),
+                Ok(None) => println!("{} is not bound", symbol),
+                Err(e) => println!("{}", e),
+            }
+            return SessionAction::Stay;
+        }
+        (Some("help"), _) | (None, _) => {
+            println!("{}", HELP_SESSION);
+            return SessionAction::Stay;
+        }
+        (Some(c), arg) => {
+            common_command(c, arg);
+            return SessionAction::Stay;
+        }
+    };
+    resume(paused, step);
+    SessionAction::Leave
+}
+
+/// Resumes the paused evaluation with the step, or interrupts it if there is none.
+fn resume(paused: &PausedEval, step: Option<Step>) {
+    let r = match step {
+        Some(step) => paused.resume(step),
+        None => paused.interrupt(),
+    };
+    if let Err(e) = r {
+        println!("{}", e)
+    }
+}
+
+/// Handles the commands available both at the top level and in a debug session.
+fn common_command(command: &str, arg: Option<&str>) {
+    match (command, arg) {
+        ("break", Some(name)) => {
+            DEBUGGER.add_breakpoint(name);
+            println!("breakpoint set on {}", name);
+        }
+        ("delete", Some(name)) => {
+            if !DEBUGGER.remove_breakpoint(name) && !DEBUGGER.detach(name) {
+                println!("no breakpoint on {}", name);
+            }
+        }
+        ("delete", None) => DEBUGGER.clear_breakpoints(),
+        ("breakpoints", _) => {
+            for name in DEBUGGER.breakpoints() {
+                println!("breakpoint on {}", name);
+            }
+            for label in DEBUGGER.attachments() {
+                println!("attached to {}", label);
+            }
+        }
+        ("paused", _) => {
+            for p in DEBUGGER.paused() {
+                println!("{}", p);
+            }
+        }
+        _ => println!("{}", HELP_TOP_LEVEL),
+    }
+}
@@ -1,2 +1,4 @@
+pub mod debugger;
 pub mod lisp_interpreter;
 pub mod repl;
+pub mod server;
@@ -1,14 +1,20 @@
+use crate::debugger::{debug_session, print_paused_reminder, top_level_command};
+use crate::server::{spawn_repl_server, ReplServerConfig};
 use im::HashMap;
 use ompas_middleware::logger::{FileDescriptor, LogClient};
 use ompas_middleware::{Master, ProcessInterface, PROCESS_TOPIC_ALL};
+use ompas_utils::other::get_and_update_id_counter;
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
+use sompas_core::debugger::DEBUGGER;
 use sompas_core::{eval, eval_init, get_root_env, parse};
 use sompas_language::LOG_TOPIC_INTERPRETER;
 use sompas_language::PROCESS_TOPIC_INTERPRETER;
 use sompas_structs::lenv::{ImportType, LEnv};
 use sompas_structs::lmodule::LModule;
 use sompas_structs::lruntimeerror::LResult;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
@@ -19,10 +25,8 @@ const PROCESS_REPL: &str = "__PROCESS_REPL__";
 
 #[derive(Debug)]
 pub struct LispInterpreterChannel {
-    sender: UnboundedSender<String>,
-    receiver: UnboundedReceiver<String>,
-    subscribers: HashMap<usize, UnboundedSender<LResult>>,
-    next_id: usize,
+    receiver: UnboundedReceiver<(usize, String)>,
+    subscriber: LispInterpreterSubscriber,
 }
 
 impl Default for LispInterpreterChannel {
@@ -30,52 +34,91 @@ impl Default for LispInterpreterChannel {
         let (sender, receiver) = mpsc::unbounded_channel();
 
         Self {
-            sender,
             receiver,
-            subscribers: Default::default(),
-            next_id: 0,
+            subscriber: LispInterpreterSubscriber {
+                sender,
+                subscribers: Default::default(),
+                next_id: Default::default(),
+            },
         }
     }
 }
 
 impl LispInterpreterChannel {
     pub fn get_new_subscriber(&mut self) -> ChannelToLispInterpreter {
-        let id = self.next_id;
-        self.next_id += 1;
+        self.subscriber.subscribe()
+    }
+
+    pub fn get_subscriber(&self) -> LispInterpreterSubscriber {
+        self.subscriber.clone()
+    }
+
+    pub async fn recv(&mut self) -> Option<(usize, String)> {
+        self.receiver.recv().await
+    }
+
+    /// Sends the result to the subscriber.
+    /// A subscriber whose channel has been closed is removed.
+    pub fn send(&mut self, id: &usize, result: LResult) -> Result<(), SendError<LResult>> {
+        let mut subscribers = self.subscriber.subscribers.lock().unwrap();
+        let r = match subscribers.get(id) {
+            Some(sender) => sender.send(result),
+            //The channel has been dropped.
+            None => return Err(SendError(result)),
+        };
+        if r.is_err() {
+            subscribers.remove(id);
+        }
+        r
+    }
+}
+
+/// Clonable handle to open new channels to the interpreter, from any task.
+#[derive(Debug, Clone)]
+pub struct LispInterpreterSubscriber {
+    sender: UnboundedSender<(usize, String)>,
+    subscribers: Arc<Mutex<HashMap<usize, UnboundedSender<LResult>>>>,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl LispInterpreterSubscriber {
+    pub fn subscribe(&self) -> ChannelToLispInterpreter {
+        let id = get_and_update_id_counter(self.next_id.clone());
 
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        self.subscribers.insert(id, tx);
+        self.subscribers.lock().unwrap().insert(id, tx);
 
         ChannelToLispInterpreter {
             sender: self.sender.clone(),
             receiver: rx,
             id,
+            subscribers: self.subscribers.clone(),
         }
     }
-
-    pub async fn recv(&mut self) -> Option<String> {
-        self.receiver.recv().await
-    }
-
-    pub fn send(&mut self, id: &usize, result: LResult) -> Result<(), SendError<LResult>> {
-        self.subscribers
-            .get(id)
-            .expect("error getting subscriber's sender")
-            .send(result)
-    }
 }
+
+/// Channel of a subscriber, that is unsubscribed when the channel is dropped.
 #[derive(Debug)]
 pub struct ChannelToLispInterpreter {
-    sender: UnboundedSender<String>,
+    sender: UnboundedSender<(usize, String)>,
     receiver: UnboundedReceiver<LResult>,
     id: usize,
+    subscribers: Arc<Mutex<HashMap<usize, UnboundedSender<LResult>>>>,
+}
+
+impl Drop for ChannelToLispInterpreter {
+    fn drop(&mut self) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.remove(&self.id);
+        }
+    }
 }
 
 impl ChannelToLispInterpreter {
     pub async fn send(&self, msg: String) -> Result<(), SendError<String>> {
-        self.sender.send(self.id.to_string())?;
-        self.sender.send(msg)?;
-        Ok(())
+        self.sender
+            .send((self.id, msg))
+            .map_err(|SendError((_, msg))| SendError(msg))
     }
 
     pub async fn recv(&mut self) -> Option<LResult> {
@@ -97,11 +140,18 @@ impl ChannelToLispInterpreter {
 #[derive(Default, Debug)]
 pub struct LispInterpreterConfig {
     repl: bool,
+    server: Option<ReplServerConfig>,
 }
 
 impl LispInterpreterConfig {
     pub fn new(repl: bool) -> Self {
-        Self { repl }
+        Self { repl, server: None }
+    }
+
+    /// Serves remote sessions while the interpreter runs.
+    pub fn with_server(mut self, server: ReplServerConfig) -> Self {
+        self.server = Some(server);
+        self
     }
 }
 
@@ -125,7 +175,7 @@ impl LispInterpreter {
         self.env.import_module(ctx, ImportType::WithPrefix)
     }
 
-    async fn recv(&mut self) -> Option<String> {
+    async fn recv(&mut self) -> Option<(usize, String)> {
         self.li_channel.recv().await
     }
 
@@ -153,30 +203,30 @@ impl LispInterpreter {
             None
         };
 
+        let handle_server = match self.config.server.take() {
+            Some(config) => match spawn_repl_server(config, self.subscriber()).await {
+                Ok(handle) => Some(handle),
+                Err(e) => {
+                    process_interface.log_error(format!("could not start the repl server: {}", e));
+                    None
+                }
+            },
+            None => None,
+        };
+
         loop {
             tokio::select! {
                 _ = process_interface.recv() => {
                     //process_interface.die().await;
                     break;
                 }
-                id_subscriber = self.recv() => {
-                    let id_subscriber: usize = match id_subscriber {
+                msg = self.recv() => {
+                    let (id_subscriber, str_lvalue) = match msg {
                         None => {
                             process_interface.log_error("Error in the interpretor");
                             continue;
                         }
-                        Some(id) => {
-                            id.parse().unwrap()
-                        }
-                    };
-                    let str_lvalue = match self.recv().await {
-                        None => {
-                            process_interface.log_error("Error in the interpretor");
-                            continue;
-                        }
-                        Some(str) => {
-                            str
-                        }
+                        Some(msg) => msg,
                     };
 
                     if str_lvalue == *"exit" {
@@ -191,16 +241,16 @@ impl LispInterpreter {
                                 Ok(lv) => lv.to_string(),
                                 Err(e) => e.to_string(),
                             }));
-                            self.li_channel
-                                .send(&id_subscriber, result)
-                                .expect("error on channel to stdout");
+                            if self.li_channel.send(&id_subscriber, result).is_err() {
+                                process_interface.log_warn(format!("subscriber {} closed its channel", id_subscriber));
+                            }
                         }
                         Err(e) => {
                             process_interface.log_trace(format!("{} => {}", str_lvalue, e));
 
-                            self.li_channel
-                                .send(&id_subscriber, Err(e))
-                                .expect("error on channel to stdout");
+                            if self.li_channel.send(&id_subscriber, Err(e)).is_err() {
+                                process_interface.log_warn(format!("subscriber {} closed its channel", id_subscriber));
+                            }
                         }
                     };
 
@@ -213,11 +263,18 @@ impl LispInterpreter {
         if let Some(handle) = handle_repl {
             handle.await.expect("Error on task repl");
         }
+        if let Some(handle) = handle_server {
+            handle.abort();
+        }
     }
 
     pub fn subscribe(&mut self) -> ChannelToLispInterpreter {
         self.li_channel.get_new_subscriber()
     }
+
+    pub fn subscriber(&self) -> LispInterpreterSubscriber {
+        self.li_channel.get_subscriber()
+    }
 }
 
 impl LispInterpreter {
@@ -258,13 +315,25 @@ async fn repl(mut com: ChannelToLispInterpreter) {
 
     let l = async {
         loop {
+            print_paused_reminder();
             let readline = rl.readline(">> ");
 
             match readline {
                 Ok(string) => {
                     rl.add_history_entry(string.clone()).unwrap();
+                    if let Some(command) = string.trim().strip_prefix(':') {
+                        top_level_command(&mut rl, command).await;
+                        continue;
+                    }
                     com.send(string).await.expect("couldn't send lisp command");
-                    let buffer = match com.recv_string().await {
+                    //The evaluation may pause on a breakpoint before returning its result.
+                    let buffer = loop {
+                        tokio::select! {
+                            buffer = com.recv_string() => break buffer,
+                            paused = DEBUGGER.wait_paused() => debug_session(&mut rl, paused).await,
+                        }
+                    };
+                    let buffer = match buffer {
                         None => {
                             eprintln!("repl task stopped working");
                             break;
@@ -314,3 +383,19 @@ async fn repl(mut com: ChannelToLispInterpreter) {
 }
 
 pub const EXIT_CODE_STDOUT: &str = "EXIT";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsubscribe() {
+        let channel = LispInterpreterChannel::default();
+        let subscriber = channel.get_subscriber();
+        let com = subscriber.subscribe();
+        assert_eq!(subscriber.subscribers.lock().unwrap().len(), 1);
+        //The subscriber is removed when its channel is dropped, as at the end of a session.
+        drop(com);
+        assert!(subscriber.subscribers.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,453 @@
+//! Remote REPL server, to drive a running interpreter from another terminal or a script.
+//!
+//! The protocol is line based, so that it can also be used with tools like `nc` or `socat`.
+//! The first line sent by the client must be `:auth <token>`, within a few seconds.
+//! The server refuses to start with an empty token.
+//! The server answers `+ <message>` if the token is valid, or `! <message>` before closing the connection.
+//!
+//! The following lines are expressions, that can span several lines:
+//! they are evaluated as soon as their parentheses are balanced.
+//! Outside of an expression, a line starting with `:` is a session command:
+//! - `:log <topic>` streams the lines of a log topic,
+//! - `:unlog <topic>` stops the stream of a log topic,
+//! - `:topics` lists the log topics,
+//! - `:quit` closes the session (as does `exit`, which does not stop the interpreter).
+//!
+//! Each line of a response is prefixed by `= ` for a result, `! ` for an error and `+ ` for a command,
+//! and a response ends with a line containing only `.`.
+//! Lines of streamed log topics are prefixed by `# ` and may arrive at any time.
+use crate::lisp_interpreter::{ChannelToLispInterpreter, LispInterpreterSubscriber};
+use ompas_middleware::logger::{LogClient, LogStream};
+use ompas_middleware::Master;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+
+pub const AUTH_COMMAND: &str = "auth";
+pub const LOG_COMMAND: &str = "log";
+pub const UNLOG_COMMAND: &str = "unlog";
+pub const TOPICS_COMMAND: &str = "topics";
+pub const QUIT_COMMAND: &str = "quit";
+pub const EXIT_EXPRESSION: &str = "exit";
+
+pub const RESULT_PREFIX: &str = "= ";
+pub const ERROR_PREFIX: &str = "! ";
+pub const INFO_PREFIX: &str = "+ ";
+pub const LOG_PREFIX: &str = "# ";
+pub const END_OF_RESPONSE: &str = ".";
+
+pub const UNIX_ADDRESS_PREFIX: &str = "unix:";
+/// Environment variable read for the token when it is not given on the command line.
+pub const REPL_TOKEN_ENV_VAR: &str = "SOMPAS_REPL_TOKEN";
+
+/// Time given to a client to authenticate.
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum length of the authentication line, read before the client is trusted.
+const MAX_AUTH_LINE_LENGTH: u64 = 1024;
+
+const PROCESS_REPL_SERVER: &str = "__PROCESS_REPL_SERVER__";
+const LOG_TOPIC_REPL_SERVER: &str = "__LOG_TOPIC_REPL_SERVER__";
+
+/// Address on which the server listens.
+#[derive(Debug, Clone)]
+pub enum ReplServerAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Display for ReplServerAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplServerAddress::Tcp(addr) => write!(f, "tcp://{}", addr),
+            ReplServerAddress::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// Parses `unix:<path>` as a unix socket, and anything else as a tcp address like `127.0.0.1:8257`.
+impl FromStr for ReplServerAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(UNIX_ADDRESS_PREFIX) {
+            Some(path) => Ok(Self::Unix(path.into())),
+            None => s
+                .parse()
+                .map(Self::Tcp)
+                .map_err(|e| format!("invalid address {}: {}", s, e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplServerConfig {
+    pub address: ReplServerAddress,
+    /// Token the clients must send to open a session.
+    pub token: String,
+}
+
+impl ReplServerConfig {
+    pub fn new(address: ReplServerAddress, token: impl Display) -> Self {
+        Self {
+            address,
+            token: token.to_string(),
+        }
+    }
+}
+
+/// Spawns the server, each accepted connection being bound to a new channel to the interpreter.
+/// Returns an error if the token is empty, as any client would then be accepted.
+pub async fn spawn_repl_server(
+    config: ReplServerConfig,
+    subscriber: LispInterpreterSubscriber,
+) -> std::io::Result<JoinHandle<()>> {
+    if config.token.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the token of the repl server is empty",
+        ));
+    }
+    let log = LogClient::new(PROCESS_REPL_SERVER, LOG_TOPIC_REPL_SERVER).await;
+    log.info(format!("listening on {}", config.address));
+    let token = config.token;
+    match config.address {
+        ReplServerAddress::Tcp(addr) => {
+            let listener = TcpListener::bind(addr).await?;
+            Ok(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, peer)) => {
+                            log.info(format!("new connection from {}", peer));
+                            tokio::spawn(session(
+                                stream,
+                                token.clone(),
+                                subscriber.subscribe(),
+                                log.clone(),
+                            ));
+                        }
+                        Err(e) => log.error(format!("error accepting connection: {}", e)),
+                    }
+                }
+            }))
+        }
+        ReplServerAddress::Unix(path) => {
+            //Removes the socket left by a previous run.
+            if std::fs::metadata(&path).is_ok_and(|m| m.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = UnixListener::bind(path)?;
+            Ok(tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            log.info("new connection on unix socket");
+                            tokio::spawn(session(
+                                stream,
+                                token.clone(),
+                                subscriber.subscribe(),
+                                log.clone(),
+                            ));
+                        }
+                        Err(e) => log.error(format!("error accepting connection: {}", e)),
+                    }
+                }
+            }))
+        }
+    }
+}
+
+/// Handles a session until the client disconnects.
+async fn session<S: AsyncRead + AsyncWrite + Send + 'static>(
+    stream: S,
+    token: String,
+    mut com: ChannelToLispInterpreter,
+    log: LogClient,
+) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    //Every message sent to the client goes through this channel, as log lines are written by other tasks.
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if writer.write_all(msg.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    //The client is not trusted yet: the line is bounded in time and length.
+    let mut line = String::new();
+    let mut auth_reader = (&mut reader).take(MAX_AUTH_LINE_LENGTH);
+    let read = auth_reader.read_line(&mut line);
+    let authenticated = match tokio::time::timeout(AUTH_TIMEOUT, read).await {
+        Ok(Ok(_)) if line.ends_with('\n') => match parse_command(&line) {
+            Some((AUTH_COMMAND, t)) => check_token(t, &token),
+            _ => false,
+        },
+        _ => false,
+    };
+    if !authenticated {
+        log.warn("session rejected: invalid token");
+        let _ = tx.send(response(ERROR_PREFIX, "invalid token"));
+        drop(tx);
+        let _ = writer_task.await;
+        return;
+    }
+    let _ = tx.send(response(INFO_PREFIX, "session opened"));
+    let mut lines = reader.lines();
+
+    let mut log_streams: HashMap<String, JoinHandle<()>> = Default::default();
+    let mut buffer = String::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if buffer.is_empty() {
+            if let Some((command, arg)) = parse_command(&line) {
+                let msg = match command {
+                    QUIT_COMMAND => break,
+                    LOG_COMMAND => match Master::stream_log_topic(arg).await {
+                        Some(stream) => {
+                            let handle = spawn_log_stream(stream, tx.clone());
+                            if let Some(old) = log_streams.insert(arg.to_string(), handle) {
+                                old.abort();
+                            }
+                            response(INFO_PREFIX, format!("streaming {}", arg))
+                        }
+                        None => response(ERROR_PREFIX, format!("unknown log topic {}", arg)),
+                    },
+                    UNLOG_COMMAND => match log_streams.remove(arg) {
+                        Some(handle) => {
+                            handle.abort();
+                            response(INFO_PREFIX, format!("stopped streaming {}", arg))
+                        }
+                        None => response(ERROR_PREFIX, format!("{} is not streamed", arg)),
+                    },
+                    TOPICS_COMMAND => {
+                        let mut topics = Master::get_log_topics().await;
+                        topics.sort();
+                        response(INFO_PREFIX, topics.join("\n"))
+                    }
+                    _ => response(ERROR_PREFIX, format!("unknown command :{}", command)),
+                };
+                let _ = tx.send(msg);
+                continue;
+            }
+            if line.trim() == EXIT_EXPRESSION {
+                break;
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !is_complete_expression(&buffer) {
+            continue;
+        }
+        let expr = std::mem::take(&mut buffer);
+        if expr.trim().is_empty() {
+            continue;
+        }
+        if com.send(expr).await.is_err() {
+            let _ = tx.send(response(ERROR_PREFIX, "interpreter stopped"));
+            break;
+        }
+        let msg = match com.recv().await {
+            Some(Ok(lv)) => response(RESULT_PREFIX, lv),
+            Some(Err(e)) => response(ERROR_PREFIX, e),
+            None => {
+                let _ = tx.send(response(ERROR_PREFIX, "interpreter stopped"));
+                break;
+            }
+        };
+        let _ = tx.send(msg);
+    }
+
+    for handle in log_streams.values() {
+        handle.abort();
+    }
+    com.close();
+    drop(tx);
+    let _ = writer_task.await;
+    log.info("session closed");
+}
+
+fn spawn_log_stream(mut stream: LogStream, tx: UnboundedSender<String>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(line) = stream.recv().await {
+            let mut msg = String::new();
+            for l in line.lines() {
+                msg.push_str(LOG_PREFIX);
+                msg.push_str(l);
+                msg.push('\n');
+            }
+            if tx.send(msg).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Splits a session command of the form `:<command> <arg>`.
+fn parse_command(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim().strip_prefix(':')?;
+    Some(match line.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (line, ""),
+    })
+}
+
+/// Compares the tokens in a time that does not depend on the position of the first difference.
+fn check_token(received: &str, expected: &str) -> bool {
+    received.len() == expected.len()
+        && received
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+/// Formats a response, prefixing each of its lines and adding the end of response marker.
+pub fn response(prefix: &str, content: impl Display) -> String {
+    let content = content.to_string();
+    let mut msg = String::new();
+    for line in content.lines() {
+        msg.push_str(prefix);
+        msg.push_str(line);
+        msg.push('\n');
+    }
+    if content.is_empty() {
+        msg.push_str(prefix);
+        msg.push('\n');
+    }
+    msg.push_str(END_OF_RESPONSE);
+    msg.push('\n');
+    msg
+}
+
+/// Returns true if all the parentheses opened in the input are closed.
+/// Parentheses in strings and comments are ignored.
+pub fn is_complete_expression(input: &str) -> bool {
+    let mut depth: i64 = 0;
+    let mut in_string = false;
+    let mut in_comment = false;
+    let mut escaped = false;
+    for c in input.chars() {
+        if in_comment {
+            in_comment = c != '\n';
+        } else if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else {
+            match c {
+                '"' => in_string = true,
+                ';' => in_comment = true,
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+    depth <= 0 && !in_string
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lisp_interpreter::{LispInterpreter, LispInterpreterConfig};
+    use tokio::net::UnixStream;
+
+    #[test]
+    fn test_is_complete_expression() {
+        assert!(is_complete_expression("(+ 1 2)"));
+        assert!(is_complete_expression("x"));
+        assert!(!is_complete_expression("(define (f x)\n"));
+        assert!(is_complete_expression("(define (f x)\n  (* x 2))\n"));
+        assert!(!is_complete_expression("(print \")\""));
+        assert!(is_complete_expression("(print \"(\\\"\") ; (\n"));
+        assert!(!is_complete_expression("\"abc"));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(":log  topic "), Some(("log", "topic")));
+        assert_eq!(parse_command(":quit"), Some(("quit", "")));
+        assert_eq!(parse_command("(+ 1 2)"), None);
+        assert!(check_token("secret", "secret"));
+        assert!(!check_token("secreT", "secret"));
+        assert!(!check_token("secret2", "secret"));
+        assert!(matches!(
+            "127.0.0.1:8257".parse(),
+            Ok(ReplServerAddress::Tcp(_))
+        ));
+        assert!(matches!(
+            "unix:/tmp/ompas.sock".parse(),
+            Ok(ReplServerAddress::Unix(_))
+        ));
+        assert!("localhost".parse::<ReplServerAddress>().is_err());
+    }
+
+    /// Connects to the socket once the server listens, and returns the lines sent by the server.
+    async fn connect(
+        path: &PathBuf,
+    ) -> (
+        tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+        tokio::net::unix::OwnedWriteHalf,
+    ) {
+        loop {
+            if let Ok(stream) = UnixStream::connect(path).await {
+                let (reader, writer) = stream.into_split();
+                return (BufReader::new(reader).lines(), writer);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session() {
+        let path = std::env::temp_dir().join(format!("sompas-repl-{}.sock", std::process::id()));
+        let address = ReplServerAddress::Unix(path.clone());
+        let mut li = LispInterpreter::new().await;
+        let config = ReplServerConfig::new(address.clone(), "");
+        assert!(spawn_repl_server(config, li.subscriber()).await.is_err());
+        let config = ReplServerConfig::new(address, "secret");
+        li.set_config(LispInterpreterConfig::new(false).with_server(config));
+        tokio::spawn(li.run(None));
+
+        //The connection is closed after a wrong token.
+        let (mut lines, mut writer) = connect(&path).await;
+        writer.write_all(b":auth wrong\n").await.unwrap();
+        for expected in ["! invalid token", "."] {
+            assert_eq!(lines.next_line().await.unwrap().as_deref(), Some(expected));
+        }
+        assert_eq!(lines.next_line().await.unwrap(), None);
+
+        let (mut lines, mut writer) = connect(&path).await;
+        writer
+            .write_all(b":auth secret\n(+ 1\n 2)\n:quit\n")
+            .await
+            .unwrap();
+        for expected in ["+ session opened", ".", "= 3", "."] {
+            assert_eq!(lines.next_line().await.unwrap().as_deref(), Some(expected));
+        }
+        assert_eq!(lines.next_line().await.unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_response() {
+        assert_eq!(response(RESULT_PREFIX, "(1\n 2)"), "= (1\n=  2)\n.\n");
+        assert_eq!(response(INFO_PREFIX, ""), "+ \n.\n");
+    }
+}
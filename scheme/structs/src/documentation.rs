@@ -39,6 +39,10 @@ impl DocCollection {
         }
     }
 
+    pub fn get_doc(&self, sym: &str) -> Option<&Doc> {
+        self.inner.get(sym)
+    }
+
     pub fn get_mut(&mut self, sym: &str) -> Option<&mut Doc> {
         self.inner.get_mut(sym)
     }
@@ -4,6 +4,7 @@ use crate::llambda::LLambda;
 use crate::lmodule::{InitScheme, LModule};
 use crate::lprimitive::LPrimitive::*;
 use crate::lruntimeerror;
+use crate::lsyntax::LSyntaxRules;
 use crate::lvalue::LValue;
 use crate::purefonction::PureFonctionCollection;
 use im::{hashmap, HashSet};
@@ -14,10 +15,15 @@ use std::mem;
 use std::ops::Deref;
 use std::sync::Arc;
 
+/// Maximal number of layers inspected below the captured symbols of a lambda when compacting.
+const COMPACTION_DEPTH: usize = 8;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct LEnvSymbols {
     inner: im::HashMap<String, LValue>,
     outer: Arc<Option<LEnvSymbols>>,
+    /// True if the layer contains the parameters and local definitions of a lambda call.
+    local: bool,
 }
 
 impl Default for LEnvSymbols {
@@ -44,8 +50,16 @@ impl Default for LEnvSymbols {
                 Err.to_string() => Err.into(),
                 Enr.to_string() => Enr.into(),
                 Race.to_string() => Race.into(),
+                While.to_string() => While.into(),
+                For.to_string() => For.into(),
+                Try.to_string() => Try.into(),
+                Raise.to_string() => Raise.into(),
+                DefSyntax.to_string() => DefSyntax.into(),
+                DefModule.to_string() => DefModule.into(),
+                Import.to_string() => Import.into(),
             },
             outer: Arc::new(None),
+            local: false,
         }
     }
 }
@@ -77,6 +91,86 @@ impl LEnvSymbols {
         }
     }
 
+    /// Returns the symbols captured by a lambda defined in this environment.
+    /// If the top layer is local to a lambda call, it is merged with the symbols captured by
+    /// the called lambda.
+    pub fn captured(&self) -> LEnvSymbols {
+        match (self.local, self.outer.deref()) {
+            (true, Some(outer)) => {
+                let mut captured = outer.clone();
+                for (k, v) in &self.inner {
+                    captured.inner.insert(k.clone(), v.clone());
+                }
+                captured.outer = Arc::new(None);
+                captured
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Compacts the chain of layers of a lambda call, without changing the result of any lookup.
+    /// The top layer is the local layer of the call, and the next one the symbols captured by the lambda.
+    /// Below them, the layers that are the same map as the captured symbols are shadowed and removed,
+    /// and consecutive local layers are merged.
+    /// Only the first layers are inspected, so the cost of a call remains constant.
+    pub fn compact(&mut self) {
+        let captured = match self.outer.deref() {
+            Some(captured) => captured,
+            None => return,
+        };
+        let mut layers: Vec<LEnvSymbols> = vec![];
+        let mut rest = captured.outer.clone();
+        let mut changed = false;
+        while layers.len() < COMPACTION_DEPTH {
+            let next = match rest.deref() {
+                Some(next) => next.clone(),
+                None => break,
+            };
+            rest = next.outer.clone();
+            if !next.local && next.inner.ptr_eq(&captured.inner) {
+                changed = true;
+                continue;
+            }
+            match layers.last_mut() {
+                Some(last) if last.local && next.local => {
+                    let mut merged = next.inner;
+                    for (k, v) in &last.inner {
+                        merged.insert(k.clone(), v.clone());
+                    }
+                    last.inner = merged;
+                    changed = true;
+                }
+                _ => layers.push(next),
+            }
+        }
+        if changed {
+            let mut outer = rest;
+            for mut layer in layers.into_iter().rev() {
+                layer.outer = outer;
+                outer = Arc::new(Some(layer));
+            }
+            let mut captured = captured.clone();
+            captured.outer = outer;
+            self.outer = Arc::new(Some(captured));
+        }
+    }
+
+    /// Returns the bindings of the local layers on top of the symbols,
+    /// i.e. the parameters and local definitions of the current lambda call.
+    pub fn local_bindings(&self) -> im::HashMap<String, LValue> {
+        if !self.local {
+            return Default::default();
+        }
+        let mut bindings = match self.outer.deref() {
+            Some(outer) => outer.local_bindings(),
+            None => Default::default(),
+        };
+        for (k, v) in &self.inner {
+            bindings.insert(k.clone(), v.clone());
+        }
+        bindings
+    }
+
     pub fn keys(&self) -> HashSet<String> {
         let mut keys: HashSet<String> = self.inner.keys().cloned().collect();
         if let Some(outer) = &*self.outer {
@@ -98,12 +192,16 @@ impl Display for LEnvSymbols {
 
 /// Structs used to store the Scheme Environment
 /// - It contains a mapping of <symbol(String), LValue>
-/// - It also contains macros, special LLambdas used to format LValue expressions.
+/// - It also contains macros, special LLambdas used to format LValue expressions,
+///   and syntaxes, macros defined by syntax rules.
+/// - It contains the modules defined in Scheme, that can be imported in the environment.
 /// - A LEnv can inherits from an outer environment. It can use symbols from it, but not modify them.
 #[derive(Clone, Debug, Default)]
 pub struct LEnv {
     symbols: LEnvSymbols,
     macro_table: im::HashMap<String, LLambda>,
+    syntax_table: im::HashMap<String, LSyntaxRules>,
+    modules: im::HashMap<String, LModule>,
     ctxs: ContextCollection,
     pfc: PureFonctionCollection,
     documentation: DocCollection,
@@ -158,10 +256,47 @@ impl LEnv {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub enum ImportType {
     WithPrefix,
     WithoutPrefix,
+    /// Imports a selection of the bindings of the module, with an optional prefix.
+    Select(ImportSelection),
+}
+
+impl ImportType {
+    /// Returns the name under which a symbol of a module is imported, if it is imported.
+    pub fn imported_name(&self, module: &str, sym: &str) -> Option<String> {
+        match self {
+            ImportType::WithPrefix => Some(format!("{}::{}", module, sym)),
+            ImportType::WithoutPrefix => Some(sym.to_string()),
+            ImportType::Select(selection) => selection.imported_name(sym),
+        }
+    }
+}
+
+/// Selection of the bindings imported from a module.
+#[derive(Debug, Clone, Default)]
+pub struct ImportSelection {
+    /// Prefix of the imported symbols, imported as <prefix>::<symbol>.
+    pub prefix: Option<String>,
+    /// Symbols imported, all of them if None.
+    pub only: Option<Vec<String>>,
+    /// Symbols not imported.
+    pub except: Vec<String>,
+}
+
+impl ImportSelection {
+    pub fn imported_name(&self, sym: &str) -> Option<String> {
+        let sym = sym.to_string();
+        if self.except.contains(&sym) || matches!(&self.only, Some(only) if !only.contains(&sym)) {
+            return None;
+        }
+        Some(match &self.prefix {
+            Some(prefix) => format!("{}::{}", prefix, sym),
+            None => sym,
+        })
+    }
 }
 
 impl LEnv {
@@ -178,13 +313,18 @@ impl LEnv {
             self.add_context(module.ctx);
             //println!("id: {}", id);
             for (sym, lv) in module.bindings {
-                match import_type {
-                    ImportType::WithPrefix => {
-                        self.insert(format!("{}::{}", module.label, sym), lv.clone());
-                    }
-                    ImportType::WithoutPrefix => {
-                        self.insert(sym.to_string(), lv.clone());
-                    }
+                if let Some(sym) = import_type.imported_name(&module.label, &sym) {
+                    self.insert(sym, lv);
+                }
+            }
+            for (sym, m) in module.macros {
+                if let Some(sym) = import_type.imported_name(&module.label, &sym) {
+                    self.add_macro(sym, m);
+                }
+            }
+            for (sym, rules) in module.syntax {
+                if let Some(sym) = import_type.imported_name(&module.label, &sym) {
+                    self.add_syntax(sym, rules);
                 }
             }
             for (context, doc) in module.subcontexts {
@@ -222,10 +362,34 @@ impl LEnv {
         self.symbols = symbols;
     }
 
+    /// Adds an empty layer on top of the symbols, for the parameters and local definitions of a lambda call.
+    pub fn new_local_symbols(&mut self) {
+        self.symbols = LEnvSymbols {
+            inner: Default::default(),
+            outer: Arc::new(Some(self.symbols.clone())),
+            local: true,
+        };
+    }
+
+    /// Returns the symbols captured by a lambda defined in the environment.
+    pub fn get_captured_symbols(&self) -> LEnvSymbols {
+        self.symbols.captured()
+    }
+
+    /// Compacts the symbols of a lambda call. See [LEnvSymbols::compact].
+    pub fn compact_symbols(&mut self) {
+        self.symbols.compact()
+    }
+
     pub fn get_symbol(&self, s: &str) -> Option<LValue> {
         self.symbols.get(s)
     }
 
+    /// Returns the bindings local to the current lambda call. See [LEnvSymbols::local_bindings].
+    pub fn local_bindings(&self) -> im::HashMap<String, LValue> {
+        self.symbols.local_bindings()
+    }
+
     pub fn get_ref_symbol(&self, s: &str) -> Option<&LValue> {
         self.symbols.get_ref(s)
     }
@@ -241,9 +405,33 @@ impl LEnv {
     }
 
     pub fn add_macro(&mut self, key: String, _macro: LLambda) {
+        self.syntax_table.remove(&key);
         self.macro_table.insert(key, _macro);
     }
 
+    pub fn add_syntax(&mut self, key: String, rules: LSyntaxRules) {
+        self.macro_table.remove(&key);
+        self.syntax_table.insert(key, rules);
+    }
+
+    pub fn get_syntax(&self, key: &str) -> Option<&LSyntaxRules> {
+        self.syntax_table.get(key)
+    }
+
+    /// Returns true if the key is a macro or a syntax.
+    pub fn is_macro(&self, key: &str) -> bool {
+        self.macro_table.contains_key(key) || self.syntax_table.contains_key(key)
+    }
+
+    /// Adds a module defined in Scheme, that can then be imported.
+    pub fn add_module(&mut self, module: LModule) {
+        self.modules.insert(module.label.clone(), module);
+    }
+
+    pub fn get_module(&self, label: &str) -> Option<&LModule> {
+        self.modules.get(label)
+    }
+
     pub fn get_contexts_labels(&self) -> Vec<String> {
         self.ctxs.get_contexts_labels()
     }
@@ -257,7 +445,11 @@ impl LEnv {
     }
 
     pub fn macros(&self) -> HashSet<String> {
-        self.macro_table.keys().cloned().collect()
+        self.macro_table
+            .keys()
+            .chain(self.syntax_table.keys())
+            .cloned()
+            .collect()
     }
 }
 
@@ -274,6 +466,7 @@ impl PartialEq for LEnv {
             && self.pfc == other.pfc
             && self.documentation == other.documentation
             && self.macro_table == other.macro_table
+            && self.syntax_table == other.syntax_table
             && self.symbols == other.symbols
     }
 }
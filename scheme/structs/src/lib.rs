@@ -13,6 +13,7 @@ pub mod lnumber;
 pub mod lprimitive;
 pub mod lruntimeerror;
 pub mod lswitch;
+pub mod lsyntax;
 pub mod lvalue;
 pub mod lvalues;
 pub mod macros;
@@ -50,6 +50,7 @@ impl LLambda {
     #[named]
     pub fn get_new_env(&self, mut env: LEnv, args: &[LValue]) -> Result<LEnv, LRuntimeError> {
         env.set_new_top_symbols(self.env.clone());
+        env.new_local_symbols();
 
         match &self.params {
             LambdaArgs::Sym(param) => {
@@ -77,6 +78,7 @@ impl LLambda {
                 }
             }
         };
+        env.compact_symbols();
         Ok(env)
     }
 
@@ -4,6 +4,8 @@ use crate::function::{
     AsyncNativeFn, AsyncNativeMutFn, LAsyncFn, LAsyncMutFn, LFn, LMutFn, NativeFn, NativeMutFn,
 };
 use crate::lenv::ImportType;
+use crate::llambda::LLambda;
+use crate::lsyntax::LSyntaxRules;
 use crate::lvalue::LValue;
 use crate::purefonction::PureFonctionCollection;
 use std::any::Any;
@@ -33,9 +35,12 @@ impl InitScheme {
 }
 
 /// Struct to define a Module, Library that will be loaded inside the Scheme Environment.
+#[derive(Clone, Debug)]
 pub struct LModule {
     pub(crate) ctx: Context,
     pub(crate) bindings: Vec<(String, LValue)>,
+    pub(crate) macros: Vec<(String, LLambda)>,
+    pub(crate) syntax: Vec<(String, LSyntaxRules)>,
     pub(crate) prelude: InitScheme,
     pub(crate) label: String,
     pub(crate) documentation: DocCollection,
@@ -54,6 +59,8 @@ impl LModule {
         let mut module = Self {
             ctx: Context::new(ctx, label.to_string()),
             bindings: vec![],
+            macros: vec![],
+            syntax: vec![],
             prelude: Default::default(),
             label: label.to_string(),
             documentation: Default::default(),
@@ -185,6 +192,18 @@ impl LModule {
         self.bindings.push((label.into(), lv));
         self.add_doc(label, doc, "Value");
     }
+
+    /// Add a macro already evaluated, contrary to [LModule::add_macro].
+    pub fn add_macro_lambda(&mut self, label: &str, lambda: LLambda, doc: impl Into<Doc>) {
+        self.macros.push((label.into(), lambda));
+        self.add_doc(label, doc, "Macro");
+    }
+
+    /// Add a macro defined by syntax rules.
+    pub fn add_syntax(&mut self, label: &str, rules: LSyntaxRules, doc: impl Into<Doc>) {
+        self.syntax.push((label.into(), rules));
+        self.add_doc(label, doc, "Syntax");
+    }
 }
 
 impl From<()> for LModule {
@@ -192,6 +211,8 @@ impl From<()> for LModule {
         Self {
             ctx: Context::new(t, "()"),
             bindings: vec![],
+            macros: vec![],
+            syntax: vec![],
             prelude: Default::default(),
             label: "".to_string(),
             documentation: Default::default(),
@@ -19,6 +19,13 @@ use std::fmt::{Display, Formatter};
 /// - Async: Evaluates in an asynchronous task a LValue.
 /// - Await: Wait on a pid the result of an async.
 /// - Eval: Evaluates an expression.
+/// - While: evaluates a body as long as a condition is true.
+/// - For: iterates over a list or a range of integers, whose values are produced one at a time.
+/// - Try: handles the errors of an expression, and evaluates cleanup expressions once it ends.
+/// - Raise: raises an error up to the innermost Try.
+/// - DefSyntax: insert a new hygienic macro defined by syntax rules in the environment.
+/// - DefModule: defines a new module, whose definitions are visible once imported.
+/// - Import: imports the definitions of a module in the environment.
 #[derive(Hash, Copy, Clone, PartialOrd, PartialEq, Eq, Debug, Serialize, Deserialize)]
 #[serde(untagged, rename_all = "lowercase")]
 pub enum LPrimitive {
@@ -43,6 +50,13 @@ pub enum LPrimitive {
     Enr,
     //QuasiInterruptible,
     Race,
+    While,
+    For,
+    Try,
+    Raise,
+    DefSyntax,
+    DefModule,
+    Import,
 }
 
 impl Display for LPrimitive {
@@ -68,6 +82,13 @@ impl Display for LPrimitive {
             LPrimitive::Race => RACE,
             LPrimitive::Enr => ENR,
             LPrimitive::Err => ERR,
+            LPrimitive::While => WHILE,
+            LPrimitive::For => FOR,
+            LPrimitive::Try => TRY,
+            LPrimitive::Raise => RAISE,
+            LPrimitive::DefSyntax => DEFINE_SYNTAX,
+            LPrimitive::DefModule => DEFINE_MODULE,
+            LPrimitive::Import => IMPORT,
         };
 
         write!(f, "{}", str)
@@ -105,6 +126,13 @@ impl TryFrom<&str> for LPrimitive {
             RACE => Ok(LPrimitive::Race),
             ENR => Ok(LPrimitive::Enr),
             ERR => Ok(LPrimitive::Err),
+            WHILE => Ok(LPrimitive::While),
+            FOR => Ok(LPrimitive::For),
+            TRY => Ok(LPrimitive::Try),
+            RAISE => Ok(LPrimitive::Raise),
+            DEFINE_SYNTAX => Ok(LPrimitive::DefSyntax),
+            DEFINE_MODULE => Ok(LPrimitive::DefModule),
+            IMPORT => Ok(LPrimitive::Import),
             //QUASI_INTERRUPTIBLE => Ok(LCoreOperator::QuasiInterruptible),
             _ => Err(LRuntimeError::new(
                 "LCoreOperator::TryFrom<str>",
@@ -0,0 +1,475 @@
+use crate::lprimitive::LPrimitive;
+use crate::lruntimeerror::LRuntimeError;
+use crate::lvalue::LValue;
+use crate::{list, lruntimeerror, symbol};
+use sompas_language::primitives::{
+    DEFINE, FN_LAMBDA, FOR, QUOTE, SYNTAX_ELLIPSIS, SYNTAX_RULES, SYNTAX_WILDCARD, TRY_CATCH,
+};
+use sompas_language::utils::{LET, LET_STAR};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counter used to rename the symbols bound by the templates.
+static RENAMING: AtomicUsize = AtomicUsize::new(0);
+
+/// Value bound to a pattern variable.
+/// A variable followed by an ellipsis in the pattern is bound to the sequence of matched values.
+#[derive(Clone, Debug)]
+enum Binding {
+    One(LValue),
+    Seq(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+/// Hygienic macro defined by a list of syntax rules.
+/// A rule is a pattern and a template. The form is rewritten with the template of the first rule
+/// whose pattern matches it, the pattern variables being substituted by the matched expressions.
+/// The symbols bound in a template (parameters of a lambda, variables of a let, for or catch,
+/// and definitions) are renamed with fresh symbols at each expansion, so that they do not capture
+/// the symbols of the form.
+/// The free symbols of the templates that are bound where the syntax is defined are replaced by
+/// their values, so that the syntax does not depend on the bindings of the form.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LSyntaxRules {
+    literals: Vec<String>,
+    rules: Vec<(LValue, LValue)>,
+    closure: HashMap<String, LValue>,
+}
+
+impl Display for LSyntaxRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} ({})", SYNTAX_RULES, self.literals.join(" "))?;
+        for (pattern, template) in &self.rules {
+            write!(f, " ({} {})", pattern, template)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl TryFrom<&LValue> for LSyntaxRules {
+    type Error = LRuntimeError;
+
+    /// Builds the rules from an expression (syntax-rules (<literal>...) (<pattern> <template>)...).
+    fn try_from(lv: &LValue) -> Result<Self, Self::Error> {
+        let error = || {
+            lruntimeerror!(
+                SYNTAX_RULES,
+                format!(
+                    "{}: expected ({} (<literal>...) (<pattern> <template>)...)",
+                    lv, SYNTAX_RULES
+                )
+            )
+        };
+        let list = match lv {
+            LValue::List(list) if list.len() >= 2 => list,
+            _ => return Err(error()),
+        };
+        if !matches!(&list[0], LValue::Symbol(s) if s.as_str() == SYNTAX_RULES) {
+            return Err(error());
+        }
+        let literals = match &list[1] {
+            LValue::Nil => vec![],
+            LValue::List(literals) => literals
+                .iter()
+                .map(|l| match l {
+                    LValue::Symbol(s) => Ok(s.to_string()),
+                    _ => Err(error()),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => return Err(error()),
+        };
+        let rules = list[2..]
+            .iter()
+            .map(|rule| match rule {
+                LValue::List(rule) if rule.len() == 2 && matches!(rule[0], LValue::List(_)) => {
+                    Ok((rule[0].clone(), rule[1].clone()))
+                }
+                _ => Err(error()),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            literals,
+            rules,
+            closure: Default::default(),
+        })
+    }
+}
+
+impl LSyntaxRules {
+    /// Binds the free symbols of the templates to their values in the environment defining the
+    /// syntax, as the definitions of a module, that are not visible where the syntax is imported.
+    pub fn close_over(&mut self, bindings: impl IntoIterator<Item = (String, LValue)>) {
+        let mut symbols = HashSet::new();
+        for (_, template) in &self.rules {
+            symbols_of(template, &mut symbols);
+        }
+        let closure: HashMap<String, LValue> = bindings
+            .into_iter()
+            .filter(|(s, _)| symbols.contains(s.as_str()))
+            .map(|(s, lv)| {
+                //The value is inserted in the expansion, and must evaluate to itself.
+                let lv = match lv {
+                    LValue::List(_) | LValue::Symbol(_) => list![LPrimitive::Quote.into(), lv],
+                    lv => lv,
+                };
+                (s, lv)
+            })
+            .collect();
+        self.closure.extend(closure);
+    }
+
+    /// Rewrites a form with the first rule whose pattern matches it.
+    /// The keyword of the macro at the head of the pattern is ignored.
+    pub fn transcribe(&self, form: &LValue) -> Result<LValue, LRuntimeError> {
+        let args = match form {
+            LValue::List(list) => &list[1..],
+            _ => &[],
+        };
+        for (pattern, template) in &self.rules {
+            let pattern = match pattern {
+                LValue::List(pattern) => &pattern[1..],
+                _ => continue,
+            };
+            let mut bindings = Bindings::new();
+            if self.match_list(pattern, args, &mut bindings) {
+                let mut bound = HashSet::new();
+                bound_symbols(template, &bindings, &mut bound);
+                let n = RENAMING.fetch_add(1, Ordering::Relaxed);
+                //The symbols bound by the template shadow the closure.
+                let mut renaming = self.closure.clone();
+                renaming.extend(bound.into_iter().map(|s| {
+                    let renamed = symbol!(format!("{}#{}", s, n));
+                    (s, renamed)
+                }));
+                return instantiate(template, &bindings, &renaming, false);
+            }
+        }
+        Err(lruntimeerror!(
+            SYNTAX_RULES,
+            format!("{}: no syntax rule matches the form", form)
+        ))
+    }
+
+    fn match_pattern(&self, pattern: &LValue, lv: &LValue, bindings: &mut Bindings) -> bool {
+        match pattern {
+            LValue::Symbol(s) if s.as_str() == SYNTAX_WILDCARD => true,
+            LValue::Symbol(s) if self.literals.contains(s) => lv == pattern,
+            LValue::Symbol(s) => {
+                bindings.insert(s.to_string(), Binding::One(lv.clone()));
+                true
+            }
+            LValue::List(pattern) => match lv {
+                LValue::List(list) => self.match_list(pattern, list, bindings),
+                LValue::Nil => self.match_list(pattern, &[], bindings),
+                _ => false,
+            },
+            LValue::Nil => match lv {
+                LValue::List(list) => list.is_empty(),
+                lv => lv == &LValue::Nil,
+            },
+            pattern => lv == pattern,
+        }
+    }
+
+    /// Matches a list of elements, the pattern preceding an ellipsis matching any number of them.
+    fn match_list(&self, pattern: &[LValue], list: &[LValue], bindings: &mut Bindings) -> bool {
+        let ellipsis = pattern.iter().position(is_ellipsis);
+        let (before, repeated, after) = match ellipsis {
+            Some(i) if i > 0 => (&pattern[..i - 1], Some(&pattern[i - 1]), &pattern[i + 1..]),
+            Some(_) => return false,
+            None => (pattern, None, &pattern[0..0]),
+        };
+        let repeated = match repeated {
+            Some(repeated) if list.len() >= before.len() + after.len() => repeated,
+            None if list.len() == before.len() => {
+                return before
+                    .iter()
+                    .zip(list)
+                    .all(|(p, lv)| self.match_pattern(p, lv, bindings))
+            }
+            _ => return false,
+        };
+        let end = list.len() - after.len();
+        if !before
+            .iter()
+            .zip(&list[..before.len()])
+            .chain(after.iter().zip(&list[end..]))
+            .all(|(p, lv)| self.match_pattern(p, lv, bindings))
+        {
+            return false;
+        }
+        let mut matches = vec![];
+        for lv in &list[before.len()..end] {
+            let mut m = Bindings::new();
+            if !self.match_pattern(repeated, lv, &mut m) {
+                return false;
+            }
+            matches.push(m);
+        }
+        let mut vars = HashSet::new();
+        self.pattern_variables(repeated, &mut vars);
+        for var in vars {
+            let seq = matches.iter_mut().filter_map(|m| m.remove(&var)).collect();
+            bindings.insert(var, Binding::Seq(seq));
+        }
+        true
+    }
+
+    fn pattern_variables(&self, pattern: &LValue, vars: &mut HashSet<String>) {
+        match pattern {
+            LValue::Symbol(s)
+                if s.as_str() != SYNTAX_WILDCARD
+                    && s.as_str() != SYNTAX_ELLIPSIS
+                    && !self.literals.contains(s) =>
+            {
+                vars.insert(s.to_string());
+            }
+            LValue::List(list) => list.iter().for_each(|p| self.pattern_variables(p, vars)),
+            _ => {}
+        }
+    }
+}
+
+fn is_ellipsis(lv: &LValue) -> bool {
+    matches!(lv, LValue::Symbol(s) if s.as_str() == SYNTAX_ELLIPSIS)
+}
+
+/// Collects the symbols of the template that are bound by a lambda, a let, a for,
+/// a catch clause or a definition, and that are not pattern variables.
+fn bound_symbols(template: &LValue, bindings: &Bindings, bound: &mut HashSet<String>) {
+    let list = match template {
+        LValue::List(list) => list,
+        _ => return,
+    };
+    let mut add = |lv: &LValue| {
+        if let LValue::Symbol(s) = lv {
+            if !bindings.contains_key(s.as_str()) && !is_ellipsis(lv) {
+                bound.insert(s.to_string());
+            }
+        }
+    };
+    if let LValue::Symbol(head) = &list[0] {
+        match head.as_str() {
+            QUOTE => return,
+            FN_LAMBDA if list.len() > 1 => match &list[1] {
+                LValue::List(params) => params.iter().for_each(&mut add),
+                params => add(params),
+            },
+            LET | LET_STAR if list.len() > 1 => {
+                let vars = match &list[1] {
+                    LValue::Symbol(_) => {
+                        add(&list[1]);
+                        list.get(2)
+                    }
+                    vars => Some(vars),
+                };
+                if let Some(LValue::List(vars)) = vars {
+                    for var in vars.iter() {
+                        if let LValue::List(var) = var {
+                            add(&var[0])
+                        }
+                    }
+                }
+            }
+            DEFINE if list.len() > 1 => match &list[1] {
+                LValue::List(f) => add(&f[0]),
+                var => add(var),
+            },
+            FOR if list.len() > 1 => add(&list[1]),
+            TRY_CATCH if list.len() > 2 => add(&list[2]),
+            _ => {}
+        }
+    }
+    list.iter()
+        .for_each(|lv| bound_symbols(lv, bindings, bound));
+}
+
+/// Substitutes the pattern variables of the template, and renames the symbols bound by it
+/// or replaces them by their value in the closure. Quoted symbols are not renamed.
+fn instantiate(
+    template: &LValue,
+    bindings: &Bindings,
+    renaming: &HashMap<String, LValue>,
+    quoted: bool,
+) -> Result<LValue, LRuntimeError> {
+    match template {
+        LValue::Symbol(s) => match bindings.get(s.as_str()) {
+            Some(Binding::One(lv)) => Ok(lv.clone()),
+            Some(Binding::Seq(_)) => Err(lruntimeerror!(
+                SYNTAX_RULES,
+                format!("{}: pattern variable used without an ellipsis", s)
+            )),
+            None => match renaming.get(s.as_str()) {
+                Some(renamed) if !quoted => Ok(renamed.clone()),
+                _ => Ok(template.clone()),
+            },
+        },
+        LValue::List(list) => {
+            let quoted = quoted || matches!(&list[0], LValue::Symbol(s) if s.as_str() == QUOTE);
+            let mut instantiated = vec![];
+            let mut i = 0;
+            while i < list.len() {
+                let element = &list[i];
+                if list.get(i + 1).is_some_and(is_ellipsis) {
+                    for bindings in repetitions(element, bindings)? {
+                        instantiated.push(instantiate(element, &bindings, renaming, quoted)?);
+                    }
+                    i += 2;
+                } else {
+                    instantiated.push(instantiate(element, bindings, renaming, quoted)?);
+                    i += 1;
+                }
+            }
+            Ok(instantiated.into())
+        }
+        lv => Ok(lv.clone()),
+    }
+}
+
+/// Returns the bindings of each repetition of an element followed by an ellipsis,
+/// in which the sequences of its pattern variables are replaced by their elements.
+fn repetitions(element: &LValue, bindings: &Bindings) -> Result<Vec<Bindings>, LRuntimeError> {
+    let mut symbols = HashSet::new();
+    symbols_of(element, &mut symbols);
+    let seqs: Vec<(&String, &Vec<Binding>)> = bindings
+        .iter()
+        .filter_map(|(var, b)| match b {
+            Binding::Seq(seq) if symbols.contains(var.as_str()) => Some((var, seq)),
+            _ => None,
+        })
+        .collect();
+    let n = match seqs.first() {
+        Some((_, seq)) => seq.len(),
+        None => {
+            return Err(lruntimeerror!(
+                SYNTAX_RULES,
+                format!(
+                    "{}: no pattern variable to repeat before the ellipsis",
+                    element
+                )
+            ))
+        }
+    };
+    if seqs.iter().any(|(_, seq)| seq.len() != n) {
+        return Err(lruntimeerror!(
+            SYNTAX_RULES,
+            format!(
+                "{}: pattern variables repeated a different number of times",
+                element
+            )
+        ));
+    }
+    Ok((0..n)
+        .map(|i| {
+            let mut bindings = bindings.clone();
+            for (var, seq) in &seqs {
+                bindings.insert(var.to_string(), seq[i].clone());
+            }
+            bindings
+        })
+        .collect())
+}
+
+fn symbols_of<'a>(lv: &'a LValue, symbols: &mut HashSet<&'a str>) {
+    match lv {
+        LValue::Symbol(s) => {
+            symbols.insert(s.as_str());
+        }
+        LValue::List(list) => list.iter().for_each(|lv| symbols_of(lv, symbols)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sym(s: &str) -> LValue {
+        symbol!(s.to_string())
+    }
+
+    #[test]
+    fn test_syntax_rules() {
+        //(syntax-rules (else)
+        //  ((_ (else e)) e)
+        //  ((_ (c e) clause ...) (if c e (my-cond clause ...))))
+        let rules: LSyntaxRules = (&list![
+            sym(SYNTAX_RULES),
+            list![sym("else")],
+            list![list![sym("_"), list![sym("else"), sym("e")]], sym("e")],
+            list![
+                list![
+                    sym("_"),
+                    list![sym("c"), sym("e")],
+                    sym("clause"),
+                    sym("...")
+                ],
+                list![
+                    sym("if"),
+                    sym("c"),
+                    sym("e"),
+                    list![sym("my-cond"), sym("clause"), sym("...")]
+                ]
+            ]
+        ])
+            .try_into()
+            .unwrap();
+        let form = list![
+            sym("my-cond"),
+            list![sym("a"), 1.into()],
+            list![sym("b"), 2.into()],
+            list![sym("else"), 3.into()]
+        ];
+        assert_eq!(
+            rules.transcribe(&form).unwrap().to_string(),
+            "(if a 1 (my-cond (b 2) (else 3)))"
+        );
+        assert!(rules.transcribe(&list![sym("my-cond")]).is_err());
+    }
+
+    #[test]
+    fn test_syntax_rules_hygiene() {
+        //(syntax-rules () ((_ a b) (let ((tmp a)) (list tmp b 'tmp))))
+        let rules: LSyntaxRules = (&list![
+            sym(SYNTAX_RULES),
+            LValue::Nil,
+            list![
+                list![sym("_"), sym("a"), sym("b")],
+                list![
+                    sym(LET),
+                    list![list![sym("tmp"), sym("a")]],
+                    list![
+                        sym("list"),
+                        sym("tmp"),
+                        sym("b"),
+                        list![sym(QUOTE), sym("tmp")]
+                    ]
+                ]
+            ]
+        ])
+            .try_into()
+            .unwrap();
+        let expanded = rules
+            .transcribe(&list![sym("m"), 1.into(), sym("tmp")])
+            .unwrap();
+        let expanded = match expanded {
+            LValue::List(l) => l,
+            _ => panic!(),
+        };
+        let tmp = match &expanded[1] {
+            LValue::List(vars) => match &vars[0] {
+                LValue::List(var) => var[0].clone(),
+                _ => panic!(),
+            },
+            _ => panic!(),
+        };
+        //The symbol bound by the template is renamed, but not the one of the form.
+        assert_ne!(tmp, sym("tmp"));
+        assert_eq!(
+            expanded[2],
+            list![sym("list"), tmp, sym("tmp"), list![sym(QUOTE), sym("tmp")]]
+        );
+    }
+}
@@ -165,13 +165,20 @@ impl LValue {
                         }
                         LValue::Symbol(s) => match s.as_str() {
                             LET | LET_STAR => {
-                                let (mut string, indent) = match s.as_str() {
+                                let (mut string, mut indent) = match s.as_str() {
                                     LET =>  ("(let ".to_string(), indent + 5),
                                     LET_STAR => ("(let* ".to_string(), indent + 6),
                                     _ => unreachable!("The value of the atom has been checked before and should be let or let*.")
                                 };
-                                let bindings = &list[1];
-                                let body = &list[2];
+                                //Named let: (let name bindings body)
+                                let (bindings, body) = match &list[1] {
+                                    LValue::Symbol(name) => {
+                                        string.push_str(format!("{} ", name).as_str());
+                                        indent += name.len() + 1;
+                                        (&list[2], &list[3])
+                                    }
+                                    _ => (&list[1], &list[2]),
+                                };
 
                                 if let LValue::List(bindings) = bindings {
                                     string.push('(');